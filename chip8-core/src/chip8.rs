@@ -0,0 +1,4127 @@
+use crate::format::{decode, decompress, encode, is_compressed, Format};
+use crate::instruction::Instruction;
+use crate::listing::{Listing, ListingLine};
+use crate::quirks::Quirks;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+pub const SCREEN_WIDTH: usize = 64;
+pub const SCREEN_HEIGHT: usize = 32;
+
+/// Size of the SUPER-CHIP's high resolution screen, which low resolution is drawn onto at double size
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+/// Memory of the original interpreters, which the machine has unless given the XO-CHIP's
+const MEMORY_SIZE: usize = 4096;
+
+/// Memory of the XO-CHIP, the whole of a 16-bit address space
+pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+const REGISTER_COUNT: usize = 16;
+const STACK_SIZE: usize = 16;
+
+/// Where the COSMAC VIP interpreter kept its stack, two bytes per level
+pub const VIP_STACK_ADDR: usize = 0xEA0;
+
+/// Levels the VIP's stack had room for
+pub const VIP_STACK_SIZE: usize = 12;
+
+/// Instructions run between looks at the clock for a time limit, since reading it costs more than most instructions
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// Where the font goes when an interpreter image takes its place, in the VIP's display buffer (which this emulator keeps out of memory)
+pub const RELOCATED_FONT_ADDR: usize = 0xF00;
+
+/// Most levels a stack in memory can have before running off the end
+pub const MAX_MEMORY_STACK_SIZE: usize = (MEMORY_SIZE - VIP_STACK_ADDR) / 2;
+const BOOT_SECTOR: usize = 512;
+
+/// Largest program that fits in memory after the boot sector
+pub const MAX_ROM_SIZE: usize = MEMORY_SIZE - BOOT_SECTOR;
+
+/// Largest program that fits in XO-CHIP's 64K address space
+pub const XO_CHIP_MAX_ROM_SIZE: usize = XO_CHIP_MEMORY_SIZE - BOOT_SECTOR;
+
+/// Instructions remembered for fault reports unless asked for more or fewer
+pub const PC_HISTORY_SIZE: usize = 16;
+
+const FONTSET_SIZE: usize = 80;
+const FONTSET: [u8; FONTSET_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// The SUPER-CHIP's 8x10 hex digits, which go straight after the small ones
+const BIG_FONTSET_SIZE: usize = 160;
+const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x18, 0x3C, 0x66, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// Registers FX75 can save into the SUPER-CHIP's RPL flags
+const RPL_FLAG_COUNT: usize = 8;
+
+/// Magic at the start of binary save states
+const STATE_MAGIC: &[u8; 4] = b"C8ST";
+
+/// How many times each memory address was read, written and fetched as an instruction
+pub struct MemoryAccessCounts {
+    pub reads: Vec<u64>,
+    pub writes: Vec<u64>,
+    pub fetches: Vec<u64>,
+}
+
+impl MemoryAccessCounts {
+    fn new(size: usize) -> Self {
+        return Self {
+            reads: vec![0; size],
+            writes: vec![0; size],
+            fetches: vec![0; size],
+        };
+    }
+}
+
+/// A request a test ROM makes of the host with the FxFF custom instruction
+#[derive(Debug, PartialEq)]
+pub enum HostCall {
+    /// F0FF - the test passed
+    Pass,
+    /// F1FF - the test failed, with V0 as the failure code
+    Fail(u8),
+    /// F2FF - print the registers
+    Debug(String),
+}
+
+/// Something the machine did that a frontend may want to show, so it can follow along without asking the machine every frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoreEvent {
+    /// FX0A found no key down and is waiting for one, raised again every instruction it waits unless nothing came in between
+    WaitingForKey,
+    /// FX15 set the delay timer to this
+    DelaySet(u8),
+    /// The ROM stopped, either by exiting or, with `fault`, at an error, something strict mode doesn't allow or a run limit
+    Halted { fault: bool },
+}
+
+/// The parts of the machine an extension's handler can change
+/// 
+/// Memory written here isn't watched or counted, and reading it back before the ROM writes it warns as uninitialized.
+pub struct ExtensionState<'a> {
+    pub registers: &'a mut [u8; REGISTER_COUNT],
+    pub index: &'a mut u16,
+    /// Already past the instruction, so a handler can leave it to carry on with the next one
+    pub pc: &'a mut u16,
+    /// 4K, or 64K after `Chip8::set_memory_size`
+    pub memory: &'a mut [u8],
+    pub delay_timer: &'a mut u8,
+    pub sound_timer: &'a mut u8,
+}
+
+/// A handler for opcodes the interpreter doesn't run itself, registered with `Chip8::register_extension`
+struct Extension {
+    /// Whether the handler takes an opcode
+    matcher: Box<dyn Fn(u16) -> bool + Send>,
+    handler: Box<dyn FnMut(u16, ExtensionState) + Send>,
+}
+
+/// The sprite draw that lit a pixel, for finding the code behind what is on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelSource {
+    /// Address of the DRW instruction
+    pub pc: u16,
+    /// Where the sprite was read from, which is I when it was drawn
+    pub sprite: u16,
+}
+
+/// A register or byte of memory to stop on when its value changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Watchpoint {
+    /// V0-VF
+    Register(usize),
+    Memory(u16),
+}
+
+impl Watchpoint {
+    /// Reads a watchpoint like the debugger takes them: `V3`, or `mem 0x300`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim().to_uppercase();
+        if let Some(addr) = text.strip_prefix("MEM") {
+            return match parse_number(addr.trim()) {
+                Some(addr) => Ok(Self::Memory(addr)),
+                None => Err(format!("{} is not an address in memory", addr.trim())),
+            };
+        }
+        return match text.strip_prefix('V').and_then(|register| usize::from_str_radix(register, 16).ok()) {
+            Some(register) if register < REGISTER_COUNT => Ok(Self::Register(register)),
+            _ => Err(format!("Can't watch {}, expected a register like V3 or an address like mem 0x300", text)),
+        };
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Self::Register(register) => write!(f, "V{:X}", register),
+            Self::Memory(addr) => write!(f, "mem {:03X}", addr),
+        };
+    }
+}
+
+/// A watched value changing
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchHit {
+    /// Address of the instruction that changed it
+    pub pc: u16,
+    pub watchpoint: Watchpoint,
+    pub old: u8,
+    pub new: u8,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{} changed from {:02X} to {:02X} at {:03X}", self.watchpoint, self.old, self.new, self.pc);
+    }
+}
+
+/// A problem the emulator noticed that doesn't stop the ROM, reported once per instruction it happened at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Address of the instruction
+    pub pc: u16,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{:03X}: {}", self.pc, self.message);
+    }
+}
+
+/// Why a ROM couldn't be loaded, or what stopped the machine in the middle of one, which it can't carry on from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chip8Error {
+    /// The ROM is `size` bytes, more than the `max` that fit in memory from 0x200
+    RomTooLarge { size: usize, max: usize },
+    /// The PC got to the last byte of memory or past it, where there is no whole instruction to run
+    PcOutOfBounds { pc: u16 },
+    /// The instruction at `pc` read or wrote `addr`, past the end of memory
+    MemoryOutOfBounds { pc: u16, addr: usize },
+    /// The opcode at `pc` isn't an instruction on this platform, and `OpcodePolicy::Halt` stops the ROM at it
+    UnknownOpcode { pc: u16, opcode: u16 },
+    /// The `CALL` at `pc` had no room left on the stack for its return address
+    StackOverflow { pc: u16 },
+    /// The `RET` at `pc` had nothing on the stack to return to
+    StackUnderflow { pc: u16 },
+    /// Strict mode stopped the ROM at something the most conservative interpreter wouldn't allow
+    Strict(Diagnostic),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Self::RomTooLarge { size, max } => {
+                let hint = match *size <= XO_CHIP_MAX_ROM_SIZE {
+                    true => "ROMs this big are usually made for the XO-CHIP, so try its 64K of memory with --platform xochip",
+                    false => "that is more than even the XO-CHIP's 64K of memory, so it is probably not a ROM",
+                };
+                write!(f, "The ROM is {} bytes, but only {} fit in memory from {:03X}; {}", size, max, BOOT_SECTOR, hint)
+            },
+            Self::PcOutOfBounds { pc } => write!(f, "{:03X}: the PC ran off the end of memory", pc),
+            Self::MemoryOutOfBounds { pc, addr } => write!(f, "{:03X}: {:03X} is past the end of memory", pc, addr),
+            Self::UnknownOpcode { pc, opcode } => write!(f, "{:03X}: unknown opcode 0x{:04X}", pc, opcode),
+            Self::StackOverflow { pc } => write!(f, "{:03X}: CALL with the stack full", pc),
+            Self::StackUnderflow { pc } => write!(f, "{:03X}: RET with nothing on the stack to return to", pc),
+            Self::Strict(diagnostic) => write!(f, "{}", diagnostic),
+        };
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+/// Lets functions that report errors as text pass these on with `?`
+impl From<Chip8Error> for String {
+    fn from(error: Chip8Error) -> Self {
+        return error.to_string();
+    }
+}
+
+/// What the machine does with an opcode that isn't an instruction on its platform
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OpcodePolicy {
+    /// Skip it and carry on, as most interpreters did
+    #[default]
+    Ignore,
+    /// Stop the ROM at it with `Chip8Error::UnknownOpcode`
+    Halt,
+    /// Skip it, but have `cycle` return `CycleStatus::Trapped` so the frontend can stop in its debugger
+    Trap,
+}
+
+impl OpcodePolicy {
+    /// Looks a policy up by name: `ignore`, `halt` or `trap`, which can also be spelled `trap-to-debugger`
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        return match name {
+            "ignore" => Ok(Self::Ignore),
+            "halt" => Ok(Self::Halt),
+            "trap" | "trap-to-debugger" => Ok(Self::Trap),
+            other => Err(format!("Unknown opcode policy {}, the policies are ignore, halt and trap", other)),
+        };
+    }
+}
+
+/// What running an instruction came to, when it didn't stop the machine with an error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CycleStatus {
+    /// An instruction ran
+    Ran,
+    /// Nothing ran, as the ROM has exited or run into its limits
+    Halted,
+    /// The opcode at `pc` isn't an instruction and was skipped, and `OpcodePolicy::Trap` asks the frontend to stop in its debugger
+    Trapped { pc: u16, opcode: u16 },
+}
+
+/// How far a ROM may run before the machine stops it, for ROMs that can't be trusted to stop by themselves
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunLimits {
+    /// Most instructions to run
+    pub max_instructions: Option<u64>,
+    /// Most time to spend running, from when the limits were set
+    pub max_duration: Option<Duration>,
+}
+
+/// The run limit that stopped the ROM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitHit {
+    Instructions(u64),
+    Duration(Duration),
+}
+
+impl fmt::Display for LimitHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Self::Instructions(count) => write!(f, "ran its limit of {} instructions", count),
+            Self::Duration(duration) => write!(f, "ran past its time limit of {} ms", duration.as_millis()),
+        };
+    }
+}
+
+/// The screen as a frontend draws it, which stays the same shape however the machine keeps its own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameBuffer<'a> {
+    pub width: usize,
+    pub height: usize,
+    /// A row at a time, with a byte per pixel that is 0 when it is off
+    pub pixels: &'a [u8],
+}
+
+impl<'a> FrameBuffer<'a> {
+    /// Whether the pixel at a column and row is lit, which it never is off the screen
+    pub fn is_lit(&self, x: usize, y: usize) -> bool {
+        return x < self.width && self.pixels.get(y * self.width + x).is_some_and(|pixel| *pixel != 0);
+    }
+
+    /// The rows of pixels, from the top
+    pub fn rows(&self) -> std::slice::Chunks<'a, u8> {
+        return self.pixels.chunks(self.width);
+    }
+}
+
+/// Run limits being counted down
+struct ActiveLimits {
+    limits: RunLimits,
+    started: Instant,
+    /// Instructions run since the limits were set
+    instructions: u64,
+    hit: Option<LimitHit>,
+}
+
+impl ActiveLimits {
+    /// Counts an instruction about to run, returning the limit that stops it if one does
+    fn count(&mut self) -> Option<LimitHit> {
+        if self.hit.is_none() {
+            if self.limits.max_instructions.is_some_and(|max| self.instructions >= max) {
+                self.hit = Some(LimitHit::Instructions(self.instructions));
+            } else if let Some(max) = self.limits.max_duration.filter(|_| self.instructions.is_multiple_of(TIME_CHECK_INTERVAL)) {
+                self.hit = (self.started.elapsed() >= max).then_some(LimitHit::Duration(max));
+            }
+        }
+        if self.hit.is_none() {
+            self.instructions += 1;
+        }
+        return self.hit;
+    }
+}
+
+/// Everything a save state puts back: the machine, and the settings that change how it runs.
+/// Debugging aids like access counts, warnings and memory poisoning stay as they are in the emulator being loaded into.
+/// What was in the middle of happening is mostly the machine too: a beep is the sound timer, FX0A waits by leaving the PC on itself
+/// with `key_down` holding the key it is waiting to see let go, and a per-frame subroutine that was running is `callback_sp`.
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    memory: Vec<u8>,
+    initialized: Vec<bool>,
+    registers: [u8; REGISTER_COUNT],
+    index: u16,
+    pc: u16,
+    stack: Vec<u16>,
+    stack_in_memory: bool,
+    font_addr: u16,
+    sp: usize,
+    delay_timer: u8,
+    sound_timer: u8,
+    screen: Vec<u8>,
+    keypad: [bool; 16],
+    key_down: Option<usize>,
+    unknown_opcodes: u64,
+    custom_ops: bool,
+    schip: bool,
+    hires: bool,
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    halted: bool,
+    frame_callback: Option<u16>,
+    callback_sp: Option<usize>,
+    frame_cycles: u64,
+    quirks: Quirks,
+    rng: ChaCha12Rng,
+}
+
+/// What `Chip8::inspect_state` found in a save state
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateInfo {
+    pub format: Format,
+    pub compressed: bool,
+    /// Bytes the state takes up as given
+    pub size: usize,
+    /// Bytes the state takes up once decompressed
+    pub decompressed_size: usize,
+    pub schip: bool,
+    /// 4K, or 64K for the XO-CHIP
+    pub memory_size: usize,
+    pub pc: u16,
+}
+
+/// Mnemonics of this emulator's own instructions, which the assembler warns about
+const CUSTOM_MNEMONICS: [&str; 4] = ["FRAME", "PASS", "FAIL", "DEBUG"];
+
+/// A CHIP-8 machine, which runs a ROM an instruction at a time and leaves what it drew in a screen buffer of one byte per pixel
+/// 
+/// The frontend decides how fast it runs: call `cycle` for each instruction and `update_timers` 60 times a second.
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8_core::{assemble, Chip8};
+/// 
+/// // Draws the font's 0 in the top left corner, then waits there
+/// let rom = assemble("LD V0 0\nLD F V0\nDRW V0 V0 5\nloop: JP loop").unwrap();
+/// let mut chip8 = Chip8::new();
+/// chip8.load_rom(&rom).unwrap();
+/// for _ in 0..100 {
+///     chip8.cycle().unwrap();
+/// }
+/// 
+/// let (width, height) = chip8.get_screen_size();
+/// assert_eq!((width, height), (64, 32));
+/// let rows: Vec<String> = chip8.get_screen_buffer().chunks(width).take(5)
+///     .map(|row| row[..4].iter().map(|pixel| if *pixel != 0 { '#' } else { '.' }).collect())
+///     .collect();
+/// assert_eq!(rows, ["####", "#..#", "#..#", "#..#", "####"]);
+/// assert_eq!(chip8.get_screen_buffer().iter().filter(|pixel| **pixel != 0).count(), 14);
+/// ```
+pub struct Chip8 {
+    /// 4K, or 64K for the XO-CHIP
+    memory: Vec<u8>,
+    /// Which bytes of memory were loaded or written, rather than left as they were at power on
+    initialized: Vec<bool>,
+    registers: [u8; REGISTER_COUNT],
+    index: u16,
+    pc: u16,
+    stack: Vec<u16>,
+    /// Whether the stack lives in memory at VIP_STACK_ADDR, where the ROM can see it, rather than in `stack`
+    stack_in_memory: bool,
+    /// Where the hex digit sprites start
+    font_addr: u16,
+    sp: usize,
+    delay_timer: u8,
+    sound_timer: u8,
+    /// One byte per pixel, `screen_width` by `screen_height`
+    screen: Vec<u8>,
+    screen_width: usize,
+    screen_height: usize,
+    keypad: [bool; 16],
+    /// The key FX0A saw pressed, which it takes once it is let go
+    key_down: Option<usize>,
+    unknown_opcodes: u64,
+    /// Only kept when asked for, so counting costs nothing otherwise
+    access_counts: Option<Box<MemoryAccessCounts>>,
+    /// The draw that lit each pixel of the screen buffer, only kept when asked for like the access counts
+    pixel_sources: Option<Vec<Option<PixelSource>>>,
+    /// Whether this emulator's own nonstandard instructions run, rather than being treated as unknown
+    custom_ops: bool,
+    /// Whether the SUPER-CHIP 1.1 instructions run, on a 128x64 screen
+    schip: bool,
+    /// Whether a SUPER-CHIP ROM switched to high resolution; low resolution pixels are drawn as 2x2 blocks
+    hires: bool,
+    /// The SUPER-CHIP's RPL user flags, which FX75 and FX85 save registers to and load them from
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    /// Whether the ROM ran 00FD to exit the interpreter
+    halted: bool,
+    /// Subroutine the ROM asked to have called every 60 Hz tick
+    frame_callback: Option<u16>,
+    /// Stack depth the per-frame subroutine returns to, while it is running
+    callback_sp: Option<usize>,
+    /// Instructions run since the timers last counted down, which is how far into a frame the machine is
+    frame_cycles: u64,
+    /// Host calls made since they were last taken
+    host_calls: Vec<HostCall>,
+    /// Events raised since they were last taken
+    events: Vec<CoreEvent>,
+    /// Which behaviour to pick for instructions interpreters disagree on
+    quirks: Quirks,
+    /// Whether to warn about instructions that behave differently between interpreters
+    pedantic: bool,
+    /// Whether to warn when the ROM reads or runs memory that was never loaded or written
+    poison_memory: bool,
+    /// Whether to stop the ROM at the first thing it does that the most conservative interpreter wouldn't allow
+    strict: bool,
+    /// What to do with opcodes that aren't instructions, when not strict
+    opcode_policy: OpcodePolicy,
+    /// The unknown opcode the last instruction trapped on, and its address
+    trapped: Option<(u16, u16)>,
+    /// What stopped the ROM in strict mode
+    fault: Option<Diagnostic>,
+    /// What stopped the machine, in strict mode or not
+    error: Option<Chip8Error>,
+    /// Values to report changes to
+    watchpoints: Vec<Watchpoint>,
+    /// Changes to watched values since they were last taken
+    watch_hits: Vec<WatchHit>,
+    /// Diagnostics raised since they were last taken
+    diagnostics: Vec<Diagnostic>,
+    /// Every diagnostic raised so far, so each is only reported once
+    reported: HashSet<(u16, String)>,
+    /// Source of RND's random bytes, seeded so a run can be repeated exactly. This is what `StdRng` is underneath, but it can be saved
+    rng: ChaCha12Rng,
+    /// Handlers for opcodes of other crates' own, checked before the instruction set
+    extensions: Vec<Extension>,
+    /// Address and opcode of the last instructions run, oldest first
+    pc_history: VecDeque<(u16, u16)>,
+    /// Most instructions `pc_history` keeps
+    pc_history_size: usize,
+    /// How far the ROM may run, if it was limited
+    limits: Option<ActiveLimits>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+// Public interface
+impl Chip8 {
+    pub fn new() -> Self {
+        let mut new_chip = Self {
+            memory: vec![0; MEMORY_SIZE],
+            initialized: vec![false; MEMORY_SIZE],
+            registers: [0; REGISTER_COUNT],
+            index: 0,
+            pc: BOOT_SECTOR as u16,
+            stack: vec![0; STACK_SIZE],
+            stack_in_memory: false,
+            font_addr: 0,
+            sp: 0,
+            delay_timer: 0,
+            sound_timer: 0,
+            screen: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            keypad: [false; 16],
+            key_down: None,
+            unknown_opcodes: 0,
+            access_counts: None,
+            pixel_sources: None,
+            custom_ops: false,
+            schip: false,
+            hires: false,
+            rpl_flags: [0; RPL_FLAG_COUNT],
+            halted: false,
+            frame_callback: None,
+            callback_sp: None,
+            frame_cycles: 0,
+            host_calls: Vec::new(),
+            events: Vec::new(),
+            quirks: Quirks::default(),
+            pedantic: false,
+            poison_memory: false,
+            strict: false,
+            opcode_policy: OpcodePolicy::Ignore,
+            trapped: None,
+            fault: None,
+            error: None,
+            watchpoints: Vec::new(),
+            watch_hits: Vec::new(),
+            diagnostics: Vec::new(),
+            reported: HashSet::new(),
+            rng: ChaCha12Rng::from_entropy(),
+            extensions: Vec::new(),
+            pc_history: VecDeque::with_capacity(PC_HISTORY_SIZE),
+            pc_history_size: PC_HISTORY_SIZE,
+            limits: None,
+        };
+
+        // Copy the font set
+        new_chip.memory[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        new_chip.initialized[..FONTSET_SIZE].fill(true);
+
+        return new_chip;
+    }
+
+    /// Loads a ROM into memory after the boot sector, where programs start
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `rom` - The ROM
+    /// 
+    /// ## Returns
+    /// 
+    /// `Chip8Error::RomTooLarge` if the ROM doesn't fit in memory, which is 4K unless `set_memory_size` gave it 64K
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let max = self.memory.len() - BOOT_SECTOR;
+        if rom.len() > max {
+            return Err(Chip8Error::RomTooLarge { size: rom.len(), max });
+        }
+
+        let start = BOOT_SECTOR;
+        let end = start + rom.len();
+
+        self.memory[start..end].copy_from_slice(rom);
+        self.initialized[start..end].fill(true);
+        return Ok(());
+    }
+
+    /// The screen, a row at a time, with a byte per pixel that is 0 when it is off
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom(&assemble("LD I dot\nLD V0 3\nDRW V0 V0 1\nloop: JP loop\ndot: .db 0x80").unwrap()).unwrap();
+    /// for _ in 0..3 {
+    ///     chip8.cycle().unwrap();
+    /// }
+    /// let (width, _) = chip8.get_screen_size();
+    /// assert_eq!(chip8.get_screen_buffer()[3 * width + 3], 1);
+    /// ```
+    pub fn get_screen_buffer(&self) -> &[u8] {
+        return &self.screen;
+    }
+
+    /// Width and height of the screen buffer, which is 128x64 for SUPER-CHIP ROMs whatever resolution they are in
+    pub fn get_screen_size(&self) -> (usize, usize) {
+        return (self.screen_width, self.screen_height);
+    }
+
+    /// The screen with its size, to draw without knowing how the machine keeps it
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom(&assemble("LD I dot\nLD V0 3\nDRW V0 V0 1\nloop: JP loop\ndot: .db 0x80").unwrap()).unwrap();
+    /// for _ in 0..3 {
+    ///     chip8.cycle().unwrap();
+    /// }
+    /// let frame = chip8.get_frame_buffer();
+    /// assert!(frame.is_lit(3, 3) && !frame.is_lit(4, 3) && !frame.is_lit(64, 3));
+    /// assert_eq!(frame.rows().count(), 32);
+    /// ```
+    pub fn get_frame_buffer(&self) -> FrameBuffer<'_> {
+        return FrameBuffer { width: self.screen_width, height: self.screen_height, pixels: &self.screen };
+    }
+
+    /// The sprite a `DRW` with this height would draw, read from memory at I as it is now
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `n` - The height in the instruction, where 0 asks the SUPER-CHIP for a 16x16 sprite
+    /// 
+    /// ## Returns
+    /// 
+    /// The sprite's bytes, and how many of them make up each row
+    pub fn get_sprite(&self, n: u8) -> (Vec<u8>, usize) {
+        let (rows, bytes_per_row) = self.sprite_shape(n as usize);
+        let start = self.index as usize;
+        let bytes = (start..start + rows * bytes_per_row).filter_map(|addr| self.memory.get(addr).copied()).collect();
+        return (bytes, bytes_per_row);
+    }
+
+    /// Rows in a sprite of height `n` and the bytes in each, which are 16x16 for Dxy0 on the SUPER-CHIP and 8 pixels wide otherwise
+    fn sprite_shape(&self, n: usize) -> (usize, usize) {
+        return match n {
+            0 if self.schip && self.hires => (16, 2),
+            0 if self.schip => (16, 1),
+            n => (n, 1),
+        };
+    }
+
+    /// Whether instructions have run since the timers last counted down, so the frame they belong to hasn't ended
+    /// 
+    /// A frontend that pauses, saves a state or hands the machine to another thread while this is false leaves it at a frame boundary,
+    /// where the screen is whole and the timers agree with the instructions run.
+    pub fn is_mid_frame(&self) -> bool {
+        return self.frame_cycles > 0;
+    }
+
+    /// Whether the ROM has exited with 00FD, after which it runs no more instructions
+    pub fn is_halted(&self) -> bool {
+        return self.halted;
+    }
+
+    pub fn get_pc(&self) -> u16 {
+        return self.pc;
+    }
+
+    pub fn get_index(&self) -> u16 {
+        return self.index;
+    }
+
+    pub fn get_registers(&self) -> &[u8] {
+        return &self.registers;
+    }
+
+    pub fn get_memory(&self) -> &[u8] {
+        return &self.memory;
+    }
+
+    pub fn get_keypad(&self) -> &[bool] {
+        return &self.keypad;
+    }
+
+    pub fn get_sound_timer(&self) -> u8 {
+        return self.sound_timer;
+    }
+
+    pub fn get_delay_timer(&self) -> u8 {
+        return self.delay_timer;
+    }
+
+    /// Return addresses on the stack, oldest first, wherever the stack lives
+    pub fn get_stack(&self) -> Vec<u16> {
+        if self.stack_in_memory {
+            return (0..self.sp).map(|level| {
+                let addr = VIP_STACK_ADDR + level * 2;
+                return u16::from_be_bytes([self.memory[addr], self.memory[addr + 1]]);
+            }).collect();
+        }
+        return self.stack[..self.sp].to_vec();
+    }
+
+    /// The unknown opcode the last instruction trapped on, with its address, for frontends running the machine with `step`
+    pub fn get_trap(&self) -> Option<(u16, u16)> {
+        return self.trapped;
+    }
+
+    pub fn get_unknown_opcode_count(&self) -> u64 {
+        return self.unknown_opcodes;
+    }
+
+    /// Starts counting memory accesses
+    pub fn enable_access_counts(&mut self) {
+        let size = self.memory.len();
+        self.access_counts.get_or_insert_with(|| Box::new(MemoryAccessCounts::new(size)));
+    }
+
+    pub fn get_access_counts(&self) -> Option<&MemoryAccessCounts> {
+        return self.access_counts.as_deref();
+    }
+
+    /// Starts keeping track of which sprite draw lit each pixel, for `get_pixel_source`
+    pub fn enable_pixel_sources(&mut self) {
+        self.pixel_sources.get_or_insert_with(|| vec![None; self.screen.len()]);
+    }
+
+    /// Finds the sprite draw that lit a pixel, if it is lit and pixel sources are being kept
+    /// 
+    /// Pixels lit before `enable_pixel_sources`, or before a save state was loaded, have no source.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `x` - Column in the screen buffer, which is 128 pixels wide on the SUPER-CHIP whatever the resolution
+    /// * `y` - Row in the screen buffer
+    pub fn get_pixel_source(&self, x: usize, y: usize) -> Option<PixelSource> {
+        if x >= self.screen_width || y >= self.screen_height {
+            return None;
+        }
+        return self.pixel_sources.as_ref()?[y * self.screen_width + x];
+    }
+
+    /// Fills the interpreter's area (0x000-0x1FF) with an image of a real interpreter, like the COSMAC VIP's, for ROMs that read or jump into it
+    /// 
+    /// The font has to move out of the way, so it goes to 0xF00.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `image` - Up to 512 bytes; anything shorter is padded with zeros
+    pub fn load_interpreter_image(&mut self, image: &[u8]) -> Result<(), String> {
+        if image.len() > BOOT_SECTOR {
+            return Err(format!("The interpreter image is {} bytes, but only {} fit before the program", image.len(), BOOT_SECTOR));
+        }
+
+        self.memory[..BOOT_SECTOR].fill(0);
+        self.memory[..image.len()].copy_from_slice(image);
+        self.memory[RELOCATED_FONT_ADDR..RELOCATED_FONT_ADDR + FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.initialized[..BOOT_SECTOR].fill(false);
+        self.initialized[..image.len()].fill(true);
+        self.initialized[RELOCATED_FONT_ADDR..RELOCATED_FONT_ADDR + FONTSET_SIZE].fill(true);
+        self.font_addr = RELOCATED_FONT_ADDR as u16;
+        if self.schip {
+            self.load_big_font();
+        }
+        return Ok(());
+    }
+
+    /// Sets how deep subroutines can nest, and where the return addresses are kept
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `depth` - Number of levels
+    /// * `in_memory` - Keep the stack in memory at 0xEA0 like the COSMAC VIP, for ROMs that look at it
+    pub fn configure_stack(&mut self, depth: usize, in_memory: bool) -> Result<(), String> {
+        if in_memory && depth > MAX_MEMORY_STACK_SIZE {
+            return Err(format!("A stack in memory has room for at most {} levels", MAX_MEMORY_STACK_SIZE));
+        }
+        if depth == 0 {
+            return Err(String::from("The stack needs at least one level"));
+        }
+
+        self.stack = vec![0; depth];
+        self.stack_in_memory = in_memory;
+        self.sp = 0;
+        return Ok(());
+    }
+
+    /// Gives the machine the XO-CHIP's 64K of memory, or the original 4K
+    /// 
+    /// Call it before loading the ROM: memory past 4K is cleared when it grows and dropped when it shrinks.
+    /// Only memory grows; instructions still take 12-bit addresses, so past 4K is reached by running on into it or moving I with `ADD I, Vx`.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `size` - 4096 or `XO_CHIP_MEMORY_SIZE`
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::chip8::XO_CHIP_MEMORY_SIZE;
+    /// use chip8_core::Chip8;
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// assert!(chip8.load_rom(&[0; 5000]).is_err());
+    /// chip8.set_memory_size(XO_CHIP_MEMORY_SIZE).unwrap();
+    /// chip8.load_rom(&[0; 5000]).unwrap();
+    /// assert_eq!(chip8.get_memory().len(), 0x10000);
+    /// ```
+    pub fn set_memory_size(&mut self, size: usize) -> Result<(), String> {
+        if size != MEMORY_SIZE && size != XO_CHIP_MEMORY_SIZE {
+            return Err(format!("Memory is {} or {} bytes, not {}", MEMORY_SIZE, XO_CHIP_MEMORY_SIZE, size));
+        }
+        self.memory.resize(size, 0);
+        self.initialized.resize(size, false);
+        if let Some(counts) = self.access_counts.as_mut() {
+            **counts = MemoryAccessCounts::new(size);
+        }
+        return Ok(());
+    }
+
+    /// Seeds the random numbers RND draws from, so the same seed and input give the same run
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+    }
+
+    /// Lets ROMs use this emulator's own nonstandard instructions
+    pub fn enable_custom_ops(&mut self) {
+        self.custom_ops = true;
+    }
+
+    /// Runs the SUPER-CHIP 1.1 instructions, switching to its 128x64 screen and adding its big hex digits after the font
+    pub fn enable_schip(&mut self) {
+        self.schip = true;
+        self.screen_width = HIRES_WIDTH;
+        self.screen_height = HIRES_HEIGHT;
+        self.screen = vec![0; HIRES_WIDTH * HIRES_HEIGHT];
+        if let Some(sources) = self.pixel_sources.as_mut() {
+            *sources = vec![None; self.screen.len()];
+        }
+        self.load_big_font();
+    }
+
+    /// Registers a handler for opcodes of its own, to try out new instructions without changing the interpreter
+    /// 
+    /// Extensions are checked in the order they were registered, before the instruction set, so one can take over
+    /// an opcode the interpreter already runs as well as one it doesn't know. They run during `run_ahead` too,
+    /// so a handler that keeps state of its own should expect to see instructions that are then undone.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `matcher` - Whether the handler takes an opcode, like `|opcode| opcode & 0xF00F == 0x5001`
+    /// * `handler` - Runs an opcode the matcher took, with the machine state to change
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// // 5xy1 multiplies Vx by Vy
+    /// let mut chip8 = Chip8::new();
+    /// chip8.register_extension(|opcode| opcode & 0xF00F == 0x5001, |opcode, state| {
+    ///     let (x, y) = (((opcode >> 8) & 0xF) as usize, ((opcode >> 4) & 0xF) as usize);
+    ///     state.registers[x] = state.registers[x].wrapping_mul(state.registers[y]);
+    /// });
+    /// chip8.load_rom(&assemble("LD V1 6\nLD V2 7\nEXT 0x5121").unwrap()).unwrap();
+    /// for _ in 0..3 {
+    ///     chip8.cycle().unwrap();
+    /// }
+    /// assert_eq!(chip8.get_registers()[1], 42);
+    /// ```
+    pub fn register_extension(&mut self, matcher: impl Fn(u16) -> bool + Send + 'static, handler: impl FnMut(u16, ExtensionState) + Send + 'static) {
+        self.extensions.push(Extension { matcher: Box::new(matcher), handler: Box::new(handler) });
+    }
+
+    /// Reports every change to a register, or to a byte of memory by FX33 or FX55, from now on, through `take_watch_hits`
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn get_watchpoints(&self) -> &[Watchpoint] {
+        return &self.watchpoints;
+    }
+
+    /// Takes the changes to watched values since the last time, oldest first
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        return std::mem::take(&mut self.watch_hits);
+    }
+
+    /// Takes the host calls the ROM has made since the last time
+    pub fn take_host_calls(&mut self) -> Vec<HostCall> {
+        return std::mem::take(&mut self.host_calls);
+    }
+
+    /// Takes the events raised since the last time
+    pub fn take_events(&mut self) -> Vec<CoreEvent> {
+        return std::mem::take(&mut self.events);
+    }
+
+    /// Picks the behaviour of the instructions interpreters disagree on
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::quirks::Quirks;
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// // SHR V0 V1 shifts V1 into V0 on the COSMAC VIP, but shifts V0 in place on most later interpreters
+    /// let rom = assemble("LD V0 0x10\nLD V1 0x04\nSHR V0 V1").unwrap();
+    /// let mut quirks = Quirks::default();
+    /// quirks.set("shift=vy").unwrap();
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// chip8.set_quirks(quirks);
+    /// chip8.load_rom(&rom).unwrap();
+    /// for _ in 0..3 {
+    ///     chip8.cycle().unwrap();
+    /// }
+    /// assert_eq!(chip8.get_registers()[0], 0x02);
+    /// ```
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn get_quirks(&self) -> Quirks {
+        return self.quirks;
+    }
+
+    /// Warns about instructions whose behaviour differs between interpreters, where the ROM may be relying on another one
+    pub fn enable_pedantic(&mut self) {
+        self.pedantic = true;
+    }
+
+    /// Warns when the ROM reads or runs memory that it never wrote and that wasn't loaded, which other interpreters may not fill with zeros
+    pub fn enable_memory_poisoning(&mut self) {
+        self.poison_memory = true;
+    }
+
+    /// Stops the ROM, as if it had exited, at the first unknown opcode, I past the end of memory, read or write outside the ROM's memory,
+    /// jump to an odd address, or call or return the stack can't take, rather than carrying on the way some interpreters would
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// let rom = assemble("LD V0 1\nRET").unwrap();
+    /// let mut chip8 = Chip8::new();
+    /// chip8.enable_strict();
+    /// chip8.load_rom(&rom).unwrap();
+    /// chip8.cycle().unwrap();
+    /// let error = chip8.cycle().unwrap_err();
+    /// 
+    /// assert!(chip8.is_halted());
+    /// assert_eq!(error.to_string(), "202: RET with nothing on the stack to return to");
+    /// assert_eq!(chip8.get_fault().unwrap().to_string(), "202: RET with nothing on the stack to return to");
+    /// assert_eq!(chip8.get_pc(), 0x202);
+    /// ```
+    pub fn enable_strict(&mut self) {
+        self.strict = true;
+    }
+
+    /// Chooses what to do with opcodes that aren't instructions on this platform, which strict mode always stops at
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `policy` - Whether to skip them, stop at them, or skip them and have the frontend stop in its debugger
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::chip8::{CycleStatus, OpcodePolicy};
+    /// use chip8_core::{assemble, Chip8, Chip8Error};
+    /// 
+    /// let rom = assemble("NOP\n.dw 0xF0F0\nNOP").unwrap();
+    /// let mut chip8 = Chip8::new();
+    /// chip8.set_opcode_policy(OpcodePolicy::Trap);
+    /// chip8.load_rom(&rom).unwrap();
+    /// assert_eq!(chip8.cycle(), Ok(CycleStatus::Ran));
+    /// assert_eq!(chip8.cycle(), Ok(CycleStatus::Trapped { pc: 0x202, opcode: 0xF0F0 }));
+    /// assert_eq!(chip8.cycle(), Ok(CycleStatus::Ran));
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// chip8.set_opcode_policy(OpcodePolicy::Halt);
+    /// chip8.load_rom(&rom).unwrap();
+    /// assert_eq!(chip8.cycle(), Ok(CycleStatus::Ran));
+    /// assert_eq!(chip8.cycle(), Err(Chip8Error::UnknownOpcode { pc: 0x202, opcode: 0xF0F0 }));
+    /// assert_eq!(chip8.get_pc(), 0x202);
+    /// ```
+    pub fn set_opcode_policy(&mut self, policy: OpcodePolicy) {
+        self.opcode_policy = policy;
+    }
+
+    /// What stopped the ROM in strict mode, or the error that stopped it otherwise, with the machine left as it was
+    /// when the instruction at fault started
+    pub fn get_fault(&self) -> Option<&Diagnostic> {
+        return self.fault.as_ref();
+    }
+
+    /// The error that stopped the machine, which `cycle` also returns
+    pub fn get_error(&self) -> Option<&Chip8Error> {
+        return self.error.as_ref();
+    }
+
+    /// Stops the ROM, as if it had exited, once it has run so many instructions or for so long
+    /// 
+    /// The limits are checked before every instruction, so a ROM stuck in a loop is stopped however the frontend drives it.
+    /// The time is only looked at every 1024 instructions, so the ROM may run a little past it.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `limits` - How far the ROM may run, counted from now
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::chip8::{LimitHit, RunLimits};
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom(&assemble("loop: JP loop").unwrap()).unwrap();
+    /// chip8.set_limits(RunLimits { max_instructions: Some(100), max_duration: None });
+    /// for _ in 0..1000 {
+    ///     chip8.cycle().unwrap();
+    /// }
+    /// 
+    /// assert!(chip8.is_halted());
+    /// assert_eq!(chip8.get_limit_hit(), Some(LimitHit::Instructions(100)));
+    /// ```
+    pub fn set_limits(&mut self, limits: RunLimits) {
+        self.limits = Some(ActiveLimits { limits, started: Instant::now(), instructions: 0, hit: None });
+    }
+
+    /// The run limit that stopped the ROM, if one did
+    pub fn get_limit_hit(&self) -> Option<LimitHit> {
+        return self.limits.as_ref().and_then(|limits| limits.hit);
+    }
+
+    /// Sets how many of the last instructions run are remembered, 0 to remember none
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `size` - Instructions to remember, 16 unless set
+    pub fn set_pc_history_size(&mut self, size: usize) {
+        self.pc_history_size = size;
+        let excess = self.pc_history.len().saturating_sub(size);
+        self.pc_history.drain(..excess);
+    }
+
+    /// The address and opcode of the last instructions run, oldest first, to see how the machine got where it is
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom(&assemble("CALL sub\nsub: LD V0 1").unwrap()).unwrap();
+    /// chip8.cycle().unwrap();
+    /// chip8.cycle().unwrap();
+    /// assert_eq!(*chip8.get_pc_history(), [(0x200, 0x2202), (0x202, 0x6001)]);
+    /// ```
+    pub fn get_pc_history(&self) -> &VecDeque<(u16, u16)> {
+        return &self.pc_history;
+    }
+
+    /// Takes the diagnostics raised since the last time
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        return std::mem::take(&mut self.diagnostics);
+    }
+
+    /// Counts the timers down, which ends a frame
+    pub fn update_timers(&mut self) {
+        self.frame_cycles = 0;
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        if self.sound_timer > 0 {
+            // Beep
+            self.sound_timer -= 1;
+        }
+
+        // Interrupt the program to call its per-frame subroutine, unless that is still running from the last tick
+        if let (Some(addr), None) = (self.frame_callback, self.callback_sp) {
+            let sp = self.sp;
+            if self.push_stack(self.pc) {
+                self.callback_sp = Some(sp);
+                self.pc = addr;
+            }
+        }
+    }
+
+    /// Saves the state of the machine, to carry on from later with `load_state`
+    /// 
+    /// ## Returns
+    /// 
+    /// A binary save state, which starts with a magic and the format version
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom(&assemble("loop: ADD V0 1\nJP loop").unwrap()).unwrap();
+    /// chip8.cycle().unwrap();
+    /// let state = chip8.save_state().unwrap();
+    /// for _ in 0..10 {
+    ///     chip8.cycle().unwrap();
+    /// }
+    /// 
+    /// chip8.load_state(&state).unwrap();
+    /// assert_eq!(chip8.get_registers()[0], 1);
+    /// ```
+    pub fn save_state(&self) -> Result<Vec<u8>, String> {
+        return self.save_state_as(Format::Binary);
+    }
+
+    /// Captures everything needed to carry on from this point later, like `save_state`, in a format of the caller's choosing
+    /// 
+    /// JSON states are much larger, but can be read and edited by hand, say to set a register while debugging. `load_state` reads either.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `format` - Binary or JSON; states have no text format
+    pub fn save_state_as(&self, format: Format) -> Result<Vec<u8>, String> {
+        let state = SavedState {
+            memory: self.memory.to_vec(),
+            initialized: self.initialized.to_vec(),
+            registers: self.registers,
+            index: self.index,
+            pc: self.pc,
+            stack: self.stack.clone(),
+            stack_in_memory: self.stack_in_memory,
+            font_addr: self.font_addr,
+            sp: self.sp,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            screen: self.screen.clone(),
+            keypad: self.keypad,
+            key_down: self.key_down,
+            unknown_opcodes: self.unknown_opcodes,
+            custom_ops: self.custom_ops,
+            schip: self.schip,
+            hires: self.hires,
+            rpl_flags: self.rpl_flags,
+            halted: self.halted,
+            frame_callback: self.frame_callback,
+            callback_sp: self.callback_sp,
+            frame_cycles: self.frame_cycles,
+            quirks: self.quirks,
+            rng: self.rng.clone(),
+        };
+        return encode(&state, format, STATE_MAGIC);
+    }
+
+    /// Puts the machine back the way it was when a state was saved
+    /// 
+    /// The state is checked before anything changes, so a bad one leaves the emulator as it was.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `bytes` - A state from `save_state`, or the same written as JSON
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let state: SavedState = decode(bytes, STATE_MAGIC)?;
+        if state.schip != self.schip {
+            return Err(format!("The state was saved with SUPER-CHIP {}, but it is {} here", if state.schip { "on" } else { "off" }, if self.schip { "on" } else { "off" }));
+        }
+        if state.memory.len() != self.memory.len() || state.initialized.len() != self.memory.len() {
+            return Err(format!("The state has {} bytes of memory, expected {}", state.memory.len(), self.memory.len()));
+        }
+        if state.screen.len() != self.screen.len() {
+            return Err(format!("The state has {} pixels, expected {}", state.screen.len(), self.screen.len()));
+        }
+        if state.sp > state.stack.len() || state.callback_sp.is_some_and(|sp| sp > state.stack.len()) {
+            return Err(format!("The state's stack pointer is past its {} levels", state.stack.len()));
+        }
+        if state.stack_in_memory && state.stack.len() > MAX_MEMORY_STACK_SIZE {
+            return Err(format!("The state's stack in memory has {} levels, but there is room for at most {}", state.stack.len(), MAX_MEMORY_STACK_SIZE));
+        }
+        if state.pc as usize > self.memory.len() - 2 || state.font_addr as usize + FONTSET_SIZE + BIG_FONTSET_SIZE > self.memory.len() {
+            return Err(String::from("The state's PC or font is outside memory"));
+        }
+        if let Some(key) = state.key_down.filter(|key| *key >= self.keypad.len()) {
+            return Err(format!("The state waits for key {}, but the keypad only has {}", key, self.keypad.len()));
+        }
+
+        self.memory.copy_from_slice(&state.memory);
+        self.initialized.copy_from_slice(&state.initialized);
+        self.registers = state.registers;
+        self.index = state.index;
+        self.pc = state.pc;
+        self.stack = state.stack;
+        self.stack_in_memory = state.stack_in_memory;
+        self.font_addr = state.font_addr;
+        self.sp = state.sp;
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.screen = state.screen;
+        if let Some(sources) = self.pixel_sources.as_mut() {
+            sources.fill(None);
+        }
+        self.keypad = state.keypad;
+        self.key_down = state.key_down;
+        self.unknown_opcodes = state.unknown_opcodes;
+        self.custom_ops = state.custom_ops;
+        self.hires = state.hires;
+        self.rpl_flags = state.rpl_flags;
+        self.halted = state.halted;
+        self.frame_callback = state.frame_callback;
+        self.callback_sp = state.callback_sp;
+        self.frame_cycles = state.frame_cycles;
+        self.quirks = state.quirks;
+        self.rng = state.rng;
+        // What ran before belongs to wherever the machine was, not to the state
+        self.pc_history.clear();
+        return Ok(());
+    }
+
+    /// Describes a save state without loading it
+    /// 
+    /// The state is decompressed and loaded into a new machine set up like the one that saved it, so it is only described if it would load.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `bytes` - A state from `save_state`, compressed or not, or the same written as JSON
+    pub fn inspect_state(bytes: &[u8]) -> Result<StateInfo, String> {
+        let format = Format::detect(bytes, STATE_MAGIC);
+        let compressed = format == Format::Binary && is_compressed(bytes);
+        let decompressed = if compressed { decompress(bytes)? } else { bytes.to_vec() };
+        let state: SavedState = decode(&decompressed, STATE_MAGIC)?;
+        let mut chip8 = Chip8::new();
+        if state.schip {
+            chip8.enable_schip();
+        }
+        if state.memory.len() == XO_CHIP_MEMORY_SIZE {
+            chip8.set_memory_size(XO_CHIP_MEMORY_SIZE)?;
+        }
+        chip8.load_state(&decompressed)?;
+        return Ok(StateInfo { format, compressed, size: bytes.len(), decompressed_size: decompressed.len(), schip: state.schip, memory_size: state.memory.len(), pc: state.pc });
+    }
+
+    /// Runs a frame further than the machine has got, with the keys held now, and puts it back as it was
+    /// 
+    /// Showing that frame rather than the current one takes a frame off the time between a key press and the screen answering it.
+    /// Nothing the frame ahead does is kept, including host calls, events, warnings, watchpoint hits, memory access counts and pixel sources.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `ticks` - Instructions to run
+    /// * `timer_ticks` - Times to count the timers down, which is usually how many times they were this frame
+    /// 
+    /// ## Returns
+    /// 
+    /// The screen as the frame ahead leaves it
+    pub fn run_ahead(&mut self, ticks: u64, timer_ticks: u64) -> Result<Vec<u8>, String> {
+        let state = self.save_state()?;
+        let (host_calls, diagnostics, watch_hits, reported) = (self.host_calls.len(), self.diagnostics.len(), self.watch_hits.len(), self.reported.clone());
+        let (events, fault, error, pc_history) = (self.events.len(), self.fault.clone(), self.error.clone(), self.pc_history.clone());
+        let (access_counts, pixel_sources) = (self.access_counts.take(), self.pixel_sources.take());
+
+        for _ in 0..ticks {
+            self.step();
+        }
+        for _ in 0..timer_ticks {
+            self.update_timers();
+        }
+        let screen = self.screen.clone();
+
+        self.host_calls.truncate(host_calls);
+        self.events.truncate(events);
+        self.diagnostics.truncate(diagnostics);
+        self.watch_hits.truncate(watch_hits);
+        self.reported = reported;
+        self.fault = fault;
+        self.error = error;
+        self.load_state(&state)?;
+        self.pc_history = pc_history;
+        self.access_counts = access_counts;
+        self.pixel_sources = pixel_sources;
+        return Ok(screen);
+    }
+
+    /// Lists every way another emulator's state differs from this one's, or nothing if they match
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `other` - The emulator to compare against
+    pub fn state_differences(&self, other: &Chip8) -> Vec<String> {
+        let mut differences = Vec::new();
+        let mut compare = |name: &str, this: String, that: String| {
+            if this != that {
+                differences.push(format!("{}: {} vs {}", name, this, that));
+            }
+        };
+
+        compare("PC", format!("{:03X}", self.pc), format!("{:03X}", other.pc));
+        compare("I", format!("{:03X}", self.index), format!("{:03X}", other.index));
+        for register in 0..REGISTER_COUNT {
+            compare(&format!("V{:X}", register), format!("{:02X}", self.registers[register]), format!("{:02X}", other.registers[register]));
+        }
+        compare("stack", format!("{:03X?}", &self.stack[..self.sp]), format!("{:03X?}", &other.stack[..other.sp]));
+        compare("delay timer", self.delay_timer.to_string(), other.delay_timer.to_string());
+        compare("sound timer", self.sound_timer.to_string(), other.sound_timer.to_string());
+        compare("keypad", format!("{:?}", self.keypad), format!("{:?}", other.keypad));
+        compare("key down", format!("{:?}", self.key_down), format!("{:?}", other.key_down));
+        compare("unknown opcodes", self.unknown_opcodes.to_string(), other.unknown_opcodes.to_string());
+        compare("high resolution", self.hires.to_string(), other.hires.to_string());
+        compare("halted", self.halted.to_string(), other.halted.to_string());
+        compare("RPL flags", format!("{:02X?}", self.rpl_flags), format!("{:02X?}", other.rpl_flags));
+        compare("per-frame subroutine", format!("{:03X?}", self.frame_callback), format!("{:03X?}", other.frame_callback));
+        compare("instructions into the frame", self.frame_cycles.to_string(), other.frame_cycles.to_string());
+
+        // Memory and the screen are too big to print, so only say where they start to differ
+        let memory: Vec<usize> = (0..self.memory.len().min(other.memory.len())).filter(|addr| self.memory[*addr] != other.memory[*addr]).collect();
+        if self.memory.len() != other.memory.len() {
+            differences.push(format!("memory: {} bytes against {}", self.memory.len(), other.memory.len()));
+        }
+        if let Some(first) = memory.first() {
+            differences.push(format!("memory: {} bytes differ, the first at {:03X}", memory.len(), first));
+        }
+        let pixels = self.screen.iter().zip(other.screen.iter()).filter(|(this, that)| this != that).count();
+        if pixels > 0 || self.screen.len() != other.screen.len() {
+            differences.push(format!("screen: {} pixels differ", pixels));
+        }
+        if self.rng != other.rng {
+            differences.push(String::from("RND: the random number generators are in different states"));
+        }
+        return differences;
+    }
+
+    pub fn clear_keypad(&mut self) {
+        self.keypad = [false; 16];
+    }
+
+    pub fn set_keypress(&mut self, key: usize) {
+        self.set_key(key, true);
+    }
+
+    /// Presses or lets go of a key, which stays that way until it is set again, for frontends that know when keys are let go
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `key` - The keypad key, from 0x0 to 0xF; anything past it isn't a key and is left alone
+    /// * `pressed` - Whether it is held down
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// // Counts in V1 the instructions run with key 5 held
+    /// let rom = assemble("LD V0 5\nloop: SKNP V0\nADD V1 1\nJP loop").unwrap();
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom(&rom).unwrap();
+    /// chip8.set_key(5, true);
+    /// for _ in 0..7 {
+    ///     chip8.cycle().unwrap();
+    /// }
+    /// chip8.set_key(5, false);
+    /// for _ in 0..6 {
+    ///     chip8.cycle().unwrap();
+    /// }
+    /// assert_eq!(chip8.get_registers()[1], 2);
+    /// ```
+    pub fn set_key(&mut self, key: usize, pressed: bool) {
+        if let Some(down) = self.keypad.get_mut(key) {
+            *down = pressed;
+        }
+    }
+
+    /// Runs one instruction
+    /// 
+    /// ## Returns
+    /// 
+    /// Whether it ran, or trapped on an unknown opcode. Or the error that stopped the machine, if one has, which it returns
+    /// again every time it is called after; a ROM that exits or runs into its limits stops without an error
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::chip8::{Chip8Error, CycleStatus};
+    /// use chip8_core::{assemble, Chip8};
+    /// 
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom(&assemble("LD I 0xFFE\nLD [I] V3").unwrap()).unwrap();
+    /// assert_eq!(chip8.cycle(), Ok(CycleStatus::Ran));
+    /// assert_eq!(chip8.cycle(), Err(Chip8Error::MemoryOutOfBounds { pc: 0x202, addr: 0x1000 }));
+    /// assert_eq!(chip8.cycle().unwrap_err().to_string(), "202: 1000 is past the end of memory");
+    /// assert!(chip8.is_halted());
+    /// ```
+    pub fn cycle(&mut self) -> Result<CycleStatus, Chip8Error> {
+        let executed = self.step();
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        return Ok(match (executed, self.trapped) {
+            (None, _) => CycleStatus::Halted,
+            (Some(_), Some((pc, opcode))) => CycleStatus::Trapped { pc, opcode },
+            (Some(_), None) => CycleStatus::Ran,
+        });
+    }
+
+    /// Runs one instruction, like `cycle`
+    /// 
+    /// ## Returns
+    /// 
+    /// The instruction as the interpreter read it, which shows as `LD V0 0x5` and the like, or `None` once the ROM has exited.
+    /// Nothing is formatted unless the caller does it, so running without a debugger costs no more than `cycle`.
+    pub fn step(&mut self) -> Option<Instruction> {
+        self.trapped = None;
+        if self.halted {
+            return None;
+        }
+        if self.limits.as_mut().and_then(ActiveLimits::count).is_some() {
+            self.halted = true;
+            self.events.push(CoreEvent::Halted { fault: true });
+            return None;
+        }
+
+        let (pc, registers) = (self.pc, self.registers);
+        if pc as usize + 1 >= self.memory.len() {
+            self.fail(pc, Chip8Error::PcOutOfBounds { pc }, String::from("runs off the end of memory"));
+            return None;
+        }
+        let opcode = self.fetch_instruction();
+        if self.pc_history_size > 0 {
+            if self.pc_history.len() >= self.pc_history_size {
+                self.pc_history.pop_front();
+            }
+            self.pc_history.push_back((pc, opcode));
+        }
+        let instruction = match self.extensions.iter_mut().find(|extension| (extension.matcher)(opcode)) {
+            Some(extension) => {
+                (extension.handler)(opcode, ExtensionState {
+                    registers: &mut self.registers,
+                    index: &mut self.index,
+                    pc: &mut self.pc,
+                    memory: &mut self.memory,
+                    delay_timer: &mut self.delay_timer,
+                    sound_timer: &mut self.sound_timer,
+                });
+                Instruction::Extension(opcode)
+            },
+            None => {
+                let instruction = Instruction::decode(opcode);
+                self.execute_instruction(instruction);
+                instruction
+            },
+        };
+
+        // Only jumps, calls and returns move the PC anywhere but on to the next instruction
+        if self.strict && !self.halted {
+            if self.pc % 2 == 1 {
+                self.abort(pc, format!("{} leaves the PC on an odd address, {:03X}", instruction, self.pc));
+            } else if self.pc as usize + 1 >= self.memory.len() {
+                self.abort(pc, format!("{} leaves the PC past the end of memory, at {:03X}", instruction, self.pc));
+            }
+        }
+
+        // Memory is watched as it is written, but registers are written all over, so they are compared once the instruction is done
+        for &watchpoint in &self.watchpoints {
+            if let Watchpoint::Register(register) = watchpoint {
+                if registers[register] != self.registers[register] {
+                    self.watch_hits.push(WatchHit { pc, watchpoint, old: registers[register], new: self.registers[register] });
+                }
+            }
+        }
+        self.frame_cycles += 1;
+        return Some(instruction);
+    }
+}
+
+// Private methods
+impl Chip8 {
+    /// Raises a diagnostic, unless the same one was already raised for this instruction
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `pc` - Address of the instruction
+    /// * `message` - What went wrong
+    fn warn(&mut self, pc: u16, message: String) {
+        if self.reported.insert((pc, message.clone())) {
+            self.diagnostics.push(Diagnostic { pc, message });
+        }
+    }
+
+    /// The key SKP or SKNP checks, from the low digit of Vx like the COSMAC VIP, warning when the rest of Vx is set
+    fn key_in(&mut self, x: usize) -> usize {
+        let value = self.registers[x];
+        if value > 0xF {
+            self.warn(self.pc - 2, format!("V{:X} is 0x{:02X}, past the keypad's 16 keys, so only key {:X} is checked", x, value, value & 0xF));
+        }
+        return (value & 0xF) as usize;
+    }
+
+    /// Stops the ROM in strict mode, putting the PC back on the instruction at fault so the machine shows where it went wrong
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `pc` - Address of the instruction
+    /// * `message` - What it did wrong
+    /// 
+    /// ## Returns
+    /// 
+    /// Whether the ROM was stopped, which it only is in strict mode
+    fn abort(&mut self, pc: u16, message: String) -> bool {
+        if !self.strict {
+            return false;
+        }
+        self.fail(pc, Chip8Error::Strict(Diagnostic { pc, message: message.clone() }), message);
+        return true;
+    }
+
+    /// Stops the ROM at something the machine can't carry on from, in strict mode or not, putting the PC back on the instruction at fault
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `pc` - Address of the instruction
+    /// * `error` - What stopped it
+    /// * `message` - What the instruction did, for the fault
+    fn fail(&mut self, pc: u16, error: Chip8Error, message: String) {
+        if self.error.is_none() {
+            self.fault = Some(Diagnostic { pc, message });
+            self.error = Some(error);
+            self.pc = pc;
+            self.events.push(CoreEvent::Halted { fault: true });
+        }
+        self.halted = true;
+    }
+
+    fn load_big_font(&mut self) {
+        let addr = self.font_addr as usize + FONTSET_SIZE;
+        self.memory[addr..addr + BIG_FONTSET_SIZE].copy_from_slice(&BIG_FONTSET);
+        self.initialized[addr..addr + BIG_FONTSET_SIZE].fill(true);
+    }
+
+    /// Runs the VF reset quirk after OR, AND or XOR, warning about it when pedantic
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `mnemonic` - The instruction that ran
+    fn logic_quirk(&mut self, mnemonic: &str) {
+        if self.pedantic {
+            let message = match self.quirks.vf_reset {
+                true => format!("{} resets VF to 0 here, but SUPER-CHIP left it alone", mnemonic),
+                false => format!("{} leaves VF alone here, but the COSMAC VIP reset it to 0", mnemonic),
+            };
+            self.warn(self.pc - 2, message);
+        }
+        if self.quirks.vf_reset {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    /// Warns that a load or store may not leave I where the ROM expects
+    fn warn_memory_quirk(&mut self, mnemonic: &str) {
+        let message = match self.quirks.memory_increments_i {
+            true => format!("{} moves I past the last register, which SUPER-CHIP didn't", mnemonic),
+            false => format!("{} leaves I where it was, but the COSMAC VIP moved it past the last register", mnemonic),
+        };
+        self.warn(self.pc - 2, message);
+    }
+
+    /// Flips one pixel of a SUPER-CHIP sprite, which is a 2x2 block in low resolution
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `x` - Column, in the current resolution
+    /// * `y` - Row, in the current resolution
+    /// 
+    /// ## Returns
+    /// 
+    /// Whether a lit pixel was erased
+    fn flip_pixel(&mut self, x: usize, y: usize) -> bool {
+        if self.hires {
+            let index = y * self.screen_width + x;
+            self.screen[index] ^= 1;
+            self.note_pixel_source(index);
+            return self.screen[index] == 0;
+        }
+
+        let index = y * 2 * self.screen_width + x * 2;
+        let erased = self.screen[index] == 1;
+        for offset in [0, 1, self.screen_width, self.screen_width + 1] {
+            self.screen[index + offset] ^= 1;
+            self.note_pixel_source(index + offset);
+        }
+        return erased;
+    }
+
+    /// Draws a sprite the SUPER-CHIP way, clipping it at the edges of the screen
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `x` - Column of the top left corner, which wraps around the screen
+    /// * `y` - Row of the top left corner, which wraps around the screen
+    /// * `n` - Number of rows; 0 draws a 16x16 sprite in high resolution, or 8x16 in low
+    /// 
+    /// ## Returns
+    /// 
+    /// VF: in high resolution, how many rows hit a lit pixel or fell off the bottom, otherwise 1 if any lit pixel was erased
+    fn draw_schip_sprite(&mut self, x: usize, y: usize, n: usize) -> u8 {
+        let (width, height) = if self.hires { (HIRES_WIDTH, HIRES_HEIGHT) } else { (SCREEN_WIDTH, SCREEN_HEIGHT) };
+        let (x, y) = (x % width, y % height);
+        let (rows, bytes_per_row) = self.sprite_shape(n);
+
+        let mut collided_rows = 0;
+        for line in 0..rows {
+            if y + line >= height {
+                if self.hires {
+                    collided_rows += 1;
+                }
+                continue;
+            }
+
+            let mut collided = false;
+            for byte in 0..bytes_per_row {
+                let row = self.read_memory(self.index as usize + line * bytes_per_row + byte);
+                for col in 0..8 {
+                    let column = x + byte * 8 + col;
+                    if (row & (0x80 >> col)) != 0 && column < width {
+                        collided |= self.flip_pixel(column, y + line);
+                    }
+                }
+            }
+            collided_rows += collided as u8;
+        }
+
+        if self.hires {
+            return collided_rows;
+        }
+        return (collided_rows > 0) as u8;
+    }
+
+    /// Scrolls the SUPER-CHIP screen, filling the pixels it uncovers with black
+    /// 
+    /// Distances are in the 128x64 buffer's pixels, so low resolution scrolls by half its pixels like SUPER-CHIP 1.1 did.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `right` - Pixels to scroll right, or left if negative
+    /// * `down` - Pixels to scroll down
+    fn scroll(&mut self, right: isize, down: usize) {
+        let (width, height) = (self.screen_width, self.screen_height);
+        self.screen = scrolled(&self.screen, width, height, right, down);
+        if let Some(sources) = self.pixel_sources.as_mut() {
+            *sources = scrolled(sources, width, height, right, down);
+        }
+    }
+
+    /// Records the sprite draw running now as the source of a pixel it just flipped, or forgets the source if it went dark
+    fn note_pixel_source(&mut self, index: usize) {
+        if let Some(sources) = self.pixel_sources.as_mut() {
+            sources[index] = (self.screen[index] == 1).then_some(PixelSource { pc: self.pc - 2, sprite: self.index });
+        }
+    }
+
+    fn fetch_instruction(&mut self) -> u16 {
+        let pc = self.pc as usize;
+        let byte1 = self.memory[pc] as u16;
+        let byte2 = self.memory[pc + 1] as u16;
+
+        if self.poison_memory && !(self.initialized[pc] && self.initialized[pc + 1]) {
+            self.warn(pc as u16, String::from("runs memory that was never loaded or written as an instruction"));
+        }
+
+        if let Some(counts) = self.access_counts.as_mut() {
+            counts.fetches[pc] += 1;
+            counts.fetches[pc + 1] += 1;
+        }
+
+        self.pc = self.pc.wrapping_add(2); // Because one instruction is two bytes
+        return (byte1 << 8) | byte2;
+    }
+
+    /// Reads a byte of data, counting the access if asked to
+    fn read_memory(&mut self, addr: usize) -> u8 {
+        if addr >= self.memory.len() {
+            let pc = self.pc - 2;
+            self.fail(pc, Chip8Error::MemoryOutOfBounds { pc, addr }, format!("reads {:03X}, past the end of memory", addr));
+            return 0;
+        }
+        if let Some(counts) = self.access_counts.as_mut() {
+            counts.reads[addr] += 1;
+        }
+        if self.poison_memory && !self.initialized[addr] {
+            // The instruction doing the read has already been fetched. Only its first bad address is reported,
+            // so FX65 or a sprite reading several bytes past the end of the ROM raises one diagnostic rather than one per byte
+            let pc = self.pc - 2;
+            if self.reported.insert((pc, String::from("uninitialized read"))) {
+                self.diagnostics.push(Diagnostic { pc, message: format!("reads {:03X}, which was never loaded or written", addr) });
+            }
+        }
+        return self.memory[addr];
+    }
+
+    /// Writes a byte of data, counting the access if asked to
+    fn write_memory(&mut self, addr: usize, value: u8) {
+        if addr >= self.memory.len() {
+            let pc = self.pc - 2;
+            self.fail(pc, Chip8Error::MemoryOutOfBounds { pc, addr }, format!("writes {:03X}, past the end of memory", addr));
+            return;
+        }
+        if addr < BOOT_SECTOR && self.abort(self.pc - 2, format!("writes {:03X}, which belongs to the interpreter", addr)) {
+            return;
+        }
+        if let Some(counts) = self.access_counts.as_mut() {
+            counts.writes[addr] += 1;
+        }
+        if self.memory[addr] != value && self.watchpoints.contains(&Watchpoint::Memory(addr as u16)) {
+            self.watch_hits.push(WatchHit { pc: self.pc - 2, watchpoint: Watchpoint::Memory(addr as u16), old: self.memory[addr], new: value });
+        }
+        self.memory[addr] = value;
+        self.initialized[addr] = true;
+    }
+
+    fn execute_instruction(&mut self, instruction: Instruction) {
+        match instruction {
+            Instruction::Nop => {},
+
+            Instruction::Clear => {
+                self.screen.fill(0);
+                if let Some(sources) = self.pixel_sources.as_mut() {
+                    sources.fill(None);
+                }
+            },
+
+            Instruction::Return => {
+                let pc = self.pc - 2;
+                if let Some(addr) = self.pop_stack() {
+                    self.pc = addr;
+                } else if !self.abort(pc, String::from("RET with nothing on the stack to return to")) {
+                    self.fail(pc, Chip8Error::StackUnderflow { pc }, String::from("RET with nothing on the stack to return to"));
+                }
+
+                // Returning from the per-frame subroutine lets the next tick call it again
+                if self.callback_sp == Some(self.sp) {
+                    self.callback_sp = None;
+                }
+            },
+
+            Instruction::ScrollDown { n } if self.schip => self.scroll(0, n as usize),
+            Instruction::ScrollRight if self.schip => self.scroll(4, 0),
+            Instruction::ScrollLeft if self.schip => self.scroll(-4, 0),
+            Instruction::Exit if self.schip => {
+                self.halted = true;
+                self.events.push(CoreEvent::Halted { fault: false });
+            },
+            Instruction::LowRes if self.schip => self.hires = false,
+            Instruction::HighRes if self.schip => self.hires = true,
+
+            Instruction::FrameOff if self.custom_ops => self.frame_callback = None,
+            Instruction::FrameI if self.custom_ops => self.frame_callback = Some(self.index),
+
+            Instruction::Jump { addr } => self.pc = addr,
+
+            Instruction::Call { addr } => {
+                if self.push_stack(self.pc) {
+                    if self.sp + 1 >= self.stack.len() {
+                        self.warn(self.pc - 2, format!("this call nearly fills the stack, which has room for {} levels", self.stack.len()));
+                    }
+                    self.pc = addr;
+                } else {
+                    let (pc, message) = (self.pc - 2, format!("CALL with the stack full, at {} levels", self.stack.len()));
+                    if !self.abort(pc, message.clone()) {
+                        self.fail(pc, Chip8Error::StackOverflow { pc }, message);
+                    }
+                }
+            },
+
+            Instruction::SkipEqualByte { x, byte } => {
+                if self.registers[x] == byte {
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            },
+
+            Instruction::SkipNotEqualByte { x, byte } => {
+                if self.registers[x] != byte {
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            },
+
+            Instruction::SkipEqual { x, y } => {
+                if self.registers[x] == self.registers[y] {
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            },
+
+            Instruction::LoadByte { x, byte } => self.registers[x] = byte,
+
+            // Handles overflow
+            Instruction::AddByte { x, byte } => self.registers[x] = self.registers[x].wrapping_add(byte),
+
+            Instruction::Load { x, y } => self.registers[x] = self.registers[y],
+
+            Instruction::Or { x, y } => {
+                self.registers[x] |= self.registers[y];
+                self.logic_quirk("OR");
+            },
+
+            Instruction::And { x, y } => {
+                self.registers[x] &= self.registers[y];
+                self.logic_quirk("AND");
+            },
+
+            Instruction::Xor { x, y } => {
+                self.registers[x] ^= self.registers[y];
+                self.logic_quirk("XOR");
+            },
+
+            Instruction::Add { x, y } => {
+                let (sum, carry) = self.registers[x].overflowing_add(self.registers[y]);
+
+                self.registers[x] = sum;
+                self.registers[0xF] = if carry {1} else {0};
+            },
+
+            Instruction::Sub { x, y } => {
+                let (sub, carry) = self.registers[x].overflowing_sub(self.registers[y]);
+
+                self.registers[x] = sub;
+                self.registers[0xF] = if carry {0} else {1};
+            },
+
+            Instruction::ShiftRight { x, y } => {
+                if self.pedantic && x != y {
+                    let message = match self.quirks.shift_uses_vy {
+                        true => format!("SHR shifts V{:X} into V{:X} here, but SUPER-CHIP shifted V{:X} in place", y, x, x),
+                        false => format!("SHR shifts V{:X} in place here, but the COSMAC VIP shifted V{:X} into it", x, y),
+                    };
+                    self.warn(self.pc - 2, message);
+                }
+
+                // The COSMAC VIP shifted Vy, leaving the result in Vx
+                if self.quirks.shift_uses_vy {
+                    self.registers[x] = self.registers[y];
+                }
+
+                self.registers[0xF] = self.registers[x] & 1;
+                self.registers[x] >>= 1;
+            },
+
+            Instruction::SubN { x, y } => {
+                let (sub, carry) = self.registers[y].overflowing_sub(self.registers[x]);
+
+                self.registers[x] = sub;
+                self.registers[0xF] = if carry {0} else {1};
+            },
+
+            Instruction::ShiftLeft { x, y } => {
+                if self.pedantic && x != y {
+                    let message = match self.quirks.shift_uses_vy {
+                        true => format!("SHL shifts V{:X} into V{:X} here, but SUPER-CHIP shifted V{:X} in place", y, x, x),
+                        false => format!("SHL shifts V{:X} in place here, but the COSMAC VIP shifted V{:X} into it", x, y),
+                    };
+                    self.warn(self.pc - 2, message);
+                }
+
+                // The COSMAC VIP shifted Vy, leaving the result in Vx
+                if self.quirks.shift_uses_vy {
+                    self.registers[x] = self.registers[y];
+                }
+
+                self.registers[0xF] = (self.registers[x] >> 7) & 1;
+                self.registers[x] <<= 1;
+            },
+
+            Instruction::SkipNotEqual { x, y } => {
+                if self.registers[x] != self.registers[y] {
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            },
+
+            Instruction::LoadI { addr } => self.index = addr,
+
+            Instruction::JumpV0 { addr } => {
+                let x = (addr >> 8) as usize;
+                if self.pedantic && x != 0 {
+                    let message = match self.quirks.jump_uses_vx {
+                        true => format!("JP V0 adds V{:X} here, but the COSMAC VIP added V0", x),
+                        false => format!("JP V0 adds V0 here, but SUPER-CHIP added V{:X}", x),
+                    };
+                    self.warn(self.pc - 2, message);
+                }
+
+                // BXNN jumps to XNN plus VX
+                let offset = if self.quirks.jump_uses_vx { self.registers[x] } else { self.registers[0] };
+                self.pc = addr + offset as u16;
+            },
+
+            Instruction::Random { x, byte } => {
+                let rand = self.rng.gen::<u8>();
+                self.registers[x] = rand & byte;
+            },
+
+            Instruction::Draw { x, y, n } => {
+                let (vx, vy) = (x, y);
+                let x = self.registers[vx] as usize;
+                let y = self.registers[vy] as usize;
+                let n = n as usize;
+
+                // Dxy0 draws a 16x16 sprite on the SUPER-CHIP
+                if self.schip {
+                    self.registers[0xF] = self.draw_schip_sprite(x, y, n);
+                    return;
+                }
+
+                if self.pedantic && (x % SCREEN_WIDTH + 8 > SCREEN_WIDTH || y % SCREEN_HEIGHT + n > SCREEN_HEIGHT) {
+                    let message = match self.quirks.clip_sprites {
+                        true => "DRW cuts the sprite off at the edge of the screen, where other interpreters wrap it around",
+                        false => "DRW wraps the sprite around the edge of the screen, where other interpreters clip it",
+                    };
+                    self.warn(self.pc - 2, String::from(message));
+                }
+
+                self.registers[0xF] = 0;
+
+                for line in 0..n {
+                    let row = self.read_memory(self.index as usize + line);
+
+                    for col in 0..8 {
+                        // Sprites always wrap where they start. Past the right or bottom edge they are cut off when clipping,
+                        // or wrap around to the left or top on their own row or column
+                        let (column, screen_row) = (x % SCREEN_WIDTH + col, y % SCREEN_HEIGHT + line);
+                        if self.quirks.clip_sprites && (column >= SCREEN_WIDTH || screen_row >= SCREEN_HEIGHT) {
+                            continue;
+                        }
+
+                        // Check if each bit of the row is set
+                        if (row & (0x80 >> col)) != 0 {
+                            let index = column % SCREEN_WIDTH + (screen_row % SCREEN_HEIGHT) * SCREEN_WIDTH;
+
+                            // If any existing pixels are erased, set VF to 1
+                            if self.screen[index] == 1 {
+                                self.registers[0xF] = 1;
+                            }
+
+                            self.screen[index] ^= 1;
+                            self.note_pixel_source(index);
+                        }
+                    }
+                }
+            },
+
+            Instruction::SkipKey { x } => {
+                if self.keypad[self.key_in(x)] {
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            },
+
+            Instruction::SkipNotKey { x } => {
+                if !self.keypad[self.key_in(x)] {
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            },
+
+            Instruction::LoadDelay { x } => self.registers[x] = self.delay_timer,
+
+            Instruction::WaitKey { x } => {
+                let pressed = (0..16).rev().find(|key| self.keypad[*key]);
+                let key = match (self.quirks.key_wait_on_press, self.key_down) {
+                    (true, _) => pressed,
+                    // The COSMAC VIP took a key once it was let go, so one held down isn't taken again by the next FX0A
+                    (false, Some(key)) if !self.keypad[key] => {
+                        self.key_down = None;
+                        Some(key)
+                    },
+                    (false, key_down) => {
+                        self.key_down = key_down.or(pressed);
+                        None
+                    },
+                };
+
+                // Until there is a key, decrement PC to repeat instruction
+                if let Some(key) = key {
+                    self.registers[x] = key as u8;
+                } else {
+                    self.pc -= 2;
+                    if self.events.last() != Some(&CoreEvent::WaitingForKey) {
+                        self.events.push(CoreEvent::WaitingForKey);
+                    }
+                }
+            },
+
+            Instruction::SetDelay { x } => {
+                self.delay_timer = self.registers[x];
+                self.events.push(CoreEvent::DelaySet(self.delay_timer));
+            },
+
+            Instruction::SetSound { x } => self.sound_timer = self.registers[x],
+
+            Instruction::AddI { x } => {
+                let sum = self.index as usize + self.registers[x] as usize;
+                let size = self.memory.len();
+                if sum >= size && self.abort(self.pc - 2, format!("ADD I, V{:X} takes I past the end of memory, to {:03X}", x, sum)) {
+                    return;
+                }
+                if sum >= size {
+                    self.warn(self.pc - 2, String::from("I went past the end of memory and wrapped around to 0x000"));
+                }
+                self.index = (sum % size) as u16;
+            },
+
+            Instruction::LoadFont { x } => self.index = self.font_addr + self.registers[x] as u16 * 5,
+
+            Instruction::LoadBigFont { x } if self.schip => {
+                self.index = self.font_addr + FONTSET_SIZE as u16 + (self.registers[x] & 0xF) as u16 * 10;
+            },
+
+            Instruction::StoreBcd { x } => {
+                let value = self.registers[x];
+
+                self.write_memory(self.index as usize, value / 100);
+                self.write_memory(self.index as usize + 1, (value / 10) % 10);
+                self.write_memory(self.index as usize + 2, (value % 100) % 10);
+            },
+
+            Instruction::StoreRegisters { x } => {
+                if self.pedantic {
+                    self.warn_memory_quirk("LD [I], Vx");
+                }
+
+                for i in 0..=x {
+                    self.write_memory(self.index as usize + i, self.registers[i]);
+                }
+
+                if self.quirks.memory_increments_i {
+                    self.index = self.index.wrapping_add(x as u16 + 1);
+                }
+            },
+
+            Instruction::LoadRegisters { x } => {
+                if self.pedantic {
+                    self.warn_memory_quirk("LD Vx, [I]");
+                }
+
+                for i in 0..=x {
+                    self.registers[i] = self.read_memory(self.index as usize + i);
+                }
+
+                if self.quirks.memory_increments_i {
+                    self.index = self.index.wrapping_add(x as u16 + 1);
+                }
+            },
+
+            // At most V7 fits in the RPL flags
+            Instruction::SaveFlags { x } if self.schip => {
+                let count = (x + 1).min(RPL_FLAG_COUNT);
+                self.rpl_flags[..count].copy_from_slice(&self.registers[..count]);
+            },
+
+            Instruction::LoadFlags { x } if self.schip => {
+                let count = (x + 1).min(RPL_FLAG_COUNT);
+                self.registers[..count].copy_from_slice(&self.rpl_flags[..count]);
+            },
+
+            Instruction::Pass if self.custom_ops => self.host_calls.push(HostCall::Pass),
+            Instruction::Fail if self.custom_ops => self.host_calls.push(HostCall::Fail(self.registers[0])),
+            Instruction::Debug if self.custom_ops => {
+                let registers: Vec<String> = self.registers.iter().enumerate().map(|(i, value)| format!("V{:X}={:02X}", i, value)).collect();
+                self.host_calls.push(HostCall::Debug(format!("PC={:03X} I={:03X} {}", self.pc - 2, self.index, registers.join(" "))));
+            },
+
+            // Unknown opcodes, and ones this platform doesn't have
+            _ => {
+                self.unknown_opcodes += 1;
+                let (pc, opcode) = (self.pc - 2, instruction.encode());
+                let message = format!("{:04X} isn't an instruction on this platform", opcode);
+                if !self.abort(pc, message.clone()) {
+                    match self.opcode_policy {
+                        OpcodePolicy::Ignore => {},
+                        OpcodePolicy::Halt => self.fail(pc, Chip8Error::UnknownOpcode { pc, opcode }, message),
+                        OpcodePolicy::Trap => self.trapped = Some((pc, opcode)),
+                    }
+                }
+            },
+        }
+    }
+
+    fn push_stack(&mut self, value: u16) -> bool {
+        if self.sp < self.stack.len() {
+            if self.stack_in_memory {
+                let addr = VIP_STACK_ADDR + self.sp * 2;
+                self.memory[addr..addr + 2].copy_from_slice(&value.to_be_bytes());
+                self.initialized[addr..addr + 2].fill(true);
+            } else {
+                self.stack[self.sp] = value;
+            }
+            self.sp += 1;
+            return true;
+        } else {
+            return false;
+        }
+    }
+
+    // Using "pop" very liberally here; it doesn't actually return the value at the top of the stack, it pops a value and then return the new top of the stack
+    fn pop_stack(&mut self) -> Option<u16> {
+        if self.sp > 0 {
+            self.sp -= 1;
+            if self.stack_in_memory {
+                let addr = VIP_STACK_ADDR + self.sp * 2;
+                return Some(u16::from_be_bytes([self.memory[addr], self.memory[addr + 1]]));
+            }
+            return Some(self.stack[self.sp]);
+        } else {
+            return None;
+        }
+    }
+}
+
+/// Signatures of the files most often loaded as a ROM by mistake, and what they are
+const FILE_SIGNATURES: [(&[u8], &str); 8] = [
+    (b"PK\x03\x04", "a zip archive"),
+    (b"\x1F\x8B", "a gzip archive"),
+    (b"7z\xBC\xAF\x27\x1C", "a 7-Zip archive"),
+    (b"Rar!\x1A\x07", "a RAR archive"),
+    (b"\x7FELF", "a program for this computer"),
+    (b"\x89PNG", "a PNG image"),
+    (b"\xFF\xD8\xFF", "a JPEG image"),
+    (b"%PDF", "a PDF document"),
+];
+
+/// Shortest file taken for text when every byte is printable, as short programs can be by chance
+const MIN_TEXT_SIZE: usize = 16;
+
+/// Checks whether a file looks like a CHIP-8 ROM, to warn about one loaded by mistake
+/// 
+/// Any bytes can be run, so this only catches files that are plainly something else: archives, images, programs for
+/// the computer itself, and text like assembler source.
+/// 
+/// ## Arguments
+/// 
+/// * `rom` - The contents of the file
+/// 
+/// ## Returns
+/// 
+/// What the file looks like instead, if it doesn't look like a ROM
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8_core::chip8::sniff_rom;
+/// 
+/// assert_eq!(sniff_rom(include_bytes!("../../c8games/PONG")), None);
+/// assert_eq!(sniff_rom(b"loop: JP loop ; waits forever\n").as_deref(), Some("looks like text, not a ROM; if it is assembler source, build it with asm first"));
+/// ```
+pub fn sniff_rom(rom: &[u8]) -> Option<String> {
+    if rom.is_empty() {
+        return Some(String::from("is empty"));
+    }
+    if let Some((_, kind)) = FILE_SIGNATURES.iter().find(|(signature, _)| rom.starts_with(signature)) {
+        let advice = if kind.ends_with("archive") { "; unpack it and load the ROM inside" } else { "" };
+        return Some(format!("looks like {}, not a ROM{}", kind, advice));
+    }
+
+    // Text saved by some editors starts with a byte order mark
+    let text = rom.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(rom);
+    let printable = |byte: &u8| byte.is_ascii_graphic() || matches!(byte, b' ' | b'\t' | b'\n' | b'\r');
+    if text.len() >= MIN_TEXT_SIZE && text.iter().all(printable) {
+        return Some(String::from("looks like text, not a ROM; if it is assembler source, build it with asm first"));
+    }
+    return None;
+}
+
+/// Disassembles a Chip-8 program into a human-readable format
+/// 
+/// Opcodes that aren't instructions are written as `.dw` data, and an odd byte at the end as `.db`, so the output assembles back into the same bytes.
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program to disassemble, as a byte array
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8_core::{assemble, disassemble};
+/// 
+/// let program = [0x60, 0x05, 0xA2, 0x1E, 0xD0, 0x15];
+/// assert_eq!(disassemble(&program), "LD V0 0x5\nLD I 0x21E\nDRW V0 V1 0x5\n");
+/// 
+/// // What comes out assembles back into the same bytes
+/// assert_eq!(assemble(&disassemble(&program)).unwrap(), program);
+/// ```
+pub fn disassemble(program: &[u8]) -> String {
+    let mut str_buffer = String::new();
+    let opcodes = program.chunks_exact(2);
+    let remainder = opcodes.remainder();
+    for opcode in opcodes {
+        match Instruction::decode(u16::from_be_bytes([opcode[0], opcode[1]])) {
+            Instruction::Unknown(opcode) => str_buffer.push_str(&format!(".dw 0x{:04X}", opcode)),
+            instruction => str_buffer.push_str(&instruction.to_string()),
+        }
+        str_buffer.push('\n');
+    }
+    if let [byte] = remainder {
+        str_buffer.push_str(&format!(".db 0x{:02X}\n", byte));
+    }
+    return str_buffer;
+}
+
+/// Disassembles a Chip-8 program like `disassemble`, telling its code from its data and naming the places it jumps and calls to
+/// 
+/// Only the instructions the program can reach from 0x200 are written as instructions, found with `trace_code`.
+/// Everything else, like sprites, is written as `.db` bytes, so it doesn't come out as instructions that make no sense.
+/// Every `JP`, `CALL` and `JP V0` target in the program gets a label like `L_0234:` before it, and is referred to by that name,
+/// so the output can still be assembled once code is added or moved around. Targets outside the program stay as addresses.
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program to disassemble, as a byte array
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8_core::assemble;
+/// use chip8_core::chip8::disassemble_with_labels;
+/// 
+/// let program = assemble("CALL sub\nloop: JP loop\nsub: CLS\nRET\n.db 0x3C 0x42").unwrap();
+/// assert_eq!(disassemble_with_labels(&program), "CALL L_0204\nL_0202:\nJP L_0202\nL_0204:\nCLS\nRET\n.db 0x3C 0x42\n");
+/// assert_eq!(assemble(&disassemble_with_labels(&program)).unwrap(), program);
+/// ```
+pub fn disassemble_with_labels(program: &[u8]) -> String {
+    let starts = trace_code(program);
+    let decode = |i: usize| Instruction::decode(u16::from_be_bytes([program[i], program[i + 1]]));
+    let labels: HashSet<u16> = (0..program.len())
+        .filter(|i| starts[*i])
+        .filter_map(|i| match decode(i) {
+            Instruction::Jump { addr } | Instruction::Call { addr } | Instruction::JumpV0 { addr } => Some(addr),
+            _ => None,
+        })
+        .filter(|addr| (BOOT_SECTOR..BOOT_SECTOR + program.len()).contains(&(*addr as usize)))
+        .collect();
+    let label_at = |i: usize| labels.contains(&((BOOT_SECTOR + i) as u16));
+
+    let mut str_buffer = String::new();
+    let mut i = 0;
+    while i < program.len() {
+        if label_at(i) {
+            str_buffer.push_str(&format!("L_{:04X}:\n", BOOT_SECTOR + i));
+        }
+
+        // An instruction can only be written whole, so a label pointing into its second byte leaves it as data
+        if starts[i] && !label_at(i + 1) {
+            match decode(i) {
+                Instruction::Unknown(opcode) => str_buffer.push_str(&format!(".dw 0x{:04X}", opcode)),
+                Instruction::Jump { addr } if labels.contains(&addr) => str_buffer.push_str(&format!("JP L_{:04X}", addr)),
+                Instruction::Call { addr } if labels.contains(&addr) => str_buffer.push_str(&format!("CALL L_{:04X}", addr)),
+                Instruction::JumpV0 { addr } if labels.contains(&addr) => str_buffer.push_str(&format!("JP V0 L_{:04X}", addr)),
+                instruction => str_buffer.push_str(&instruction.to_string()),
+            }
+            str_buffer.push('\n');
+            i += 2;
+            continue;
+        }
+
+        // Bytes that never run are data, up to 8 to a line, until the next label or instruction
+        str_buffer.push_str(&format!(".db 0x{:02X}", program[i]));
+        i += 1;
+        let mut count = 1;
+        while i < program.len() && count < 8 && !starts[i] && !label_at(i) {
+            str_buffer.push_str(&format!(" 0x{:02X}", program[i]));
+            i += 1;
+            count += 1;
+        }
+        str_buffer.push('\n');
+    }
+    return str_buffer;
+}
+
+/// Marks the start of every instruction reachable from the entry point, following jumps, calls and skips
+/// 
+/// `JP V0` is only followed to its base address, since where it lands depends on V0 at run time.
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program, as it is loaded at 0x200
+/// 
+/// ## Returns
+/// 
+/// Whether an instruction starts at each byte of the program
+pub fn trace_code(program: &[u8]) -> Vec<bool> {
+    let mut starts = vec![false; program.len()];
+    let mut pending = vec![BOOT_SECTOR];
+
+    while let Some(addr) = pending.pop() {
+        if addr < BOOT_SECTOR {
+            continue;
+        }
+
+        let offset = addr - BOOT_SECTOR;
+        if offset + 1 >= program.len() || starts[offset] {
+            continue;
+        }
+
+        starts[offset] = true;
+
+        let opcode = (program[offset] as u16) << 8 | program[offset + 1] as u16;
+        let target = (opcode & 0x0FFF) as usize;
+        match (opcode & 0xF000) >> 12 {
+            // RET and the SUPER-CHIP's EXIT end the path
+            0 if opcode == 0x00EE || opcode == 0x00FD => {},
+
+            // JP addr
+            1 => pending.push(target),
+
+            // CALL addr returns to the next instruction
+            2 => {
+                pending.push(target);
+                pending.push(addr + 2);
+            },
+
+            // JP V0, addr can only be followed to its base address
+            0xB => pending.push(target),
+
+            // Skips can land on either of the next two instructions
+            3 | 4 | 5 | 9 | 0xE => {
+                pending.push(addr + 2);
+                pending.push(addr + 4);
+            },
+
+            _ => pending.push(addr + 2),
+        }
+    }
+
+    return starts;
+}
+
+
+/// A problem in the assembler source that doesn't stop it from being assembled
+#[derive(Debug)]
+pub struct AssembleWarning {
+    /// The line the warning refers to, starting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+
+/// A mistake in the assembler source that stops it from being assembled
+#[derive(Debug, PartialEq)]
+pub struct AssembleError {
+    /// The line the error is on, starting from 1
+    pub line: usize,
+    /// The part of the line that is wrong, uppercased like the rest of the source, or the mnemonic when an operand is missing
+    pub token: String,
+    pub message: String,
+}
+
+impl AssembleError {
+    pub fn new(line: usize, token: &str, message: String) -> Self {
+        return Self { line, token: token.to_string(), message };
+    }
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "line {}: {} {}", self.line, self.token, self.message);
+    }
+}
+
+/// Assembles a Chip-8 program into machine code
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program to assemble, as a string read from a file
+/// 
+/// ## Returns
+/// 
+/// The machine code, or every mistake that stopped it from being assembled
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8_core::assemble;
+/// 
+/// let rom = assemble("start: CLS\nLD V0 0x2A ; a comment\nJP start").unwrap();
+/// assert_eq!(rom, [0x00, 0xE0, 0x60, 0x2A, 0x12, 0x00]);
+/// 
+/// let errors = assemble("LD V0 300\nJP nowhere").unwrap_err();
+/// assert_eq!(errors[0].to_string(), "line 1: 300 doesn't fit in 8 bits");
+/// assert_eq!(errors[1].to_string(), "line 2: NOWHERE isn't a label, a number or a name given to one by equ");
+/// ```
+pub fn assemble(program: &str) -> Result<Vec<u8>, Vec<AssembleError>> {
+    return assemble_with_warnings(program).map(|(bytes, _)| bytes);
+}
+
+/// Assembles a Chip-8 program into machine code, also reporting likely mistakes in the source
+/// 
+/// Labels are defined with `NAME:`, either on their own line or before an instruction, and can be used in place of an address.
+/// Warnings are raised for labels that are never referenced and for code that can't be reached because it follows a jump.
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program to assemble, as a string read from a file
+/// 
+/// ## Returns
+/// 
+/// The machine code and any warnings about the source, or every mistake that stopped it from being assembled
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8_core::chip8::assemble_with_warnings;
+/// 
+/// let (rom, warnings) = assemble_with_warnings("unused: CLS").unwrap();
+/// assert_eq!(rom, [0x00, 0xE0]);
+/// assert_eq!(warnings[0].to_string(), "line 1: label UNUSED is never referenced");
+/// ```
+pub fn assemble_with_warnings(program: &str) -> Result<(Vec<u8>, Vec<AssembleWarning>), Vec<AssembleError>> {
+    return assemble_checked(program).map(|pass| (pass.bytes, pass.warnings));
+}
+
+/// Assembles a Chip-8 program into machine code, along with a listing of where each line went
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program to assemble, as a string read from a file
+/// 
+/// ## Returns
+/// 
+/// The machine code and its listing, or every mistake that stopped it from being assembled
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8_core::chip8::assemble_listing;
+/// 
+/// let (rom, listing) = assemble_listing("start: CLS\n.db 1 2 3\nJP start").unwrap();
+/// assert_eq!(listing.lines[1].addr, 0x202);
+/// assert_eq!(listing.lines[1].bytes, [1, 2, 3]);
+/// assert_eq!(listing.symbols, [(String::from("START"), 0x200)]);
+/// assert_eq!(rom.len(), 7);
+/// ```
+pub fn assemble_listing(program: &str) -> Result<(Vec<u8>, Listing), Vec<AssembleError>> {
+    let pass = assemble_checked(program)?;
+    let lines = program
+        .lines()
+        .zip(pass.spans.iter())
+        .enumerate()
+        .map(|(index, (text, span))| ListingLine {
+            line: index + 1,
+            addr: (BOOT_SECTOR + span.start) as u16,
+            bytes: pass.bytes[span.clone()].to_vec(),
+            text: text.to_string(),
+        })
+        .collect();
+    let mut symbols: Vec<(String, u16)> = pass.definitions.into_iter().map(|(name, addr, _)| (name, addr)).collect();
+    symbols.sort_by_key(|(_, addr)| *addr);
+    return Ok((pass.bytes, Listing { lines, symbols }));
+}
+
+/// Runs both passes of the assembler, checking for mistakes and likely mistakes
+fn assemble_checked(program: &str) -> Result<AssemblePass, Vec<AssembleError>> {
+    // The first pass only finds where labels and jump targets land, which the second pass needs to encode references
+    let first = assemble_pass(program, &HashMap::new(), &HashSet::new());
+
+    let labels: HashMap<String, u16> = first.definitions.iter().map(|(name, addr, _)| (name.clone(), *addr)).collect();
+    let mut targets = first.targets;
+    targets.extend(labels.values());
+
+    let mut second = assemble_pass(program, &labels, &targets);
+
+    for (name, _, line) in first.definitions.iter() {
+        if !second.references.contains(name) && !second.exports.contains(name) {
+            second.warnings.push(AssembleWarning {
+                line: *line,
+                message: format!("label {} is never referenced", name),
+            });
+        }
+    }
+    second.warnings.sort_by_key(|warning| warning.line);
+
+    // Nothing else will define the labels left over, as there is no linking to do
+    for (line, name) in second.unknown_labels.drain(..) {
+        second.errors.push(AssembleError::new(line, &name, String::from("isn't a label, a number or a name given to one by equ")));
+    }
+    if !second.errors.is_empty() {
+        second.errors.sort_by_key(|error| error.line);
+        return Err(second.errors);
+    }
+    return Ok(second);
+}
+
+/// Finds every label defined in a Chip-8 program
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program, as a string read from a file
+/// 
+/// ## Returns
+/// 
+/// The name and address of each label, in the order they are defined
+pub fn assemble_labels(program: &str) -> Vec<(String, u16)> {
+    let pass = assemble_pass(program, &HashMap::new(), &HashSet::new());
+    return pass.definitions.into_iter().map(|(name, addr, _)| (name, addr)).collect();
+}
+
+/// Machine code that hasn't been given its final address yet, for the linker to place
+pub struct Relocatable {
+    pub bytes: Vec<u8>,
+    /// Every label and its offset from the start of the code
+    pub labels: Vec<(String, usize)>,
+    /// Labels other objects may refer to, named by `.export`
+    pub exports: Vec<String>,
+    /// Offsets of instructions whose address operand names a label, which may be defined in another object
+    pub relocations: Vec<(usize, String)>,
+}
+
+/// Assembles a Chip-8 program into relocatable code
+/// 
+/// Labels it uses but doesn't define are left for the linker to find in other objects.
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program, as a string read from a file
+/// 
+/// ## Returns
+/// 
+/// The code, or every mistake that stopped it from being assembled
+pub fn assemble_relocatable(program: &str) -> Result<Relocatable, Vec<AssembleError>> {
+    let first = assemble_pass(program, &HashMap::new(), &HashSet::new());
+    let labels: HashMap<String, u16> = first.definitions.iter().map(|(name, addr, _)| (name.clone(), *addr)).collect();
+    let mut second = assemble_pass(program, &labels, &first.targets);
+
+    // The linker decides where an object goes, so it can't ask for an address of its own
+    for line in second.origins.iter() {
+        second.errors.push(AssembleError::new(*line, ".ORG", String::from("can't be used in an object, as the layout decides where it goes")));
+    }
+    if !second.errors.is_empty() {
+        second.errors.sort_by_key(|error| error.line);
+        return Err(second.errors);
+    }
+
+    return Ok(Relocatable {
+        bytes: second.bytes,
+        labels: second.definitions.into_iter().map(|(name, addr, _)| (name, addr as usize - BOOT_SECTOR)).collect(),
+        exports: second.exports,
+        relocations: second.relocations,
+    });
+}
+
+/// The result of one pass over the assembler source
+struct AssemblePass {
+    bytes: Vec<u8>,
+    /// Name, address, and line of every label definition
+    definitions: Vec<(String, u16, usize)>,
+    /// Labels named by `.export`
+    exports: Vec<String>,
+    /// Labels used as operands
+    references: HashSet<String>,
+    /// Addresses jumped to, called, or loaded into I
+    targets: HashSet<u16>,
+    /// Offsets of instructions whose address names a label
+    relocations: Vec<(usize, String)>,
+    warnings: Vec<AssembleWarning>,
+    errors: Vec<AssembleError>,
+    /// Line and name of every label used but not defined, which only the linker may find
+    unknown_labels: Vec<(usize, String)>,
+    /// Lines with a `.org`
+    origins: Vec<usize>,
+    /// Where in `bytes` each line's bytes are, by line
+    spans: Vec<Range<usize>>,
+}
+
+/// Runs a single pass of the assembler
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program to assemble
+/// * `labels` - Addresses of the labels found by a previous pass
+/// * `targets` - Every address that control flow or I can reach, found by a previous pass
+fn assemble_pass(program: &str, labels: &HashMap<String, u16>, targets: &HashSet<u16>) -> AssemblePass {
+    let mut bytes = Vec::new();
+    let mut definitions = Vec::new();
+    let mut exports = Vec::new();
+    let mut warnings = Vec::new();
+    let mut errors = Vec::new();
+    let mut unknown_labels = Vec::new();
+    let mut origins = Vec::new();
+    let mut spans: Vec<Range<usize>> = Vec::new();
+
+    // Line of the last unconditional jump, while the code after it hasn't been reached by anything
+    let mut unreachable_after: Option<usize> = None;
+    let mut previous_was_skip = false;
+
+    let symbols = define_symbols(program);
+    let mut resolver = AddressResolver {
+        labels,
+        references: HashSet::new(),
+        targets: HashSet::new(),
+        offset: 0,
+        relocations: Vec::new(),
+        unknown_labels: Vec::new(),
+    };
+
+    for (line_index, line) in program.lines().enumerate() {
+        // A line's bytes end where the next line's start
+        if let Some(span) = spans.last_mut() {
+            span.end = bytes.len();
+        }
+        spans.push(bytes.len()..bytes.len());
+        let mut normalized = tokenize(strip_comment(line));
+
+        // NAME equ VALUE and alias NAME Vx only name things, which `define_symbols` already found
+        match normalized.iter().map(|token| token.as_str()).collect::<Vec<_>>()[..] {
+            [name, "EQU", value] | ["ALIAS", name, value] => {
+                if normalized[1] == "EQU" && parse_number(value).is_none() {
+                    errors.push(AssembleError::new(line_index + 1, value, format!("can't be given to {} by equ, as it isn't a number", name)));
+                } else if normalized[0] == "ALIAS" && !is_register(value) {
+                    errors.push(AssembleError::new(line_index + 1, value, format!("can't be given to {} by alias, as it isn't a register", name)));
+                } else if let Some(defined) = symbols.get(name).filter(|defined| *defined != value) {
+                    warnings.push(AssembleWarning {
+                        line: line_index + 1,
+                        message: format!("{} is given another value later on, {}, which is used everywhere", name, defined),
+                    });
+                }
+                continue;
+            },
+            _ => {},
+        }
+        for token in normalized.iter_mut() {
+            if let Some(value) = symbols.get(token) {
+                *token = value.clone();
+            }
+        }
+
+        let mut tokens = normalized.iter().map(|token| token.as_str()).peekable();
+        let current_addr = (BOOT_SECTOR + bytes.len()) as u16;
+
+        // LABEL: at the start of the line
+        if let Some(name) = tokens.peek().and_then(|token| token.strip_suffix(':')) {
+            definitions.push((name.to_string(), current_addr, line_index + 1));
+            tokens.next();
+        }
+
+        let Some(opcode) = tokens.next() else {
+            continue;
+        };
+
+        // .db BYTE... - raw data, which doesn't count as code that could be unreachable
+        if opcode == ".DB" {
+            for token in tokens {
+                match parse_number(token).map(u8::try_from) {
+                    Some(Ok(byte)) => bytes.push(byte),
+                    Some(Err(_)) => errors.push(AssembleError::new(line_index + 1, token, String::from("doesn't fit in a byte"))),
+                    None => errors.push(AssembleError::new(line_index + 1, token, String::from("isn't a number, or a name given to one by equ"))),
+                }
+            }
+            continue;
+        }
+
+        // .dw WORD... - raw data two bytes at a time, most significant byte first like opcodes
+        if opcode == ".DW" {
+            for token in tokens {
+                match parse_number(token) {
+                    Some(word) => bytes.extend(word.to_be_bytes()),
+                    None => errors.push(AssembleError::new(line_index + 1, token, String::from("isn't a number, or a name given to one by equ"))),
+                }
+            }
+            continue;
+        }
+
+        // .sprite "ROW"... - sprite rows drawn with X for a lit pixel and . for a dark one, 8 pixels to a byte
+        if opcode == ".SPRITE" {
+            for token in tokens {
+                match sprite_row(token) {
+                    Some(row) => bytes.extend(row),
+                    None => errors.push(AssembleError::new(line_index + 1, token, String::from("isn't a sprite row, which is written in quotes with X for a lit pixel and . for a dark one"))),
+                }
+            }
+            continue;
+        }
+
+        // .export NAME - make labels visible to other objects when linking
+        if opcode == ".EXPORT" {
+            exports.extend(tokens.map(|name| name.to_string()));
+            continue;
+        }
+
+        // .org ADDR - carry on at a fixed address, with zeros in the gap up to it
+        if opcode == ".ORG" {
+            origins.push(line_index + 1);
+            match tokens.next().map(|token| (token, parse_number(token))) {
+                Some((_, Some(addr))) if addr >= current_addr => {
+                    bytes.resize(addr as usize - BOOT_SECTOR, 0);
+                    // The gap isn't any line's
+                    if let Some(span) = spans.last_mut() {
+                        span.start = bytes.len();
+                    }
+                    // A label on the same line names the new address
+                    if let Some(definition) = definitions.last_mut().filter(|(_, _, line)| *line == line_index + 1) {
+                        definition.1 = addr;
+                    }
+                },
+                Some((token, Some(_))) => errors.push(AssembleError::new(line_index + 1, token, format!("is before {:03X}, where the program has already got to", current_addr))),
+                Some((token, None)) => errors.push(AssembleError::new(line_index + 1, token, String::from("isn't a number, or a name given to one by equ"))),
+                None => errors.push(AssembleError::new(line_index + 1, opcode, String::from("is missing an operand"))),
+            }
+            continue;
+        }
+
+        if targets.contains(&current_addr) {
+            unreachable_after = None;
+        } else if let Some(jump_line) = unreachable_after.take() {
+            warnings.push(AssembleWarning {
+                line: line_index + 1,
+                message: format!("unreachable code after the jump on line {}", jump_line),
+            });
+        }
+
+        // A jump is only unconditional if the instruction before it can't skip over it
+        if opcode == "JP" && !previous_was_skip {
+            unreachable_after = Some(line_index + 1);
+        }
+        previous_was_skip = matches!(opcode, "SE" | "SNE" | "SKP" | "SKNP");
+        resolver.offset = bytes.len();
+
+        if CUSTOM_MNEMONICS.contains(&opcode) {
+            warnings.push(AssembleWarning {
+                line: line_index + 1,
+                message: format!("{} only runs in this emulator, with --enable-custom-ops", opcode),
+            });
+        }
+
+        let problems = RefCell::new(Vec::new());
+        let instruction = parse_instruction(opcode, &mut tokens, &mut resolver, &problems);
+        let problems = problems.into_inner();
+        if instruction.is_none() && problems.is_empty() {
+            errors.push(AssembleError::new(line_index + 1, opcode, String::from("isn't an instruction")));
+        }
+        errors.extend(problems.into_iter().map(|(token, message)| AssembleError::new(line_index + 1, &token, message)));
+        unknown_labels.extend(resolver.unknown_labels.drain(..).map(|name| (line_index + 1, name)));
+        let Some(instruction) = instruction else {
+            continue;
+        };
+        bytes.extend(instruction.encode().to_be_bytes());
+
+        // Anything left over is most likely a comment that doesn't start with ;
+        let rest: Vec<&str> = tokens.collect();
+        if !rest.is_empty() {
+            warnings.push(AssembleWarning {
+                line: line_index + 1,
+                message: format!("{} after the instruction was ignored; comments start with ;", rest.join(" ")),
+            });
+        }
+    }
+
+    if let Some(span) = spans.last_mut() {
+        span.end = bytes.len();
+    }
+
+    return AssemblePass {
+        bytes,
+        definitions,
+        exports,
+        references: resolver.references,
+        targets: resolver.targets,
+        relocations: resolver.relocations,
+        warnings,
+        errors,
+        unknown_labels,
+        origins,
+        spans,
+    };
+}
+
+/// Reads a row of a `.sprite`, like `"X..X...."`, into bytes
+/// 
+/// Rows of 16 pixels, for SUPER-CHIP's big sprites, take two bytes, and a row that doesn't fill its last byte is dark in the rest of it.
+/// 
+/// ## Arguments
+/// 
+/// * `token` - The row, in quotes and uppercased
+/// 
+/// ## Returns
+/// 
+/// The row's bytes, or None if it isn't in quotes or has something other than X and . in it
+fn sprite_row(token: &str) -> Option<Vec<u8>> {
+    let row = token.strip_prefix('"')?.strip_suffix('"')?;
+    if row.is_empty() || !row.chars().all(|pixel| pixel == 'X' || pixel == '.') {
+        return None;
+    }
+    return Some(row.as_bytes().chunks(8).map(|pixels| {
+        return pixels.iter().enumerate().fold(0, |byte, (bit, pixel)| if *pixel == b'X' { byte | (0x80 >> bit) } else { byte });
+    }).collect());
+}
+
+/// Cuts the comment off a line of assembler source, which is everything after a `;` that isn't a character literal
+/// 
+/// ## Arguments
+/// 
+/// * `line` - The line, which is returned whole if it has no comment
+fn strip_comment(line: &str) -> &str {
+    let mut in_literal = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '\'' => in_literal = !in_literal,
+            ';' if !in_literal => return &line[..index],
+            _ => {},
+        }
+    }
+    return line;
+}
+
+/// Splits a line of assembler source into normalized tokens
+/// 
+/// Operands can be separated by spaces, commas or both, so `LD V1, 0x20` and `LD V1 0x20` read the same.
+/// A comma inside a character literal, like `','`, is kept.
+/// 
+/// ## Arguments
+/// 
+/// * `line` - The line, with its comment already cut off
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut in_literal = false;
+    for (index, character) in line.char_indices() {
+        if character == '\'' {
+            in_literal = !in_literal;
+        }
+        if !in_literal && (character.is_whitespace() || character == ',') {
+            if let Some(from) = start.take() {
+                tokens.push(normalize_token(&line[from..index]));
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(from) = start {
+        tokens.push(normalize_token(&line[from..]));
+    }
+    return tokens;
+}
+
+/// Reads one instruction's operands from the assembler source
+/// 
+/// Operands that don't parse are reported, and stand in as 0xF so the rest of the line can still be read.
+/// 
+/// ## Arguments
+/// 
+/// * `mnemonic` - The instruction's mnemonic, uppercased
+/// * `tokens` - The operands after it, of which only the ones the instruction takes are read
+/// * `resolver` - Turns address operands into addresses
+/// * `problems` - Where to put each operand that doesn't parse and what is wrong with it, even when the instruction doesn't parse either
+/// 
+/// ## Returns
+/// 
+/// The instruction, or None if the mnemonic isn't one or can't take the operands
+fn parse_instruction<'a>(mnemonic: &str, tokens: &mut impl Iterator<Item = &'a str>, resolver: &mut AddressResolver, problems: &RefCell<Vec<(String, String)>>) -> Option<Instruction> {
+    // Operands that are missing come through as empty
+    let mut tokens = tokens.chain(std::iter::repeat(""));
+    let problem = |token: &str, message: &str| {
+        let problem = match token.is_empty() {
+            true => (mnemonic.to_string(), String::from("is missing an operand")),
+            false => (token.to_string(), message.to_string()),
+        };
+        problems.borrow_mut().push(problem);
+        return 0xF;
+    };
+    let register = |token: &str| match is_register(token) {
+        true => usize::from_str_radix(&token[1..], 16).unwrap(),
+        false => problem(token, "isn't a register, or a name given to one by alias") as usize,
+    };
+    let number = |token: &str, bits: u32| match parse_number(token) {
+        Some(value) if value.checked_shr(bits).unwrap_or(0) == 0 => value,
+        Some(_) => problem(token, &format!("doesn't fit in {} bits", bits)),
+        None => problem(token, "isn't a number, or a name given to one by equ"),
+    };
+    let byte = |token: &str| number(token, 8) as u8;
+    let nibble = |token: &str| number(token, 4) as u8;
+    // Names that aren't labels here are left to the linker, or reported by `assemble_with_warnings`
+    let mut address = |token: &str| {
+        if !is_name(token) {
+            number(token, 12);
+        }
+        return resolver.resolve(token);
+    };
+
+    let instruction = match mnemonic {
+        "NOP" => Instruction::Nop,
+        "CLS" => Instruction::Clear,
+        "RET" => Instruction::Return,
+        "SCD" => Instruction::ScrollDown { n: nibble(tokens.next().unwrap_or_default()) },
+        "SCR" => Instruction::ScrollRight,
+        "SCL" => Instruction::ScrollLeft,
+        "EXIT" => Instruction::Exit,
+        "LOW" => Instruction::LowRes,
+        "HIGH" => Instruction::HighRes,
+
+        // Can either be JP addr or JP V0, addr
+        "JP" => {
+            let next = tokens.next().unwrap_or_default();
+            if is_register(next) {
+                Instruction::JumpV0 { addr: address(tokens.next().unwrap_or_default()) }
+            } else {
+                Instruction::Jump { addr: address(next) }
+            }
+        },
+
+        "CALL" => Instruction::Call { addr: address(tokens.next().unwrap_or_default()) },
+
+        // Can either be SE Vx, byte or SE Vx, Vy, and the same for SNE
+        "SE" | "SNE" => {
+            let x = register(tokens.next().unwrap_or_default());
+            let next = tokens.next().unwrap_or_default();
+            match (mnemonic, next.starts_with("V")) {
+                ("SE", true) => Instruction::SkipEqual { x, y: register(next) },
+                ("SE", false) => Instruction::SkipEqualByte { x, byte: byte(next) },
+                (_, true) => Instruction::SkipNotEqual { x, y: register(next) },
+                (_, false) => Instruction::SkipNotEqualByte { x, byte: byte(next) },
+            }
+        },
+
+        // Bunch of cases: Vx can be loaded from a byte, Vy, DT, K, [I] or R, and I, DT, ST, HF, R, F, B and [I] from Vx or an address
+        "LD" => {
+            let arg1 = tokens.next().unwrap_or_default();
+            let arg2 = tokens.next().unwrap_or_default();
+
+            if arg1.starts_with("V") {
+                let x = register(arg1);
+                if arg2.starts_with("V") {
+                    Instruction::Load { x, y: register(arg2) }
+                } else if arg2.starts_with("DT") {
+                    Instruction::LoadDelay { x }
+                } else if arg2.starts_with("K") {
+                    Instruction::WaitKey { x }
+                } else if arg2.starts_with("[I]") {
+                    Instruction::LoadRegisters { x }
+                } else if arg2 == "R" {
+                    Instruction::LoadFlags { x }
+                } else {
+                    Instruction::LoadByte { x, byte: byte(arg2) }
+                }
+            } else if arg1.starts_with("I") {
+                Instruction::LoadI { addr: address(arg2) }
+            } else {
+                let x = register(arg2);
+                if arg1.starts_with("DT") {
+                    Instruction::SetDelay { x }
+                } else if arg1.starts_with("ST") {
+                    Instruction::SetSound { x }
+                } else if arg1 == "HF" {
+                    Instruction::LoadBigFont { x }
+                } else if arg1 == "R" {
+                    Instruction::SaveFlags { x }
+                } else if arg1.starts_with("F") {
+                    Instruction::LoadFont { x }
+                } else if arg1.starts_with("B") {
+                    Instruction::StoreBcd { x }
+                } else if arg1.starts_with("[I]") {
+                    Instruction::StoreRegisters { x }
+                } else {
+                    problem(arg1, "isn't something LD can load");
+                    return None;
+                }
+            }
+        },
+
+        // Either ADD Vx, byte or ADD Vx, Vy or ADD I, Vx
+        "ADD" => {
+            let arg1 = tokens.next().unwrap_or_default();
+            let arg2 = tokens.next().unwrap_or_default();
+
+            if arg1.starts_with("V") {
+                let x = register(arg1);
+                if arg2.starts_with("V") {
+                    Instruction::Add { x, y: register(arg2) }
+                } else {
+                    Instruction::AddByte { x, byte: byte(arg2) }
+                }
+            } else if arg1.starts_with("I") {
+                Instruction::AddI { x: register(arg2) }
+            } else {
+                problem(arg1, "isn't something ADD can add to");
+                return None;
+            }
+        },
+
+        "OR" | "AND" | "XOR" | "SUB" | "SUBN" => {
+            let x = register(tokens.next().unwrap_or_default());
+            let y = register(tokens.next().unwrap_or_default());
+            match mnemonic {
+                "OR" => Instruction::Or { x, y },
+                "AND" => Instruction::And { x, y },
+                "XOR" => Instruction::Xor { x, y },
+                "SUB" => Instruction::Sub { x, y },
+                _ => Instruction::SubN { x, y },
+            }
+        },
+
+        // Vy is only used by the original interpreter, so it defaults to Vx to shift the same way everywhere
+        "SHR" | "SHL" => {
+            let x = register(tokens.next().unwrap_or_default());
+            let y = tokens.next().filter(|token| !token.is_empty()).map_or(x, register);
+            match mnemonic {
+                "SHR" => Instruction::ShiftRight { x, y },
+                _ => Instruction::ShiftLeft { x, y },
+            }
+        },
+
+        "RND" => {
+            let x = register(tokens.next().unwrap_or_default());
+            Instruction::Random { x, byte: byte(tokens.next().unwrap_or_default()) }
+        },
+
+        "DRW" => {
+            let x = register(tokens.next().unwrap_or_default());
+            let y = register(tokens.next().unwrap_or_default());
+            Instruction::Draw { x, y, n: nibble(tokens.next().unwrap_or_default()) }
+        },
+
+        "SKP" => Instruction::SkipKey { x: register(tokens.next().unwrap_or_default()) },
+        "SKNP" => Instruction::SkipNotKey { x: register(tokens.next().unwrap_or_default()) },
+
+        // FRAME I or FRAME OFF (custom)
+        "FRAME" if tokens.next() == Some("OFF") => Instruction::FrameOff,
+        "FRAME" => Instruction::FrameI,
+
+        "PASS" => Instruction::Pass,
+        "FAIL" => Instruction::Fail,
+        "DEBUG" => Instruction::Debug,
+        "AUDIO" => Instruction::Audio,
+        "PITCH" => Instruction::Pitch { x: register(tokens.next().unwrap_or_default()) },
+        // EXT opcode, for instructions an extension runs
+        "EXT" => Instruction::Extension(number(tokens.next().unwrap_or_default(), 16)),
+        _ => return None,
+    };
+    return Some(instruction);
+}
+
+/// Finds the names given by `NAME equ VALUE` and `alias NAME Vx`, which the assembler swaps for their values wherever they are used
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program, as a string read from a file
+/// 
+/// ## Returns
+/// 
+/// What each name stands for, uppercased like the rest of the source. Names given something other than a number or a register are left out
+fn define_symbols(program: &str) -> HashMap<String, String> {
+    let mut symbols = HashMap::new();
+    for line in program.lines() {
+        let tokens = tokenize(strip_comment(line));
+        match tokens.iter().map(|token| token.as_str()).collect::<Vec<_>>()[..] {
+            [name, "EQU", value] if parse_number(value).is_some() => symbols.insert(name.to_string(), value.to_string()),
+            ["ALIAS", name, value] if is_register(value) => symbols.insert(name.to_string(), value.to_string()),
+            _ => None,
+        };
+    }
+    return symbols;
+}
+
+/// Turns address operands into addresses, remembering which labels and addresses were used
+struct AddressResolver<'a> {
+    labels: &'a HashMap<String, u16>,
+    references: HashSet<String>,
+    targets: HashSet<u16>,
+    /// Offset of the instruction being assembled
+    offset: usize,
+    /// Offsets of instructions whose address names a label, defined here or not
+    relocations: Vec<(usize, String)>,
+    /// Labels used on the line being assembled that aren't defined here
+    unknown_labels: Vec<String>,
+}
+
+impl AddressResolver<'_> {
+    /// Resolves an address operand, which can be a label or a number
+    fn resolve(&mut self, token: &str) -> u16 {
+        let addr = match self.labels.get(token) {
+            Some(addr) => {
+                self.references.insert(token.to_string());
+                self.relocations.push((self.offset, token.to_string()));
+                *addr
+            },
+            None => match parse_number(token) {
+                Some(addr) => addr,
+                None => {
+                    // Possibly defined in another object, so leave it for the linker
+                    if is_name(token) {
+                        self.relocations.push((self.offset, token.to_string()));
+                        self.unknown_labels.push(token.to_string());
+                    }
+                    0xF
+                },
+            },
+        };
+        self.targets.insert(addr);
+        return addr;
+    }
+}
+
+/// Whether a token could be a label, rather than a number
+fn is_name(token: &str) -> bool {
+    return token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+}
+
+/// Whether a token names a register, V0 to VF
+fn is_register(token: &str) -> bool {
+    return token.len() == 2 && token.starts_with('V') && token[1..].chars().all(|digit| digit.is_ascii_hexdigit());
+}
+
+/// Uppercases a token so mnemonics and registers are case-insensitive, leaving character literals untouched
+fn normalize_token(token: &str) -> String {
+    if token.starts_with('\'') {
+        return token.to_string();
+    }
+    return token.to_uppercase();
+}
+
+/// Parses a numeric literal from the assembler source
+/// 
+/// Accepts decimal (`31`), hex with a `0x` or `$` prefix (`0x1F`, `$1F`), binary with a `0b` or `%` prefix (`0b00011111`, `%00011111`), and character literals (`'A'`).
+/// 
+/// ## Arguments
+/// 
+/// * `token` - The literal, already uppercased unless it is a character literal
+/// 
+/// ## Returns
+/// 
+/// The value of the literal, or None if it is not a valid number
+fn parse_number(token: &str) -> Option<u16> {
+    if let Some(hex) = token.strip_prefix("0X").or_else(|| token.strip_prefix('$')) {
+        return u16::from_str_radix(hex, 16).ok();
+    }
+
+    if let Some(binary) = token.strip_prefix("0B").or_else(|| token.strip_prefix('%')) {
+        return u16::from_str_radix(binary, 2).ok();
+    }
+
+    if let Some(character) = token.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\'')) {
+        let mut chars = character.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii() => Some(c as u16),
+            _ => None,
+        };
+    }
+
+    return token.parse::<u16>().ok();
+}
+
+/// Moves a screen-sized buffer's contents, filling what it uncovers with the default, like black or no source
+/// 
+/// ## Arguments
+/// 
+/// * `old` - The buffer, a row at a time
+/// * `width` - Width of the buffer
+/// * `height` - Height of the buffer
+/// * `right` - Pixels to scroll right, or left if negative
+/// * `down` - Pixels to scroll down
+fn scrolled<T: Copy + Default>(old: &[T], width: usize, height: usize, right: isize, down: usize) -> Vec<T> {
+    let mut new = vec![T::default(); width * height];
+    for y in down..height {
+        for x in 0..width {
+            let from = x as isize - right;
+            if (0..width as isize).contains(&from) {
+                new[y * width + x] = old[(y - down) * width + from as usize];
+            }
+        }
+    }
+    return new;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_literal_formats() {
+        assert_eq!(parse_number("31"), Some(31));
+        assert_eq!(parse_number("0X1F"), Some(0x1F));
+        assert_eq!(parse_number("$1F"), Some(0x1F));
+        assert_eq!(parse_number("%00011111"), Some(0x1F));
+        assert_eq!(parse_number("0B00011111"), Some(0x1F));
+        assert_eq!(parse_number("0B"), None);
+        assert_eq!(parse_number("'A'"), Some(65));
+        assert_eq!(parse_number("1F"), None);
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let source = "; the whole line\n\n   \t\nstart: ; a label on its own\nLD V0 ';' ; a character literal\nCLS;no space\n";
+        let (bytes, warnings) = assemble_with_warnings(source).unwrap();
+        assert_eq!(bytes, vec![0x60, b';', 0x00, 0xE0]);
+        assert_eq!(warnings.iter().map(|warning| warning.to_string()).collect::<Vec<_>>(), vec!["line 4: label START is never referenced"]);
+
+        let (bytes, warnings) = assemble_with_warnings("CLS // not a comment\nRET").unwrap();
+        assert_eq!(bytes, vec![0x00, 0xE0, 0x00, 0xEE]);
+        assert_eq!(warnings.iter().map(|warning| warning.to_string()).collect::<Vec<_>>(), vec![
+            "line 1: // NOT A COMMENT after the instruction was ignored; comments start with ;",
+        ]);
+
+        let errors = assemble("CLS\n# not a comment either").unwrap_err();
+        assert_eq!(errors, vec![AssembleError::new(2, "#", String::from("isn't an instruction"))]);
+    }
+
+    #[test]
+    fn names_stand_for_numbers_and_registers() {
+        let source = "SPEED equ 5\nalias score V3\nLD score SPEED\nADD score LIMIT\nLD I SPRITE_AT\nSPRITE_AT EQU 0x300\nLIMIT equ %11";
+        let (bytes, warnings) = assemble_with_warnings(source).unwrap();
+        assert_eq!(bytes, vec![0x63, 0x05, 0x73, 0x03, 0xA3, 0x00]);
+        assert!(warnings.is_empty());
+
+        let (_, warnings) = assemble_with_warnings("FAST equ 1\nFAST equ 2").unwrap();
+        assert_eq!(warnings.iter().map(|warning| warning.to_string()).collect::<Vec<_>>(), vec![
+            "line 1: FAST is given another value later on, 2, which is used everywhere",
+        ]);
+
+        let errors = assemble("alias lives 5\nLD lives SPEED\nLD V0 0x100\nADD V0\nJP nowhere").unwrap_err();
+        assert_eq!(errors.iter().map(|error| error.to_string()).collect::<Vec<_>>(), vec![
+            "line 1: 5 can't be given to LIVES by alias, as it isn't a register",
+            "line 2: SPEED isn't a register, or a name given to one by alias",
+            "line 2: LIVES isn't something LD can load",
+            "line 3: 0X100 doesn't fit in 8 bits",
+            "line 4: ADD is missing an operand",
+            "line 5: NOWHERE isn't a label, a number or a name given to one by equ",
+        ]);
+    }
+
+    #[test]
+    fn commas_separate_operands() {
+        let spaced = assemble("LD V1 0x20\nDRW VA VB 5\nJP V0 0x300\nLD [I] V3\nSE V0 ','").unwrap();
+        let cowgod = assemble("LD V1, 0x20\nDRW VA,VB, 5\nJP V0, 0x300\nLD [I] , V3\nSE V0, ','").unwrap();
+        assert_eq!(spaced, vec![0x61, 0x20, 0xDA, 0xB5, 0xB3, 0x00, 0xF3, 0x55, 0x30, b',']);
+        assert_eq!(cowgod, spaced);
+
+        let aliased = assemble("alias score, V3\nSPEED equ 5\nADD score, SPEED\n.db 1, 2").unwrap();
+        assert_eq!(aliased, vec![0x73, 0x05, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn lowercase_source_assembles() {
+        let upper = assemble("LD V1 0x20\nDRW VA VB 5\nLD [I] V3\nJP 0x200").unwrap();
+        let lower = assemble("ld v1 0x20\ndrw va vb 5\nld [i] v3\njp $200").unwrap();
+        assert_eq!(upper, vec![0x61, 0x20, 0xDA, 0xB5, 0xF3, 0x55, 0x12, 0x00]);
+        assert_eq!(lower, upper);
+    }
+
+    #[test]
+    fn disassembly_reassembles() {
+        let program = [0x61, 0x20, 0xA2, 0x34, 0xDA, 0xB5, 0x7E, 0xFF, 0x3F, 0x01, 0xB3, 0x00, 0x23, 0x00, 0xF0, 0x02, 0xF3, 0x3A, 0x81, 0x16, 0x82, 0x0E, 0x00, 0xF1, 0x00, 0xF0, 0xF0, 0xFF, 0xF1, 0xFF, 0xF2, 0xFF, 0x00, 0xC3, 0x00, 0xFB, 0x00, 0xFC, 0x00, 0xFD, 0x00, 0xFE, 0x00, 0xFF, 0xF4, 0x30, 0xF7, 0x75, 0xF7, 0x85];
+        assert_eq!(assemble(&disassemble(&program)).unwrap(), program);
+
+        // Every opcode comes back as itself, whichever way it is disassembled
+        for opcode in 0..=u16::MAX {
+            let rom = opcode.to_be_bytes();
+            assert_eq!(assemble(&disassemble(&rom)).unwrap(), rom, "{:04X}", opcode);
+            assert_eq!(assemble(&disassemble_with_labels(&rom)).unwrap(), rom, "{:04X}", opcode);
+        }
+
+        // Data that isn't instructions comes back as .dw and .db
+        let data = [0x51, 0x23, 0xFF, 0xFF, 0x80];
+        assert_eq!(disassemble(&data), ".dw 0x5123\n.dw 0xFFFF\n.db 0x80\n");
+        assert_eq!(assemble(&disassemble(&data)).unwrap(), data);
+
+        // Only the code that can run comes out as instructions, and sprites stay bytes
+        let program = assemble("LD I sprite\nSE V0 1\nDRW V0 V0 2\nloop: JP loop\nsprite: .db 0x3C 0x42 0xFF 0x00 0x81 0x42 0x3C 0x18 0x10").unwrap();
+        assert_eq!(disassemble_with_labels(&program), "LD I 0x208\nSE V0 0x1\nDRW V0 V0 0x2\nL_0206:\nJP L_0206\n.db 0x3C 0x42 0xFF 0x00 0x81 0x42 0x3C 0x18\n.db 0x10\n");
+
+        // Code can be reached in the middle of what looks like another instruction, and targets outside the program keep their addresses
+        let jumps = [0x12, 0x03, 0xFF, 0x00, 0xE0, 0x23, 0x00, 0x12, 0x03];
+        assert_eq!(disassemble_with_labels(&jumps), "JP L_0203\n.db 0xFF\nL_0203:\nCLS\nCALL 0x300\nJP L_0203\n");
+        assert_eq!(assemble(&disassemble_with_labels(&jumps)).unwrap(), jumps);
+
+        // Every game comes back the same with labels too
+        for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../c8games")).unwrap() {
+            let rom = std::fs::read(entry.unwrap().path()).unwrap();
+            assert_eq!(assemble(&disassemble_with_labels(&rom)).unwrap(), rom);
+        }
+    }
+
+    #[test]
+    fn data_directives_embed_bytes() {
+        let source = "LD I smiley\nsmiley: .sprite \"..XXXX..\" \".X....X.\"\n.sprite \"X.X\"\n.sprite \"XXXXXXXX........\"\n.dw 0x1234 4660\n.db 1 2";
+        let (bytes, warnings) = assemble_with_warnings(source).unwrap();
+        assert_eq!(bytes, vec![0xA2, 0x02, 0x3C, 0x42, 0xA0, 0xFF, 0x00, 0x12, 0x34, 0x12, 0x34, 0x01, 0x02]);
+        assert!(warnings.is_empty());
+
+        let errors = assemble(".sprite \"X.O.\" X...\n.db 256 -\n.dw 0x10000").unwrap_err();
+        assert_eq!(errors.iter().map(|error| (error.line, error.token.as_str())).collect::<Vec<_>>(), vec![
+            (1, "\"X.O.\""), (1, "X..."), (2, "256"), (2, "-"), (3, "0X10000"),
+        ]);
+    }
+
+    #[test]
+    fn org_places_code_at_fixed_addresses() {
+        let source = "LD I table\nJP main\ntable: .org 0x208\n.db 7\n.org 0x20C\nmain: JP main";
+        let bytes = assemble(source).unwrap();
+        assert_eq!(bytes, vec![0xA2, 0x08, 0x12, 0x0C, 0, 0, 0, 0, 0x07, 0, 0, 0, 0x12, 0x0C]);
+
+        let errors = assemble("CLS\nCLS\n.org 0x201\n.org\n.org here").unwrap_err();
+        assert_eq!(errors.iter().map(|error| error.to_string()).collect::<Vec<_>>(), vec![
+            "line 3: 0X201 is before 204, where the program has already got to",
+            "line 4: .ORG is missing an operand",
+            "line 5: HERE isn't a number, or a name given to one by equ",
+        ]);
+        let errors = assemble_relocatable(".org 0x300").err().unwrap();
+        assert_eq!(errors[0].to_string(), "line 1: .ORG can't be used in an object, as the layout decides where it goes");
+    }
+
+    #[test]
+    fn labels_resolve_forward_and_backward() {
+        let bytes = assemble("start: call sub\njp start\nsub:\nld i sprite\nret\nsprite: cls").unwrap();
+        assert_eq!(bytes, vec![0x22, 0x04, 0x12, 0x00, 0xA2, 0x08, 0x00, 0xEE, 0x00, 0xE0]);
+    }
+
+    #[test]
+    fn warns_about_unreferenced_labels() {
+        let (_, warnings) = assemble_with_warnings("main: cls\nunused: jp main").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "line 2: label UNUSED is never referenced");
+    }
+
+    #[test]
+    fn warns_about_code_after_unconditional_jump() {
+        let source = "loop: cls\njp loop\nld v0 1\nld v1 2\nhere: ld v2 3\njp here";
+        let (_, warnings) = assemble_with_warnings(source).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "line 3: unreachable code after the jump on line 2");
+
+        // Skipped jumps and jumps to numeric addresses don't leave dead code behind
+        let (_, warnings) = assemble_with_warnings("se v0 1\njp 0x200\ncls\njp 0x208\ncls\nret").unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn memory_accesses_are_counted() {
+        // Store V0-V1 at 0x300, read them back, then loop forever
+        let rom = assemble("LD I 0x300\nLD [I] V1\nLD I 0x300\nLD V1 [I]\nloop: JP loop").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        chip8.cycle().unwrap();
+        assert!(chip8.get_access_counts().is_none());
+
+        chip8.enable_access_counts();
+        for _ in 0..6 {
+            chip8.cycle().unwrap();
+        }
+
+        let counts = chip8.get_access_counts().unwrap();
+        assert_eq!((counts.writes[0x300], counts.writes[0x301], counts.writes[0x302]), (1, 1, 0));
+        assert_eq!((counts.reads[0x300], counts.reads[0x301]), (1, 1));
+        assert_eq!((counts.fetches[0x200], counts.fetches[0x202], counts.fetches[0x208]), (0, 1, 3));
+    }
+
+    #[test]
+    fn frame_callback_runs_every_tick() {
+        let (rom, warnings) = assemble_with_warnings("LD I tick\nFRAME I\nloop: JP loop\ntick: ADD V1 1\nRET").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "line 2: FRAME only runs in this emulator, with --enable-custom-ops");
+
+        let mut chip8 = Chip8::new();
+        chip8.enable_custom_ops();
+        chip8.load_rom(&rom).unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+
+        chip8.update_timers();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!((chip8.registers[1], chip8.pc), (1, 0x204));
+
+        // A tick while the subroutine is still running doesn't call it again
+        chip8.update_timers();
+        chip8.update_timers();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!((chip8.registers[1], chip8.pc, chip8.sp), (2, 0x204, 0));
+
+        // Without custom instructions, FRAME is unknown and nothing gets called
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        chip8.update_timers();
+        assert_eq!((chip8.get_unknown_opcode_count(), chip8.pc), (1, 0x204));
+    }
+
+    #[test]
+    fn test_roms_report_to_the_host() {
+        let (rom, warnings) = assemble_with_warnings("LD V3 0x2A\nDEBUG\nLD V0 7\nFAIL\nPASS").unwrap();
+        assert_eq!(warnings.iter().map(|warning| warning.line).collect::<Vec<usize>>(), vec![2, 4, 5]);
+
+        let mut chip8 = Chip8::new();
+        chip8.enable_custom_ops();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..5 {
+            chip8.cycle().unwrap();
+        }
+
+        let calls = chip8.take_host_calls();
+        assert_eq!(calls.len(), 3);
+        assert!(matches!(&calls[0], HostCall::Debug(line) if line.starts_with("PC=202 I=000 V0=00 V1=00 V2=00 V3=2A ")), "{:?}", calls[0]);
+        assert_eq!(calls[1..], [HostCall::Fail(7), HostCall::Pass]);
+        assert!(chip8.take_host_calls().is_empty());
+    }
+
+    #[test]
+    fn stack_depth_and_placement() {
+        // Recurse until the stack runs out
+        let rom = assemble("deep: CALL deep").unwrap();
+
+        let mut chip8 = Chip8::new();
+        chip8.configure_stack(40, false).unwrap();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..40 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.sp, 40);
+
+        // On the VIP, the return addresses show up in memory
+        let mut chip8 = Chip8::new();
+        chip8.configure_stack(VIP_STACK_SIZE, true).unwrap();
+        chip8.load_rom(&rom).unwrap();
+        chip8.cycle().unwrap();
+        chip8.cycle().unwrap();
+        assert_eq!(chip8.memory[VIP_STACK_ADDR..VIP_STACK_ADDR + 4], [0x02, 0x02, 0x02, 0x02]);
+
+        assert!(chip8.configure_stack(MAX_MEMORY_STACK_SIZE + 1, true).is_err());
+        assert!(chip8.configure_stack(0, false).is_err());
+    }
+
+    #[test]
+    fn interpreter_image_fills_low_memory() {
+        let rom = assemble("LD I 0x1FE\nLD V1 [I]\nLD V2 0xA\nLD F V2").unwrap();
+        let image: Vec<u8> = (0..=0xFF).chain(0..=0xFF).collect();
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        chip8.load_interpreter_image(&image).unwrap();
+        for _ in 0..4 {
+            chip8.cycle().unwrap();
+        }
+
+        assert_eq!((chip8.registers[0], chip8.registers[1]), (0xFE, 0xFF));
+        assert_eq!(chip8.index as usize, RELOCATED_FONT_ADDR + 0xA * 5);
+        assert_eq!(chip8.memory[chip8.index as usize], 0xF0);
+        assert!(chip8.load_interpreter_image(&[0; 513]).is_err());
+    }
+
+    #[test]
+    fn diagnostics_are_raised_once() {
+        let rom = assemble("LD I 0xFF0\nLD V0 0x20\nloop: ADD I V0\nOR V1 V2\nJP loop").unwrap();
+        let diagnostics = |pedantic: bool| {
+            let mut chip8 = Chip8::new();
+            chip8.load_rom(&rom).unwrap();
+            if pedantic {
+                chip8.enable_pedantic();
+            }
+            for _ in 0..11 {
+                chip8.cycle().unwrap();
+            }
+            return chip8.take_diagnostics().iter().map(|diagnostic| diagnostic.to_string()).collect::<Vec<String>>();
+        };
+
+        let wrapped = String::from("204: I went past the end of memory and wrapped around to 0x000");
+        assert_eq!(diagnostics(false), vec![wrapped.clone()]);
+        assert_eq!(diagnostics(true), vec![wrapped, String::from("206: OR leaves VF alone here, but the COSMAC VIP reset it to 0")]);
+    }
+
+    #[test]
+    fn uninitialized_memory_is_reported() {
+        // Reads its own code and the font, which are fine, then a table it never wrote, then falls off the end of the ROM
+        let rom = assemble("LD I 0x200\nLD V3 [I]\nLD I 0x300\nLD [I] V0\nLD I 0x300\nLD V0 [I]\nLD I 0x310\nLD V3 [I]\nLD F V0\nDRW V0 V0 5").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        chip8.enable_memory_poisoning();
+        for _ in 0..11 {
+            chip8.cycle().unwrap();
+        }
+
+        let diagnostics: Vec<String> = chip8.take_diagnostics().iter().map(|diagnostic| diagnostic.to_string()).collect();
+        assert_eq!(diagnostics, vec![String::from("20E: reads 310, which was never loaded or written"), String::from("214: runs memory that was never loaded or written as an instruction")]);
+
+        // Nothing is reported unless asked for
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..11 {
+            chip8.cycle().unwrap();
+        }
+        assert!(chip8.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn pc_history_keeps_the_last_instructions() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble("LD V0 1\nloop: ADD V0 1\nJP loop").unwrap()).unwrap();
+        chip8.set_pc_history_size(3);
+        for _ in 0..6 {
+            chip8.step();
+        }
+        assert_eq!(*chip8.get_pc_history(), [(0x202, 0x7001), (0x204, 0x1202), (0x202, 0x7001)]);
+
+        // Looking ahead leaves it alone, and a state loaded doesn't come with one
+        let state = chip8.save_state().unwrap();
+        chip8.run_ahead(10, 1).unwrap();
+        assert_eq!(*chip8.get_pc_history(), [(0x202, 0x7001), (0x204, 0x1202), (0x202, 0x7001)]);
+        chip8.load_state(&state).unwrap();
+        assert!(chip8.get_pc_history().is_empty());
+
+        chip8.set_pc_history_size(0);
+        chip8.step();
+        assert!(chip8.get_pc_history().is_empty());
+    }
+
+    #[test]
+    fn xo_chip_memory_holds_bigger_roms() {
+        // LD I 0xFFF, LD V0 1, ADD I V0, LD V0 [I], which reads the ROM's byte at 0x1000
+        let mut rom = vec![0; 5000];
+        rom[..8].copy_from_slice(&[0xAF, 0xFF, 0x60, 0x01, 0xF0, 0x1E, 0xF0, 0x65]);
+        rom[0x1000 - BOOT_SECTOR] = 0x42;
+        let mut chip8 = Chip8::new();
+        assert_eq!(chip8.load_rom(&rom), Err(Chip8Error::RomTooLarge { size: 5000, max: MAX_ROM_SIZE }));
+        chip8.set_memory_size(XO_CHIP_MEMORY_SIZE).unwrap();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..4 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.get_registers()[0], 0x42);
+
+        // States keep the whole 64K, and only load into a machine with as much
+        let state = chip8.save_state().unwrap();
+        assert_eq!(Chip8::inspect_state(&state).map(|info| info.pc), Ok(0x208));
+        assert_eq!(Chip8::new().load_state(&state), Err(format!("The state has {} bytes of memory, expected {}", XO_CHIP_MEMORY_SIZE, MEMORY_SIZE)));
+    }
+
+    #[test]
+    fn oversized_roms_are_turned_away() {
+        let mut chip8 = Chip8::new();
+        assert!(chip8.load_rom(&vec![0x12; MAX_ROM_SIZE]).is_ok());
+        let error = chip8.load_rom(&vec![0; MAX_ROM_SIZE + 1]).err().unwrap().to_string();
+        assert_eq!(error, "The ROM is 3585 bytes, but only 3584 fit in memory from 200; ROMs this big are usually made for the XO-CHIP, so try its 64K of memory with --platform xochip");
+        assert!(chip8.load_rom(&vec![0; XO_CHIP_MAX_ROM_SIZE + 1]).err().unwrap().to_string().ends_with("so it is probably not a ROM"));
+        assert!(chip8.set_memory_size(8192).is_err());
+
+        assert_eq!(sniff_rom(b"PK\x03\x04zipped").as_deref(), Some("looks like a zip archive, not a ROM; unpack it and load the ROM inside"));
+        assert_eq!(sniff_rom(b"\x89PNG\r\n").as_deref(), Some("looks like a PNG image, not a ROM"));
+        assert_eq!(sniff_rom(b"").as_deref(), Some("is empty"));
+        // Real ROMs aren't mistaken for anything, and neither are tiny ones that happen to be printable
+        for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../c8games")).unwrap() {
+            let path = entry.unwrap().path();
+            assert_eq!(sniff_rom(&std::fs::read(&path).unwrap()), None, "{}", path.display());
+        }
+        assert_eq!(sniff_rom(b"`\x05a\x06"), None);
+    }
+
+    #[test]
+    fn strict_mode_stops_at_the_first_fault() {
+        let fault = |program: &str| {
+            let mut chip8 = Chip8::new();
+            chip8.enable_strict();
+            chip8.load_rom(&assemble(program).unwrap()).unwrap();
+            let error = (0..100).find_map(|_| chip8.cycle().err()).unwrap();
+            assert!(chip8.is_halted());
+            let fault = chip8.get_fault().unwrap().clone();
+            // Going past the end of memory stops the machine whether strict or not, so it comes back as its own error
+            if !matches!(error, Chip8Error::MemoryOutOfBounds { .. }) {
+                assert_eq!(error, Chip8Error::Strict(fault.clone()));
+            }
+            assert_eq!(chip8.get_pc(), fault.pc);
+            return fault.to_string();
+        };
+
+        assert_eq!(fault("NOP\n.dw 0x5121"), "202: 5121 isn't an instruction on this platform");
+        assert_eq!(fault("LD I 0xFF0\nLD V0 0x20\nADD I V0"), "204: ADD I, V0 takes I past the end of memory, to 1010");
+        assert_eq!(fault("LD I 0xFFE\nLD V3 [I]"), "202: reads 1000, past the end of memory");
+        assert_eq!(fault("LD I 0x1FF\nLD [I] V0"), "202: writes 1FF, which belongs to the interpreter");
+        assert_eq!(fault("LD V0 1\nJP V0 0x300"), "202: JP V0 0x300 leaves the PC on an odd address, 301");
+        assert_eq!(fault("RET"), "200: RET with nothing on the stack to return to");
+        assert_eq!(fault("loop: CALL loop"), "200: CALL with the stack full, at 16 levels");
+
+        // The same ROMs carry on when not strict, as far as they can
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble("NOP\n.dw 0x5121\nLD I 0x1FF\nLD [I] V0").unwrap()).unwrap();
+        for _ in 0..4 {
+            chip8.cycle().unwrap();
+        }
+        assert!(!chip8.is_halted() && chip8.get_fault().is_none());
+    }
+
+    #[test]
+    fn unknown_opcodes_follow_the_policy() {
+        let run = |policy: OpcodePolicy, strict: bool| {
+            let mut chip8 = Chip8::new();
+            chip8.set_opcode_policy(policy);
+            if strict {
+                chip8.enable_strict();
+            }
+            chip8.load_rom(&assemble("NOP\n.dw 0x5121\nNOP").unwrap()).unwrap();
+            return (0..3).map(|_| chip8.cycle()).collect::<Vec<_>>();
+        };
+
+        assert_eq!(run(OpcodePolicy::Ignore, false), vec![Ok(CycleStatus::Ran); 3]);
+        assert_eq!(run(OpcodePolicy::Trap, false), vec![Ok(CycleStatus::Ran), Ok(CycleStatus::Trapped { pc: 0x202, opcode: 0x5121 }), Ok(CycleStatus::Ran)]);
+        assert_eq!(run(OpcodePolicy::Halt, false)[1..], vec![Err(Chip8Error::UnknownOpcode { pc: 0x202, opcode: 0x5121 }); 2]);
+        assert!(matches!(run(OpcodePolicy::Trap, true)[1], Err(Chip8Error::Strict(_))));
+
+        // The trap is there for a frontend running with step until the next instruction
+        let mut chip8 = Chip8::new();
+        chip8.set_opcode_policy(OpcodePolicy::Trap);
+        chip8.load_rom(&[0x51, 0x21, 0x00, 0x00]).unwrap();
+        assert_eq!(chip8.step(), Some(Instruction::Unknown(0x5121)));
+        assert_eq!(chip8.get_trap(), Some((0x200, 0x5121)));
+        chip8.step();
+        assert_eq!((chip8.get_trap(), chip8.get_unknown_opcode_count()), (None, 1));
+        assert_eq!(OpcodePolicy::from_name("halt"), Ok(OpcodePolicy::Halt));
+        assert_eq!(OpcodePolicy::from_name("trap"), Ok(OpcodePolicy::Trap));
+        assert_eq!(OpcodePolicy::from_name("trap-to-debugger"), Ok(OpcodePolicy::Trap));
+        assert!(OpcodePolicy::from_name("panic").is_err());
+    }
+
+    #[test]
+    fn errors_stop_the_machine_instead_of_panicking() {
+        let error = |program: &str| {
+            let mut chip8 = Chip8::new();
+            chip8.load_rom(&assemble(program).unwrap()).unwrap();
+            let error = (0..20).find_map(|_| chip8.cycle().err()).unwrap();
+            assert!(chip8.is_halted());
+            assert_eq!(chip8.take_events(), vec![CoreEvent::Halted { fault: true }]);
+
+            // It stays stopped on the instruction at fault, and says so every time it is asked to carry on
+            assert_eq!(chip8.cycle(), Err(error.clone()));
+            assert_eq!(chip8.get_pc(), chip8.get_fault().unwrap().pc);
+            return (error, chip8.get_fault().unwrap().to_string());
+        };
+
+        assert_eq!(error("LD I 0xFFE\nLD V3 [I]"), (Chip8Error::MemoryOutOfBounds { pc: 0x202, addr: 0x1000 }, String::from("202: reads 1000, past the end of memory")));
+        assert_eq!(error("LD I 0xFFF\nLD [I] V1"), (Chip8Error::MemoryOutOfBounds { pc: 0x202, addr: 0x1000 }, String::from("202: writes 1000, past the end of memory")));
+        assert_eq!(error("LD I 0xFFF\nLD V0 99\nLD B V0").0, Chip8Error::MemoryOutOfBounds { pc: 0x204, addr: 0x1000 });
+        assert_eq!(error("JP 0xFFF"), (Chip8Error::PcOutOfBounds { pc: 0xFFF }, String::from("FFF: runs off the end of memory")));
+        assert_eq!(error("JP 0xFFF").0.to_string(), "FFF: the PC ran off the end of memory");
+        assert_eq!(error("CLS\nRET"), (Chip8Error::StackUnderflow { pc: 0x202 }, String::from("202: RET with nothing on the stack to return to")));
+        assert_eq!(error("deeper: CALL deeper"), (Chip8Error::StackOverflow { pc: 0x200 }, String::from("200: CALL with the stack full, at 16 levels")));
+    }
+
+    #[test]
+    fn keys_past_the_keypad_are_masked() {
+        // SKP and SKNP check the low digit of a register past 0xF, like the COSMAC VIP, rather than reading past the keypad
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble("LD V0 0x25\nSKP V0\nLD V1 1\nSKNP V0\nLD V2 1\nend: JP end").unwrap()).unwrap();
+        chip8.set_key(5, true);
+        chip8.set_key(0x25, true);
+        for _ in 0..5 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!((chip8.registers[1], chip8.registers[2]), (0, 1));
+        assert_eq!(chip8.get_keypad().iter().filter(|down| **down).count(), 1);
+        assert_eq!(chip8.take_diagnostics()[0].to_string(), "202: V0 is 0x25, past the keypad's 16 keys, so only key 5 is checked");
+
+        // A state can't leave FX0A waiting on a key the keypad doesn't have
+        let mut state: SavedState = decode(&chip8.save_state().unwrap(), STATE_MAGIC).unwrap();
+        state.key_down = Some(16);
+        let bytes = encode(&state, Format::Binary, STATE_MAGIC).unwrap();
+        assert_eq!(Chip8::new().load_state(&bytes), Err(String::from("The state waits for key 16, but the keypad only has 16")));
+    }
+
+    #[test]
+    fn waiting_for_a_key_takes_it_once_let_go() {
+        let run = |quirks: Quirks| {
+            let mut chip8 = Chip8::new();
+            chip8.set_quirks(quirks);
+            chip8.load_rom(&assemble("LD V1 K\nLD V2 K\nend: JP end").unwrap()).unwrap();
+            chip8.set_keypress(5);
+            for _ in 0..2 {
+                chip8.cycle().unwrap();
+            }
+            return chip8;
+        };
+
+        // Held down, the key isn't taken yet, and once let go it is taken by the first FX0A alone
+        let mut chip8 = run(Quirks::default());
+        assert_eq!((chip8.pc, chip8.registers[1]), (0x200, 0));
+        chip8.clear_keypad();
+        for _ in 0..3 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!((chip8.pc, chip8.registers[1], chip8.registers[2]), (0x202, 5, 0));
+
+        let chip8 = run(Quirks { key_wait_on_press: true, ..Quirks::default() });
+        assert_eq!((chip8.pc, chip8.registers[1], chip8.registers[2]), (0x204, 5, 5));
+    }
+
+    #[test]
+    fn events_follow_what_the_rom_does() {
+        let mut chip8 = Chip8::new();
+        chip8.enable_schip();
+        chip8.load_rom(&assemble("LD V0 30\nLD DT V0\nLD V1 K\nEXIT").unwrap()).unwrap();
+        for _ in 0..5 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.take_events(), vec![CoreEvent::DelaySet(30), CoreEvent::WaitingForKey]);
+
+        chip8.set_keypress(3);
+        chip8.cycle().unwrap();
+        chip8.clear_keypad();
+        for _ in 0..3 {
+            chip8.cycle().unwrap();
+        }
+        // It waits on while the key is held, and takes it once let go
+        assert_eq!(chip8.take_events(), vec![CoreEvent::WaitingForKey, CoreEvent::Halted { fault: false }]);
+    }
+
+    #[test]
+    fn running_ahead_leaves_the_machine_as_it_was() {
+        // Draw a digit once the key is pressed, and report it to the host
+        let rom = assemble("LD V0 5\nwait: SKP V0\nJP wait\nLD F V0\nDRW V1 V1 5\nDEBUG\nloop: JP loop").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.enable_custom_ops();
+        chip8.enable_access_counts();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..8 {
+            chip8.step();
+        }
+        chip8.set_keypress(5);
+        let before = chip8.save_state().unwrap();
+        let fetches = chip8.get_access_counts().unwrap().fetches.clone();
+
+        let ahead = chip8.run_ahead(8, 1).unwrap();
+        assert!(ahead.contains(&1));
+        assert_eq!(chip8.save_state().unwrap(), before);
+        assert_eq!(chip8.get_access_counts().unwrap().fetches, fetches);
+        assert!(chip8.take_host_calls().is_empty());
+
+        // The real frame, with the key still held, catches up with it
+        for _ in 0..8 {
+            chip8.step();
+        }
+        chip8.update_timers();
+        assert_eq!(chip8.get_screen_buffer(), &ahead[..]);
+    }
+
+    #[test]
+    fn pixels_know_which_draw_lit_them() {
+        let rom = assemble("LD I 0x300\nDRW V0 V0 1\nLD I 0x301\nLD V1 1\nDRW V1 V0 1\nSCR\nCLS").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.enable_schip();
+        chip8.enable_pixel_sources();
+        chip8.load_rom(&rom).unwrap();
+        chip8.write_memory(0x300, 0x80);
+        chip8.write_memory(0x301, 0x80);
+        chip8.step();
+        chip8.step();
+        assert_eq!(chip8.get_pixel_source(1, 1), Some(PixelSource { pc: 0x202, sprite: 0x300 }));
+
+        // The second draw lights the pixel to the right, and the screen scrolls right by 4 buffer pixels
+        for _ in 0..4 {
+            chip8.step();
+        }
+        assert_eq!(chip8.get_pixel_source(0, 0), None);
+        assert_eq!(chip8.get_pixel_source(5, 1), Some(PixelSource { pc: 0x202, sprite: 0x300 }));
+        assert_eq!(chip8.get_pixel_source(7, 0), Some(PixelSource { pc: 0x208, sprite: 0x301 }));
+        assert_eq!(chip8.get_pixel_source(500, 0), None);
+
+        chip8.step();
+        assert_eq!(chip8.get_pixel_source(5, 1), None);
+    }
+
+    #[test]
+    fn watchpoints_report_changes() {
+        assert_eq!(Watchpoint::parse("v3"), Ok(Watchpoint::Register(3)));
+        assert_eq!(Watchpoint::parse("mem 0x300"), Ok(Watchpoint::Memory(0x300)));
+        assert!(Watchpoint::parse("VG").is_err());
+        assert_eq!(Watchpoint::parse("mem 0x1000"), Ok(Watchpoint::Memory(0x1000)));
+        assert!(Watchpoint::parse("mem 0x10000").is_err());
+
+        let rom = assemble("LD V3 0\nLD V3 7\nLD I 0x300\nLD [I] V0\nLD V0 1\nLD I 0x300\nLD [I] V0").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.add_watchpoint(Watchpoint::Register(3));
+        chip8.add_watchpoint(Watchpoint::Memory(0x300));
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..7 {
+            chip8.step();
+        }
+
+        // Writing the value already there isn't a change
+        let hits: Vec<String> = chip8.take_watch_hits().iter().map(WatchHit::to_string).collect();
+        assert_eq!(hits, ["V3 changed from 00 to 07 at 202", "mem 300 changed from 00 to 01 at 20C"]);
+        assert!(chip8.take_watch_hits().is_empty());
+    }
+
+    #[test]
+    fn quirks_flipped_mid_run_apply_straight_away() {
+        let rom = assemble("LD V1 8\nSHR V0 V1\nSHR V2 V1").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        chip8.step();
+        chip8.step();
+        let mut quirks = chip8.get_quirks();
+        quirks.toggle("shift").unwrap();
+        chip8.set_quirks(quirks);
+        chip8.step();
+        assert_eq!(&chip8.get_registers()[..3], [0, 8, 4]);
+    }
+
+    #[test]
+    fn steps_show_what_ran_and_the_stack() {
+        let rom = assemble("CALL sub\nsub: CALL again\nagain: LD V0 5").unwrap();
+        for in_memory in [false, true] {
+            let mut chip8 = Chip8::new();
+            chip8.configure_stack(12, in_memory).unwrap();
+            chip8.load_rom(&rom).unwrap();
+            chip8.step();
+            chip8.step();
+            assert_eq!(chip8.get_stack(), vec![0x202, 0x204]);
+            assert_eq!(chip8.step(), Some(Instruction::LoadByte { x: 0, byte: 5 }));
+        }
+    }
+
+    #[test]
+    fn json_states_can_be_edited_by_hand() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble("LD V3 7\nloop: ADD V3 1\nJP loop").unwrap()).unwrap();
+        chip8.cycle().unwrap();
+        let state = chip8.save_state_as(Format::Json).unwrap();
+        assert!(state.starts_with(b"{"));
+        assert!(chip8.save_state_as(Format::Text).is_err());
+
+        let mut text: serde_json::Value = serde_json::from_slice(&state).unwrap();
+        text["registers"][3] = serde_json::json!(0x40);
+        let mut loaded = Chip8::new();
+        loaded.load_state(serde_json::to_string_pretty(&text).unwrap().as_bytes()).unwrap();
+        assert_eq!((loaded.get_registers()[3], loaded.get_pc()), (0x40, 0x202));
+        loaded.cycle().unwrap();
+        assert_eq!(loaded.get_registers()[3], 0x41);
+    }
+
+    #[test]
+    fn save_states_carry_on_where_they_left_off() {
+        let rom = assemble("LD V1 3\nCALL draw\nloop: RND V0 0xFF\nLD I 0x300\nLD [I] V0\nADD V1 1\nJP loop\ndraw: LD F V1\nDRW V1 V1 5\nLD DT V1\nRET").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        chip8.seed_rng(7);
+        chip8.set_quirks(Quirks { vf_reset: true, ..Quirks::default() });
+        for _ in 0..6 {
+            chip8.cycle().unwrap();
+        }
+        let state = chip8.save_state().unwrap();
+        assert!(state.starts_with(STATE_MAGIC));
+        let info = Chip8::inspect_state(&state).unwrap();
+        assert!(info.format == Format::Binary && !info.compressed && info.size == state.len() && info.pc == chip8.pc);
+        #[cfg(feature = "compression")]
+        {
+            let compressed = crate::format::compress(&state);
+            let info = Chip8::inspect_state(&compressed).unwrap();
+            assert!(info.compressed && info.size * 4 < state.len() && info.decompressed_size == state.len(), "{:?}", info);
+            assert!(Chip8::inspect_state(&compressed[..compressed.len() - 8]).is_err());
+        }
+
+        // A fresh emulator picks up the quirks, stack, screen and random numbers, and runs on in step
+        let mut loaded = Chip8::new();
+        loaded.load_state(&state).unwrap();
+        assert_eq!(chip8.state_differences(&loaded), Vec::<String>::new());
+        assert!(loaded.get_quirks().vf_reset);
+        for _ in 0..20 {
+            chip8.cycle().unwrap();
+            loaded.cycle().unwrap();
+        }
+        assert_eq!(chip8.state_differences(&loaded), Vec::<String>::new());
+
+        // States that don't fit are turned away without touching the emulator
+        let mut schip = Chip8::new();
+        schip.enable_schip();
+        assert_eq!(schip.load_state(&state), Err(String::from("The state was saved with SUPER-CHIP off, but it is on here")));
+        assert!(loaded.load_state(&state[..state.len() / 2]).is_err());
+        assert_eq!(chip8.state_differences(&loaded), Vec::<String>::new());
+    }
+
+    #[test]
+    fn save_states_keep_what_was_in_flight() {
+        let rom = assemble("LD VA 0x3C\nLD ST VA\nLD V0 K\nLD V1 1").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..5 {
+            chip8.cycle().unwrap();
+        }
+        for _ in 0..20 {
+            chip8.update_timers();
+        }
+        let state = chip8.save_state().unwrap();
+
+        // Loaded mid-beep and mid-FX0A, it keeps waiting for a key and the beep ends on the same tick
+        let mut loaded = Chip8::new();
+        loaded.load_state(&state).unwrap();
+        assert_eq!((loaded.get_pc(), loaded.get_sound_timer()), (0x204, 40));
+        for _ in 0..3 {
+            loaded.cycle().unwrap();
+        }
+        assert_eq!(loaded.get_pc(), 0x204);
+        for _ in 0..39 {
+            loaded.update_timers();
+        }
+        assert!(loaded.get_sound_timer() > 0);
+        loaded.update_timers();
+        assert_eq!(loaded.get_sound_timer(), 0);
+
+        // The key FX0A saw pressed is taken once let go, even after another save and load in between
+        loaded.set_keypress(7);
+        loaded.cycle().unwrap();
+        let mut released = Chip8::new();
+        released.load_state(&loaded.save_state().unwrap()).unwrap();
+        released.clear_keypad();
+        released.cycle().unwrap();
+        released.cycle().unwrap();
+        assert_eq!((released.registers[0], released.registers[1]), (7, 1));
+    }
+
+    #[test]
+    fn frames_end_when_the_timers_count_down() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble("loop: ADD V0 1\nJP loop").unwrap()).unwrap();
+        assert!(!chip8.is_mid_frame());
+        chip8.cycle().unwrap();
+        assert!(chip8.is_mid_frame());
+
+        // A state saved partway through a frame is still partway through once loaded
+        let state = chip8.save_state().unwrap();
+        chip8.update_timers();
+        assert!(!chip8.is_mid_frame());
+        chip8.load_state(&state).unwrap();
+        assert!(chip8.is_mid_frame());
+    }
+
+    #[test]
+    fn quirks_change_behaviour() {
+        let rom = assemble("LD V1 0x81\nSHR V0 V1\nLD VF 1\nOR V5 V1\nLD I 0x300\nLD [I] V2\nLD V2 2\nJP V0 0x220\nloop: JP loop\n.db 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\nLD V4 63\nLD I 0x310\nDRW V4 V4 1").unwrap();
+        let run = |quirks: Quirks| {
+            let mut chip8 = Chip8::new();
+            chip8.load_rom(&rom).unwrap();
+            chip8.set_quirks(quirks);
+            chip8.memory[0x310] = 0xFF;
+            for _ in 0..8 {
+                chip8.cycle().unwrap();
+            }
+            return chip8;
+        };
+
+        // BXNN takes X from the address, so 0x220 adds V2
+        let chip8 = run(Quirks::default());
+        assert_eq!((chip8.registers[0], chip8.registers[0xF], chip8.index), (0, 1, 0x303));
+        assert_eq!(chip8.pc, 0x220);
+
+        let mut quirks = Quirks::default();
+        for spec in ["shift=vy", "vf-reset=on", "memory=fixed", "jump=vx", "sprites=clip"] {
+            quirks.set(spec).unwrap();
+        }
+        let mut chip8 = run(quirks);
+        assert_eq!((chip8.registers[0], chip8.registers[0xF], chip8.index), (0x40, 0, 0x300));
+        assert_eq!(chip8.pc, 0x222);
+
+        // Only the top left pixel of a sprite at the bottom right corner is left when clipping
+        for _ in 0..3 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.screen.iter().filter(|pixel| **pixel != 0).count(), 1);
+        assert_eq!(chip8.screen[31 * SCREEN_WIDTH + 63], 1);
+    }
+
+    #[test]
+    fn sprites_wrap_or_clip_on_each_axis() {
+        // Two rows of eight pixels from (124, 63), which wraps to (60, 31), crossing the right and bottom edges
+        let lit = |quirks: Quirks| {
+            let mut chip8 = Chip8::new();
+            chip8.set_quirks(quirks);
+            chip8.load_rom(&assemble("LD I sprite\nLD V0 124\nLD V1 63\nDRW V0 V1 2\nend: JP end\nsprite: .db 0xFF 0xFF").unwrap()).unwrap();
+            for _ in 0..4 {
+                chip8.cycle().unwrap();
+            }
+            return (0..SCREEN_HEIGHT).flat_map(|y| (0..SCREEN_WIDTH).map(move |x| (x, y))).filter(|(x, y)| chip8.screen[y * SCREEN_WIDTH + x] != 0).collect::<Vec<(usize, usize)>>();
+        };
+
+        // Each row wraps to its own left edge, rather than running on into the next row, and the second row wraps to the top
+        let corners: Vec<(usize, usize)> = [0, 31].iter().flat_map(|y| [0, 1, 2, 3, 60, 61, 62, 63].map(|x| (x, *y))).collect();
+        assert_eq!(lit(Quirks::default()), corners);
+        assert_eq!(lit(Quirks { clip_sprites: true, ..Quirks::default() }), vec![(60, 31), (61, 31), (62, 31), (63, 31)]);
+    }
+
+    #[test]
+    fn schip_hires_sprites_scroll_and_clip() {
+        let rom = assemble("HIGH\nLD I 0x300\nDRW V0 V1 0\nSCD 2\nSCR\nLD V1 56\nDRW V0 V1 0").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.enable_schip();
+        chip8.load_rom(&rom).unwrap();
+        chip8.memory[0x300..0x320].fill(0xFF);
+        for _ in 0..5 {
+            chip8.cycle().unwrap();
+        }
+
+        // The 16x16 block moved down 2 and right 4
+        assert_eq!(chip8.get_screen_size(), (HIRES_WIDTH, HIRES_HEIGHT));
+        assert_eq!(chip8.screen.iter().filter(|pixel| **pixel != 0).count(), 256);
+        assert_eq!((chip8.screen[2 * HIRES_WIDTH + 4], chip8.screen[17 * HIRES_WIDTH + 19]), (1, 1));
+        assert_eq!((chip8.screen[HIRES_WIDTH + 4], chip8.screen[2 * HIRES_WIDTH + 3]), (0, 0));
+
+        // Half of a sprite at the bottom is clipped, and VF counts the rows that fell off
+        for _ in 0..2 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.registers[0xF], 8);
+        assert_eq!(chip8.screen[63 * HIRES_WIDTH + 15], 1);
+        assert_eq!(chip8.screen[..HIRES_WIDTH].iter().filter(|pixel| **pixel != 0).count(), 0);
+    }
+
+    #[test]
+    fn schip_lores_pixels_are_doubled() {
+        let rom = assemble("LD I 0x300\nLD V0 63\nDRW V0 V0 1\nSCL\nLD V3 9\nLD R V3\nLD V3 0\nLD V3 R\nLD HF V3\nEXIT\nCLS").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.enable_schip();
+        chip8.load_rom(&rom).unwrap();
+        chip8.memory[0x300] = 0xFF;
+        for _ in 0..3 {
+            chip8.cycle().unwrap();
+        }
+
+        // The sprite starts at the bottom right pixel and is clipped to it, a 2x2 block in the 128x64 buffer
+        let lit: Vec<usize> = (0..chip8.screen.len()).filter(|index| chip8.screen[*index] != 0).collect();
+        assert_eq!(lit, vec![62 * HIRES_WIDTH + 126, 62 * HIRES_WIDTH + 127, 63 * HIRES_WIDTH + 126, 63 * HIRES_WIDTH + 127]);
+
+        // Scrolling goes by buffer pixels, so half a low resolution pixel at a time
+        chip8.cycle().unwrap();
+        assert_eq!((chip8.screen[62 * HIRES_WIDTH + 122], chip8.screen[62 * HIRES_WIDTH + 124]), (1, 0));
+
+        for _ in 0..5 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(chip8.registers[3], 9);
+        assert_eq!(chip8.memory[chip8.index as usize..chip8.index as usize + 10], BIG_FONTSET[90..100]);
+
+        // Nothing runs after EXIT
+        for _ in 0..2 {
+            chip8.cycle().unwrap();
+        }
+        assert!(chip8.is_halted());
+        assert_eq!(chip8.screen[62 * HIRES_WIDTH + 122], 1);
+    }
+    #[test]
+    fn extensions_run_opcodes_of_their_own() {
+        // 5xy1, which isn't a CHIP-8 opcode, multiplies Vx by Vy
+        let rom = assemble("LD V1 6\nLD V2 7\nEXT 0x5121\nSE V1 V2").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.register_extension(|opcode| opcode & 0xF00F == 0x5001, |opcode, state| {
+            let (x, y) = (((opcode & 0x0F00) >> 8) as usize, ((opcode & 0x00F0) >> 4) as usize);
+            state.registers[x] = state.registers[x].wrapping_mul(state.registers[y]);
+        });
+        chip8.load_rom(&rom).unwrap();
+        chip8.step();
+        chip8.step();
+
+        assert_eq!(chip8.step(), Some(Instruction::Extension(0x5121)));
+        assert_eq!(chip8.registers[1], 42);
+        assert_eq!(chip8.step(), Some(Instruction::SkipEqual { x: 1, y: 2 }));
+        assert_eq!(chip8.unknown_opcodes, 0);
+    }
+
+    #[test]
+    fn sprites_are_read_at_i() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble("LD I sprite\nloop: JP loop\nsprite: .db 0x3C 0x42 0xFF").unwrap()).unwrap();
+        chip8.step();
+        assert_eq!(chip8.get_sprite(2), (vec![0x3C, 0x42], 1));
+        assert_eq!(chip8.get_sprite(0), (vec![], 1));
+
+        chip8.enable_schip();
+        assert_eq!(chip8.get_sprite(0).0.len(), 16);
+        chip8.execute_instruction(Instruction::HighRes);
+        assert_eq!(chip8.get_sprite(0), ([0x3C, 0x42, 0xFF].into_iter().chain([0; 29]).collect(), 2));
+    }
+}
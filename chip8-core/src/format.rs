@@ -0,0 +1,184 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Files this crate writes come in a compact binary form and a JSON form that can be read and edited by hand.
+// Binary files start with a 4 byte magic naming what they hold and a version byte, followed by the bincode encoding.
+// JSON files are a plain serde_json encoding, so reading one back only needs the fields to line up.
+// With the compression feature, binary files can have everything after the header compressed with LZ4, which turns a save state's
+// mostly empty memory and screen into a few hundred bytes. The top bit of the version byte marks them, followed by the length of
+// the bincode encoding as 4 little-endian bytes and the LZ4 block. Builds without the feature still write and read uncompressed files.
+
+/// Version of the binary layout, bumped whenever a serialized type changes shape
+pub const FORMAT_VERSION: u8 = 3;
+
+/// Set in the version byte of binary files whose encoding is compressed
+const COMPRESSED: u8 = 0x80;
+
+/// Most bytes a compressed file may say it holds, well past any real file, so a corrupt length can't ask for gigabytes
+const MAX_DECOMPRESSED: usize = 1 << 24;
+
+/// How a file is encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Line-based text, for files that have one
+    Text,
+    Json,
+    Binary,
+}
+
+impl Format {
+    /// Looks a format up by name: `text`, `json` or `binary`
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        return match name {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "binary" => Ok(Format::Binary),
+            _ => Err(format!("Unknown format {}, expected text, json or binary", name)),
+        };
+    }
+
+    /// Guesses the format of a file from its first bytes
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `bytes` - The file
+    /// * `magic` - The magic binary files of this kind start with
+    pub fn detect(bytes: &[u8], magic: &[u8; 4]) -> Self {
+        if bytes.starts_with(magic) {
+            return Format::Binary;
+        }
+        return match bytes.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') | Some(b'[') => Format::Json,
+            _ => Format::Text,
+        };
+    }
+}
+
+/// Encodes a value as JSON or binary
+/// 
+/// ## Arguments
+/// 
+/// * `value` - What to encode
+/// * `format` - JSON or binary; text has to be written by the type itself
+/// * `magic` - The magic binary files of this kind start with
+pub fn encode<T: Serialize>(value: &T, format: Format, magic: &[u8; 4]) -> Result<Vec<u8>, String> {
+    return match format {
+        Format::Json => serde_json::to_vec_pretty(value).map_err(|e| e.to_string()),
+        Format::Binary => {
+            let mut bytes = magic.to_vec();
+            bytes.push(FORMAT_VERSION);
+            bincode::serialize_into(&mut bytes, value).map_err(|e| e.to_string())?;
+            Ok(bytes)
+        },
+        Format::Text => Err(String::from("This has no text format")),
+    };
+}
+
+/// Compresses a binary file `encode` wrote, leaving its magic as it was
+/// 
+/// JSON, files already compressed and anything in a build without the compression feature come back unchanged.
+/// 
+/// ## Arguments
+/// 
+/// * `bytes` - The encoded value
+pub fn compress(bytes: &[u8]) -> Vec<u8> {
+    #[cfg(feature = "compression")]
+    if bytes.get(4) == Some(&FORMAT_VERSION) {
+        let body = &bytes[5..];
+        let mut compressed = bytes[..4].to_vec();
+        compressed.push(FORMAT_VERSION | COMPRESSED);
+        compressed.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        compressed.extend_from_slice(&lz4_flex::block::compress(body));
+        return compressed;
+    }
+    return bytes.to_vec();
+}
+
+/// Undoes `compress`, checking the compressed data holds as many bytes as it says it does
+/// 
+/// ## Arguments
+/// 
+/// * `bytes` - The encoded value, compressed or not
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let Some(&version) = bytes.get(4).filter(|version| *version & COMPRESSED != 0) else {
+        return Ok(bytes.to_vec());
+    };
+    let length = bytes.get(5..9).ok_or("Truncated file")?;
+    let length = u32::from_le_bytes(length.try_into().expect("The slice is 4 bytes")) as usize;
+    if length > MAX_DECOMPRESSED {
+        return Err(format!("The file says it holds {} bytes once decompressed, more than the {} allowed", length, MAX_DECOMPRESSED));
+    }
+
+    #[cfg(feature = "compression")]
+    {
+        let mut decompressed = bytes[..4].to_vec();
+        decompressed.push(version & !COMPRESSED);
+        decompressed.extend(lz4_flex::block::decompress(&bytes[9..], length).map_err(|e| format!("Invalid compressed data: {}", e))?);
+        return Ok(decompressed);
+    }
+    #[cfg(not(feature = "compression"))]
+    {
+        let _ = (version, length);
+        return Err(String::from("The file is compressed, but this build was made without the compression feature"));
+    }
+}
+
+/// Whether a binary file `encode` wrote was compressed afterwards
+pub fn is_compressed(bytes: &[u8]) -> bool {
+    return bytes.get(4).is_some_and(|version| version & COMPRESSED != 0);
+}
+
+/// Decodes a value written by `encode`, working out whether it is JSON or binary, and compressed or not
+/// 
+/// ## Arguments
+/// 
+/// * `bytes` - The encoded value
+/// * `magic` - The magic binary files of this kind start with
+pub fn decode<T: DeserializeOwned>(bytes: &[u8], magic: &[u8; 4]) -> Result<T, String> {
+    return match Format::detect(bytes, magic) {
+        Format::Json => serde_json::from_slice(bytes).map_err(|e| format!("Invalid JSON: {}", e)),
+        Format::Binary if is_compressed(bytes) => decode(&decompress(bytes)?, magic),
+        Format::Binary => match bytes.get(magic.len()) {
+            Some(&FORMAT_VERSION) => bincode::deserialize(&bytes[magic.len() + 1..]).map_err(|e| format!("Invalid binary data: {}", e)),
+            Some(version) => Err(format!("Written by format version {}, but this build reads version {}", version, FORMAT_VERSION)),
+            None => Err(String::from("Truncated file")),
+        },
+        Format::Text => Err(String::from("Neither JSON nor binary")),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAGIC: &[u8; 4] = b"TEST";
+
+    #[test]
+    fn both_formats_round_trip() {
+        let value = (0x200u16, vec![1u8, 2, 3], Some(String::from("state")));
+        for format in [Format::Json, Format::Binary] {
+            let bytes = encode(&value, format, MAGIC).unwrap();
+            assert_eq!(Format::detect(&bytes, MAGIC), format);
+            assert_eq!(decode::<(u16, Vec<u8>, Option<String>)>(&bytes, MAGIC), Ok(value.clone()));
+        }
+
+        let mut future = encode(&value, Format::Binary, MAGIC).unwrap();
+        future[4] = FORMAT_VERSION + 1;
+        assert_eq!(decode::<(u16, Vec<u8>, Option<String>)>(&future, MAGIC), Err(format!("Written by format version {}, but this build reads version {}", FORMAT_VERSION + 1, FORMAT_VERSION)));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_files_decode_like_the_originals() {
+        let value = (0x200u16, vec![0u8; 4096]);
+        let bytes = encode(&value, Format::Binary, MAGIC).unwrap();
+        let compressed = compress(&bytes);
+        assert!(compressed.starts_with(MAGIC) && is_compressed(&compressed) && compressed.len() * 20 < bytes.len(), "{} bytes", compressed.len());
+        assert_eq!(decompress(&compressed), Ok(bytes.clone()));
+        assert_eq!(decode::<(u16, Vec<u8>)>(&compressed, MAGIC), Ok(value));
+        assert_eq!(compress(&compressed), compressed);
+
+        // The length up front catches data cut short
+        assert!(decompress(&compressed[..compressed.len() - 1]).is_err());
+    }
+}
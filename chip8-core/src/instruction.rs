@@ -0,0 +1,362 @@
+use std::fmt;
+
+// Every instruction the interpreter, assembler and disassembler know, decoded from the two bytes of an opcode once
+// so the three of them agree on what each opcode means. Adding an opcode is a variant plus its arms in `decode`, `encode` and `fmt`.
+// Decoding doesn't depend on the platform: SUPER-CHIP, XO-CHIP and this emulator's own instructions always decode,
+// and the interpreter treats the ones its platform doesn't have as unknown.
+// Instructions are written without commas and with hex operands, like `LD V0 0x5`, which the assembler reads as well as `LD V0, 0x5`.
+
+/// One decoded instruction, where `x` and `y` are register numbers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    /// 0000 - NOP
+    Nop,
+    /// 00E0 - CLS - Clear screen
+    Clear,
+    /// 00EE - RET - Return from subroutine
+    Return,
+    /// 00Cn - SCD n - Scroll down n pixels (SUPER-CHIP)
+    ScrollDown { n: u8 },
+    /// 00FB - SCR - Scroll right 4 pixels (SUPER-CHIP)
+    ScrollRight,
+    /// 00FC - SCL - Scroll left 4 pixels (SUPER-CHIP)
+    ScrollLeft,
+    /// 00FD - EXIT - Stop running (SUPER-CHIP)
+    Exit,
+    /// 00FE - LOW - Switch to 64x32 low resolution (SUPER-CHIP)
+    LowRes,
+    /// 00FF - HIGH - Switch to 128x64 high resolution (SUPER-CHIP)
+    HighRes,
+    /// 00F0 - FRAME OFF - Stop calling the per-frame subroutine (custom)
+    FrameOff,
+    /// 00F1 - FRAME I - Call the subroutine at I every 60 Hz tick (custom)
+    FrameI,
+    /// 1nnn - JP addr - Jump to address
+    Jump { addr: u16 },
+    /// 2nnn - CALL addr - Call subroutine
+    Call { addr: u16 },
+    /// 3xkk - SE Vx, byte - Skip next if Vx == byte
+    SkipEqualByte { x: usize, byte: u8 },
+    /// 4xkk - SNE Vx, byte - Skip next if Vx != byte
+    SkipNotEqualByte { x: usize, byte: u8 },
+    /// 5xy0 - SE Vx, Vy - Skip next if Vx == Vy
+    SkipEqual { x: usize, y: usize },
+    /// 6xkk - LD Vx, byte - Set Vx to byte
+    LoadByte { x: usize, byte: u8 },
+    /// 7xkk - ADD Vx, byte
+    AddByte { x: usize, byte: u8 },
+    /// 8xy0 - LD Vx, Vy - Set Vx = Vy
+    Load { x: usize, y: usize },
+    /// 8xy1 - OR Vx, Vy
+    Or { x: usize, y: usize },
+    /// 8xy2 - AND Vx, Vy
+    And { x: usize, y: usize },
+    /// 8xy3 - XOR Vx, Vy
+    Xor { x: usize, y: usize },
+    /// 8xy4 - ADD Vx, Vy
+    Add { x: usize, y: usize },
+    /// 8xy5 - SUB Vx, Vy
+    Sub { x: usize, y: usize },
+    /// 8xy6 - SHR Vx, Vy - Shift right
+    ShiftRight { x: usize, y: usize },
+    /// 8xy7 - SUBN Vx, Vy - Vx = Vy SUB Vx
+    SubN { x: usize, y: usize },
+    /// 8xyE - SHL Vx, Vy - Shift left
+    ShiftLeft { x: usize, y: usize },
+    /// 9xy0 - SNE Vx, Vy - Skip next if Vx != Vy
+    SkipNotEqual { x: usize, y: usize },
+    /// Annn - LD I, addr - Set I to nnn
+    LoadI { addr: u16 },
+    /// Bnnn - JP V0, addr - Jump to addr offset by V0, or by Vx where x is the top digit of addr with the jump quirk
+    JumpV0 { addr: u16 },
+    /// Cxkk - RND Vx, kk - Set Vx to random byte & kk
+    Random { x: usize, byte: u8 },
+    /// Dxyn - DRW Vx, Vy, n - Draw n lines at Vx, Vy from index location
+    Draw { x: usize, y: usize, n: u8 },
+    /// Ex9E - SKP Vx - Skip next if key Vx is pressed
+    SkipKey { x: usize },
+    /// ExA1 - SKNP Vx - Skip next if key Vx is not pressed
+    SkipNotKey { x: usize },
+    /// Fx07 - LD Vx, DT - Set Vx to delay timer
+    LoadDelay { x: usize },
+    /// Fx0A - LD Vx, K - Wait for key press, store in Vx
+    WaitKey { x: usize },
+    /// F002 - AUDIO - Load the 16-byte XO-CHIP audio pattern at I
+    Audio,
+    /// Fx15 - LD DT, Vx - Set delay timer to Vx
+    SetDelay { x: usize },
+    /// Fx18 - LD ST, Vx - Set sound timer to Vx
+    SetSound { x: usize },
+    /// Fx1E - ADD I, Vx - Set I to I + Vx
+    AddI { x: usize },
+    /// Fx29 - LD F, Vx - Set I to location of sprite for digit Vx
+    LoadFont { x: usize },
+    /// Fx30 - LD HF, Vx - Set I to location of the big sprite for digit Vx (SUPER-CHIP)
+    LoadBigFont { x: usize },
+    /// Fx33 - LD B, Vx - Store BCD representation of Vx in memory locations I, I+1, I+2
+    StoreBcd { x: usize },
+    /// Fx3A - PITCH Vx - Set the XO-CHIP audio playback rate from Vx
+    Pitch { x: usize },
+    /// Fx55 - LD [I], Vx - Store registers V0 through Vx in memory starting at I
+    StoreRegisters { x: usize },
+    /// Fx65 - LD Vx, [I] - Fill registers V0 through Vx with memory starting at I
+    LoadRegisters { x: usize },
+    /// Fx75 - LD R, Vx - Save registers V0 through Vx to the RPL flags (SUPER-CHIP)
+    SaveFlags { x: usize },
+    /// Fx85 - LD Vx, R - Load registers V0 through Vx from the RPL flags (SUPER-CHIP)
+    LoadFlags { x: usize },
+    /// F0FF - PASS - Tell the host running a test ROM that it passed (custom)
+    Pass,
+    /// F1FF - FAIL - Tell the host running a test ROM that it failed, with V0 as the code (custom)
+    Fail,
+    /// F2FF - DEBUG - Send the host running a test ROM the registers (custom)
+    Debug,
+    /// EXT opcode - An opcode run by an extension registered with `Chip8::register_extension`, which decoding never gives
+    Extension(u16),
+    /// An opcode that isn't any of the above
+    Unknown(u16),
+}
+
+impl Instruction {
+    /// Decodes an opcode, read from memory most significant byte first
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::Instruction;
+    /// 
+    /// let instruction = Instruction::decode(0xD125);
+    /// assert_eq!(instruction, Instruction::Draw { x: 1, y: 2, n: 5 });
+    /// assert_eq!(instruction.to_string(), "DRW V1 V2 0x5");
+    /// assert_eq!(instruction.encode(), 0xD125);
+    /// ```
+    pub fn decode(opcode: u16) -> Self {
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        let y = ((opcode & 0x00F0) >> 4) as usize;
+        let n = (opcode & 0x000F) as u8;
+        let byte = (opcode & 0x00FF) as u8;
+        let addr = opcode & 0x0FFF;
+
+        return match (opcode >> 12, x, y, n) {
+            (0, 0, 0, 0) => Self::Nop,
+            (0, 0, 0xE, 0) => Self::Clear,
+            (0, 0, 0xE, 0xE) => Self::Return,
+            (0, 0, 0xC, _) => Self::ScrollDown { n },
+            (0, 0, 0xF, 0xB) => Self::ScrollRight,
+            (0, 0, 0xF, 0xC) => Self::ScrollLeft,
+            (0, 0, 0xF, 0xD) => Self::Exit,
+            (0, 0, 0xF, 0xE) => Self::LowRes,
+            (0, 0, 0xF, 0xF) => Self::HighRes,
+            (0, 0, 0xF, 0) => Self::FrameOff,
+            (0, 0, 0xF, 1) => Self::FrameI,
+            (1, _, _, _) => Self::Jump { addr },
+            (2, _, _, _) => Self::Call { addr },
+            (3, _, _, _) => Self::SkipEqualByte { x, byte },
+            (4, _, _, _) => Self::SkipNotEqualByte { x, byte },
+            (5, _, _, 0) => Self::SkipEqual { x, y },
+            (6, _, _, _) => Self::LoadByte { x, byte },
+            (7, _, _, _) => Self::AddByte { x, byte },
+            (8, _, _, 0) => Self::Load { x, y },
+            (8, _, _, 1) => Self::Or { x, y },
+            (8, _, _, 2) => Self::And { x, y },
+            (8, _, _, 3) => Self::Xor { x, y },
+            (8, _, _, 4) => Self::Add { x, y },
+            (8, _, _, 5) => Self::Sub { x, y },
+            (8, _, _, 6) => Self::ShiftRight { x, y },
+            (8, _, _, 7) => Self::SubN { x, y },
+            (8, _, _, 0xE) => Self::ShiftLeft { x, y },
+            (9, _, _, 0) => Self::SkipNotEqual { x, y },
+            (0xA, _, _, _) => Self::LoadI { addr },
+            (0xB, _, _, _) => Self::JumpV0 { addr },
+            (0xC, _, _, _) => Self::Random { x, byte },
+            (0xD, _, _, _) => Self::Draw { x, y, n },
+            (0xE, _, 9, 0xE) => Self::SkipKey { x },
+            (0xE, _, 0xA, 1) => Self::SkipNotKey { x },
+            (0xF, _, 0, 7) => Self::LoadDelay { x },
+            (0xF, _, 0, 0xA) => Self::WaitKey { x },
+            (0xF, 0, 0, 2) => Self::Audio,
+            (0xF, _, 1, 5) => Self::SetDelay { x },
+            (0xF, _, 1, 8) => Self::SetSound { x },
+            (0xF, _, 1, 0xE) => Self::AddI { x },
+            (0xF, _, 2, 9) => Self::LoadFont { x },
+            (0xF, _, 3, 0) => Self::LoadBigFont { x },
+            (0xF, _, 3, 3) => Self::StoreBcd { x },
+            (0xF, _, 3, 0xA) => Self::Pitch { x },
+            (0xF, _, 5, 5) => Self::StoreRegisters { x },
+            (0xF, _, 6, 5) => Self::LoadRegisters { x },
+            (0xF, _, 7, 5) => Self::SaveFlags { x },
+            (0xF, _, 8, 5) => Self::LoadFlags { x },
+            (0xF, 0, 0xF, 0xF) => Self::Pass,
+            (0xF, 1, 0xF, 0xF) => Self::Fail,
+            (0xF, 2, 0xF, 0xF) => Self::Debug,
+            _ => Self::Unknown(opcode),
+        };
+    }
+
+    /// Roughly how long the COSMAC VIP's interpreter took to run the instruction, in microseconds
+    /// 
+    /// These are averages measured on the original interpreter. `DRW` waited for the display to be drawn as well,
+    /// which is why ROMs that draw a lot ran slowly on it. Instructions the VIP didn't have, and `LD Vx K`,
+    /// which waits for a key, have no cost.
+    pub fn vip_cost(&self) -> Option<u32> {
+        return match *self {
+            Self::Clear => Some(109),
+            Self::Return | Self::Jump { .. } | Self::Call { .. } | Self::JumpV0 { .. } => Some(105),
+            Self::SkipEqualByte { .. } | Self::SkipNotEqualByte { .. } | Self::LoadI { .. } => Some(55),
+            Self::SkipEqual { .. } | Self::SkipNotEqual { .. } | Self::SkipKey { .. } | Self::SkipNotKey { .. } => Some(73),
+            Self::LoadByte { .. } => Some(27),
+            Self::AddByte { .. } | Self::LoadDelay { .. } | Self::SetDelay { .. } | Self::SetSound { .. } => Some(45),
+            Self::Load { .. } | Self::Or { .. } | Self::And { .. } | Self::Xor { .. } | Self::Add { .. } => Some(200),
+            Self::Sub { .. } | Self::ShiftRight { .. } | Self::SubN { .. } | Self::ShiftLeft { .. } => Some(200),
+            Self::Random { .. } => Some(164),
+            Self::Draw { .. } => Some(22734),
+            Self::AddI { .. } => Some(86),
+            Self::LoadFont { .. } => Some(91),
+            Self::StoreBcd { .. } => Some(927),
+            Self::StoreRegisters { .. } | Self::LoadRegisters { .. } => Some(605),
+            _ => None,
+        };
+    }
+
+    /// Encodes the instruction as its opcode, keeping only the bits each operand has room for
+    pub fn encode(&self) -> u16 {
+        let x_byte = |op: u16, x: usize, byte: u8| op | ((x as u16 & 0xF) << 8) | byte as u16;
+        let x_y = |op: u16, x: usize, y: usize, n: u8| op | ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4) | (n as u16 & 0xF);
+
+        return match *self {
+            Self::Nop => 0x0000,
+            Self::Clear => 0x00E0,
+            Self::Return => 0x00EE,
+            Self::ScrollDown { n } => 0x00C0 | (n as u16 & 0xF),
+            Self::ScrollRight => 0x00FB,
+            Self::ScrollLeft => 0x00FC,
+            Self::Exit => 0x00FD,
+            Self::LowRes => 0x00FE,
+            Self::HighRes => 0x00FF,
+            Self::FrameOff => 0x00F0,
+            Self::FrameI => 0x00F1,
+            Self::Jump { addr } => 0x1000 | (addr & 0xFFF),
+            Self::Call { addr } => 0x2000 | (addr & 0xFFF),
+            Self::SkipEqualByte { x, byte } => x_byte(0x3000, x, byte),
+            Self::SkipNotEqualByte { x, byte } => x_byte(0x4000, x, byte),
+            Self::SkipEqual { x, y } => x_y(0x5000, x, y, 0),
+            Self::LoadByte { x, byte } => x_byte(0x6000, x, byte),
+            Self::AddByte { x, byte } => x_byte(0x7000, x, byte),
+            Self::Load { x, y } => x_y(0x8000, x, y, 0),
+            Self::Or { x, y } => x_y(0x8000, x, y, 1),
+            Self::And { x, y } => x_y(0x8000, x, y, 2),
+            Self::Xor { x, y } => x_y(0x8000, x, y, 3),
+            Self::Add { x, y } => x_y(0x8000, x, y, 4),
+            Self::Sub { x, y } => x_y(0x8000, x, y, 5),
+            Self::ShiftRight { x, y } => x_y(0x8000, x, y, 6),
+            Self::SubN { x, y } => x_y(0x8000, x, y, 7),
+            Self::ShiftLeft { x, y } => x_y(0x8000, x, y, 0xE),
+            Self::SkipNotEqual { x, y } => x_y(0x9000, x, y, 0),
+            Self::LoadI { addr } => 0xA000 | (addr & 0xFFF),
+            Self::JumpV0 { addr } => 0xB000 | (addr & 0xFFF),
+            Self::Random { x, byte } => x_byte(0xC000, x, byte),
+            Self::Draw { x, y, n } => x_y(0xD000, x, y, n),
+            Self::SkipKey { x } => x_byte(0xE000, x, 0x9E),
+            Self::SkipNotKey { x } => x_byte(0xE000, x, 0xA1),
+            Self::LoadDelay { x } => x_byte(0xF000, x, 0x07),
+            Self::WaitKey { x } => x_byte(0xF000, x, 0x0A),
+            Self::Audio => 0xF002,
+            Self::SetDelay { x } => x_byte(0xF000, x, 0x15),
+            Self::SetSound { x } => x_byte(0xF000, x, 0x18),
+            Self::AddI { x } => x_byte(0xF000, x, 0x1E),
+            Self::LoadFont { x } => x_byte(0xF000, x, 0x29),
+            Self::LoadBigFont { x } => x_byte(0xF000, x, 0x30),
+            Self::StoreBcd { x } => x_byte(0xF000, x, 0x33),
+            Self::Pitch { x } => x_byte(0xF000, x, 0x3A),
+            Self::StoreRegisters { x } => x_byte(0xF000, x, 0x55),
+            Self::LoadRegisters { x } => x_byte(0xF000, x, 0x65),
+            Self::SaveFlags { x } => x_byte(0xF000, x, 0x75),
+            Self::LoadFlags { x } => x_byte(0xF000, x, 0x85),
+            Self::Pass => 0xF0FF,
+            Self::Fail => 0xF1FF,
+            Self::Debug => 0xF2FF,
+            Self::Extension(opcode) | Self::Unknown(opcode) => opcode,
+        };
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            Self::Nop => write!(f, "NOP"),
+            Self::Clear => write!(f, "CLS"),
+            Self::Return => write!(f, "RET"),
+            Self::ScrollDown { n } => write!(f, "SCD 0x{:X}", n),
+            Self::ScrollRight => write!(f, "SCR"),
+            Self::ScrollLeft => write!(f, "SCL"),
+            Self::Exit => write!(f, "EXIT"),
+            Self::LowRes => write!(f, "LOW"),
+            Self::HighRes => write!(f, "HIGH"),
+            Self::FrameOff => write!(f, "FRAME OFF"),
+            Self::FrameI => write!(f, "FRAME I"),
+            Self::Jump { addr } => write!(f, "JP 0x{:X}", addr),
+            Self::Call { addr } => write!(f, "CALL 0x{:X}", addr),
+            Self::SkipEqualByte { x, byte } => write!(f, "SE V{:X} 0x{:X}", x, byte),
+            Self::SkipNotEqualByte { x, byte } => write!(f, "SNE V{:X} 0x{:X}", x, byte),
+            Self::SkipEqual { x, y } => write!(f, "SE V{:X} V{:X}", x, y),
+            Self::LoadByte { x, byte } => write!(f, "LD V{:X} 0x{:X}", x, byte),
+            Self::AddByte { x, byte } => write!(f, "ADD V{:X} 0x{:X}", x, byte),
+            Self::Load { x, y } => write!(f, "LD V{:X} V{:X}", x, y),
+            Self::Or { x, y } => write!(f, "OR V{:X} V{:X}", x, y),
+            Self::And { x, y } => write!(f, "AND V{:X} V{:X}", x, y),
+            Self::Xor { x, y } => write!(f, "XOR V{:X} V{:X}", x, y),
+            Self::Add { x, y } => write!(f, "ADD V{:X} V{:X}", x, y),
+            Self::Sub { x, y } => write!(f, "SUB V{:X} V{:X}", x, y),
+            // Vy only matters to the COSMAC VIP's shifts, so it is left out when it is Vx
+            Self::ShiftRight { x, y } if x == y => write!(f, "SHR V{:X}", x),
+            Self::ShiftRight { x, y } => write!(f, "SHR V{:X} V{:X}", x, y),
+            Self::SubN { x, y } => write!(f, "SUBN V{:X} V{:X}", x, y),
+            Self::ShiftLeft { x, y } if x == y => write!(f, "SHL V{:X}", x),
+            Self::ShiftLeft { x, y } => write!(f, "SHL V{:X} V{:X}", x, y),
+            Self::SkipNotEqual { x, y } => write!(f, "SNE V{:X} V{:X}", x, y),
+            Self::LoadI { addr } => write!(f, "LD I 0x{:X}", addr),
+            Self::JumpV0 { addr } => write!(f, "JP V0 0x{:X}", addr),
+            Self::Random { x, byte } => write!(f, "RND V{:X} 0x{:X}", x, byte),
+            Self::Draw { x, y, n } => write!(f, "DRW V{:X} V{:X} 0x{:X}", x, y, n),
+            Self::SkipKey { x } => write!(f, "SKP V{:X}", x),
+            Self::SkipNotKey { x } => write!(f, "SKNP V{:X}", x),
+            Self::LoadDelay { x } => write!(f, "LD V{:X} DT", x),
+            Self::WaitKey { x } => write!(f, "LD V{:X} K", x),
+            Self::Audio => write!(f, "AUDIO"),
+            Self::SetDelay { x } => write!(f, "LD DT V{:X}", x),
+            Self::SetSound { x } => write!(f, "LD ST V{:X}", x),
+            Self::AddI { x } => write!(f, "ADD I V{:X}", x),
+            Self::LoadFont { x } => write!(f, "LD F V{:X}", x),
+            Self::LoadBigFont { x } => write!(f, "LD HF V{:X}", x),
+            Self::StoreBcd { x } => write!(f, "LD B V{:X}", x),
+            Self::Pitch { x } => write!(f, "PITCH V{:X}", x),
+            Self::StoreRegisters { x } => write!(f, "LD [I] V{:X}", x),
+            Self::LoadRegisters { x } => write!(f, "LD V{:X} [I]", x),
+            Self::SaveFlags { x } => write!(f, "LD R V{:X}", x),
+            Self::LoadFlags { x } => write!(f, "LD V{:X} R", x),
+            Self::Pass => write!(f, "PASS"),
+            Self::Fail => write!(f, "FAIL"),
+            Self::Debug => write!(f, "DEBUG"),
+            Self::Extension(opcode) => write!(f, "EXT 0x{:04X}", opcode),
+            Self::Unknown(_) => write!(f, "???"),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_known_opcode_encodes_back() {
+        for opcode in 0..=u16::MAX {
+            let instruction = Instruction::decode(opcode);
+            if !matches!(instruction, Instruction::Unknown(_)) {
+                assert_eq!(instruction.encode(), opcode, "{} came from {:04X}", instruction, opcode);
+            }
+        }
+        assert_eq!(Instruction::decode(0xD12F), Instruction::Draw { x: 1, y: 2, n: 0xF });
+        assert_eq!(Instruction::decode(0x5121), Instruction::Unknown(0x5121));
+        assert_eq!(Instruction::LoadByte { x: 3, byte: 0x2A }.to_string(), "LD V3 0x2A");
+    }
+}
@@ -0,0 +1,13 @@
+// The CHIP-8 interpreter, assembler and disassembler, without a frontend.
+// The chip8 crate's terminal emulator and tools are built on this, and other frontends can depend on it without them.
+// Everything public follows semver, and the names exported here are the ones a frontend needs. tests/api.rs uses each
+// of them the way a frontend would, so a change that would break one fails to build before it can be published.
+pub mod chip8;
+pub mod instruction;
+pub mod quirks;
+pub mod format;
+pub mod listing;
+
+pub use crate::chip8::{assemble, disassemble, AssembleError as AsmError, Chip8, Chip8Error, CycleStatus, FrameBuffer, OpcodePolicy, StateInfo};
+pub use crate::instruction::Instruction;
+pub use crate::quirks::Quirks;
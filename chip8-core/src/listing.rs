@@ -0,0 +1,156 @@
+// A listing shows where each line of the source went, for checking a ROM against the source it came from:
+//   ADDR  BYTES         LINE  SOURCE
+//   0200  00 E0            1  start: CLS
+//   0202  01 02 03 04      2  .db 1 2 3 4 5
+//   0206  05
+// Lines that become nothing, like comments and labels on their own, show the address they are at.
+// The symbol map beside it names every label's address, one to a line in address order, like `0200 START`.
+// The columns are fixed, so the emulator can read both back as a source map for its debugger.
+
+use std::path::Path;
+
+/// Most bytes shown on one row of a listing, with the rest carrying on below
+const BYTES_PER_ROW: usize = 4;
+
+/// Where the columns of a listing row start: the bytes, the line number, and the source
+const BYTES_COLUMN: usize = 6;
+const LINE_COLUMN: usize = 19;
+const SOURCE_COLUMN: usize = 26;
+
+/// Where the lines of a program were assembled, and the labels in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    /// Every line of the source, in order
+    pub lines: Vec<ListingLine>,
+    /// Every label and its address, in address order
+    pub symbols: Vec<(String, u16)>,
+}
+
+/// One line of the source and what it became
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListingLine {
+    /// The line, starting from 1
+    pub line: usize,
+    /// The address its bytes start at, or where the program had got to if it has none
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+    /// The line as written
+    pub text: String,
+}
+
+impl Listing {
+    /// Writes out the listing as a `.lst` file
+    pub fn text(&self) -> String {
+        let mut str_buffer = String::from("ADDR  BYTES         LINE  SOURCE\n");
+        for line in self.lines.iter() {
+            let mut rows = line.bytes.chunks(BYTES_PER_ROW);
+            let first = rows.next().map(hex_bytes).unwrap_or_default();
+            str_buffer.push_str(format!("{:04X}  {:<12} {:>5}  {}", line.addr, first, line.line, line.text).trim_end());
+            str_buffer.push('\n');
+
+            for (row, bytes) in rows.enumerate() {
+                str_buffer.push_str(&format!("{:04X}  {}\n", line.addr as usize + (row + 1) * BYTES_PER_ROW, hex_bytes(bytes)));
+            }
+        }
+        return str_buffer;
+    }
+
+    /// Writes out the labels as a `.sym` file
+    pub fn symbol_map(&self) -> String {
+        return self.symbols.iter().map(|(name, addr)| format!("{:04X} {}\n", addr, name)).collect();
+    }
+
+    /// Reads a listing and symbol map back, as written by `text` and `symbol_map`
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `listing` - The `.lst` file
+    /// * `symbol_map` - The `.sym` file
+    pub fn parse(listing: &str, symbol_map: &str) -> Result<Self, String> {
+        let mut lines: Vec<ListingLine> = Vec::new();
+        for (index, row) in listing.lines().enumerate().skip(1) {
+            let bad_row = || format!("line {} of the listing isn't an address, bytes and a source line", index + 1);
+            let addr = row.get(..4).and_then(|addr| u16::from_str_radix(addr, 16).ok()).ok_or_else(bad_row)?;
+            let bytes_text = row.get(BYTES_COLUMN..LINE_COLUMN.min(row.len())).unwrap_or_default();
+            let bytes = bytes_text.split_whitespace().map(|byte| u8::from_str_radix(byte, 16)).collect::<Result<Vec<u8>, _>>().map_err(|_| bad_row())?;
+
+            // A row without a line number carries on the bytes of the one above
+            match row.get(LINE_COLUMN..SOURCE_COLUMN - 2).map(str::trim).filter(|line| !line.is_empty()) {
+                Some(line) => lines.push(ListingLine {
+                    line: line.parse().map_err(|_| bad_row())?,
+                    addr,
+                    bytes,
+                    text: row.get(SOURCE_COLUMN..).unwrap_or_default().to_string(),
+                }),
+                None => lines.last_mut().ok_or_else(bad_row)?.bytes.extend(bytes),
+            }
+        }
+
+        let mut symbols = Vec::new();
+        for (index, row) in symbol_map.lines().enumerate() {
+            let (addr, name) = row.split_once(' ').ok_or_else(|| format!("line {} of the symbol map isn't an address and a label", index + 1))?;
+            let addr = u16::from_str_radix(addr, 16).map_err(|_| format!("line {} of the symbol map has {} for an address", index + 1, addr))?;
+            symbols.push((name.trim().to_string(), addr));
+        }
+        return Ok(Self { lines, symbols });
+    }
+
+    /// Reads the listing and symbol map asm writes beside a ROM, like `game.lst` and `game.sym` for `game.ch8`, if it wrote one
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `rom_path` - The ROM
+    pub fn beside(rom_path: &Path) -> Result<Option<Self>, String> {
+        let (listing_path, symbols_path) = (rom_path.with_extension("lst"), rom_path.with_extension("sym"));
+        if !listing_path.is_file() {
+            return Ok(None);
+        }
+
+        let read = |path: &Path| std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+        let symbol_map = if symbols_path.is_file() { read(&symbols_path)? } else { String::new() };
+        return Self::parse(&read(&listing_path)?, &symbol_map).map(Some).map_err(|e| format!("{}: {}", listing_path.display(), e));
+    }
+
+    /// The source line whose bytes include an address
+    pub fn line_at(&self, addr: u16) -> Option<&ListingLine> {
+        return self.lines.iter().find(|line| (line.addr as usize..line.addr as usize + line.bytes.len()).contains(&(addr as usize)));
+    }
+
+    /// The address of a label, whatever case it is written in
+    pub fn address_of(&self, name: &str) -> Option<u16> {
+        return self.symbols.iter().find(|(symbol, _)| symbol.eq_ignore_ascii_case(name)).map(|(_, addr)| *addr);
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    return bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble_listing;
+
+    #[test]
+    fn listings_show_where_each_line_went() {
+        let (_, listing) = assemble_listing("; Counts down\nstart: LD V0 5\n.db 1 2 3 4 5 6\n.org 0x220\nend: JP start").unwrap();
+        let expected = "\
+ADDR  BYTES         LINE  SOURCE
+0200                   1  ; Counts down
+0200  60 05            2  start: LD V0 5
+0202  01 02 03 04      3  .db 1 2 3 4 5 6
+0206  05 06
+0220                   4  .org 0x220
+0220  12 00            5  end: JP start
+";
+        assert_eq!(listing.text(), expected);
+        assert_eq!(listing.symbol_map(), "0200 START\n0220 END\n");
+
+        // Both read back as they were, minus the space at the end of rows
+        assert_eq!(Listing::parse(&listing.text(), &listing.symbol_map()), Ok(listing.clone()));
+        assert_eq!(listing.line_at(0x205).map(|line| line.line), Some(3));
+        assert_eq!(listing.line_at(0x208), None);
+        assert_eq!(listing.address_of("end"), Some(0x220));
+        assert!(Listing::parse("ADDR\nzzzz", "").is_err());
+    }
+}
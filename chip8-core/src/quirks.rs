@@ -0,0 +1,267 @@
+use crate::chip8::XO_CHIP_MEMORY_SIZE;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Interpreters disagree on what a handful of instructions do, and ROMs are written against whichever one their author used.
+// Each quirk picks one of the behaviours, set from the command line as name=value:
+//   shift=vx|vy          8xy6/8xyE shift Vx in place, or shift Vy into Vx like the COSMAC VIP
+//   memory=increment|fixed  Fx55/Fx65 leave I past the last register, or where it was like SUPER-CHIP
+//   jump=v0|vx           Bnnn adds V0, or treats it as BXNN and adds Vx like SUPER-CHIP
+//   sprites=wrap|clip    Sprites that cross an edge of the screen wrap around to the other side, or are cut off
+//   vf-reset=off|on      8xy1/8xy2/8xy3 leave VF alone, or reset it to 0 like the COSMAC VIP
+//   key-wait=release|press  Fx0A takes a key once it is let go like the COSMAC VIP, or as soon as it is pressed
+// The defaults are the first of each, which is how this emulator has always behaved but for key-wait, which used to be press.
+//
+// A platform bundles the quirks, memory, screen, stack and speed of one family of interpreters:
+//   vip     COSMAC VIP: shift=vy, jump=v0, sprites=clip, vf-reset=on, a 12-level stack in memory, 8 cycles a frame
+//   schip   SUPER-CHIP 1.1: shift=vx, memory=fixed, jump=vx, sprites=clip, 128x64 screen, 30 cycles a frame
+//   xochip  XO-CHIP: shift=vy, sprites=wrap, 128x64 screen, 64K of memory, 100 cycles a frame. Its own instructions aren't emulated
+//   modern  What most newer interpreters do, which is this emulator's defaults, at 12 cycles a frame
+// Every platform has key-wait=release, and memory is 4K on every platform but the XO-CHIP.
+
+/// Names of the platforms `Platform::from_name` knows
+pub const PLATFORM_NAMES: [&str; 4] = ["vip", "schip", "xochip", "modern"];
+
+/// Each quirk's name, its value when off and when on, and what it changes
+const QUIRK_TABLE: [(&str, &str, &str, &str); 6] = [
+    ("shift", "vx", "vy", "8xy6/8xyE shift Vx in place, or shift Vy into Vx like the COSMAC VIP"),
+    ("memory", "fixed", "increment", "Fx55/Fx65 leave I past the last register, or where it was like SUPER-CHIP"),
+    ("jump", "v0", "vx", "Bnnn adds V0, or is BXNN and adds Vx like SUPER-CHIP"),
+    ("sprites", "wrap", "clip", "Sprites that cross an edge of the screen wrap around to the other side, or are cut off"),
+    ("vf-reset", "off", "on", "8xy1/8xy2/8xy3 leave VF alone, or reset it to 0 like the COSMAC VIP"),
+    ("key-wait", "release", "press", "Fx0A takes a key once it is let go like the COSMAC VIP, or as soon as it is pressed"),
+];
+
+/// Everything there is to know about one quirk, for listing them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuirkDescription {
+    /// The name `--quirk` takes
+    pub name: &'static str,
+    /// The two behaviours, the default first
+    pub values: [&'static str; 2],
+    pub description: &'static str,
+    /// The behaviour without a platform
+    pub default: &'static str,
+    /// The behaviour each platform picks, by platform name
+    pub platforms: BTreeMap<&'static str, &'static str>,
+    /// The behaviour ROMs ask for, by ROM; `describe` leaves it empty, for callers that know the ROMs to fill in
+    pub demanded_by: BTreeMap<String, String>,
+}
+
+/// A preset for one family of interpreters, which quirk and stack flags given on their own override
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Platform {
+    pub quirks: Quirks,
+    /// Whether the SUPER-CHIP instructions and 128x64 screen are used
+    pub schip: bool,
+    /// Whether the stack lives in memory like the COSMAC VIP's, 12 levels deep
+    pub vip_stack: bool,
+    /// Bytes of memory, which is the original 4K everywhere but the XO-CHIP
+    pub memory_size: usize,
+    pub cycles_per_frame: u64,
+}
+
+impl Platform {
+    /// Looks a platform up by name: `vip`, `schip`, `xochip` or `modern`
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        let modern = Self { quirks: Quirks::default(), schip: false, vip_stack: false, memory_size: 0x1000, cycles_per_frame: 12 };
+        return match name {
+            "vip" => Ok(Self {
+                quirks: Quirks { shift_uses_vy: true, clip_sprites: true, vf_reset: true, ..Quirks::default() },
+                vip_stack: true,
+                cycles_per_frame: 8,
+                ..modern
+            }),
+            "schip" => Ok(Self {
+                quirks: Quirks { memory_increments_i: false, jump_uses_vx: true, clip_sprites: true, ..Quirks::default() },
+                schip: true,
+                cycles_per_frame: 30,
+                ..modern
+            }),
+            "xochip" => Ok(Self {
+                quirks: Quirks { shift_uses_vy: true, ..Quirks::default() },
+                schip: true,
+                memory_size: XO_CHIP_MEMORY_SIZE,
+                cycles_per_frame: 100,
+                ..modern
+            }),
+            "modern" => Ok(modern),
+            _ => Err(format!("Unknown platform {}, expected vip, schip, xochip or modern", name)),
+        };
+    }
+}
+
+/// Which behaviour the emulator picks for each instruction interpreters disagree on
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift Vy into Vx, rather than Vx in place
+    pub shift_uses_vy: bool,
+    /// Fx55/Fx65 leave I pointing past the last register
+    pub memory_increments_i: bool,
+    /// Bnnn is BXNN, jumping to nnn + Vx rather than nnn + V0
+    pub jump_uses_vx: bool,
+    /// Sprites are cut off at the edges of the screen rather than wrapping around; SUPER-CHIP always clips
+    pub clip_sprites: bool,
+    /// 8xy1/8xy2/8xy3 reset VF to 0
+    pub vf_reset: bool,
+    /// Fx0A takes a key as soon as it is pressed, rather than once it is let go
+    pub key_wait_on_press: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        return Self {
+            shift_uses_vy: false,
+            memory_increments_i: true,
+            jump_uses_vx: false,
+            clip_sprites: false,
+            vf_reset: false,
+            key_wait_on_press: false,
+        };
+    }
+}
+
+impl Quirks {
+    /// Sets one quirk from a `name=value` pair, like `shift=vy`
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `spec` - The quirk and the behaviour to pick
+    /// 
+    /// ## Example
+    /// 
+    /// ```
+    /// use chip8_core::quirks::Quirks;
+    /// 
+    /// let mut quirks = Quirks::default();
+    /// quirks.set("shift=vy").unwrap();
+    /// assert!(quirks.shift_uses_vy);
+    /// assert_eq!(quirks.value("shift"), Some("vy"));
+    /// assert!(quirks.set("shift=sideways").is_err());
+    /// ```
+    pub fn set(&mut self, spec: &str) -> Result<(), String> {
+        let Some((name, value)) = spec.split_once('=') else {
+            return Err(format!("Expected a quirk as name=value, got {}", spec));
+        };
+
+        let name = name.trim();
+        let Some(&(_, off, on, _)) = QUIRK_TABLE.iter().find(|quirk| quirk.0 == name) else {
+            return Err(format!("Unknown quirk {}, expected shift, memory, jump, sprites, vf-reset or key-wait", name));
+        };
+
+        *self.flag_mut(name).expect("Every quirk in the table has a flag") = match value.trim() {
+            value if value == on => true,
+            value if value == off => false,
+            value => return Err(format!("Unknown value {} for quirk {}, expected {} or {}", value, name, off, on)),
+        };
+        return Ok(());
+    }
+
+    /// Names the behaviour picked for a quirk, like `vy` for `shift`, or `None` if there is no such quirk
+    pub fn value(&self, name: &str) -> Option<&'static str> {
+        let &(_, off, on, _) = QUIRK_TABLE.iter().find(|quirk| quirk.0 == name)?;
+        let mut quirks = *self;
+        return Some(if *quirks.flag_mut(name)? { on } else { off });
+    }
+
+    /// Switches a quirk to its other behaviour
+    /// 
+    /// ## Returns
+    /// 
+    /// The behaviour it switched to, like `vy` for `shift`
+    pub fn toggle(&mut self, name: &str) -> Result<&'static str, String> {
+        let Some(flag) = self.flag_mut(name) else {
+            return Err(format!("Unknown quirk {}, expected shift, memory, jump, sprites, vf-reset or key-wait", name));
+        };
+        *flag = !*flag;
+        return Ok(self.value(name).expect("Every quirk with a flag has a value"));
+    }
+
+    /// Lists every quirk's name with the behaviour picked for it, in the order `describe` lists them
+    pub fn values(&self) -> Vec<(&'static str, &'static str)> {
+        return QUIRK_TABLE.iter().map(|&(name, ..)| (name, self.value(name).expect("Every quirk in the table has a value"))).collect();
+    }
+
+    /// Describes every quirk, with the behaviour each platform picks
+    pub fn describe() -> Vec<QuirkDescription> {
+        let default = Quirks::default();
+        let platforms: Vec<(&str, Quirks)> = PLATFORM_NAMES.iter().map(|&name| (name, Platform::from_name(name).expect("Every platform name is known").quirks)).collect();
+        return QUIRK_TABLE.iter().map(|&(name, off, on, description)| {
+            let default = default.value(name).expect("Every quirk in the table has a value");
+            return QuirkDescription {
+                name,
+                values: if default == off { [off, on] } else { [on, off] },
+                description,
+                default,
+                platforms: platforms.iter().map(|(platform, quirks)| (*platform, quirks.value(name).expect("Every quirk in the table has a value"))).collect(),
+                demanded_by: BTreeMap::new(),
+            };
+        }).collect();
+    }
+
+    /// The flag behind a quirk's name
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        return match name {
+            "shift" => Some(&mut self.shift_uses_vy),
+            "memory" => Some(&mut self.memory_increments_i),
+            "jump" => Some(&mut self.jump_uses_vx),
+            "sprites" => Some(&mut self.clip_sprites),
+            "vf-reset" => Some(&mut self.vf_reset),
+            "key-wait" => Some(&mut self.key_wait_on_press),
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quirks_are_set_by_name() {
+        let mut quirks = Quirks::default();
+        quirks.set("shift=vy").unwrap();
+        quirks.set("memory=fixed").unwrap();
+        quirks.set("sprites = clip").unwrap();
+        assert_eq!(quirks, Quirks { shift_uses_vy: true, memory_increments_i: false, clip_sprites: true, ..Quirks::default() });
+
+        assert_eq!(quirks.toggle("shift"), Ok("vx"));
+        assert_eq!(quirks.toggle("jump"), Ok("vx"));
+        assert!(quirks.jump_uses_vx && !quirks.shift_uses_vy);
+        assert!(quirks.toggle("display").is_err());
+
+        assert_eq!(quirks.set("shift"), Err(String::from("Expected a quirk as name=value, got shift")));
+        assert_eq!(quirks.set("jump=v1"), Err(String::from("Unknown value v1 for quirk jump, expected v0 or vx")));
+        assert!(quirks.set("display=wait").is_err());
+    }
+
+    #[test]
+    fn quirks_describe_themselves() {
+        let descriptions = Quirks::describe();
+        assert_eq!(descriptions.iter().map(|quirk| quirk.name).collect::<Vec<_>>(), ["shift", "memory", "jump", "sprites", "vf-reset", "key-wait"]);
+
+        let shift = &descriptions[0];
+        assert_eq!((shift.values, shift.default), (["vx", "vy"], "vx"));
+        assert_eq!(shift.platforms["vip"], "vy");
+        assert_eq!(shift.platforms["schip"], "vx");
+        assert_eq!(descriptions[1].values, ["increment", "fixed"]);
+        assert_eq!(Quirks::default().value("memory"), Some("increment"));
+        assert_eq!(Quirks::default().value("display"), None);
+        assert_eq!(Quirks::default().values()[4], ("vf-reset", "off"));
+        assert_eq!(descriptions[5].values, ["release", "press"]);
+    }
+
+    #[test]
+    fn platforms_bundle_quirks() {
+        let vip = Platform::from_name("vip").unwrap();
+        let mut quirks = vip.quirks;
+        quirks.set("sprites=wrap").unwrap();
+        assert_eq!(quirks, Quirks { shift_uses_vy: true, vf_reset: true, ..Quirks::default() });
+        assert!(vip.vip_stack && !vip.schip);
+
+        assert!(Platform::from_name("schip").unwrap().schip);
+        assert_eq!(Platform::from_name("xochip").unwrap().memory_size, 0x10000);
+        assert_eq!(Platform::from_name("modern").unwrap().quirks, Quirks::default());
+        assert_eq!(Platform::from_name("eti660"), Err(String::from("Unknown platform eti660, expected vip, schip, xochip or modern")));
+    }
+}
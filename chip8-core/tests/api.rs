@@ -0,0 +1,61 @@
+// Uses the names chip8-core exports the way a frontend would, with their types spelled out.
+// A change that breaks this breaks frontends too, so it needs a new major version (or minor, before 1.0).
+
+use chip8_core::{assemble, disassemble, AsmError, Chip8, Chip8Error, CycleStatus, FrameBuffer, Instruction, OpcodePolicy, Quirks, StateInfo};
+
+#[test]
+fn a_frontend_can_run_a_rom() {
+    let rom: Vec<u8> = assemble("LD V0 0\nLD F V0\nDRW V0 V0 5\nloop: JP loop").unwrap();
+    let mut chip8: Chip8 = Chip8::new();
+    chip8.set_quirks(Quirks { clip_sprites: true, ..Quirks::default() });
+    chip8.set_opcode_policy(OpcodePolicy::Trap);
+    let loaded: Result<(), Chip8Error> = chip8.load_rom(&rom);
+    loaded.unwrap();
+    chip8.clear_keypad();
+    chip8.set_keypress(5);
+    chip8.set_key(5, false);
+    for _ in 0..3 {
+        let ran: Result<CycleStatus, Chip8Error> = chip8.cycle();
+        assert_eq!(ran, Ok(CycleStatus::Ran));
+    }
+    let executed: Option<Instruction> = chip8.step();
+    assert_eq!(executed, Some(Instruction::Jump { addr: 0x206 }));
+    chip8.update_timers();
+
+    let frame: FrameBuffer = chip8.get_frame_buffer();
+    assert_eq!((frame.width, frame.height, frame.pixels.len()), (64, 32, 64 * 32));
+    assert!(frame.is_lit(0, 0) && !frame.is_lit(1, 1));
+    assert_eq!(frame.rows().next().unwrap()[..4], [1, 1, 1, 1]);
+
+    let mut xo_chip: Chip8 = Chip8::new();
+    let resized: Result<(), String> = xo_chip.set_memory_size(0x10000);
+    resized.unwrap();
+    xo_chip.load_rom(&[0; 0x1000]).unwrap();
+
+    let error: Chip8Error = Chip8::new().load_rom(&[0; 0x1000]).unwrap_err();
+    assert_eq!(error, Chip8Error::RomTooLarge { size: 0x1000, max: 0xE00 });
+    let _: String = error.into();
+}
+
+#[test]
+fn a_frontend_can_assemble_and_disassemble() {
+    let errors: Vec<AsmError> = assemble("LD V0 300").unwrap_err();
+    assert_eq!((errors[0].line, errors[0].token.as_str(), errors[0].message.as_str()), (1, "300", "doesn't fit in 8 bits"));
+
+    let text: String = disassemble(&[0x00, 0xE0]);
+    assert_eq!(text, "CLS\n");
+    assert_eq!(Instruction::decode(0x00E0).to_string(), "CLS");
+    assert_eq!(Instruction::Clear.encode(), 0x00E0);
+}
+
+#[test]
+fn a_frontend_can_save_and_check_states() {
+    let mut chip8: Chip8 = Chip8::new();
+    chip8.load_rom(&assemble("loop: ADD V0 1\nJP loop").unwrap()).unwrap();
+    let state: Vec<u8> = chip8.save_state().unwrap();
+    let compressed: Vec<u8> = chip8_core::format::compress(&state);
+    let info: StateInfo = Chip8::inspect_state(&compressed).unwrap();
+    assert_eq!((info.decompressed_size, info.schip, info.pc), (state.len(), false, 0x200));
+    let loaded: Result<(), String> = chip8.load_state(&compressed);
+    loaded.unwrap();
+}
@@ -0,0 +1,51 @@
+// Drives the debugging API from code: a breakpoint, a watchpoint on memory, stepping one instruction at a time and looking at
+// the registers, stack and the instructions that led up to a stop. The --debug panel is built on the same calls.
+//   cargo run --example debugger
+
+use chip8::chip8::Watchpoint;
+use chip8::{assemble, Chip8};
+
+const SOURCE: &str = "
+    LD V0 0
+loop:
+    ADD V0 3
+    CALL store
+    JP loop
+store:
+    LD I 0x300
+    LD [I] V0
+    RET
+";
+
+fn main() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&assemble(SOURCE).expect("The program assembles")).unwrap();
+    chip8.set_pc_history_size(8);
+    chip8.add_watchpoint(Watchpoint::parse("mem 0x300").unwrap());
+
+    // Run to a breakpoint at `store`, the fifth instruction
+    let breakpoint = 0x208;
+    while chip8.get_pc() != breakpoint {
+        chip8.cycle().unwrap();
+    }
+    println!("Stopped at {:03X} with V0 = {}, stack {:03X?}", chip8.get_pc(), chip8.get_registers()[0], chip8.get_stack());
+
+    // Step on until the watched byte changes a few times, printing each instruction as it runs
+    let mut hits = 0;
+    while hits < 3 {
+        let pc = chip8.get_pc();
+        let Some(instruction) = chip8.step() else {
+            break;
+        };
+        println!("{:03X}  {}", pc, instruction);
+        for hit in chip8.take_watch_hits() {
+            println!("      {}", hit);
+            hits += 1;
+        }
+    }
+
+    println!("The last instructions run:");
+    for (pc, opcode) in chip8.get_pc_history() {
+        println!("{:03X}  {:04X}", pc, opcode);
+    }
+}
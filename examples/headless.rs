@@ -0,0 +1,32 @@
+// Runs a ROM for a few seconds without a terminal and prints the screen as text.
+//   cargo run --example headless -- c8games/BRIX 120
+
+use chip8::input_script::{replay, InputScript};
+use chip8::Chip8;
+use std::env;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let Some(rom_path) = args.next() else {
+        println!("Usage: headless <rom> [frames]");
+        return;
+    };
+    let frames = args.next().and_then(|frames| frames.parse().ok()).unwrap_or(180);
+
+    let rom = std::fs::read(&rom_path).expect("Failed to read ROM");
+    let mut chip8 = Chip8::new();
+    if let Err(message) = chip8.load_rom(&rom) {
+        println!("{}", message);
+        return;
+    }
+    chip8.seed_rng(0);
+
+    // No keys are pressed; an InputScript loaded from a file would press them on cue
+    replay(&mut chip8, &InputScript::new(), frames, 8);
+
+    let (width, _height) = chip8.get_screen_size();
+    for row in chip8.get_screen_buffer().chunks(width) {
+        let line: String = row.iter().map(|pixel| if *pixel != 0 { '#' } else { ' ' }).collect();
+        println!("{}", line.trim_end());
+    }
+}
@@ -0,0 +1,70 @@
+// Draws frames through a render sink of the frontend's own. The library hands over each frame as a FrameBuffer and leaves
+// drawing it to the frontend, so a terminal, a window or a file all fit behind the same trait.
+// This sink writes every frame that differs from the one before as a PBM image, which most image viewers open.
+//   cargo run --example render_sink -- c8games/BRIX frames 120
+
+use chip8::chip8::FrameBuffer;
+use chip8::Chip8;
+use std::env;
+use std::path::PathBuf;
+
+/// Somewhere finished frames go
+trait RenderSink {
+    fn present(&mut self, frame: &FrameBuffer) -> Result<(), String>;
+}
+
+/// Writes each frame that changed as a numbered PBM image in a directory
+struct PbmSink {
+    dir: PathBuf,
+    last: Vec<u8>,
+    written: usize,
+}
+
+impl RenderSink for PbmSink {
+    fn present(&mut self, frame: &FrameBuffer) -> Result<(), String> {
+        if frame.pixels == self.last.as_slice() {
+            return Ok(());
+        }
+        self.last = frame.pixels.to_vec();
+
+        let mut image = format!("P1\n{} {}\n", frame.width, frame.height);
+        for row in frame.rows() {
+            let pixels: Vec<&str> = row.iter().map(|pixel| if *pixel != 0 { "1" } else { "0" }).collect();
+            image.push_str(&pixels.join(" "));
+            image.push('\n');
+        }
+        self.written += 1;
+        let path = self.dir.join(format!("{:04}.pbm", self.written));
+        return std::fs::write(&path, image).map_err(|e| format!("Failed to write {}: {}", path.display(), e));
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [rom_path, dir, ..] = &args[..] else {
+        println!("Usage: render_sink <rom> <dir> [frames]");
+        return;
+    };
+    let frames = args.get(2).and_then(|frames| frames.parse().ok()).unwrap_or(120);
+
+    let rom = std::fs::read(rom_path).expect("Failed to read ROM");
+    let mut chip8 = Chip8::new();
+    if let Err(message) = chip8.load_rom(&rom) {
+        println!("{}", message);
+        return;
+    }
+    std::fs::create_dir_all(dir).expect("Failed to create the directory");
+    let mut sink = PbmSink { dir: PathBuf::from(dir), last: Vec::new(), written: 0 };
+
+    for _ in 0..frames {
+        for _ in 0..8 {
+            chip8.cycle().expect("The ROM stopped with an error");
+        }
+        chip8.update_timers();
+        if let Err(message) = sink.present(&chip8.get_frame_buffer()) {
+            println!("{}", message);
+            return;
+        }
+    }
+    println!("Wrote {} frames to {}", sink.written, dir);
+}
@@ -0,0 +1,66 @@
+// Feeds keys to the machine from an input script rather than a keyboard. The library has no input trait of its own, as keys
+// reach the machine through set_key, so a frontend that wants a keyboard and a script to be interchangeable puts a trait like
+// this one in front of it. The script is the one --record-input writes, and this one plays BRIX with the paddle moving right.
+//   cargo run --example scripted_input -- c8games/BRIX [script]
+
+use chip8::input_script::InputScript;
+use chip8::Chip8;
+use std::env;
+
+/// Where the keys held down come from, asked before every instruction
+trait Input {
+    fn keys(&mut self, frame: u32, tick: u64) -> Vec<usize>;
+}
+
+/// Keys pressed on cue from a script
+struct Scripted(InputScript);
+
+impl Input for Scripted {
+    fn keys(&mut self, frame: u32, tick: u64) -> Vec<usize> {
+        return self.0.keys_at(frame, tick);
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let Some(rom_path) = args.next() else {
+        println!("Usage: scripted_input <rom> [script]");
+        return;
+    };
+    let script = match args.next() {
+        Some(path) => InputScript::load(&path),
+        None => InputScript::parse(&(60..90).map(|frame| format!("{} 0 6\n", frame)).collect::<String>()),
+    };
+    let mut input = match script {
+        Ok(script) => Scripted(script),
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
+
+    let rom = std::fs::read(&rom_path).expect("Failed to read ROM");
+    let mut chip8 = Chip8::new();
+    if let Err(message) = chip8.load_rom(&rom) {
+        println!("{}", message);
+        return;
+    }
+    chip8.seed_rng(0);
+
+    // Scripts hold a key for each frame it is listed in, so the keypad starts every frame with every key up
+    for frame in 0..180 {
+        chip8.clear_keypad();
+        for tick in 0..8 {
+            for key in input.keys(frame, tick) {
+                chip8.set_key(key, true);
+            }
+            chip8.cycle().expect("The ROM stopped with an error");
+        }
+        chip8.update_timers();
+    }
+
+    for row in chip8.get_frame_buffer().rows() {
+        let line: String = row.iter().map(|pixel| if *pixel != 0 { '#' } else { ' ' }).collect();
+        println!("{}", line.trim_end());
+    }
+}
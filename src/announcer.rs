@@ -0,0 +1,180 @@
+use crate::chip8::Chip8;
+use std::fs::File;
+use std::io::{self, Write};
+use std::process;
+
+// An announcement script names the values in a ROM's memory that matter to the player, one per line:
+//   # label = value
+//   Score = bcd[0x2F0]
+//   Lives = mem[0x2F4]
+//   Level = V7
+// mem[addr] is the byte at an address, bcd[addr] the three decimal digits LD B, Vx leaves there, and Vx a register.
+// Whenever a value changes, "Label: value" is announced, so a game can be followed without seeing the screen.
+
+/// Where a watched value is read from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Watch {
+    Memory(u16),
+    Bcd(u16),
+    Register(usize),
+}
+
+impl Watch {
+    /// Reads the value from the emulator
+    pub fn read(&self, chip8: &Chip8) -> u32 {
+        let memory = chip8.get_memory();
+        return match *self {
+            Watch::Memory(addr) => memory[addr as usize] as u32,
+            Watch::Bcd(addr) => memory[addr as usize..addr as usize + 3].iter().fold(0, |value, digit| value * 10 + *digit as u32),
+            Watch::Register(register) => chip8.get_registers()[register] as u32,
+        };
+    }
+
+    /// Reads a watch from a script, like `mem[0x2F0]`, `bcd[0x2F0]` or `V3`
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        // The last address the value's bytes can start at
+        let address = |inner: &str, last: u16| {
+            let digits = inner.trim_start_matches("0x").trim_start_matches("0X");
+            return match u16::from_str_radix(digits, 16) {
+                Ok(addr) if addr <= last => Ok(addr),
+                _ => Err(format!("{} is not an address", inner)),
+            };
+        };
+
+        if let Some(inner) = text.strip_prefix("mem[").and_then(|rest| rest.strip_suffix(']')) {
+            return address(inner, 0xFFF).map(Watch::Memory);
+        }
+        if let Some(inner) = text.strip_prefix("bcd[").and_then(|rest| rest.strip_suffix(']')) {
+            return address(inner, 0xFFD).map(Watch::Bcd);
+        }
+        if let Some(register) = text.strip_prefix(['V', 'v']).and_then(|digit| usize::from_str_radix(digit, 16).ok()).filter(|register| *register < 16) {
+            return Ok(Watch::Register(register));
+        }
+        return Err(format!("{} is not mem[addr], bcd[addr] or a register", text));
+    }
+}
+
+/// Where announcements go
+pub enum AnnounceTarget {
+    Stdout,
+    File(File),
+    /// Spoken through speech-dispatcher's spd-say
+    Speech,
+}
+
+impl AnnounceTarget {
+    /// Whether a target name means stdout
+    pub fn is_stdout(name: &str) -> bool {
+        return name == "stdout" || name == "-";
+    }
+
+    /// Picks a target by name: `stdout`, `speech`, or a path to append to
+    pub fn open(name: &str) -> io::Result<Self> {
+        return match name {
+            name if Self::is_stdout(name) => Ok(AnnounceTarget::Stdout),
+            "speech" => Ok(AnnounceTarget::Speech),
+            path => File::options().create(true).append(true).open(path).map(AnnounceTarget::File),
+        };
+    }
+
+    fn announce(&mut self, line: &str) -> io::Result<()> {
+        return match self {
+            AnnounceTarget::Stdout => {
+                let mut stdout = io::stdout();
+                writeln!(stdout, "{}\r", line)?;
+                stdout.flush()
+            },
+            AnnounceTarget::File(file) => writeln!(file, "{}", line),
+            AnnounceTarget::Speech => process::Command::new("spd-say").arg(line).spawn().map(|_| ()),
+        };
+    }
+}
+
+/// Watches values in memory and announces them when they change
+pub struct Announcer {
+    watches: Vec<(String, Watch)>,
+    /// What each watch read last time, `None` before the first poll
+    last: Vec<Option<u32>>,
+}
+
+impl Announcer {
+    /// Reads an announcement script
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `script` - Lines of `Label = watch`, with blank lines and # comments allowed
+    pub fn parse(script: &str) -> Result<Self, String> {
+        let mut watches = Vec::new();
+        for (number, line) in script.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let Some((label, watch)) = line.split_once('=') else {
+                return Err(format!("line {}: expected Label = value", number + 1));
+            };
+            let watch = Watch::parse(watch).map_err(|message| format!("line {}: {}", number + 1, message))?;
+            watches.push((label.trim().to_string(), watch));
+        }
+
+        let last = vec![None; watches.len()];
+        return Ok(Self { watches, last });
+    }
+
+    /// Checks every watched value, returning an announcement for each one that changed since the last poll
+    /// 
+    /// The first poll announces everything, so the player hears where the game starts.
+    pub fn poll(&mut self, chip8: &Chip8) -> Vec<String> {
+        let mut announcements = Vec::new();
+        for ((label, watch), last) in self.watches.iter().zip(self.last.iter_mut()) {
+            let value = watch.read(chip8);
+            if *last != Some(value) {
+                *last = Some(value);
+                announcements.push(format!("{}: {}", label, value));
+            }
+        }
+        return announcements;
+    }
+
+    /// Polls and sends any announcements to a target
+    pub fn announce(&mut self, chip8: &Chip8, target: &mut AnnounceTarget) -> io::Result<()> {
+        for line in self.poll(chip8) {
+            target.announce(&line)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble;
+
+    #[test]
+    fn changes_are_announced_once() {
+        let mut announcer = Announcer::parse("# Test game\nScore = bcd[0x300]\n\nLives = V3  # counts down\n").unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble("LD V3 3\nLD V0 142\nLD I 0x300\nLD B V0\nADD V3 0xFF\nloop: JP loop").unwrap()).unwrap();
+
+        assert_eq!(announcer.poll(&chip8), vec!["Score: 0", "Lives: 0"]);
+        for _ in 0..4 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(announcer.poll(&chip8), vec!["Score: 142", "Lives: 3"]);
+        assert!(announcer.poll(&chip8).is_empty());
+
+        chip8.cycle().unwrap();
+        assert_eq!(announcer.poll(&chip8), vec!["Lives: 2"]);
+    }
+
+    #[test]
+    fn scripts_are_checked() {
+        assert_eq!(Watch::parse("mem[0x2F0]"), Ok(Watch::Memory(0x2F0)));
+        assert_eq!(Watch::parse("vA"), Ok(Watch::Register(0xA)));
+        assert_eq!(Announcer::parse("Score").err(), Some(String::from("line 1: expected Label = value")));
+        assert_eq!(Announcer::parse("\nScore = V10").err(), Some(String::from("line 2: V10 is not mem[addr], bcd[addr] or a register")));
+        assert_eq!(Announcer::parse("Score = bcd[0xFFE]").err(), Some(String::from("line 1: 0xFFE is not an address")));
+    }
+}
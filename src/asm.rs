@@ -1,28 +1,194 @@
-use crate::chip8::*;
-pub mod chip8;
+use chip8::chip8::*;
+use chip8::include::*;
+use chip8::link::*;
+use chip8::listing::*;
+use chip8::octo::*;
+use chip8::register_usage::*;
+use chip8::size_report::*;
 
 use std::env;
+use std::path::{Path, PathBuf};
 
 fn main() {
-    // Read arguments
-    let args: Vec<String> = env::args().collect();
+    // Read arguments, pulling out the optional flags
+    let mut show_size_report = false;
+    let mut show_registers = false;
+    let mut object = false;
+    let mut link_layout = false;
+    let mut limit = MAX_ROM_SIZE;
+    let mut octo = false;
+    let mut write_listing = false;
+    let mut args: Vec<String> = Vec::new();
+    let mut all_args = env::args().skip(1);
+    while let Some(arg) = all_args.next() {
+        match arg.as_str() {
+            "--size-report" => show_size_report = true,
+            "--registers" => show_registers = true,
+            "--xo-chip" => limit = XO_CHIP_MAX_ROM_SIZE,
+            "--object" => object = true,
+            "--link" => link_layout = true,
+            "--listing" => write_listing = true,
+            "--dialect" => match all_args.next().as_deref() {
+                Some("octo") => octo = true,
+                Some("cowgod") => octo = false,
+                other => {
+                    println!("Unknown dialect {}, the assembler reads cowgod (the default) or octo", other.unwrap_or_default());
+                    return;
+                }
+            },
+            _ => args.push(arg),
+        }
+    }
 
     // Check if there are enough arguments
-    if args.len() < 3 {
-        println!("Usage: asm <input> <output>");
+    if args.len() < 2 {
+        println!("Usage: asm [--dialect cowgod|octo] [--size-report] [--registers] [--listing] [--xo-chip] <input> <output>");
+        println!("       asm [--dialect cowgod|octo] --object <input> <output>");
+        println!("       asm --link [--xo-chip] <layout> <output>");
+        return;
+    }
+
+    if link_layout {
+        link_objects(&args[0], &args[1], limit);
         return;
     }
 
-    // Read input file
-    let input: String = std::fs::read_to_string(&args[1]).expect("Failed to read input file");
+    // Read input file and everything it includes
+    let (source, files) = match expand_file(Path::new(&args[0])) {
+        Ok(expanded) => expanded,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
 
-    // Assemble input file
+    // Assemble input file, translating Octo's syntax into ours first
     let start_time = std::time::Instant::now();
-    let output = assemble(&input);
+    let translation = octo.then(|| translate_octo(&source).unwrap_or_else(|errors| fail(&errors)));
+    let input = match translation.as_ref() {
+        Some(translation) => translation.source.clone(),
+        None => source.clone(),
+    };
+    let octo_lines = |errors: Vec<AssembleError>| match translation.as_ref() {
+        Some(translation) => translation.errors(errors),
+        None => errors,
+    };
+    if object {
+        // Labels left undefined are for the linker to find, so only an object can use them
+        let code = assemble_relocatable(&input).unwrap_or_else(|errors| fail(&octo_lines(errors)));
+        std::fs::write(&args[1], write_object(&code)).expect("Failed to write output file");
+        println!("Assembled {} in {}us. Output: {}", describe_inputs(&files), start_time.elapsed().as_micros(), &args[1]);
+        return;
+    }
+    let (output, warnings) = assemble_with_warnings(&input).unwrap_or_else(|errors| fail(&octo_lines(errors)));
+    let warnings = match translation.as_ref() {
+        Some(translation) => translation.warnings(warnings),
+        None => warnings,
+    };
+    for warning in warnings.iter() {
+        println!("warning: {}", warning);
+    }
+
+    if show_size_report {
+        print!("{}", size_report(&input, output.len(), limit));
+    }
 
-    // Write output file
-    std::fs::write(&args[2], output).expect("Failed to write output file");
+    if show_registers {
+        print!("{}", RegisterUsage::analyze(&output).report());
+    }
+
+    // Write output file, and the listing and symbol map beside it
+    std::fs::write(&args[1], output).expect("Failed to write output file");
+    if write_listing {
+        let (_, listing) = assemble_listing(&input).unwrap_or_else(|errors| fail(&octo_lines(errors)));
+        let listing = match translation.as_ref() {
+            Some(translation) => translation.listing(listing, &source),
+            None => listing,
+        };
+        write_listing_files(&listing, Path::new(&args[1]));
+    }
 
     // Print time taken
-    println!("Assembled file in {}us. Output: {}", start_time.elapsed().as_micros(), &args[2]);
-}
\ No newline at end of file
+    println!("Assembled {} in {}us. Output: {}", describe_inputs(&files), start_time.elapsed().as_micros(), &args[1]);
+}
+
+/// Names the files that went into the output for the timing report, like `3 files (main.s8, sprites.s8, ...)`
+/// 
+/// ## Arguments
+/// 
+/// * `files` - The input file and everything it included
+fn describe_inputs(files: &[PathBuf]) -> String {
+    if files.len() == 1 {
+        return String::from("file");
+    }
+    let names: Vec<String> = files.iter().map(|file| file.display().to_string()).collect();
+    return format!("{} files ({})", files.len(), names.join(", "));
+}
+
+/// Writes a listing and symbol map named after the ROM, like `game.lst` and `game.sym` for `game.ch8`
+/// 
+/// ## Arguments
+/// 
+/// * `listing` - The listing
+/// * `output_path` - Where the ROM was written
+fn write_listing_files(listing: &Listing, output_path: &Path) {
+    let listing_path = output_path.with_extension("lst");
+    let symbols_path = output_path.with_extension("sym");
+    std::fs::write(&listing_path, listing.text()).expect("Failed to write listing file");
+    std::fs::write(&symbols_path, listing.symbol_map()).expect("Failed to write symbol map");
+    println!("Listing: {}, symbols: {}", listing_path.display(), symbols_path.display());
+}
+
+/// Prints every error that stopped the source from being assembled and exits with a failure code
+/// 
+/// ## Arguments
+/// 
+/// * `errors` - The errors, in line order
+fn fail(errors: &[AssembleError]) -> ! {
+    for error in errors {
+        println!("error: {}", error);
+    }
+    println!("{} error{}, nothing was written", errors.len(), if errors.len() == 1 { "" } else { "s" });
+    std::process::exit(1);
+}
+
+/// Links the objects listed in a layout file into a ROM
+/// 
+/// ## Arguments
+/// 
+/// * `layout_path` - The layout file
+/// * `output_path` - Where to write the ROM
+/// * `limit` - The most bytes the platform can load
+fn link_objects(layout_path: &str, output_path: &str, limit: usize) {
+    let start_time = std::time::Instant::now();
+    let layout_text = std::fs::read_to_string(layout_path).expect("Failed to read layout file");
+    let dir = Path::new(layout_path).parent().unwrap_or(Path::new("."));
+
+    let layout = match parse_layout(&layout_text, dir) {
+        Ok(layout) => layout,
+        Err(e) => {
+            println!("{}: {}", layout_path, e);
+            return;
+        }
+    };
+
+    let mut objects = Vec::new();
+    for (path, addr) in layout {
+        let text = std::fs::read_to_string(&path).expect("Failed to read object file");
+        match read_object(&text) {
+            Ok(code) => objects.push(LinkObject { name: path, code, addr }),
+            Err(e) => {
+                println!("{}: {}", path, e);
+                return;
+            }
+        }
+    }
+
+    match link(&objects, limit) {
+        Ok(rom) => {
+            std::fs::write(output_path, rom).expect("Failed to write output file");
+            println!("Linked {} objects in {}us. Output: {}", objects.len(), start_time.elapsed().as_micros(), output_path);
+        },
+        Err(e) => println!("Link failed: {}", e),
+    }
+}
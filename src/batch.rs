@@ -0,0 +1,181 @@
+use crate::chip8::Chip8;
+use crate::input_script::{replay, InputScript};
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Smoke tests a directory of ROMs by running each one headless for a fixed number of frames, several at a time.
+// Every ROM gets a fresh emulator with RND seeded to 0 and no keys pressed, so the same build always gives the same results,
+// and the screen hashes can be compared between releases to spot ROMs whose output changed.
+
+/// How a ROM got on when it was run without crashing
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    /// FNV-1a hash of the final screen
+    pub screen_hash: u64,
+    pub unknown_opcodes: u64,
+    /// Where the ROM got to
+    pub pc: u16,
+}
+
+/// The result of running one ROM of a batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomReport {
+    /// File name of the ROM
+    pub name: String,
+    /// The summary, or why the ROM couldn't be read or what it crashed with
+    pub outcome: Result<RunSummary, String>,
+}
+
+/// Hashes a screen buffer with 64-bit FNV-1a, which stays the same between builds and platforms
+pub fn hash_screen(screen: &[u8]) -> u64 {
+    let mut hash: u64 = 0xCBF29CE484222325;
+    for pixel in screen {
+        hash ^= *pixel as u64;
+        hash = hash.wrapping_mul(0x100000001B3);
+    }
+    return hash;
+}
+
+/// Runs a ROM headless from power on, catching any panic it causes
+/// 
+/// ## Arguments
+/// 
+/// * `rom` - The ROM
+/// * `frames` - Number of frames to run
+/// * `ticks_per_frame` - Instructions run each frame
+/// 
+/// ## Returns
+/// 
+/// The summary of the run, or the error that stopped it or the message it crashed with
+pub fn run_rom(rom: &[u8], frames: u32, ticks_per_frame: u64) -> Result<RunSummary, String> {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(rom)?;
+
+    let run = panic::catch_unwind(AssertUnwindSafe(|| {
+        chip8.seed_rng(0);
+        replay(&mut chip8, &InputScript::new(), frames, ticks_per_frame);
+        if let Some(error) = chip8.get_error() {
+            return Err(format!("stopped: {}", error));
+        }
+        return Ok(RunSummary {
+            screen_hash: hash_screen(chip8.get_screen_buffer()),
+            unknown_opcodes: chip8.get_unknown_opcode_count(),
+            pc: chip8.get_pc(),
+        });
+    }));
+
+    return run.unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<&str>().map(|message| message.to_string()).or_else(|| payload.downcast_ref::<String>().cloned());
+        return Err(format!("crashed: {}", message.unwrap_or_else(|| String::from("unknown panic"))));
+    });
+}
+
+/// Runs every file in a directory as a ROM, spread over several threads
+/// 
+/// Panics in the ROMs are caught, but the panic hook still reports them; set a quiet one first to keep stderr clean.
+/// 
+/// ## Arguments
+/// 
+/// * `dir` - The directory; subdirectories are skipped
+/// * `frames` - Number of frames to run each ROM for
+/// * `ticks_per_frame` - Instructions run each frame
+/// * `jobs` - Number of ROMs to run at once
+/// 
+/// ## Returns
+/// 
+/// A report for every ROM, in order of file name
+pub fn run_batch(dir: &Path, frames: u32, ticks_per_frame: u64, jobs: usize) -> Result<Vec<RomReport>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_file()).collect();
+    paths.sort();
+
+    // Workers take the next ROM off the list until there are none left
+    let next = AtomicUsize::new(0);
+    let reports: Mutex<Vec<(usize, RomReport)>> = Mutex::new(Vec::with_capacity(paths.len()));
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(i) else {
+                        return;
+                    };
+
+                    let outcome = std::fs::read(path)
+                        .map_err(|e| format!("unreadable: {}", e))
+                        .and_then(|rom| run_rom(&rom, frames, ticks_per_frame));
+                    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    reports.lock().expect("A worker panicked outside a ROM").push((i, RomReport { name, outcome }));
+                }
+            });
+        }
+    });
+
+    let mut reports = reports.into_inner().expect("A worker panicked outside a ROM");
+    reports.sort_by_key(|(i, _)| *i);
+    return Ok(reports.into_iter().map(|(_, report)| report).collect());
+}
+
+/// Lays a batch's reports out as a table, with a line of totals at the end
+/// 
+/// ## Arguments
+/// 
+/// * `reports` - The reports, in the order to list them
+pub fn summary_table(reports: &[RomReport]) -> String {
+    let name_width = reports.iter().map(|report| report.name.len()).max().unwrap_or(0).max(3);
+    let mut table = String::new();
+    writeln!(table, "{:<width$}  {:<16}  {:>7}  {:>5}  Result", "ROM", "Screen hash", "Unknown", "PC", width = name_width).unwrap();
+
+    let (mut crashed, mut with_unknown) = (0, 0);
+    for report in reports {
+        match &report.outcome {
+            Ok(summary) => {
+                writeln!(table, "{:<width$}  {:016X}  {:>7}  {:>5}  ok", report.name, summary.screen_hash, summary.unknown_opcodes, format!("{:03X}", summary.pc), width = name_width).unwrap();
+                if summary.unknown_opcodes > 0 {
+                    with_unknown += 1;
+                }
+            },
+            Err(message) => {
+                writeln!(table, "{:<width$}  {:<16}  {:>7}  {:>5}  {}", report.name, "-", "-", "-", message, width = name_width).unwrap();
+                crashed += 1;
+            },
+        }
+    }
+
+    writeln!(table, "{} ROMs: {} ran, {} failed, {} hit unknown opcodes", reports.len(), reports.len() - crashed, crashed, with_unknown).unwrap();
+    return table;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{assemble, MAX_ROM_SIZE};
+
+    #[test]
+    fn crashes_are_caught() {
+        assert_eq!(run_rom(&assemble("RET").unwrap(), 10, 8), Err(String::from("stopped: 200: RET with nothing on the stack to return to")));
+        assert!(run_rom(&vec![0; MAX_ROM_SIZE + 1], 10, 8).is_err());
+        assert_eq!(run_rom(&assemble("LD I 0xFFF\nLD V1 [I]").unwrap(), 10, 8), Err(String::from("stopped: 202: 1000 is past the end of memory")));
+
+        let brix = include_bytes!("../c8games/BRIX");
+        let summary = run_rom(brix, 60, 8).unwrap();
+        assert_eq!(run_rom(brix, 60, 8), Ok(summary));
+    }
+
+    #[test]
+    fn directories_run_in_order() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("c8games");
+        let reports = run_batch(&dir, 30, 8, 4).unwrap();
+        let names: Vec<&str> = reports.iter().map(|report| report.name.as_str()).collect();
+        assert_eq!(names.len(), std::fs::read_dir(&dir).unwrap().count());
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // Running one at a time gives the same results
+        assert_eq!(run_batch(&dir, 30, 8, 1).unwrap(), reports);
+        assert!(summary_table(&reports).ends_with(&format!("{} ROMs: {} ran, 0 failed, 0 hit unknown opcodes\n", reports.len(), reports.len())));
+    }
+}
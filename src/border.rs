@@ -0,0 +1,123 @@
+use chip8::chip8::CoreEvent;
+
+// The frame's border shows how the machine is doing at a glance, from the events it raises rather than by asking it:
+//   red                  the ROM was stopped by --strict
+//   yellow               the emulator is paused, in the background or in the debugger
+//   pulsing cyan         FX0A is waiting for a key
+//   ╰───•─────────╯      the delay timer's progress, the tick moving right as it counts down from where the ROM set it
+
+/// Border color once the ROM has been stopped at a fault
+const FAULT_COLOR: &str = "\x1b[31m";
+
+/// Border color while the emulator is paused
+const PAUSED_COLOR: &str = "\x1b[33m";
+
+/// Border colors while the ROM waits for a key, taking turns
+const WAITING_COLORS: [&str; 2] = ["\x1b[1;36m", "\x1b[2;36m"];
+
+/// Frames each of the waiting colors lasts
+const PULSE_FRAMES: u32 = 15;
+
+/// What the border shows around the screen
+#[derive(Clone, Copy, Default)]
+pub struct Border {
+    /// Whether the beep light is on
+    pub beeping: bool,
+    /// Escape sequence to draw the border in, or the terminal's own color if `None`
+    pub color: Option<&'static str>,
+    /// How far the delay timer has counted down, from 0 when the ROM set it to 1 when it runs out
+    pub delay_progress: Option<f32>,
+}
+
+impl Border {
+    /// A border with nothing but the beep light, for frames drawn without following the machine
+    pub fn plain(sound_timer: u8) -> Self {
+        return Self { beeping: sound_timer > 0, ..Self::default() };
+    }
+
+    /// A border for while the emulator is paused
+    pub fn paused() -> Self {
+        return Self { color: Some(PAUSED_COLOR), ..Self::default() };
+    }
+
+    /// Column of the delay timer's tick along a border `width` characters long, if there's a tick to show
+    pub fn delay_tick(&self, width: usize) -> Option<usize> {
+        let progress = self.delay_progress?;
+        return Some(((progress * width.saturating_sub(1) as f32).round() as usize).min(width.saturating_sub(1)));
+    }
+}
+
+/// How the machine is doing, kept up to date from its events
+#[derive(Default)]
+pub struct MachineHealth {
+    /// Whether FX0A waited for a key during the last frame
+    waiting_for_key: bool,
+    /// Whether strict mode stopped the ROM
+    fault: bool,
+    /// The delay timer's value when the ROM last set it, and what is left of it
+    delay: Option<(u8, u8)>,
+}
+
+impl MachineHealth {
+    /// Takes in what happened during a frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `events` - The events the machine raised during the frame
+    /// * `timer_ticks` - How many times the timers counted down at the end of it
+    pub fn frame(&mut self, events: &[CoreEvent], timer_ticks: u64) {
+        self.waiting_for_key = false;
+        for event in events {
+            match *event {
+                CoreEvent::WaitingForKey => self.waiting_for_key = true,
+                CoreEvent::DelaySet(0) => self.delay = None,
+                CoreEvent::DelaySet(value) => self.delay = Some((value, value)),
+                CoreEvent::Halted { fault } => self.fault |= fault,
+            }
+        }
+
+        if let Some((start, left)) = self.delay {
+            let left = left.saturating_sub(timer_ticks.min(u8::MAX as u64) as u8);
+            self.delay = (left > 0).then_some((start, left));
+        }
+    }
+
+    /// The border to draw around a frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `sound_timer` - The sound timer, for the beep light
+    /// * `frame` - The frame being drawn, which the pulse is timed by
+    pub fn border(&self, sound_timer: u8, frame: u32) -> Border {
+        let color = match (self.fault, self.waiting_for_key) {
+            (true, _) => Some(FAULT_COLOR),
+            (false, true) => Some(WAITING_COLORS[(frame / PULSE_FRAMES % 2) as usize]),
+            (false, false) => None,
+        };
+        let delay_progress = self.delay.map(|(start, left)| 1.0 - left as f32 / start as f32);
+        return Border { beeping: sound_timer > 0, color, delay_progress };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_set_the_border() {
+        let mut health = MachineHealth::default();
+        health.frame(&[CoreEvent::DelaySet(4), CoreEvent::WaitingForKey], 1);
+        let border = health.border(0, 0);
+        assert_eq!(border.color, Some(WAITING_COLORS[0]));
+        assert_eq!(border.delay_tick(9), Some(2));
+        assert_eq!(health.border(0, PULSE_FRAMES).color, Some(WAITING_COLORS[1]));
+
+        // The key came, and the delay timer runs out
+        health.frame(&[], 3);
+        let border = health.border(1, 0);
+        assert!(border.beeping && border.color.is_none() && border.delay_tick(9).is_none());
+
+        health.frame(&[CoreEvent::WaitingForKey, CoreEvent::Halted { fault: true }], 1);
+        assert_eq!(health.border(0, 0).color, Some(FAULT_COLOR));
+    }
+}
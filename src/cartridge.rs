@@ -0,0 +1,168 @@
+use crate::octo::assemble_octo;
+
+// Octo cartridges are GIF images with a program hidden inside them.
+// The low two bits of every pixel's palette index carry data, four pixels to a byte (most significant pair first), read across all frames in order.
+// The decoded bytes start with a 32-bit big-endian length, followed by that many bytes of JSON: { "program": "<source>", "options": { ... } }
+
+/// Settings embedded in an Octo cartridge
+#[derive(Debug, Default)]
+pub struct CartridgeOptions {
+    pub tickrate: Option<u64>,
+    pub shift_quirks: Option<bool>,
+    pub load_store_quirks: Option<bool>,
+    pub jump_quirks: Option<bool>,
+    pub logic_quirks: Option<bool>,
+    pub clip_quirks: Option<bool>,
+    pub vblank_quirks: Option<bool>,
+}
+
+impl CartridgeOptions {
+    /// The quirks the cartridge asks for, as `--quirk` takes them, like `shift=vx`
+    /// 
+    /// Octo's quirk flags are named for the SUPER-CHIP behaviour, so `shiftQuirks` is `shift=vx` and `loadStoreQuirks` is `memory=fixed`.
+    /// It has no flag for wrapping sprites or for `key-wait`, and this emulator has no quirk for `vBlankQuirks`.
+    pub fn quirks(&self) -> Vec<String> {
+        let flags = [
+            (self.shift_quirks, "shift", "vy", "vx"),
+            (self.load_store_quirks, "memory", "increment", "fixed"),
+            (self.jump_quirks, "jump", "v0", "vx"),
+            (self.logic_quirks, "vf-reset", "off", "on"),
+            (self.clip_quirks, "sprites", "wrap", "clip"),
+        ];
+        return flags.iter().filter_map(|&(flag, name, off, on)| flag.map(|set| format!("{}={}", name, if set { on } else { off }))).collect();
+    }
+}
+
+/// The payload of an Octo cartridge
+#[derive(Debug)]
+pub struct Cartridge {
+    /// The program source, written in Octo syntax
+    pub program: String,
+    pub options: CartridgeOptions,
+}
+
+impl Cartridge {
+    /// Assembles the program with the Octo dialect
+    /// 
+    /// ## Returns
+    /// 
+    /// The machine code, or every mistake that stopped it from being assembled, a line each
+    pub fn assemble(&self) -> Result<Vec<u8>, String> {
+        return match assemble_octo(&self.program) {
+            Ok((rom, _)) => Ok(rom),
+            Err(errors) => Err(errors.iter().map(|error| error.to_string()).collect::<Vec<_>>().join("\n")),
+        };
+    }
+}
+
+/// Checks whether a file looks like an Octo cartridge, based on its GIF signature
+/// 
+/// ## Arguments
+/// 
+/// * `data` - The contents of the file
+pub fn is_cartridge(data: &[u8]) -> bool {
+    return data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a");
+}
+
+/// Extracts the program and options from an Octo cartridge
+/// 
+/// ## Arguments
+/// 
+/// * `data` - The contents of the GIF file
+/// 
+/// ## Returns
+/// 
+/// The cartridge payload, or a message describing why it could not be read
+pub fn load_cartridge(data: &[u8]) -> Result<Cartridge, String> {
+    let payload = extract_payload(data)?;
+
+    let json: serde_json::Value = serde_json::from_slice(&payload)
+        .map_err(|e| format!("Cartridge payload is not valid JSON: {}", e))?;
+
+    let program = json["program"]
+        .as_str()
+        .ok_or("Cartridge payload has no program")?
+        .to_string();
+
+    let options = &json["options"];
+    let flag = |key: &str| options[key].as_bool();
+
+    return Ok(Cartridge {
+        program,
+        options: CartridgeOptions {
+            tickrate: options["tickrate"].as_u64(),
+            shift_quirks: flag("shiftQuirks"),
+            load_store_quirks: flag("loadStoreQuirks"),
+            jump_quirks: flag("jumpQuirks"),
+            logic_quirks: flag("logicQuirks"),
+            clip_quirks: flag("clipQuirks"),
+            vblank_quirks: flag("vBlankQuirks"),
+        },
+    });
+}
+
+/// Decodes the GIF and reassembles the length-prefixed payload from the pixel data
+fn extract_payload(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+
+    let mut decoder = options.read_info(data).map_err(|e| format!("Failed to decode cartridge: {}", e))?;
+
+    let mut bytes = Vec::new();
+    let mut current: u8 = 0;
+    let mut pairs = 0;
+    while let Some(frame) = decoder.read_next_frame().map_err(|e| format!("Failed to decode cartridge: {}", e))? {
+        for pixel in frame.buffer.iter() {
+            current = (current << 2) | (pixel & 0x03);
+            pairs += 1;
+
+            if pairs == 4 {
+                bytes.push(current);
+                current = 0;
+                pairs = 0;
+            }
+        }
+    }
+
+    if bytes.len() < 4 {
+        return Err(String::from("Cartridge is too small to hold a payload"));
+    }
+
+    let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    if bytes.len() < 4 + size {
+        return Err(format!("Cartridge payload is truncated (expected {} bytes, found {})", size, bytes.len() - 4));
+    }
+
+    return Ok(bytes[4..4 + size].to_vec());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hides a payload in a GIF the way Octo does, four pixels to a byte after a length
+    fn make_cartridge(payload: &str) -> Vec<u8> {
+        let mut bytes = (payload.len() as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(payload.as_bytes());
+        let pixels: Vec<u8> = bytes.iter().flat_map(|byte| [byte >> 6, byte >> 4 & 3, byte >> 2 & 3, byte & 3]).collect();
+
+        let mut gif = Vec::new();
+        let mut encoder = gif::Encoder::new(&mut gif, pixels.len() as u16, 1, &[0, 0, 0, 85, 85, 85, 170, 170, 170, 255, 255, 255]).unwrap();
+        encoder.write_frame(&gif::Frame::from_indexed_pixels(pixels.len() as u16, 1, pixels, None)).unwrap();
+        drop(encoder);
+        return gif;
+    }
+
+    #[test]
+    fn cartridges_assemble_with_their_options() {
+        let data = make_cartridge(r#"{ "program": ": main\n  v0 := 5\n  loop again", "options": { "tickrate": 20, "shiftQuirks": true, "clipQuirks": false } }"#);
+        assert!(is_cartridge(&data));
+        let cartridge = load_cartridge(&data).unwrap();
+        assert_eq!(cartridge.assemble(), Ok(vec![0x60, 0x05, 0x12, 0x02]));
+        assert_eq!(cartridge.options.tickrate, Some(20));
+        assert_eq!(cartridge.options.quirks(), ["shift=vx", "sprites=wrap"]);
+
+        let broken = load_cartridge(&make_cartridge(r#"{ "program": ": main\n  v0 := 300" }"#)).unwrap();
+        assert!(broken.assemble().unwrap_err().starts_with("line 2:"));
+    }
+}
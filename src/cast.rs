@@ -0,0 +1,96 @@
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::Duration;
+
+// Recordings use the asciinema v2 format: a JSON header line, then one [time, "o", data] line per chunk of output.
+// Times come from the frame count rather than the wall clock, so the same run always produces the same file.
+
+/// Records terminal output as an asciinema cast
+pub struct CastRecorder {
+    writer: Box<dyn Write>,
+}
+
+impl CastRecorder {
+    /// Creates a cast file and writes its header
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `path` - Where to write the cast
+    /// * `width` - Width of the recorded terminal in columns
+    /// * `height` - Height of the recorded terminal in rows
+    /// * `title` - Title shown by players
+    pub fn create(path: &str, width: u16, height: u16, title: &str) -> io::Result<Self> {
+        let file = File::create(path)?;
+        return Self::new(Box::new(BufWriter::new(file)), width, height, title);
+    }
+
+    /// Starts a cast on any writer
+    pub fn new(mut writer: Box<dyn Write>, width: u16, height: u16, title: &str) -> io::Result<Self> {
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "title": title,
+        });
+        writeln!(writer, "{}", header)?;
+
+        return Ok(Self { writer });
+    }
+
+    /// Records a chunk of output
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `time` - When the output happened, from the start of the recording
+    /// * `output` - What was written to the terminal
+    pub fn write_output(&mut self, time: Duration, output: &[u8]) -> io::Result<()> {
+        let event = json!([time.as_secs_f64(), "o", String::from_utf8_lossy(output)]);
+        return writeln!(self.writer, "{}", event);
+    }
+}
+
+impl Drop for CastRecorder {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer the test can read back after the recorder is done with it
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            return Ok(());
+        }
+    }
+
+    #[test]
+    fn writes_asciinema_v2() {
+        let buffer = SharedBuffer::default();
+        let mut recorder = CastRecorder::new(Box::new(buffer.clone()), 36, 22, "pong.ch8").unwrap();
+        recorder.write_output(Duration::ZERO, "\x1b[2J╭─\r\n".as_bytes()).unwrap();
+        recorder.write_output(Duration::from_millis(250), b"frame").unwrap();
+        drop(recorder);
+
+        let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!((lines[0]["width"].as_u64(), lines[0]["height"].as_u64()), (Some(36), Some(22)));
+        assert_eq!(lines[1], json!([0.0, "o", "\x1b[2J╭─\r\n"]));
+        assert_eq!(lines[2], json!([0.25, "o", "frame"]));
+    }
+}
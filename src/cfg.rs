@@ -0,0 +1,163 @@
+use crate::chip8::trace_code;
+use crate::instruction::Instruction;
+use std::collections::BTreeSet;
+
+// Draws the code of a ROM as a Graphviz control flow graph, for working out what an unknown ROM does:
+//   dot -Tsvg rom.dot -o rom.svg
+// Each box is a basic block, a run of instructions that always run one after the other, named after where it starts.
+// Plain edges are fall-throughs and jumps, dashed edges are calls, and a skip has a "skip" edge past the next instruction.
+// JP V0 is drawn to its base address, dotted, since where it lands depends on V0. Targets outside the ROM get a
+// plain node of their own, so the edges to them still show.
+
+const PROGRAM_START: usize = 0x200;
+
+/// How control leaves an instruction
+enum Exit {
+    /// On to the next instruction
+    Next,
+    Jump(usize),
+    /// To a subroutine, coming back to the next instruction
+    Call(usize),
+    JumpV0(usize),
+    /// On to the next instruction, or past it
+    Skip,
+    /// RET or EXIT, to somewhere the ROM doesn't say
+    End,
+}
+
+/// Exports the code of a Chip-8 program as a Graphviz control flow graph
+/// 
+/// Only code reachable from the entry point is drawn, the same code `disassemble_with_labels` writes as instructions.
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program, as it is loaded at 0x200
+/// 
+/// ## Returns
+/// 
+/// The graph in the DOT language
+pub fn export_cfg(program: &[u8]) -> String {
+    let starts = trace_code(program);
+    let decode = |addr: usize| {
+        let offset = addr - PROGRAM_START;
+        return Instruction::decode(u16::from_be_bytes([program[offset], program[offset + 1]]));
+    };
+    let addrs: Vec<usize> = (0..program.len()).filter(|i| starts[*i]).map(|i| PROGRAM_START + i).collect();
+    let in_program = |addr: usize| addr >= PROGRAM_START && starts.get(addr - PROGRAM_START).copied().unwrap_or(false);
+
+    // Blocks start at the entry point, at anything jumped, called or skipped to, and after anything that leaves a block
+    let mut leaders = BTreeSet::from([PROGRAM_START]);
+    for addr in addrs.iter().copied() {
+        match exit(decode(addr)) {
+            Exit::Next => continue,
+            Exit::Jump(target) | Exit::Call(target) | Exit::JumpV0(target) => {
+                leaders.insert(target);
+            },
+            Exit::Skip => {
+                leaders.insert(addr + 4);
+            },
+            Exit::End => {},
+        }
+        leaders.insert(addr + 2);
+    }
+    let name = |addr: usize| if in_program(addr) { format!("L_{:04X}", addr) } else { format!("0x{:03X}", addr) };
+
+    let mut nodes = String::new();
+    let mut edges = String::new();
+    let mut outside = BTreeSet::new();
+    for leader in leaders.iter().copied().filter(|addr| in_program(*addr)) {
+        let mut label = format!("{}\\l", name(leader));
+        let mut addr = leader;
+        loop {
+            let instruction = decode(addr);
+            let text = match instruction {
+                Instruction::Jump { addr: target } if in_program(target as usize) => format!("JP {}", name(target as usize)),
+                Instruction::Call { addr: target } if in_program(target as usize) => format!("CALL {}", name(target as usize)),
+                Instruction::JumpV0 { addr: target } if in_program(target as usize) => format!("JP V0 {}", name(target as usize)),
+                instruction => instruction.to_string(),
+            };
+            label.push_str(&format!("  {}\\l", text));
+
+            let mut edge = |target: usize, style: &str| {
+                if !in_program(target) {
+                    outside.insert(target);
+                }
+                edges.push_str(&format!("    \"{}\" -> \"{}\"{};\n", name(leader), name(target), style));
+            };
+            match exit(instruction) {
+                Exit::Next if in_program(addr + 2) && !leaders.contains(&(addr + 2)) => {
+                    addr += 2;
+                    continue;
+                },
+                Exit::Next => {
+                    if in_program(addr + 2) {
+                        edge(addr + 2, "");
+                    }
+                },
+                Exit::Jump(target) => edge(target, ""),
+                Exit::Call(target) => {
+                    edge(target, " [style=dashed label=\"call\"]");
+                    edge(addr + 2, "");
+                },
+                Exit::JumpV0(target) => edge(target, " [style=dotted label=\"+V0\"]"),
+                Exit::Skip => {
+                    edge(addr + 2, "");
+                    edge(addr + 4, " [label=\"skip\"]");
+                },
+                Exit::End => {},
+            }
+            break;
+        }
+        nodes.push_str(&format!("    \"{}\" [label=\"{}\"];\n", name(leader), label));
+    }
+    for addr in outside {
+        nodes.push_str(&format!("    \"{}\" [shape=plaintext];\n", name(addr)));
+    }
+
+    return format!("digraph rom {{\n    node [shape=box fontname=\"monospace\"];\n{}{}}}\n", nodes, edges);
+}
+
+/// Where control goes after an instruction, as `trace_code` follows it
+fn exit(instruction: Instruction) -> Exit {
+    return match instruction {
+        Instruction::Return | Instruction::Exit => Exit::End,
+        Instruction::Jump { addr } => Exit::Jump(addr as usize),
+        Instruction::Call { addr } => Exit::Call(addr as usize),
+        Instruction::JumpV0 { addr } => Exit::JumpV0(addr as usize),
+        Instruction::SkipEqualByte { .. }
+        | Instruction::SkipNotEqualByte { .. }
+        | Instruction::SkipEqual { .. }
+        | Instruction::SkipNotEqual { .. }
+        | Instruction::SkipKey { .. }
+        | Instruction::SkipNotKey { .. } => Exit::Skip,
+        _ => Exit::Next,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble;
+
+    #[test]
+    fn blocks_are_split_at_branches() {
+        let rom = assemble("start: CLS\nCALL draw\nSE V0 0\nJP start\nJP 0x100\ndraw: DRW V0 V1 5\nRET").unwrap();
+        assert_eq!(export_cfg(&rom), concat!(
+            "digraph rom {\n",
+            "    node [shape=box fontname=\"monospace\"];\n",
+            "    \"L_0200\" [label=\"L_0200\\l  CLS\\l  CALL L_020A\\l\"];\n",
+            "    \"L_0204\" [label=\"L_0204\\l  SE V0 0x0\\l\"];\n",
+            "    \"L_0206\" [label=\"L_0206\\l  JP L_0200\\l\"];\n",
+            "    \"L_0208\" [label=\"L_0208\\l  JP 0x100\\l\"];\n",
+            "    \"L_020A\" [label=\"L_020A\\l  DRW V0 V1 0x5\\l  RET\\l\"];\n",
+            "    \"0x100\" [shape=plaintext];\n",
+            "    \"L_0200\" -> \"L_020A\" [style=dashed label=\"call\"];\n",
+            "    \"L_0200\" -> \"L_0204\";\n",
+            "    \"L_0204\" -> \"L_0206\";\n",
+            "    \"L_0204\" -> \"L_0208\" [label=\"skip\"];\n",
+            "    \"L_0206\" -> \"L_0200\";\n",
+            "    \"L_0208\" -> \"0x100\";\n",
+            "}\n",
+        ));
+    }
+}
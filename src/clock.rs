@@ -0,0 +1,298 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::Cell;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Rate at which the delay and sound timers count down
+pub const TIMER_HZ: u64 = 60;
+
+/// A source of time for the timers and frame pacing
+pub trait Clock {
+    /// Time elapsed since the clock was created
+    fn now(&self) -> Duration;
+
+    /// Waits for the given amount of time to pass
+    fn sleep(&self, duration: Duration);
+}
+
+/// A clock backed by the system's monotonic clock
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        return Self { start: Instant::now() };
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        return self.start.elapsed();
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// A clock that only moves when told to, so tests can advance virtual time deterministically
+#[derive(Default)]
+pub struct MockClock {
+    now: Cell<Duration>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        return self.now.get();
+    }
+
+    // Sleeping simply moves virtual time forward
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Turns elapsed time into a steady number of ticks per second, however irregularly it is polled
+/// 
+/// Ticks are counted from the total elapsed time rather than accumulated per call, so rounding never drifts.
+pub struct Ticker {
+    rate: u64,
+    start: Duration,
+    emitted: u64,
+}
+
+impl Ticker {
+    /// Creates a ticker starting at the given time
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `rate` - Ticks per second
+    /// * `start` - The time to count from, usually `clock.now()`
+    pub fn new(rate: u64, start: Duration) -> Self {
+        return Self { rate, start, emitted: 0 };
+    }
+
+    /// Returns how many ticks have come due since the last call
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `now` - The current time, from the same clock as `start`
+    pub fn ticks_due(&mut self, now: Duration) -> u64 {
+        let elapsed = now.saturating_sub(self.start).as_nanos();
+        let total = (elapsed * self.rate as u128 / 1_000_000_000) as u64;
+        let due = total.saturating_sub(self.emitted);
+
+        self.emitted += due;
+        return due;
+    }
+}
+
+/// How long a frame lasts at a framerate, to the nanosecond
+/// 
+/// ## Arguments
+/// 
+/// * `framerate` - Frames per second, at least 1
+pub fn frame_period(framerate: u64) -> Duration {
+    return Duration::from_secs(1) / framerate as u32;
+}
+
+/// Frames a late frame can fall behind by before the deadlines start over, rather than rushing through every frame missed
+const MAX_LAG_FRAMES: u32 = 5;
+
+/// Paces frames from deadlines on the clock, so the time spent emulating and drawing a frame comes out of the wait after it
+/// 
+/// A frame that runs late is followed by the next straight away, to make up for it, unless it is more than `MAX_LAG_FRAMES` behind.
+pub struct Pacer {
+    period: Duration,
+    /// When the frame being run should end
+    deadline: Duration,
+}
+
+impl Pacer {
+    /// Creates a pacer with the first frame starting at the given time
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `period` - How long each frame lasts, from `frame_period`
+    /// * `start` - The time to count from, usually `clock.now()`
+    pub fn new(period: Duration, start: Duration) -> Self {
+        return Self { period, deadline: start + period };
+    }
+
+    /// Whether the frame being run is already past its deadline, so drawing it would only put it further behind
+    pub fn is_late(&self, now: Duration) -> bool {
+        return now > self.deadline;
+    }
+
+    /// Returns how long is left until the frame's deadline, which is nothing for a late frame, and moves on to the next frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `now` - The current time, from the same clock as `start`
+    pub fn wait(&mut self, now: Duration) -> Duration {
+        if now > self.deadline + self.period * MAX_LAG_FRAMES {
+            self.deadline = now;
+        }
+        let wait = self.deadline.saturating_sub(now);
+        self.deadline += self.period;
+        return wait;
+    }
+
+    /// Starts the deadlines over from now, after a pause that shouldn't be made up for
+    pub fn restart(&mut self, now: Duration) {
+        self.deadline = now + self.period;
+    }
+}
+
+/// Varies how many instructions run each frame, like a real interpreter whose instructions don't all take the same time
+/// 
+/// A frame's budget depends only on the seed and the frame's number, so replays, rewinds and runs with the same seed get the same budgets.
+#[derive(Debug, Clone, Copy)]
+pub struct CycleJitter {
+    seed: u64,
+    spread: u64,
+}
+
+impl CycleJitter {
+    /// Creates jitter of up to `spread` instructions either way
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `seed` - The seed RND draws from, so one seed repeats the whole run
+    /// * `spread` - The most instructions a frame can run more or fewer than usual
+    pub fn new(seed: u64, spread: u64) -> Self {
+        return Self { seed, spread };
+    }
+
+    /// Returns how many instructions to run in a frame, never fewer than one
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `frame` - The frame's number, from 0
+    /// * `ticks_per_frame` - Instructions a frame runs without jitter
+    pub fn budget(&self, frame: u32, ticks_per_frame: u64) -> u64 {
+        // Spread the frame number over the seed's bits, so neighbouring seeds don't share budgets a frame apart
+        let mut rng = StdRng::seed_from_u64(self.seed ^ (frame as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let offset = rng.gen_range(0..=self.spread * 2);
+        return (ticks_per_frame + offset).saturating_sub(self.spread).max(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::Chip8;
+
+    #[test]
+    fn ticker_counts_exact_rate() {
+        let clock = MockClock::new();
+        let mut ticker = Ticker::new(TIMER_HZ, clock.now());
+
+        let mut ticks = 0;
+        for _ in 0..1000 {
+            clock.advance(Duration::from_millis(1));
+            ticks += ticker.ticks_due(clock.now());
+        }
+
+        assert_eq!(ticks, 60);
+    }
+
+    #[test]
+    fn ticker_catches_up_after_a_long_gap() {
+        let clock = MockClock::new();
+        let mut ticker = Ticker::new(TIMER_HZ, clock.now());
+
+        clock.sleep(Duration::from_millis(500));
+        assert_eq!(ticker.ticks_due(clock.now()), 30);
+        assert_eq!(ticker.ticks_due(clock.now()), 0);
+    }
+
+    #[test]
+    fn sound_timer_runs_at_60hz_regardless_of_framerate() {
+        for framerate in [25, 50, 100, 250] {
+            let clock = MockClock::new();
+            let mut ticker = Ticker::new(TIMER_HZ, clock.now());
+
+            // LD VA, 3C; LD ST, VA - start a one second beep
+            let mut chip8 = Chip8::new();
+            chip8.load_rom(&[0x6A, 0x3C, 0xFA, 0x18, 0x12, 0x04]).unwrap();
+            chip8.cycle().unwrap();
+            chip8.cycle().unwrap();
+            assert_eq!(chip8.get_sound_timer(), 60);
+
+            // Run just short of a second, then the rest of it
+            let frame = Duration::from_secs(1) / framerate;
+            for _ in 0..framerate - 1 {
+                clock.sleep(frame);
+                for _ in 0..ticker.ticks_due(clock.now()) {
+                    chip8.update_timers();
+                }
+            }
+            assert!(chip8.get_sound_timer() > 0, "beep ended early at {} FPS", framerate);
+
+            clock.sleep(frame);
+            for _ in 0..ticker.ticks_due(clock.now()) {
+                chip8.update_timers();
+            }
+            assert_eq!(chip8.get_sound_timer(), 0, "beep ran long at {} FPS", framerate);
+        }
+    }
+
+    #[test]
+    fn pacer_sleeps_only_what_is_left_of_the_frame() {
+        let clock = MockClock::new();
+        let mut pacer = Pacer::new(frame_period(50), clock.now());
+        let ms = Duration::from_millis;
+
+        // A frame that took 5 ms of its 20 waits the other 15
+        clock.advance(ms(5));
+        assert!(!pacer.is_late(clock.now()));
+        clock.sleep(pacer.wait(clock.now()));
+        assert_eq!(clock.now(), ms(20));
+
+        // One that took 30 ms doesn't wait, and the next makes up for it, so three frames still take 60 ms
+        clock.advance(ms(30));
+        assert!(pacer.is_late(clock.now()));
+        assert_eq!(pacer.wait(clock.now()), Duration::ZERO);
+        clock.advance(ms(5));
+        clock.sleep(pacer.wait(clock.now()));
+        assert_eq!(clock.now(), ms(60));
+
+        // Falling far behind starts the deadlines over, rather than running flat out to catch up
+        clock.advance(ms(1000));
+        assert_eq!(pacer.wait(clock.now()), Duration::ZERO);
+        clock.advance(ms(5));
+        assert_eq!(pacer.wait(clock.now()), ms(15));
+    }
+
+    #[test]
+    fn jitter_repeats_with_the_seed() {
+        let jitter = CycleJitter::new(7, 3);
+        let budgets: Vec<u64> = (0..600).map(|frame| jitter.budget(frame, 10)).collect();
+        assert!(budgets.iter().all(|budget| (7..=13).contains(budget)));
+        assert!((7..=13).all(|budget| budgets.contains(&budget)));
+        assert_eq!(budgets, (0..600).map(|frame| CycleJitter::new(7, 3).budget(frame, 10)).collect::<Vec<u64>>());
+        assert_ne!(budgets, (0..600).map(|frame| CycleJitter::new(8, 3).budget(frame, 10)).collect::<Vec<u64>>());
+
+        assert_eq!(CycleJitter::new(7, 0).budget(5, 10), 10);
+        assert!((0..600).all(|frame| CycleJitter::new(7, 5).budget(frame, 2) >= 1));
+    }
+}
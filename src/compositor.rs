@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+
+// A frame on the terminal is built up from layers, each placed at a cell of its own and drawn lowest first:
+//   screen   the ROM's screen, with its border and keypad
+//   panes    the status line, key history and warnings under the frame, and the debugger's panel beside it
+//   toasts   a line saying why the emulator is stopped
+// A layer only writes the cells its text reaches, and blank lines write nothing, so whatever a layer leaves out
+// shows the layers underneath. Each feature places its own text without knowing what else is on the screen.
+
+/// How high a layer sits, the later ones being drawn over the earlier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Plane {
+    Screen,
+    Pane,
+    Toast,
+}
+
+/// One line of a layer, at the cell it starts on
+struct Span {
+    plane: Plane,
+    /// Row and column, from 1 like the terminal counts them
+    row: usize,
+    column: usize,
+    text: String,
+}
+
+/// The layers of a frame, put together once they are all in
+#[derive(Default)]
+pub struct Compositor {
+    spans: Vec<Span>,
+    /// Lowest row any layer reaches
+    bottom: usize,
+}
+
+impl Compositor {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Adds a layer of lines, one under the other
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `plane` - How high the layer sits
+    /// * `row` - Row of the first line, from 1
+    /// * `column` - Column the lines start at, from 1
+    /// * `text` - The lines, which may hold escape sequences and end in `\r\n` as they would be written to the terminal
+    /// 
+    /// ## Returns
+    /// 
+    /// How many rows the layer takes, to put the next one under it
+    pub fn add(&mut self, plane: Plane, row: usize, column: usize, text: &str) -> usize {
+        let lines: Vec<&str> = text.lines().map(|line| line.trim_end_matches('\r')).collect();
+        for (offset, line) in lines.iter().enumerate().filter(|(_, line)| !line.is_empty()) {
+            self.spans.push(Span { plane, row: row + offset, column, text: line.to_string() });
+        }
+        if !lines.is_empty() {
+            self.bottom = self.bottom.max(row + lines.len() - 1);
+        }
+        return lines.len();
+    }
+
+    /// Adds a layer written by a function that draws to the terminal, like `add`
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `plane` - How high the layer sits
+    /// * `row` - Row of the first line, from 1
+    /// * `column` - Column the lines start at, from 1
+    /// * `draw` - Writes the lines
+    pub fn draw(&mut self, plane: Plane, row: usize, column: usize, draw: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> io::Result<usize> {
+        let mut text = Vec::new();
+        draw(&mut text)?;
+        return Ok(self.add(plane, row, column, &String::from_utf8_lossy(&text)));
+    }
+
+    /// Writes the frame over a cleared terminal, leaving the cursor at the start of the row under it
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `out` - Where to write it
+    pub fn render(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut spans: Vec<&Span> = self.spans.iter().collect();
+        spans.sort_by_key(|span| span.plane);
+
+        write!(out, "\x1b[2J")?;
+        for span in spans {
+            write!(out, "\x1b[{};{}H{}", span.row, span.column, span.text)?;
+        }
+        return write!(out, "\x1b[{};1H", self.bottom + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_are_drawn_lowest_first() {
+        let mut compositor = Compositor::new();
+        compositor.add(Plane::Toast, 2, 3, "on top\r");
+        let rows = compositor.add(Plane::Screen, 1, 1, "╭──╮\r\n\r\n╰──╯\r\n");
+        assert_eq!(rows, 3);
+        assert_eq!(compositor.draw(Plane::Pane, rows + 1, 1, |out| write!(out, "Slot 1\r\n")).unwrap(), 1);
+
+        // The blank line in the middle of the screen writes nothing, leaving the toast to show through
+        let mut frame = Vec::new();
+        compositor.render(&mut frame).unwrap();
+        assert_eq!(String::from_utf8(frame).unwrap(), "\x1b[2J\x1b[1;1H╭──╮\x1b[3;1H╰──╯\x1b[4;1HSlot 1\x1b[2;3Hon top\x1b[5;1H");
+    }
+}
@@ -0,0 +1,129 @@
+use crate::instruction::Instruction;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Prices every instruction run at roughly what it took the COSMAC VIP, to show why a ROM would have been slow on one:
+//   VIP  31.2 ms a frame (187%) · DRW 96% · LD 2% · ADD 1%
+// The time is the last frame's, against the 16.7 ms the VIP had for each, and the shares are of everything run so far.
+// The VIP's DRW waited for the display, so a loop that draws a few sprites a frame here took several frames there.
+// Instructions are counted together by mnemonic and the operands that aren't registers or numbers, like `LD I` or `LD [I]`.
+
+/// Microseconds the VIP had for each frame, at 60 Hz
+pub const VIP_FRAME_MICROS: u64 = 16_667;
+
+/// How the time the instructions of a ROM would have taken on the VIP adds up
+pub struct CostMeter {
+    /// Time the frame being run has taken so far
+    this_frame: u64,
+    /// Time the last whole frame took
+    last_frame: u64,
+    frames: u64,
+    total: u64,
+    /// Time and count of each kind of instruction run so far
+    by_kind: HashMap<String, (u64, u64)>,
+    /// Instructions the VIP didn't have, which aren't priced
+    unpriced: u64,
+}
+
+impl CostMeter {
+    pub fn new() -> Self {
+        return Self { this_frame: 0, last_frame: 0, frames: 0, total: 0, by_kind: HashMap::new(), unpriced: 0 };
+    }
+
+    /// Counts an instruction that ran
+    pub fn record(&mut self, instruction: Instruction) {
+        let Some(cost) = instruction.vip_cost() else {
+            self.unpriced += 1;
+            return;
+        };
+        self.this_frame += cost as u64;
+        self.total += cost as u64;
+        let entry = self.by_kind.entry(kind(instruction)).or_insert((0, 0));
+        entry.0 += cost as u64;
+        entry.1 += 1;
+    }
+
+    /// Closes the frame that was being run
+    pub fn end_frame(&mut self) {
+        self.last_frame = self.this_frame;
+        self.this_frame = 0;
+        self.frames += 1;
+    }
+
+    /// The last frame's time and the kinds of instruction that took the most time so far, as many as fit
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `width` - Columns the line has to fit in
+    pub fn line(&self, width: usize) -> String {
+        let mut line = format!("VIP  {:.1} ms a frame ({}%)", self.last_frame as f64 / 1000.0, self.last_frame * 100 / VIP_FRAME_MICROS);
+        for (name, time, _) in self.kinds() {
+            let share = format!(" · {} {}%", name, time * 100 / self.total.max(1));
+            if line.chars().count() + share.chars().count() > width {
+                break;
+            }
+            line.push_str(&share);
+        }
+        return line;
+    }
+
+    /// Summarizes the session as a table of the time each kind of instruction took
+    pub fn report(&self) -> String {
+        let average = self.total / self.frames.max(1);
+        let mut str_buffer = format!("VIP time over {} frames: {:.1} ms a frame on average ({}% of the VIP's)\n", self.frames, average as f64 / 1000.0, average * 100 / VIP_FRAME_MICROS);
+        let _ = writeln!(str_buffer, "{:<8}{:>10}{:>12}{:>7}", "", "Count", "Time", "Share");
+        for (name, time, count) in self.kinds() {
+            let _ = writeln!(str_buffer, "{:<8}{:>10}{:>10.1}ms{:>6}%", name, count, time as f64 / 1000.0, time * 100 / self.total.max(1));
+        }
+        if self.unpriced > 0 {
+            let _ = writeln!(str_buffer, "{} instructions the VIP didn't have aren't counted", self.unpriced);
+        }
+        return str_buffer;
+    }
+
+    /// Each kind of instruction with its time and count, the most time first
+    fn kinds(&self) -> Vec<(&str, u64, u64)> {
+        let mut kinds: Vec<(&str, u64, u64)> = self.by_kind.iter().map(|(name, (time, count))| (name.as_str(), *time, *count)).collect();
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        return kinds;
+    }
+}
+
+impl Default for CostMeter {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// Names an instruction by its mnemonic and the operands that aren't registers or numbers, like `LD [I]`
+fn kind(instruction: Instruction) -> String {
+    let text = instruction.to_string();
+    let is_operand = |word: &&str| word.starts_with("0x") || (word.len() == 2 && word.starts_with('V'));
+    return text.split(' ').filter(|word| !is_operand(word)).collect::<Vec<&str>>().join(" ");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drawing_takes_the_time() {
+        let mut meter = CostMeter::new();
+        for _ in 0..2 {
+            meter.record(Instruction::Draw { x: 0, y: 1, n: 5 });
+            meter.record(Instruction::AddByte { x: 0, byte: 8 });
+            meter.record(Instruction::LoadI { addr: 0x300 });
+            meter.record(Instruction::Jump { addr: 0x200 });
+            meter.record(Instruction::ScrollLeft);
+            meter.end_frame();
+        }
+
+        // 22734 + 45 + 55 + 105 microseconds, with the SUPER-CHIP's scroll left out
+        assert_eq!(meter.line(80), "VIP  22.9 ms a frame (137%) · DRW 99% · JP 0% · LD I 0% · ADD 0%");
+        assert_eq!(meter.line(40), "VIP  22.9 ms a frame (137%) · DRW 99%");
+        let report = meter.report();
+        assert!(report.starts_with("VIP time over 2 frames: 22.9 ms a frame on average (137% of the VIP's)\n"), "{}", report);
+        assert!(report.contains("LD I             2       0.1ms     0%\n"), "{}", report);
+        assert!(report.ends_with("2 instructions the VIP didn't have aren't counted\n"), "{}", report);
+    }
+}
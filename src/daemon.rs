@@ -0,0 +1,190 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::time::Duration;
+
+// The daemon speaks a tiny protocol over a Unix socket.
+// On connect, the daemon sends the magic "C8D1" followed by the screen width and height as big-endian u16s.
+// Every frame after that is width * height pixel bytes, one sound timer byte, and 16 keypad bytes (0 or 1).
+// The client sends single bytes back, each one the hex value of a pressed key.
+
+const MAGIC: &[u8; 4] = b"C8D1";
+const KEYPAD_SIZE: usize = 16;
+
+/// The state of the machine as seen by an attached terminal
+pub struct FrameState {
+    pub screen: Vec<u8>,
+    pub sound_timer: u8,
+    pub keypad: [bool; KEYPAD_SIZE],
+}
+
+/// The emulator side of the socket, which keeps running whether or not a terminal is attached
+pub struct Daemon {
+    path: String,
+    listener: UnixListener,
+    client: Option<UnixStream>,
+    width: usize,
+    height: usize,
+}
+
+impl Daemon {
+    /// Starts listening for terminals to attach
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `path` - Where to create the socket; a stale socket left by a previous run is replaced
+    /// * `width` - Width of the screen in pixels
+    /// * `height` - Height of the screen in pixels
+    pub fn listen(path: &str, width: usize, height: usize) -> io::Result<Self> {
+        if std::fs::metadata(path).is_ok() && UnixStream::connect(path).is_err() {
+            std::fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+
+        return Ok(Self {
+            path: path.to_string(),
+            listener,
+            client: None,
+            width,
+            height,
+        });
+    }
+
+    /// Accepts a waiting terminal, if any, and collects the keys it pressed since the last call
+    /// 
+    /// A newly attached terminal replaces the previous one.
+    pub fn poll_keys(&mut self) -> Vec<usize> {
+        if let Ok((stream, _)) = self.listener.accept() {
+            self.client = self.greet(stream).ok();
+        }
+
+        let mut keys = Vec::new();
+        let Some(client) = self.client.as_mut() else {
+            return keys;
+        };
+
+        let mut buffer = [0; 64];
+        let detached = loop {
+            if client.set_nonblocking(true).is_err() {
+                break true;
+            }
+
+            match client.read(&mut buffer) {
+                Ok(0) => break true,
+                Ok(count) => keys.extend(buffer[..count].iter().map(|key| (*key & 0xF) as usize)),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break false,
+                Err(_) => break true,
+            }
+        };
+
+        if detached || client.set_nonblocking(false).is_err() {
+            self.client = None;
+        }
+
+        return keys;
+    }
+
+    /// Sends the current frame to the attached terminal, detaching it if it stopped listening
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `screen` - The screen buffer, one byte per pixel
+    /// * `sound_timer` - The current value of the sound timer
+    /// * `keypad` - The keypad state
+    pub fn send_frame(&mut self, screen: &[u8], sound_timer: u8, keypad: &[bool]) {
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+
+        let mut message = Vec::with_capacity(screen.len() + 1 + KEYPAD_SIZE);
+        message.extend_from_slice(screen);
+        message.push(sound_timer);
+        message.extend(keypad.iter().map(|pressed| *pressed as u8));
+
+        if client.write_all(&message).is_err() {
+            self.client = None;
+        }
+    }
+
+    /// Sends the handshake to a new terminal
+    fn greet(&self, mut stream: UnixStream) -> io::Result<UnixStream> {
+        // A terminal that stops reading must not be able to stall the emulator for long
+        stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+
+        stream.write_all(MAGIC)?;
+        stream.write_all(&(self.width as u16).to_be_bytes())?;
+        stream.write_all(&(self.height as u16).to_be_bytes())?;
+
+        return Ok(stream);
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The terminal side of the socket
+pub struct Client {
+    stream: UnixStream,
+    width: usize,
+    height: usize,
+}
+
+impl Client {
+    /// Connects to a running daemon
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `path` - The socket the daemon is listening on
+    pub fn attach(path: &str) -> io::Result<Self> {
+        let mut stream = UnixStream::connect(path)?;
+
+        let mut handshake = [0; 8];
+        stream.read_exact(&mut handshake)?;
+        if &handshake[..4] != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Not a chip8 daemon socket"));
+        }
+
+        let width = u16::from_be_bytes([handshake[4], handshake[5]]) as usize;
+        let height = u16::from_be_bytes([handshake[6], handshake[7]]) as usize;
+
+        return Ok(Self { stream, width, height });
+    }
+
+    /// Width and height of the screen the daemon is sending
+    pub fn screen_size(&self) -> (usize, usize) {
+        return (self.width, self.height);
+    }
+
+    /// Waits for the next frame from the daemon
+    pub fn read_frame(&mut self) -> io::Result<FrameState> {
+        let mut screen = vec![0; self.width * self.height];
+        self.stream.read_exact(&mut screen)?;
+
+        let mut rest = [0; 1 + KEYPAD_SIZE];
+        self.stream.read_exact(&mut rest)?;
+
+        let mut keypad = [false; KEYPAD_SIZE];
+        for (key, byte) in keypad.iter_mut().zip(&rest[1..]) {
+            *key = *byte != 0;
+        }
+
+        return Ok(FrameState {
+            screen,
+            sound_timer: rest[0],
+            keypad,
+        });
+    }
+
+    /// Forwards a key press to the daemon
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `key` - The hex value of the pressed key
+    pub fn send_key(&mut self, key: usize) -> io::Result<()> {
+        return self.stream.write_all(&[key as u8]);
+    }
+}
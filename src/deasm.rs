@@ -1,28 +1,98 @@
-use crate::chip8::*;
-pub mod chip8;
+use chip8::cfg::*;
+use chip8::chip8::*;
+use chip8::octo::*;
 
 use std::env;
 
 fn main() {
-    // Read arguments
-    let args: Vec<String> = env::args().collect();
+    // Read arguments, pulling out the optional --octo <output> and --cfg <output> pairs, --no-labels and --verify
+    let mut octo_output: Option<String> = None;
+    let mut cfg_output: Option<String> = None;
+    let mut labels = true;
+    let mut verify = false;
+    let mut args: Vec<String> = Vec::new();
+    let mut raw_args = env::args().skip(1);
+    while let Some(arg) = raw_args.next() {
+        if arg == "--octo" {
+            octo_output = raw_args.next();
+        } else if arg == "--cfg" {
+            cfg_output = raw_args.next();
+        } else if arg == "--no-labels" {
+            labels = false;
+        } else if arg == "--verify" {
+            verify = true;
+        } else {
+            args.push(arg);
+        }
+    }
 
     // Check if there are enough arguments
-    if args.len() < 3 {
-        println!("Usage: deasm <input> <output>");
-        return;
-    }
+    let output_path = match (octo_output.as_ref().or(cfg_output.as_ref()), args.len()) {
+        (Some(path), 1..) => path.clone(),
+        (None, 2..) => args[1].clone(),
+        _ => {
+            println!("Usage: deasm [--no-labels] [--verify] <input> <output>");
+            println!("       deasm [--verify] <input> --octo <output>");
+            println!("       deasm <input> --cfg <output.dot>");
+            return;
+        }
+    };
 
     // Read input file
-    let input: Vec<u8> = std::fs::read(&args[1]).expect("Failed to read input file");
+    let input: Vec<u8> = std::fs::read(&args[0]).expect("Failed to read input file");
 
-    // Assemble input file
+    // Disassemble input file
     let start_time = std::time::Instant::now();
-    let output = disassemble(&input);
+    let output = if octo_output.is_some() {
+        export_octo(&input)
+    } else if cfg_output.is_some() {
+        export_cfg(&input)
+    } else if labels {
+        disassemble_with_labels(&input)
+    } else {
+        disassemble(&input)
+    };
 
     // Write output file
-    std::fs::write(&args[2], output).expect("Failed to write output file");
+    std::fs::write(&output_path, &output).expect("Failed to write output file");
 
     // Print time taken
-    println!("Diassembled file in {}us. Output: {}", start_time.elapsed().as_micros(), &args[2]);
-}
\ No newline at end of file
+    println!("Diassembled file in {}us. Output: {}", start_time.elapsed().as_micros(), &output_path);
+
+    // Assemble the output again and check it gives back the ROM
+    if verify && cfg_output.is_none() {
+        let reassembled = if octo_output.is_some() {
+            assemble_octo(&output).map(|(rom, _)| rom)
+        } else {
+            assemble(&output)
+        };
+        match compare_round_trip(&input, reassembled) {
+            Ok(()) => println!("Verified: the output assembles back into the same {} bytes", input.len()),
+            Err(e) => {
+                println!("Verify failed: {}", e);
+                std::process::exit(1);
+            },
+        }
+    }
+}
+
+/// Checks that a disassembly assembled back into the ROM it came from
+/// 
+/// ## Arguments
+/// 
+/// * `rom` - The ROM that was disassembled
+/// * `reassembled` - What assembling the disassembly gave
+/// 
+/// ## Returns
+/// 
+/// Where the two first differ, if they do
+fn compare_round_trip(rom: &[u8], reassembled: Result<Vec<u8>, Vec<AssembleError>>) -> Result<(), String> {
+    let reassembled = reassembled.map_err(|errors| format!("the output doesn't assemble, {}", errors[0]))?;
+    if let Some(i) = (0..rom.len().min(reassembled.len())).find(|i| rom[*i] != reassembled[*i]) {
+        return Err(format!("0x{:03X} was 0x{:02X} in the ROM but assembles to 0x{:02X}", 0x200 + i, rom[i], reassembled[i]));
+    }
+    if rom.len() != reassembled.len() {
+        return Err(format!("the ROM is {} bytes but the output assembles into {}", rom.len(), reassembled.len()));
+    }
+    return Ok(());
+}
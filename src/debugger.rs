@@ -0,0 +1,339 @@
+use chip8::chip8::{disassemble, Chip8, Watchpoint};
+use chip8::listing::Listing;
+use chip8::Instruction;
+use crossterm::event::KeyCode;
+use std::io::{self, Write};
+
+// With --debug the emulator starts stopped, and shows its state to the right of the screen:
+//   PC 206  I 000  DT 00  ST 00
+//   V0 00  V1 00  V2 00  V3 00  V4 00  V5 00  V6 00  V7 00
+//   V8 00  V9 00  VA 00  VB 06  VC 00  VD 00  VE 00  VF 00
+//   Stack 202 20A
+//   Ran   LD VB 0x6
+//   Next  208  D013  DRW V0 V1 0x3
+//   Line  14  DRW V0 V1 3 ; the paddle
+//   Draws 3C0  ░░████░░
+//              ░█░░░░█░
+//              ████████
+//   Pixel 12,8 drawn at 2A4 from sprite 3C0
+//   Watch V3, mem 300
+//   Break LOOP 20A
+//   V3 changed from 00 to 07 at 202
+//   Quirks 1 shift=vx  2 memory=increment  3 jump=v0  4 sprites=wrap  5 vf-reset=off  6 key-wait=release
+// While it is stopped, n runs one instruction, f runs one frame and c carries on. F8 stops it again.
+// w asks for a watchpoint, like V3 or mem 0x300, to stop at as soon as an instruction changes it.
+// b asks for a breakpoint, a label or an address like 0x20A, to stop at before the instruction there runs.
+// When asm wrote a listing beside the ROM, the source line of the next instruction is shown and labels can be broken at.
+// h swaps the panel for the last instructions run, oldest first, to see how the machine got where it is.
+// 1-6 flip the numbered quirk, which the next instruction to rely on it picks up, to find which one fixes a glitch.
+// When the next instruction, or else the last one run, is a DRW, the sprite it draws is shown as it is in memory at I.
+// Clicking the screen names the DRW that lit the pixels under the pointer. Each character cell holds 2x4 pixels in Braille,
+// so the first lit one in the cell is named.
+
+/// What a key asks the stopped emulator to do
+#[derive(Debug, PartialEq)]
+pub enum DebugCommand {
+    /// Run one instruction and stop again
+    Step,
+    /// Run one frame and stop again
+    Frame,
+    /// Carry on until stopped again
+    Continue,
+    /// Ask for a watchpoint to add
+    Watch,
+    /// Ask for a breakpoint to add
+    Break,
+    /// Show or hide the last instructions run
+    History,
+    /// Switch the quirk with this number, from 0, to its other behaviour
+    FlipQuirk(usize),
+    Quit,
+}
+
+impl DebugCommand {
+    /// Reads a key as a command, if it is one
+    pub fn from_key(key: KeyCode) -> Option<Self> {
+        return match key {
+            KeyCode::Char('n') => Some(Self::Step),
+            KeyCode::Char('f') => Some(Self::Frame),
+            KeyCode::Char('c') => Some(Self::Continue),
+            KeyCode::Char('w') => Some(Self::Watch),
+            KeyCode::Char('b') => Some(Self::Break),
+            KeyCode::Char('h') => Some(Self::History),
+            KeyCode::Char(digit @ '1'..='6') => Some(Self::FlipQuirk(digit as usize - '1' as usize)),
+            KeyCode::Esc => Some(Self::Quit),
+            _ => None,
+        };
+    }
+}
+
+/// What is being typed in at the bottom of the panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prompt {
+    Watch,
+    Break,
+}
+
+/// Finds the address to stop at for a breakpoint, a label in the source map or an address like 0x20A
+/// 
+/// ## Arguments
+/// 
+/// * `target` - The label or address
+/// * `source_map` - The listing beside the ROM, if there is one
+/// 
+/// ## Returns
+/// 
+/// What to call the breakpoint, and its address
+pub fn resolve_breakpoint(target: &str, source_map: Option<&Listing>) -> Result<(String, u16), String> {
+    let target = target.trim();
+    if let Some(addr) = source_map.and_then(|listing| listing.address_of(target)) {
+        return Ok((target.to_uppercase(), addr));
+    }
+    let hex = target.strip_prefix("0x").or_else(|| target.strip_prefix("0X"));
+    return match hex.and_then(|hex| u16::from_str_radix(hex, 16).ok()) {
+        Some(addr) => Ok((format!("{:03X}", addr), addr)),
+        None if source_map.is_some() => Err(format!("Can't break at {}, expected a label in the listing or an address like 0x20A", target)),
+        None => Err(format!("Can't break at {}, expected an address like 0x20A, or a label if asm wrote a listing beside the ROM", target)),
+    };
+}
+
+/// State of the debugger between frames
+pub struct Debugger {
+    /// Whether the emulator stops at the start of the next frame
+    pub stopped: bool,
+    /// The last instruction run, as the interpreter read it, which is only formatted when the panel is drawn
+    pub last: Option<Instruction>,
+    /// What was found under the last click on the screen
+    picked: Option<String>,
+    /// The watchpoint or breakpoint being typed in, if one is
+    typing: Option<(Prompt, String)>,
+    /// Why it last stopped on its own, or what became of the last watchpoint typed in
+    message: Option<String>,
+    /// Whether the panel shows the last instructions run, to see how the machine got where it is, rather than its state
+    pub showing_history: bool,
+    /// The listing asm wrote beside the ROM, to show the source and find labels in
+    pub source_map: Option<Listing>,
+    /// Name and address of every place to stop before running
+    pub breakpoints: Vec<(String, u16)>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl Debugger {
+    /// Creates a debugger that stops before the first instruction
+    pub fn new() -> Self {
+        return Self { stopped: true, last: None, picked: None, typing: None, message: None, showing_history: false, source_map: None, breakpoints: Vec::new() };
+    }
+
+    /// Notes an instruction that ran, stopping if it changed a watched value, reached a breakpoint or trapped on an unknown opcode
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `executed` - The instruction, as `Chip8::step` read it
+    /// * `chip8` - The emulator, to take the watchpoint hits from
+    /// 
+    /// ## Returns
+    /// 
+    /// Whether it stopped, so the rest of the frame can wait
+    pub fn ran(&mut self, executed: Option<Instruction>, chip8: &mut Chip8) -> bool {
+        self.last = executed;
+        let hits = chip8.take_watch_hits();
+        if let Some(hit) = hits.last() {
+            self.stopped = true;
+            self.message = Some(hit.to_string());
+        }
+
+        let breakpoint = self.breakpoints.iter().find(|(_, addr)| *addr == chip8.get_pc());
+        if let Some((name, _)) = breakpoint {
+            self.stopped = true;
+            self.message = Some(format!("Stopped at breakpoint {}", name));
+        }
+
+        let trap = chip8.get_trap();
+        if let Some((pc, opcode)) = trap {
+            self.stopped = true;
+            self.message = Some(format!("Unknown opcode 0x{:04X} at {:03X}", opcode, pc));
+        }
+        return !hits.is_empty() || breakpoint.is_some() || trap.is_some();
+    }
+
+    /// Starts asking for a watchpoint or breakpoint
+    pub fn start_typing(&mut self, prompt: Prompt) {
+        self.typing = Some((prompt, String::new()));
+    }
+
+    pub fn is_typing(&self) -> bool {
+        return self.typing.is_some();
+    }
+
+    /// Takes a key towards the watchpoint or breakpoint being typed in, adding it on Enter or giving up on Esc
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `key` - The key pressed
+    /// * `chip8` - The emulator, to add the watchpoint to
+    pub fn type_key(&mut self, key: KeyCode, chip8: &mut Chip8) {
+        let Some((prompt, typed)) = self.typing.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Char(c) => typed.push(c),
+            KeyCode::Backspace => {
+                typed.pop();
+            },
+            KeyCode::Enter if *prompt == Prompt::Break => {
+                self.message = Some(match resolve_breakpoint(typed, self.source_map.as_ref()) {
+                    Ok((name, addr)) => {
+                        self.breakpoints.push((name.clone(), addr));
+                        format!("Breaking at {} ({:03X})", name, addr)
+                    },
+                    Err(message) => message,
+                });
+                self.typing = None;
+            },
+            KeyCode::Enter => {
+                self.message = Some(match Watchpoint::parse(typed) {
+                    Ok(watchpoint) => {
+                        chip8.add_watchpoint(watchpoint);
+                        format!("Watching {}", watchpoint)
+                    },
+                    Err(message) => message,
+                });
+                self.typing = None;
+            },
+            KeyCode::Esc => self.typing = None,
+            _ => {},
+        }
+    }
+
+    /// Switches a quirk to its other behaviour while the ROM runs, saying what it became
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `quirk` - Number of the quirk in the panel, from 0
+    /// * `chip8` - The emulator
+    pub fn flip_quirk(&mut self, quirk: usize, chip8: &mut Chip8) {
+        let mut quirks = chip8.get_quirks();
+        let Some(&(name, _)) = quirks.values().get(quirk) else {
+            return;
+        };
+        let value = quirks.toggle(name).expect("Quirks only lists quirks it has");
+        chip8.set_quirks(quirks);
+        self.message = Some(format!("Quirk {} is now {}", name, value));
+    }
+
+    /// Finds the sprite draw behind the pixels under a click, to show with the machine's state
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `chip8` - The emulator, keeping pixel sources
+    /// * `column` - The terminal column clicked, from 0
+    /// * `row` - The terminal row clicked, from 0
+    pub fn pick(&mut self, chip8: &Chip8, column: u16, row: u16) {
+        // The screen starts inside two borders on each side
+        let (width, height) = chip8.get_screen_size();
+        let (cell_x, cell_y) = (column as usize, row as usize);
+        if cell_x < 2 || cell_y < 2 || (cell_x - 2) * 2 >= width || (cell_y - 2) * 4 >= height {
+            return;
+        }
+
+        let (left, top) = ((cell_x - 2) * 2, (cell_y - 2) * 4);
+        let screen = chip8.get_screen_buffer();
+        let lit = (top..top + 4).flat_map(|y| (left..left + 2).map(move |x| (x, y))).find(|&(x, y)| screen[y * width + x] == 1);
+        self.picked = Some(match lit {
+            Some((x, y)) => match chip8.get_pixel_source(x, y) {
+                Some(source) => format!("Pixel {},{} drawn at {:03X} from sprite {:03X}", x, y, source.pc, source.sprite),
+                None => format!("Pixel {},{} was lit before the debugger could see it", x, y),
+            },
+            None => format!("Nothing lit at {},{}", left, top),
+        });
+    }
+
+    /// Draws the machine's state, a line at a time, for the compositor to place to the right of the screen
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `out` - Where to write the panel
+    /// * `chip8` - The emulator
+    pub fn draw(&self, out: &mut impl Write, chip8: &Chip8) -> io::Result<()> {
+        let registers = chip8.get_registers();
+        let register_row = |from: usize| {
+            return (from..from + 8).map(|register| format!("V{:X} {:02X}", register, registers[register])).collect::<Vec<String>>().join("  ");
+        };
+        let quirks: Vec<String> = chip8.get_quirks().values().iter().enumerate().map(|(number, (name, value))| format!("{} {}={}", number + 1, name, value)).collect();
+        let watching: Vec<String> = chip8.get_watchpoints().iter().map(Watchpoint::to_string).collect();
+        let breaking: Vec<String> = self.breakpoints.iter().map(|(name, addr)| if *name == format!("{:03X}", addr) { name.clone() } else { format!("{} {:03X}", name, addr) }).collect();
+        let stack: Vec<String> = chip8.get_stack().iter().map(|addr| format!("{:03X}", addr)).collect();
+
+        let pc = chip8.get_pc() as usize;
+        let memory = chip8.get_memory();
+        let next = memory.get(pc..pc + 2).map_or(String::from("past the end of memory"), |opcode| {
+            return format!("{:03X}  {:02X}{:02X}  {}", pc, opcode[0], opcode[1], disassemble(opcode).trim_end());
+        });
+
+        // DRW leaves I alone, so the sprite the last one drew is still at I
+        let drawing = [memory.get(pc..pc + 2).map(|opcode| Instruction::decode(u16::from_be_bytes([opcode[0], opcode[1]]))), self.last];
+        let sprite = drawing.into_iter().flatten().find_map(|instruction| match instruction {
+            Instruction::Draw { n, .. } => Some(chip8.get_sprite(n)),
+            _ => None,
+        });
+
+        let mut lines = vec![
+            format!("PC {:03X}  I {:03X}  DT {:02X}  ST {:02X}", pc, chip8.get_index(), chip8.get_delay_timer(), chip8.get_sound_timer()),
+            register_row(0),
+            register_row(8),
+            format!("Stack {}", if stack.is_empty() { String::from("empty") } else { stack.join(" ") }),
+            format!("Ran   {}", self.last.map(|instruction| instruction.to_string()).unwrap_or_default()),
+            format!("Next  {}", next),
+            match self.source_map.as_ref().map(|listing| listing.line_at(pc as u16)) {
+                Some(Some(line)) => format!("Line  {}  {}", line.line, line.text.trim()),
+                Some(None) => String::from("Line  not in the listing"),
+                None => String::from("Line  no listing beside the ROM"),
+            },
+            self.picked.clone().unwrap_or(String::from("Click the screen to find what drew it")),
+            format!("Watch {}", if watching.is_empty() { String::from("nothing") } else { watching.join(", ") }),
+            format!("Break {}", if breaking.is_empty() { String::from("nothing") } else { breaking.join(", ") }),
+            self.message.clone().unwrap_or_default(),
+            String::new(),
+            format!("Quirks {}", quirks.join("  ")),
+            String::new(),
+            match (&self.typing, self.stopped) {
+                (Some((Prompt::Watch, typed)), _) => format!("Watch (like V3 or mem 0x300): {}_", typed),
+                (Some((Prompt::Break, typed)), _) => format!("Break at (a label or an address like 0x20A): {}_", typed),
+                (None, true) => String::from("n: step  f: frame  c: continue  w: watch  b: break  h: history  1-6: flip a quirk  Esc: quit"),
+                (None, false) => String::from("F8: stop"),
+            },
+        ];
+        if let Some((bytes, bytes_per_row)) = sprite {
+            let rows = bytes.chunks(bytes_per_row).map(|row| {
+                return row.iter().flat_map(|byte| (0..8).map(move |bit| if byte & (0x80 >> bit) != 0 { '█' } else { '░' })).collect::<String>();
+            });
+            let drawn = rows.enumerate().map(|(row, pixels)| match row {
+                0 => format!("Draws {:03X}  {}", chip8.get_index(), pixels),
+                _ => format!("           {}", pixels),
+            });
+            lines.splice(7..7, drawn.collect::<Vec<String>>());
+        }
+        if self.showing_history {
+            lines = self.history_lines(chip8);
+        }
+        for line in lines {
+            writeln!(out, "{}\r", line)?;
+        }
+        return Ok(());
+    }
+
+    /// The panel's lines while it shows how the machine got where it is
+    fn history_lines(&self, chip8: &Chip8) -> Vec<String> {
+        let history = chip8.get_pc_history();
+        let mut lines = vec![format!("Last {} instructions run, oldest first", history.len())];
+        lines.extend(history.iter().map(|(pc, opcode)| format!("{:03X}  {:04X}  {}", pc, opcode, Instruction::decode(*opcode))));
+        lines.push(String::new());
+        lines.push(String::from("h: back to the machine's state"));
+        return lines;
+    }
+}
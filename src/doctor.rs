@@ -0,0 +1,196 @@
+use crate::termcaps::TermCaps;
+use std::fmt;
+
+// `chip8 doctor` checks the things about a terminal that most often make the emulator misbehave, and writes a report to paste into a bug,
+// which starts with the environment and what the terminal said it supports, then has a line per check:
+//   chip8 0.1.0 on linux x86_64
+//   TERM=xterm-256color COLORTERM=truecolor TERM_PROGRAM=unset LANG=en_US.UTF-8
+//   Truecolor:        yes
+//   ...
+//   ok    Terminal size   140x50, room for everything
+//   warn  Colors          256 colors, so themes are shown in the nearest colors of the 256 color palette
+//   FAIL  Raw mode        Operation not permitted
+// Anything marked FAIL stops the emulator working, and anything marked warn limits what it can do.
+
+/// Columns and rows each way of drawing the emulator needs, smallest first
+const SIZE_NEEDS: [(&str, u16, u16); 5] = [
+    ("CHIP-8 with --no-keypad", 36, 14),
+    ("CHIP-8", 36, 23),
+    ("SUPER-CHIP", 68, 31),
+    ("the debugger", 115, 23),
+    ("the debugger with SUPER-CHIP", 145, 31),
+];
+
+/// Environment variables that say which terminal this is
+const ENVIRONMENT: [&str; 4] = ["TERM", "COLORTERM", "TERM_PROGRAM", "LANG"];
+
+/// How a check went
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    /// Works, but with something missing
+    Warn,
+    /// Stops the emulator working
+    Fail,
+    /// Couldn't be checked here
+    Skipped,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.pad(match self {
+            Self::Ok => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "FAIL",
+            Self::Skipped => "skip",
+        });
+    }
+}
+
+/// One thing checked, and what was found
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub status: Status,
+    pub name: &'static str,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(status: Status, name: &'static str, detail: String) -> Self {
+        return Self { status, name, detail };
+    }
+}
+
+/// Checks the terminal is big enough for each way of drawing the emulator
+/// 
+/// ## Arguments
+/// 
+/// * `columns` - Width of the terminal
+/// * `rows` - Height of the terminal
+pub fn check_size(columns: u16, rows: u16) -> Check {
+    let too_small: Vec<String> = SIZE_NEEDS.iter()
+        .filter(|(_, needs_columns, needs_rows)| columns < *needs_columns || rows < *needs_rows)
+        .map(|(what, needs_columns, needs_rows)| format!("{} ({}x{})", what, needs_columns, needs_rows))
+        .collect();
+
+    let (status, detail) = match too_small.len() {
+        0 => (Status::Ok, String::from("room for everything")),
+        _ if too_small.len() == SIZE_NEEDS.len() => (Status::Fail, format!("too small for anything, even {}", too_small[0])),
+        _ => (Status::Warn, format!("too small for {}", too_small.join(", "))),
+    };
+    return Check::new(status, "Terminal size", format!("{}x{}, {}", columns, rows, detail));
+}
+
+/// Checks Braille characters are drawn one column wide, as the screen is drawn with them
+/// 
+/// ## Arguments
+/// 
+/// * `caps` - What the terminal said it does
+/// * `looks_right` - What the player said of the test pattern, or `None` if they weren't asked
+pub fn check_unicode(caps: &TermCaps, looks_right: Option<bool>) -> Check {
+    let (status, detail) = match (caps.unicode_width, looks_right) {
+        (false, _) => (Status::Fail, "Braille is drawn at the wrong width, so the screen will look garbled"),
+        (true, Some(false)) => (Status::Fail, "the width is right, but the test pattern didn't look right, so the font may be missing Braille"),
+        (true, Some(true)) => (Status::Ok, "Braille is drawn one column wide, and the test pattern looked right"),
+        (true, None) => (Status::Ok, "Braille is drawn one column wide; the test pattern wasn't shown, as there was no one to ask"),
+    };
+    return Check::new(status, "Unicode", String::from(detail));
+}
+
+/// Checks how many colors the terminal says it can show, which themes need
+/// 
+/// ## Arguments
+/// 
+/// * `term` - The `TERM` environment variable
+/// * `colorterm` - The `COLORTERM` environment variable
+pub fn check_colors(term: Option<&str>, colorterm: Option<&str>) -> Check {
+    let (status, detail) = if colorterm.is_some_and(|value| value == "truecolor" || value == "24bit") {
+        (Status::Ok, "24-bit color")
+    } else if term.is_some_and(|term| term.contains("256color")) {
+        (Status::Warn, "256 colors, so themes are shown in the nearest colors of the 256 color palette")
+    } else if term.is_none_or(|term| term.is_empty() || term == "dumb") {
+        (Status::Warn, "TERM doesn't name a terminal, so colors and themes may not show at all")
+    } else {
+        (Status::Warn, "16 colors at least, but themes need 256")
+    };
+    return Check::new(status, "Colors", String::from(detail));
+}
+
+/// Reports whether raw mode, which reads keys as they are pressed, could be turned on
+pub fn check_raw_mode(result: Result<(), String>) -> Check {
+    return match result {
+        Ok(()) => Check::new(Status::Ok, "Raw mode", String::from("keys can be read as they are pressed")),
+        Err(e) => Check::new(Status::Fail, "Raw mode", e),
+    };
+}
+
+/// There's no audio device to find, since the sound timer only lights a marker
+pub fn check_audio() -> Check {
+    return Check::new(Status::Ok, "Audio", String::from("no device needed, the sound timer lights BEEP above the screen rather than playing a tone"));
+}
+
+/// Reports the keys that clash in the layout the arguments asked for
+/// 
+/// ## Arguments
+/// 
+/// * `conflicts` - A line for each conflict, or why the keys couldn't be laid out at all
+pub fn check_keymap(conflicts: Result<Vec<String>, String>) -> Check {
+    return match conflicts {
+        Ok(conflicts) if conflicts.is_empty() => Check::new(Status::Ok, "Keymap", String::from("no key does two things")),
+        Ok(conflicts) => Check::new(Status::Warn, "Keymap", conflicts.join("; ")),
+        Err(e) => Check::new(Status::Fail, "Keymap", e),
+    };
+}
+
+/// Everything found, to print or paste into a bug report
+pub struct Report {
+    pub caps: TermCaps,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether anything stops the emulator working
+    pub fn failed(&self) -> bool {
+        return self.checks.iter().any(|check| check.status == Status::Fail);
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "chip8 {} on {} {}", env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH)?;
+        let environment: Vec<String> = ENVIRONMENT.iter().map(|name| format!("{}={}", name, std::env::var(name).unwrap_or(String::from("unset")))).collect();
+        writeln!(f, "{}", environment.join(" "))?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.caps)?;
+        writeln!(f)?;
+
+        let name_width = self.checks.iter().map(|check| check.name.len()).max().unwrap_or_default();
+        for check in &self.checks {
+            writeln!(f, "{:<6}{:<width$}  {}", check.status, check.name, check.detail, width = name_width)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_judge_what_they_find() {
+        assert_eq!(check_size(150, 40).status, Status::Ok);
+        assert_eq!(check_size(80, 24).detail, "80x24, too small for SUPER-CHIP (68x31), the debugger (115x23), the debugger with SUPER-CHIP (145x31)");
+        assert_eq!(check_size(30, 10).status, Status::Fail);
+
+        assert_eq!(check_colors(Some("xterm-256color"), Some("truecolor")).status, Status::Ok);
+        assert_eq!(check_colors(Some("xterm-256color"), None).detail, "256 colors, so themes are shown in the nearest colors of the 256 color palette");
+        assert_eq!(check_colors(Some("dumb"), None).status, Status::Warn);
+
+        assert_eq!(check_unicode(&TermCaps::assumed(), Some(false)).status, Status::Fail);
+        assert_eq!(check_keymap(Ok(Vec::new())).status, Status::Ok);
+
+        let report = Report { caps: TermCaps::assumed(), checks: vec![check_size(80, 24), check_raw_mode(Err(String::from("no tty")))] };
+        assert!(report.failed());
+        assert!(report.to_string().ends_with("warn  Terminal size  80x24, too small for SUPER-CHIP (68x31), the debugger (115x23), the debugger with SUPER-CHIP (145x31)\nFAIL  Raw mode       no tty\n"));
+    }
+}
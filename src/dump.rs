@@ -0,0 +1,90 @@
+use crate::chip8::Chip8;
+use crate::instruction::Instruction;
+
+// `chip8 run --headless` writes where a ROM ended up as plain text, so CI can diff it against a file checked in next to the test ROM.
+// The screen is a line per row of the screen buffer, # for a lit pixel and . for an unlit one:
+//   ..####..
+//   .#....#.
+// The registers are a line each, in hex:
+//   PC 23A
+//   I 2F0
+//   V0 05
+//   ...
+//   VF 01
+//   DT 00
+//   ST 00
+//   Stack 202 20A
+// The last instructions run are a line each, oldest first, with their address, opcode and what they are:
+//   206  2210  CALL 0x210
+//   210  00EE  RET
+
+/// Writes the screen as text, a line per row
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator
+pub fn screen_text(chip8: &Chip8) -> String {
+    let (width, _height) = chip8.get_screen_size();
+    let mut text = String::new();
+    for row in chip8.get_screen_buffer().chunks(width) {
+        text.extend(row.iter().map(|pixel| if *pixel != 0 { '#' } else { '.' }));
+        text.push('\n');
+    }
+    return text;
+}
+
+/// Writes the registers, timers and stack as text, a line each
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator
+pub fn registers_text(chip8: &Chip8) -> String {
+    let mut text = format!("PC {:03X}\nI {:03X}\n", chip8.get_pc(), chip8.get_index());
+    for (register, value) in chip8.get_registers().iter().enumerate() {
+        text.push_str(&format!("V{:X} {:02X}\n", register, value));
+    }
+    text.push_str(&format!("DT {:02X}\nST {:02X}\n", chip8.get_delay_timer(), chip8.get_sound_timer()));
+
+    let stack: Vec<String> = chip8.get_stack().iter().map(|addr| format!(" {:03X}", addr)).collect();
+    text.push_str(&format!("Stack{}\n", stack.concat()));
+    return text;
+}
+
+/// Writes the last instructions run as text, a line each
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator
+pub fn history_text(chip8: &Chip8) -> String {
+    return chip8
+        .get_pc_history()
+        .iter()
+        .map(|(pc, opcode)| format!("{:03X}  {:04X}  {}\n", pc, opcode, Instruction::decode(*opcode)))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble;
+
+    #[test]
+    fn dumps_are_text() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble("LD V3 0x2A\nCALL sub\nsub: LD F V0\nDRW V0 V0 2").unwrap()).unwrap();
+        for _ in 0..4 {
+            chip8.step();
+        }
+
+        let screen = screen_text(&chip8);
+        assert_eq!(screen.lines().count(), 32);
+        assert_eq!(screen.lines().nth(1), Some(format!("#..#{}", ".".repeat(60)).as_str()));
+
+        let registers = registers_text(&chip8);
+        assert!(registers.starts_with("PC 208\nI 000\nV0 00\n"));
+        assert!(registers.contains("\nV3 2A\n"));
+        assert!(registers.ends_with("DT 00\nST 00\nStack 204\n"));
+
+        assert_eq!(history_text(&chip8), "200  632A  LD V3 0x2A\n202  2204  CALL 0x204\n204  F029  LD F V0\n206  D002  DRW V0 V0 0x2\n");
+    }
+}
@@ -0,0 +1,60 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+
+/// Marks the start of every frame in the stream
+const FRAME_MAGIC: &[u8; 4] = b"C8FR";
+
+/// A side channel that streams raw framebuffers to another program
+/// 
+/// Every frame is written as a 12 byte header followed by the pixels:
+/// 
+/// * 4 bytes - the magic `C8FR`
+/// * 2 bytes - screen width, big endian
+/// * 2 bytes - screen height, big endian
+/// * 4 bytes - frame number, big endian
+/// * width * height bytes - one byte per pixel, row by row, 0 for off and 1 for on
+pub struct FramePipe {
+    writer: Box<dyn Write>,
+    frame: u32,
+}
+
+impl FramePipe {
+    /// Opens the pipe
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `path` - A file or FIFO to write to, or `-` for stdout
+    pub fn open(path: &str) -> io::Result<Self> {
+        let writer: Box<dyn Write> = if Self::is_stdout(path) {
+            Box::new(BufWriter::new(io::stdout()))
+        } else {
+            Box::new(BufWriter::new(OpenOptions::new().write(true).create(true).truncate(true).open(path)?))
+        };
+
+        return Ok(Self { writer, frame: 0 });
+    }
+
+    /// Checks whether a path refers to stdout, which the TUI would otherwise be drawing to
+    pub fn is_stdout(path: &str) -> bool {
+        return path == "-";
+    }
+
+    /// Writes a single frame and flushes it, so the consumer sees it immediately
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `buffer` - The screen buffer, one byte per pixel
+    /// * `width` - Width of the screen in pixels
+    /// * `height` - Height of the screen in pixels
+    pub fn write_frame(&mut self, buffer: &[u8], width: usize, height: usize) -> io::Result<()> {
+        self.writer.write_all(FRAME_MAGIC)?;
+        self.writer.write_all(&(width as u16).to_be_bytes())?;
+        self.writer.write_all(&(height as u16).to_be_bytes())?;
+        self.writer.write_all(&self.frame.to_be_bytes())?;
+        self.writer.write_all(buffer)?;
+        self.writer.flush()?;
+
+        self.frame = self.frame.wrapping_add(1);
+        return Ok(());
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Width of each histogram bucket
+const BUCKET_WIDTH: Duration = Duration::from_micros(50);
+
+/// Number of buckets, covering up to 100ms; anything slower lands in the last one
+const BUCKET_COUNT: usize = 2000;
+
+/// Percentiles shown in the report
+const PERCENTILES: [f64; 3] = [0.50, 0.95, 0.99];
+
+/// Counts durations into fixed-width buckets, so a session of any length takes the same memory
+#[derive(Clone)]
+pub struct Histogram {
+    buckets: Vec<u64>,
+    count: u64,
+    max: Duration,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        return Self { buckets: vec![0; BUCKET_COUNT], count: 0, max: Duration::ZERO };
+    }
+
+    /// Counts one duration
+    pub fn record(&mut self, duration: Duration) {
+        let bucket = (duration.as_nanos() / BUCKET_WIDTH.as_nanos()) as usize;
+        self.buckets[bucket.min(BUCKET_COUNT - 1)] += 1;
+        self.count += 1;
+        self.max = self.max.max(duration);
+    }
+
+    /// The duration that the given fraction of all recorded durations are at or under
+    /// 
+    /// This is the upper edge of the bucket the percentile falls in, so it is accurate to within one bucket.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `fraction` - The percentile, between 0 and 1
+    pub fn percentile(&self, fraction: f64) -> Duration {
+        let rank = ((self.count as f64 * fraction).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank && i < BUCKET_COUNT - 1 {
+                return (BUCKET_WIDTH * (i as u32 + 1)).min(self.max);
+            }
+        }
+        return self.max;
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// How long each part of every frame took over a session
+pub struct FrameTimings {
+    /// Time each frame is meant to take
+    budget: Duration,
+    emulate: Histogram,
+    render: Histogram,
+    sleep: Histogram,
+    total: Histogram,
+    /// Frames whose emulation and rendering alone took longer than the budget
+    dropped: u64,
+}
+
+impl FrameTimings {
+    /// ## Arguments
+    /// 
+    /// * `budget` - Time each frame is meant to take at the chosen framerate
+    pub fn new(budget: Duration) -> Self {
+        return Self {
+            budget,
+            emulate: Histogram::new(),
+            render: Histogram::new(),
+            sleep: Histogram::new(),
+            total: Histogram::new(),
+            dropped: 0,
+        };
+    }
+
+    /// Records one frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `emulate` - Time spent polling input and running instructions
+    /// * `render` - Time spent drawing and sending the frame out
+    /// * `sleep` - Time spent waiting for the next frame
+    pub fn record(&mut self, emulate: Duration, render: Duration, sleep: Duration) {
+        self.emulate.record(emulate);
+        self.render.record(render);
+        self.sleep.record(sleep);
+        self.total.record(emulate + render + sleep);
+
+        if emulate + render > self.budget {
+            self.dropped += 1;
+        }
+    }
+
+    /// Summarizes the session as a table of percentiles
+    pub fn report(&self) -> String {
+        let frames = self.total.count;
+        let mut str_buffer = format!("Frame timing over {} frames ({:.2}ms budget)\n", frames, millis(self.budget));
+        if frames == 0 {
+            return str_buffer;
+        }
+
+        let _ = writeln!(str_buffer, "{:<8}{:>10}{:>10}{:>10}{:>10}", "", "p50", "p95", "p99", "max");
+        for (name, histogram) in [("Emulate", &self.emulate), ("Render", &self.render), ("Sleep", &self.sleep), ("Total", &self.total)] {
+            let _ = write!(str_buffer, "{:<8}", name);
+            for fraction in PERCENTILES {
+                let _ = write!(str_buffer, "{:>8.2}ms", millis(histogram.percentile(fraction)));
+            }
+            let _ = writeln!(str_buffer, "{:>8.2}ms", millis(histogram.max));
+        }
+
+        let _ = writeln!(str_buffer, "Dropped frames: {} ({:.1}%)", self.dropped, self.dropped as f64 * 100.0 / frames as f64);
+        return str_buffer;
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    return duration.as_secs_f64() * 1000.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_come_from_the_buckets() {
+        let mut histogram = Histogram::new();
+        for micros in 1..=100 {
+            histogram.record(Duration::from_micros(micros * 10));
+        }
+
+        // 500us is the 50th duration, and falls in the bucket ending at 550us
+        assert_eq!(histogram.percentile(0.50), Duration::from_micros(550));
+        assert_eq!(histogram.percentile(0.99), Duration::from_micros(1000));
+        assert_eq!(histogram.percentile(1.0), Duration::from_micros(1000));
+
+        // Durations past the last bucket still report their true maximum
+        histogram.record(Duration::from_secs(1));
+        assert_eq!(histogram.percentile(1.0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn slow_frames_count_as_dropped() {
+        let mut timings = FrameTimings::new(Duration::from_millis(10));
+        timings.record(Duration::from_millis(2), Duration::from_millis(3), Duration::from_millis(10));
+        timings.record(Duration::from_millis(8), Duration::from_millis(4), Duration::from_millis(10));
+
+        let report = timings.report();
+        assert!(report.starts_with("Frame timing over 2 frames (10.00ms budget)\n"), "{}", report);
+        assert!(report.contains("Dropped frames: 1 (50.0%)"), "{}", report);
+        assert!(report.contains("Total      15.05ms   22.00ms   22.00ms   22.00ms"), "{}", report);
+    }
+}
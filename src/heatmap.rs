@@ -0,0 +1,121 @@
+use crate::chip8::MemoryAccessCounts;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+// The PNG lays memory out 64 bytes to a row, so the 4K address space makes a square, and the XO-CHIP's 64K a strip 16 times as tall.
+// Each address is a block of pixels colored by how it was used: red for writes, green for reads and blue for instruction fetches.
+// Counts are log scaled against the busiest address, so rarely touched bytes still show up next to hot loops.
+
+/// Addresses per row of the image
+const ROW_WIDTH: usize = 64;
+
+/// Size in pixels of the block drawn for each address
+const BLOCK_SIZE: usize = 4;
+
+/// Writes the counts as CSV, one row per address that was touched at all
+/// 
+/// ## Arguments
+/// 
+/// * `counts` - The counts to write
+/// * `out` - Where to write them
+pub fn write_csv(counts: &MemoryAccessCounts, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "address,reads,writes,fetches")?;
+    for addr in 0..counts.reads.len() {
+        let (reads, writes, fetches) = (counts.reads[addr], counts.writes[addr], counts.fetches[addr]);
+        if reads + writes + fetches > 0 {
+            writeln!(out, "0x{:03X},{},{},{}", addr, reads, writes, fetches)?;
+        }
+    }
+    return Ok(());
+}
+
+/// Draws the counts as an RGB heatmap
+/// 
+/// ## Returns
+/// 
+/// The pixels, and the width and height of the image
+pub fn heatmap_pixels(counts: &MemoryAccessCounts) -> (Vec<u8>, usize, usize) {
+    let width = ROW_WIDTH * BLOCK_SIZE;
+    let height = counts.reads.len().div_ceil(ROW_WIDTH) * BLOCK_SIZE;
+    let mut pixels = vec![0; width * height * 3];
+
+    let channels = [&counts.writes, &counts.reads, &counts.fetches];
+    let scales = channels.map(|channel| ((*channel.iter().max().unwrap_or(&0) + 1) as f64).ln());
+
+    for addr in 0..counts.reads.len() {
+        let color: Vec<u8> = channels
+            .iter()
+            .zip(scales.iter())
+            .map(|(channel, scale)| if *scale > 0.0 { (((channel[addr] + 1) as f64).ln() / scale * 255.0) as u8 } else { 0 })
+            .collect();
+
+        let (x, y) = (addr % ROW_WIDTH * BLOCK_SIZE, addr / ROW_WIDTH * BLOCK_SIZE);
+        for dy in 0..BLOCK_SIZE {
+            for dx in 0..BLOCK_SIZE {
+                let offset = ((y + dy) * width + x + dx) * 3;
+                pixels[offset..offset + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    return (pixels, width, height);
+}
+
+/// Exports the counts to a file, as a PNG heatmap if the path ends in .png and as CSV otherwise
+/// 
+/// ## Arguments
+/// 
+/// * `counts` - The counts to export
+/// * `path` - Where to write them
+pub fn export_heatmap(counts: &MemoryAccessCounts, path: &str) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+
+    if !path.to_lowercase().ends_with(".png") {
+        return write_csv(counts, &mut writer).map_err(|e| format!("Failed to write {}: {}", path, e));
+    }
+
+    let (pixels, width, height) = heatmap_pixels(counts);
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    return encoder
+        .write_header()
+        .and_then(|mut png_writer| png_writer.write_image_data(&pixels))
+        .map_err(|e| format!("Failed to write {}: {}", path, e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts() -> MemoryAccessCounts {
+        let mut counts = MemoryAccessCounts { reads: vec![0; 4096], writes: vec![0; 4096], fetches: vec![0; 4096] };
+        counts.fetches[0x200] = 100;
+        counts.reads[0x300] = 9;
+        counts.writes[0x301] = 3;
+        return counts;
+    }
+
+    #[test]
+    fn csv_lists_touched_addresses() {
+        let mut out = Vec::new();
+        write_csv(&counts(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "address,reads,writes,fetches\n0x200,0,0,100\n0x300,9,0,0\n0x301,0,3,0\n");
+    }
+
+    #[test]
+    fn heatmap_colors_each_kind_of_access() {
+        let (pixels, width, height) = heatmap_pixels(&counts());
+        assert_eq!((width, height), (256, 256));
+
+        let pixel = |addr: usize| {
+            let offset = ((addr / ROW_WIDTH * BLOCK_SIZE) * width + addr % ROW_WIDTH * BLOCK_SIZE) * 3;
+            return [pixels[offset], pixels[offset + 1], pixels[offset + 2]];
+        };
+        assert_eq!(pixel(0x200), [0, 0, 255]);
+        assert_eq!(pixel(0x300), [0, 255, 0]);
+        assert_eq!(pixel(0x301), [255, 0, 0]);
+        assert_eq!(pixel(0x000), [0, 0, 0]);
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+// Keypad keys stay down for as long as the key on the keyboard is held, so SKP and SKNP see a held key on every frame.
+// With the kitty keyboard protocol the terminal says when a key is let go. Other terminals only send presses, repeating
+// them while a key is held, so a key is taken to be let go once no press or repeat has come for --key-hold milliseconds.
+// That has to outlast the pause before the terminal starts repeating to keep a key held through it, but a longer hold
+// makes a tap last longer too. Desktops mostly pause for 500 ms or less, so that is the default.
+
+/// Milliseconds a key stays down after its last press by default, long enough to span the usual pause before repeating
+pub const DEFAULT_HOLD_MS: u64 = 500;
+
+/// Which keypad keys are held down on the keyboard
+pub struct HeldKeys {
+    /// How long a key stays down after its last press, or `None` when the terminal says when keys are let go
+    hold: Option<Duration>,
+    /// When each key held down was last pressed or repeated
+    pressed_at: [Option<Duration>; 16],
+}
+
+impl HeldKeys {
+    /// Starts with every key up
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `hold` - How long a key stays down after its last press, or `None` if releases are reported
+    pub fn new(hold: Option<Duration>) -> Self {
+        return Self { hold, pressed_at: [None; 16] };
+    }
+
+    /// Holds a key down, or keeps it down when the terminal repeats it
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `key` - The keypad key
+    /// * `now` - The time on the emulator's clock
+    pub fn press(&mut self, key: usize, now: Duration) {
+        self.pressed_at[key] = Some(now);
+    }
+
+    pub fn release(&mut self, key: usize) {
+        self.pressed_at[key] = None;
+    }
+
+    /// Lets go of every key, for when the terminal loses focus and won't say which keys were let go
+    pub fn release_all(&mut self) {
+        self.pressed_at = [None; 16];
+    }
+
+    /// Lets go of the keys that haven't been pressed again within the hold time
+    pub fn expire(&mut self, now: Duration) {
+        let Some(hold) = self.hold else {
+            return;
+        };
+        for pressed_at in self.pressed_at.iter_mut() {
+            if pressed_at.is_some_and(|time| now.saturating_sub(time) >= hold) {
+                *pressed_at = None;
+            }
+        }
+    }
+
+    pub fn is_held(&self, key: usize) -> bool {
+        return self.pressed_at[key].is_some();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_held_until_let_go_or_timed_out() {
+        let ms = Duration::from_millis;
+        let mut reported = HeldKeys::new(None);
+        reported.press(5, ms(0));
+        reported.expire(ms(10_000));
+        assert!(reported.is_held(5) && !reported.is_held(4));
+        reported.release(5);
+        assert!(!reported.is_held(5));
+
+        // Repeats keep a key down, and it is let go once they stop for the hold time
+        let mut timed = HeldKeys::new(Some(ms(100)));
+        timed.press(5, ms(0));
+        timed.press(5, ms(80));
+        timed.expire(ms(150));
+        assert!(timed.is_held(5));
+        timed.expire(ms(180));
+        assert!(!timed.is_held(5));
+
+        // The default holds a key through a usual 500 ms pause before the first repeat, but not much longer
+        let mut default = HeldKeys::new(Some(ms(DEFAULT_HOLD_MS)));
+        default.press(5, ms(0));
+        default.expire(ms(490));
+        assert!(default.is_held(5));
+        default.press(5, ms(495));
+        default.expire(ms(1000));
+        assert!(!default.is_held(5));
+        assert_eq!(DEFAULT_HOLD_MS, 500);
+    }
+}
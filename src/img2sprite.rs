@@ -0,0 +1,220 @@
+use crate::pack_data::data_source;
+use std::fs::File;
+
+/// Sprites are always one byte wide
+const SPRITE_WIDTH: usize = 8;
+
+/// DRW takes at most 15 rows
+const MAX_SPRITE_HEIGHT: usize = 15;
+
+/// One DRW's worth of an image, and where it goes relative to the image's top left corner
+#[derive(Debug, PartialEq)]
+pub struct SpritePart {
+    pub dx: usize,
+    pub dy: usize,
+    pub rows: Vec<u8>,
+}
+
+/// Reads a PNG as one brightness value per pixel, treating transparent pixels as black
+/// 
+/// ## Arguments
+/// 
+/// * `path` - The image to read
+/// 
+/// ## Returns
+/// 
+/// The brightness of every pixel, and the width and height of the image
+pub fn read_brightness(path: &str) -> Result<(Vec<u8>, usize, usize), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let channels = info.color_type.samples();
+
+    let brightness = data[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| {
+            let (value, alpha) = match pixel.len() {
+                1 => (pixel[0] as u32, 255),
+                2 => (pixel[0] as u32, pixel[1] as u32),
+                3 => (luma(pixel), 255),
+                _ => (luma(pixel), pixel[3] as u32),
+            };
+            (value * alpha / 255) as u8
+        })
+        .collect();
+
+    return Ok((brightness, info.width as usize, info.height as usize));
+}
+
+/// Perceived brightness of an RGB pixel
+fn luma(pixel: &[u8]) -> u32 {
+    return (pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000;
+}
+
+/// Turns brightness values into lit and unlit pixels
+/// 
+/// ## Arguments
+/// 
+/// * `brightness` - One brightness value per pixel
+/// * `width` - Width of the image in pixels
+/// * `threshold` - Pixels at least this bright are lit
+/// * `dither` - Spread each pixel's rounding error to its neighbours (Floyd-Steinberg), so gradients survive
+pub fn to_bitmap(brightness: &[u8], width: usize, threshold: u8, dither: bool) -> Vec<bool> {
+    let mut values: Vec<i32> = brightness.iter().map(|value| *value as i32).collect();
+    let mut bitmap = vec![false; values.len()];
+
+    for i in 0..values.len() {
+        let lit = values[i] >= threshold as i32;
+        bitmap[i] = lit;
+
+        if !dither {
+            continue;
+        }
+
+        let error = values[i] - if lit { 255 } else { 0 };
+        let x = i % width;
+        let mut spread = |offset: usize, weight: i32| {
+            if let Some(value) = values.get_mut(offset) {
+                *value += error * weight / 16;
+            }
+        };
+
+        if x + 1 < width {
+            spread(i + 1, 7);
+            spread(i + width + 1, 1);
+        }
+        if x > 0 {
+            spread(i + width - 1, 3);
+        }
+        spread(i + width, 5);
+    }
+
+    return bitmap;
+}
+
+/// Cuts a bitmap into sprites, 8 pixels wide and up to 15 rows tall, leaving out any that are blank
+/// 
+/// ## Arguments
+/// 
+/// * `bitmap` - Lit and unlit pixels, row by row
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+pub fn split_sprites(bitmap: &[bool], width: usize, height: usize) -> Vec<SpritePart> {
+    let mut parts = Vec::new();
+
+    for dy in (0..height).step_by(MAX_SPRITE_HEIGHT) {
+        for dx in (0..width).step_by(SPRITE_WIDTH) {
+            let rows: Vec<u8> = (dy..height.min(dy + MAX_SPRITE_HEIGHT))
+                .map(|y| {
+                    let mut row = 0;
+                    for bit in 0..SPRITE_WIDTH {
+                        let x = dx + bit;
+                        if x < width && bitmap[y * width + x] {
+                            row |= 0x80 >> bit;
+                        }
+                    }
+                    row
+                })
+                .collect();
+
+            if rows.iter().any(|row| *row != 0) {
+                parts.push(SpritePart { dx, dy, rows });
+            }
+        }
+    }
+
+    return parts;
+}
+
+/// Writes the sprite data and a routine that draws the whole image
+/// 
+/// Calling `DRAW_<NAME>` draws the image with its top left corner at (V0, V1), using V2, V3 and I.
+/// Each part's data is labelled `<NAME>_<X>_<Y>` after its offset in pixels.
+/// 
+/// ## Arguments
+/// 
+/// * `name` - Label prefix for the image
+/// * `parts` - The sprites making up the image
+pub fn sprite_source(name: &str, parts: &[SpritePart]) -> String {
+    let name = name.to_uppercase();
+    let mut str_buffer = format!("DRAW_{}:\n", name);
+
+    for part in parts.iter() {
+        str_buffer.push_str("LD V2 V0\n");
+        if part.dx > 0 {
+            str_buffer.push_str(&format!("ADD V2 {}\n", part.dx));
+        }
+        str_buffer.push_str("LD V3 V1\n");
+        if part.dy > 0 {
+            str_buffer.push_str(&format!("ADD V3 {}\n", part.dy));
+        }
+        str_buffer.push_str(&format!("LD I {}_{}_{}\n", name, part.dx, part.dy));
+        str_buffer.push_str(&format!("DRW V2 V3 {}\n", part.rows.len()));
+    }
+    str_buffer.push_str("RET\n");
+
+    for part in parts.iter() {
+        str_buffer.push_str(&data_source(&format!("{}_{}_{}", name, part.dx, part.dy), &part.rows, part.rows.len()));
+    }
+
+    return str_buffer;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::*;
+
+    #[test]
+    fn threshold_and_dither() {
+        assert_eq!(to_bitmap(&[0, 127, 128, 255], 4, 128, false), vec![false, false, true, true]);
+
+        // Mid gray comes out as roughly half lit when dithered, and all dark when not
+        let gray = vec![100; 64];
+        assert_eq!(to_bitmap(&gray, 8, 128, false).iter().filter(|lit| **lit).count(), 0);
+        let lit = to_bitmap(&gray, 8, 128, true).iter().filter(|lit| **lit).count();
+        assert!((20..=30).contains(&lit), "{} pixels lit", lit);
+    }
+
+    #[test]
+    fn wide_and_tall_images_split() {
+        // 10x16 with every pixel lit
+        let parts = split_sprites(&[true; 160], 10, 16);
+        let layout: Vec<(usize, usize, usize, u8)> = parts.iter().map(|part| (part.dx, part.dy, part.rows.len(), part.rows[0])).collect();
+        assert_eq!(layout, vec![(0, 0, 15, 0xFF), (8, 0, 15, 0xC0), (0, 15, 1, 0xFF), (8, 15, 1, 0xC0)]);
+    }
+
+    #[test]
+    fn generated_routine_draws_the_image() {
+        // A 12x3 frame with a hole in the middle, and a blank part that should be skipped
+        let mut bitmap = vec![true; 36];
+        bitmap[13] = false;
+        let bitmap: Vec<bool> = bitmap.into_iter().chain(vec![false; 12 * 15]).collect();
+        let parts = split_sprites(&bitmap, 12, 18);
+        assert_eq!(parts.len(), 2);
+
+        let program = format!("LD V0 4\nLD V1 2\nCALL DRAW_FRAME\nHALT: JP HALT\n{}", sprite_source("frame", &parts));
+        let (rom, warnings) = assemble_with_warnings(&program).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..50 {
+            chip8.cycle().unwrap();
+        }
+
+        let screen = chip8.get_screen_buffer();
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                let inside = (4..16).contains(&x) && (2..5).contains(&y);
+                let expected = inside && !(x == 5 && y == 3);
+                assert_eq!(screen[y * SCREEN_WIDTH + x] != 0, expected, "pixel ({}, {})", x, y);
+            }
+        }
+    }
+}
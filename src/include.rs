@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+
+/// The standard library, built into the assembler so it can be included from anywhere
+const STDLIB: [(&str, &str); 4] = [
+    ("stdlib/multiply.s8", include_str!("../stdlib/multiply.s8")),
+    ("stdlib/print_number.s8", include_str!("../stdlib/print_number.s8")),
+    ("stdlib/read_key.s8", include_str!("../stdlib/read_key.s8")),
+    ("stdlib/wait_frames.s8", include_str!("../stdlib/wait_frames.s8")),
+];
+
+/// Replaces every `.include "path"` or `%include "path"` line with the contents of that file
+/// 
+/// Paths are relative to the including file. Paths starting with `stdlib/` that don't exist there are taken from the
+/// standard library built into the assembler. A file may be included more than once, but not from inside itself.
+/// 
+/// ## Arguments
+/// 
+/// * `source` - The assembler source
+/// * `dir` - Directory of the file the source came from
+pub fn expand_includes(source: &str, dir: &Path) -> Result<String, String> {
+    return expand(source, dir, &mut Vec::new(), &mut Vec::new());
+}
+
+/// Reads a source file and pastes in everything it includes, like `expand_includes`
+/// 
+/// ## Arguments
+/// 
+/// * `path` - The file
+/// 
+/// ## Returns
+/// 
+/// The source, and every file read for it in the order they were first included, starting with `path`.
+/// Files from the built-in standard library are named `stdlib/<file>`
+pub fn expand_file(path: &Path) -> Result<(String, Vec<PathBuf>), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut files = vec![path.to_path_buf()];
+    let source = expand(&text, dir, &mut vec![identity(path)], &mut files)?;
+    return Ok((source, files));
+}
+
+/// Names a file the same way however the path to it is written, so a cycle is spotted through `..` or a link
+fn identity(path: &Path) -> PathBuf {
+    return std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+}
+
+/// Pastes includes into one file's source
+/// 
+/// ## Arguments
+/// 
+/// * `source` - The file's source
+/// * `dir` - Directory of the file
+/// * `including` - The files being included on the way to this one, to spot one that includes itself
+/// * `files` - Every file read so far
+fn expand(source: &str, dir: &Path, including: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) -> Result<String, String> {
+    let mut str_buffer = String::new();
+    for line in source.lines() {
+        let Some(path) = include_path(line) else {
+            str_buffer.push_str(line);
+            str_buffer.push('\n');
+            continue;
+        };
+
+        let mut full_path = dir.join(path);
+        let text = match std::fs::read_to_string(&full_path) {
+            Ok(text) => text,
+            Err(e) => match STDLIB.iter().find(|(name, _)| *name == path) {
+                Some((name, text)) => {
+                    full_path = PathBuf::from(name);
+                    text.to_string()
+                },
+                None => return Err(format!("Failed to include {}: {}", full_path.display(), e)),
+            },
+        };
+
+        let id = identity(&full_path);
+        if including.contains(&id) {
+            let chain: Vec<String> = including.iter().chain([&id]).map(|file| file.file_name().unwrap_or_default().to_string_lossy().to_string()).collect();
+            return Err(format!("{} includes itself: {}", full_path.display(), chain.join(" > ")));
+        }
+        if !files.contains(&full_path) {
+            files.push(full_path.clone());
+        }
+
+        let include_dir = full_path.parent().unwrap_or(dir);
+        including.push(id);
+        str_buffer.push_str(&expand(&text, include_dir, including, files)?);
+        including.pop();
+    }
+
+    return Ok(str_buffer);
+}
+
+/// Reads the path out of an include line, if it is one
+fn include_path(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let directive = line.get(..8)?;
+    if !directive.eq_ignore_ascii_case("%include") && !directive.eq_ignore_ascii_case(".include") {
+        return None;
+    }
+
+    return line[8..].trim().strip_prefix('"')?.split('"').next();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::*;
+
+    /// Assembles a test program with a standard library routine and runs it
+    fn run_with(routine: &str, program: &str, keys: &[Option<usize>]) -> Chip8 {
+        let source = format!("{}\nHALT: JP HALT\n%include \"stdlib/{}.s8\"", program, routine);
+        let source = expand_includes(&source, Path::new("/nonexistent")).unwrap();
+        let (rom, warnings) = assemble_with_warnings(&source).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        for i in 0..2000 {
+            chip8.clear_keypad();
+            // Each entry lasts 100 cycles, and the last one holds until the end
+            if let Some(Some(key)) = keys.get(i / 100).or(keys.last()) {
+                chip8.set_keypress(*key);
+            }
+            chip8.cycle().unwrap();
+            chip8.update_timers();
+        }
+        return chip8;
+    }
+
+    #[test]
+    fn documented_sizes_are_right() {
+        for (name, text) in STDLIB.iter() {
+            let size = text
+                .lines()
+                .find_map(|line| line.strip_prefix("; Size: "))
+                .and_then(|size| size.trim_end_matches(" bytes").parse::<usize>().ok())
+                .unwrap_or_else(|| panic!("{} has no size", name));
+            assert_eq!(assemble(text).unwrap().len(), size, "{}", name);
+        }
+    }
+
+    #[test]
+    fn multiply() {
+        let chip8 = run_with("multiply", "LD V0 200\nLD V1 123\nCALL MULTIPLY\nLD I 0x300\nLD [I] V3", &[]);
+        let product = 200 * 123;
+        assert_eq!(&chip8.get_memory()[0x302..0x304], &[(product >> 8) as u8, product as u8]);
+    }
+
+    #[test]
+    fn print_number() {
+        let chip8 = run_with("print_number", "LD V3 207\nLD V4 0\nLD V5 0\nCALL PRINT_NUMBER", &[]);
+
+        // The second rows of the 2, 0 and 7 glyphs are 10, 90 and 10
+        let screen = chip8.get_screen_buffer();
+        let second_row: Vec<u8> = (0..15).map(|x| screen[SCREEN_WIDTH + x]).collect();
+        assert_eq!(second_row, vec![0, 0, 0, 1, 0, 1, 0, 0, 1, 0, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn read_key_waits_for_release() {
+        // The digit for the key is only drawn once the key is let go
+        let program = "CALL READ_KEY\nLD F V0\nLD V1 0\nDRW V1 V1 5";
+        let chip8 = run_with("read_key", program, &[None, Some(5), Some(5), None]);
+        assert_eq!(chip8.get_screen_buffer()[..4], [1, 1, 1, 1]);
+
+        let chip8 = run_with("read_key", program, &[None, Some(5), Some(5), Some(5)]);
+        assert!(chip8.get_screen_buffer().iter().all(|pixel| *pixel == 0));
+    }
+
+    #[test]
+    fn includes_resolve_relative_to_the_including_file() {
+        let dir = std::env::temp_dir().join(format!("chip8-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub").join("a.s8"), "%include \"b.s8\"\nCLS").unwrap();
+        std::fs::write(dir.join("sub").join("b.s8"), "RET").unwrap();
+
+        let expanded = expand_includes("%INCLUDE \"sub/a.s8\"\nNOP", &dir).unwrap();
+        assert_eq!(expanded, "RET\nCLS\nNOP\n");
+
+        // .include works the same, and the files read come back in the order they were first included
+        std::fs::write(dir.join("main.s8"), ".include \"sub/a.s8\"\n.include \"sub/b.s8\"\n.include \"stdlib/multiply.s8\"").unwrap();
+        let (expanded, files) = expand_file(&dir.join("main.s8")).unwrap();
+        assert!(expanded.starts_with("RET\nCLS\nRET\n"));
+        assert_eq!(files, vec![dir.join("main.s8"), dir.join("sub/a.s8"), dir.join("sub/b.s8"), PathBuf::from("stdlib/multiply.s8")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(expand_includes("%include \"missing.s8\"", &dir).is_err());
+    }
+
+    #[test]
+    fn files_can_not_include_themselves() {
+        let dir = std::env::temp_dir().join(format!("chip8-include-cycle-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("main.s8"), ".include \"sub/a.s8\"").unwrap();
+        std::fs::write(dir.join("sub").join("a.s8"), ".include \"../main.s8\"").unwrap();
+
+        let error = expand_file(&dir.join("main.s8")).unwrap_err();
+        assert!(error.ends_with("includes itself: main.s8 > a.s8 > main.s8"), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
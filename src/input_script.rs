@@ -0,0 +1,322 @@
+use crate::chip8::Chip8;
+use crate::clock::CycleJitter;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+// An input script lists every key press of a session by the frame and tick it landed on, one per line:
+//   # frame tick key
+//   120 3 5
+// The keypad is cleared at the start of every frame, so a held key shows up once per frame it was held.
+// Playing a script back with the same ROM and RND seed repeats the session exactly.
+
+/// Key presses of a session, by when they happened
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputScript {
+    /// Keys pressed on each (frame, tick), one bit per key
+    presses: BTreeMap<(u32, u64), u16>,
+}
+
+impl InputScript {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Reads a script from a file
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `path` - The file to read
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        return Self::parse(&text).map_err(|message| format!("{}: {}", path, message));
+    }
+
+    /// Writes the script to a file
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `path` - The file to write
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        return std::fs::write(path, self.to_string()).map_err(|e| format!("Failed to write {}: {}", path, e));
+    }
+
+    /// Reads a script from its text form, skipping blank lines and comments
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `text` - The script
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut script = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [frame, tick, key] = fields[..] else {
+                return Err(format!("line {}: expected a frame, a tick and a key", number + 1));
+            };
+            let frame = frame.parse::<u32>().map_err(|_| format!("line {}: {} is not a frame number", number + 1, frame))?;
+            let tick = tick.parse::<u64>().map_err(|_| format!("line {}: {} is not a tick number", number + 1, tick))?;
+            let key = match usize::from_str_radix(key, 16) {
+                Ok(key) if key < 16 => key,
+                _ => return Err(format!("line {}: {} is not a key from 0 to F", number + 1, key)),
+            };
+            script.record(frame, tick, key);
+        }
+        return Ok(script);
+    }
+
+    /// Adds a key press
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `frame` - The frame it happened on
+    /// * `tick` - The instruction within the frame it happened before
+    /// * `key` - The key, from 0x0 to 0xF
+    pub fn record(&mut self, frame: u32, tick: u64, key: usize) {
+        *self.presses.entry((frame, tick)).or_insert(0) |= 1 << key;
+    }
+
+    /// Removes every press of a key at one point, returning whether there was one
+    pub fn remove(&mut self, frame: u32, tick: u64, key: usize) -> bool {
+        let Some(keys) = self.presses.get_mut(&(frame, tick)) else {
+            return false;
+        };
+        let pressed = *keys & 1 << key != 0;
+        *keys &= !(1 << key);
+        if *keys == 0 {
+            self.presses.remove(&(frame, tick));
+        }
+        return pressed;
+    }
+
+    /// Every key press, in the order they happened
+    pub fn presses(&self) -> impl Iterator<Item = (u32, u64, usize)> + '_ {
+        return self.presses.iter().flat_map(|(&(frame, tick), &keys)| {
+            return (0..16).filter(move |key| keys & 1 << key != 0).map(move |key| (frame, tick, key));
+        });
+    }
+
+    /// Presses the keys the script has for one point in a frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `chip8` - The emulator to press them on
+    /// * `frame` - The current frame
+    /// * `tick` - The instruction about to run within the frame
+    pub fn apply(&self, chip8: &mut Chip8, frame: u32, tick: u64) {
+        for key in self.keys_at(frame, tick) {
+            chip8.set_keypress(key);
+        }
+    }
+
+    /// The keys the script has for one point in a frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `frame` - The frame
+    /// * `tick` - The instruction within the frame
+    pub fn keys_at(&self, frame: u32, tick: u64) -> Vec<usize> {
+        let keys = self.presses.get(&(frame, tick)).copied().unwrap_or(0);
+        return (0..16).filter(|key| keys & 1 << key != 0).collect();
+    }
+}
+
+impl fmt::Display for InputScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# frame tick key")?;
+        for (frame, tick, key) in self.presses() {
+            writeln!(f, "{} {} {:X}", frame, tick, key)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Plays a script back without a terminal or a clock, ticking the timers once per frame
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator, with its ROM loaded and RND seeded
+/// * `script` - The key presses to play back
+/// * `frames` - Number of frames to run
+/// * `ticks_per_frame` - Instructions run each frame
+pub fn replay(chip8: &mut Chip8, script: &InputScript, frames: u32, ticks_per_frame: u64) {
+    replay_frames(chip8, script, 0..frames, ticks_per_frame, None);
+}
+
+/// Plays part of a script back, like `replay`, for an emulator that has already run the frames before it
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator, as it was at the end of the frame before the first one
+/// * `script` - The key presses to play back
+/// * `frames` - The frames to run
+/// * `ticks_per_frame` - Instructions run each frame
+/// * `jitter` - What varies the instructions run each frame, if anything does
+pub fn replay_frames(chip8: &mut Chip8, script: &InputScript, frames: Range<u32>, ticks_per_frame: u64, jitter: Option<&CycleJitter>) {
+    for frame in frames {
+        chip8.clear_keypad();
+        let budget = jitter.map_or(ticks_per_frame, |jitter| jitter.budget(frame, ticks_per_frame));
+        for tick in 0..budget {
+            script.apply(chip8, frame, tick);
+            // A machine stopped by an error runs no more, which the caller finds with `get_error`
+            if chip8.cycle().is_err() {
+                return;
+            }
+        }
+        chip8.update_timers();
+    }
+}
+
+/// Plays a script back on two emulators set up the same way, to catch anything that makes runs differ
+/// 
+/// ## Arguments
+/// 
+/// * `first` - An emulator with its ROM loaded and RND seeded
+/// * `second` - Another, set up exactly the same way
+/// * `script` - The key presses to play back on both
+/// * `frames` - Number of frames to run
+/// * `ticks_per_frame` - Instructions run each frame
+/// * `jitter` - What varies the instructions run each frame, the same for both runs
+/// 
+/// ## Returns
+/// 
+/// How the second emulator's final state differs from the first's, or nothing if the runs matched
+pub fn check_determinism(mut first: Chip8, mut second: Chip8, script: &InputScript, frames: u32, ticks_per_frame: u64, jitter: Option<&CycleJitter>) -> Vec<String> {
+    replay_frames(&mut first, script, 0..frames, ticks_per_frame, jitter);
+    replay_frames(&mut second, script, 0..frames, ticks_per_frame, jitter);
+    return first.state_differences(&second);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Fuzzes the input path by mutating scripts and playing them against fixed ROMs.
+    // Any panic fails the test, and every script has to play back to the same state twice running,
+    // including when the run is saved partway through and carried on from the save state in a fresh emulator.
+
+    const FRAMES: u32 = 300;
+    const TICKS_PER_FRAME: u64 = 10;
+    const MUTATIONS: usize = 12;
+
+    /// Waits on Fx0A in a loop, drawing the key it got, so presses at any point change what happens
+    const WAIT_FOR_KEYS: &str = "loop: LD V0 K\nLD F V0\nDRW V1 V2 5\nADD V1 5\nRND V2 0x1F\nJP loop";
+
+    fn random_script(rng: &mut StdRng, presses: usize) -> InputScript {
+        let mut script = InputScript::new();
+        for _ in 0..presses {
+            script.record(rng.gen_range(0..FRAMES), rng.gen_range(0..TICKS_PER_FRAME), rng.gen_range(0..16));
+        }
+        return script;
+    }
+
+    /// Adds, drops, moves or changes a few presses
+    fn mutate(script: &mut InputScript, rng: &mut StdRng) {
+        for _ in 0..rng.gen_range(1..=4) {
+            let presses: Vec<(u32, u64, usize)> = script.presses().collect();
+            if presses.is_empty() || rng.gen_bool(0.25) {
+                script.record(rng.gen_range(0..FRAMES), rng.gen_range(0..TICKS_PER_FRAME), rng.gen_range(0..16));
+                continue;
+            }
+
+            let (frame, tick, key) = presses[rng.gen_range(0..presses.len())];
+            script.remove(frame, tick, key);
+            match rng.gen_range(0..3) {
+                0 => {},
+                1 => script.record(frame.saturating_add_signed(rng.gen_range(-5..=5)).min(FRAMES - 1), rng.gen_range(0..TICKS_PER_FRAME), key),
+                _ => script.record(frame, tick, rng.gen_range(0..16)),
+            }
+        }
+    }
+
+    /// Plays a script from power on, returning everything it could have changed
+    fn play(rom: &[u8], script: &InputScript, seed: u64) -> (Vec<u8>, Vec<u8>, Vec<u8>, u16) {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(rom).unwrap();
+        chip8.seed_rng(seed);
+        replay(&mut chip8, script, FRAMES, TICKS_PER_FRAME);
+        return (chip8.get_screen_buffer().to_vec(), chip8.get_registers().to_vec(), chip8.get_memory().to_vec(), chip8.get_pc());
+    }
+
+    /// Plays a script from power on, saving at the end of one frame and finishing the run from the save state
+    fn play_through_save(rom: &[u8], script: &InputScript, seed: u64, save_after: u32) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(rom).unwrap();
+        chip8.seed_rng(seed);
+        replay_frames(&mut chip8, script, 0..save_after, TICKS_PER_FRAME, None);
+
+        let mut loaded = Chip8::new();
+        loaded.load_state(&chip8.save_state().unwrap()).unwrap();
+        replay_frames(&mut loaded, script, save_after..FRAMES, TICKS_PER_FRAME, None);
+        return loaded;
+    }
+
+    #[test]
+    fn scripts_round_trip_through_text() {
+        let script = InputScript::parse("# frame tick key\n0 0 5\n\n3 9 a\n3 9 F\n").unwrap();
+        assert_eq!(script.presses().collect::<Vec<_>>(), vec![(0, 0, 5), (3, 9, 0xA), (3, 9, 0xF)]);
+        assert_eq!(InputScript::parse(&script.to_string()), Ok(script));
+
+        assert_eq!(InputScript::parse("1 2"), Err(String::from("line 1: expected a frame, a tick and a key")));
+        assert_eq!(InputScript::parse("\n1 2 10"), Err(String::from("line 2: 10 is not a key from 0 to F")));
+    }
+
+    #[test]
+    fn determinism_check_spots_differences() {
+        let setup = |seed: u64| {
+            let mut chip8 = Chip8::new();
+            chip8.load_rom(include_bytes!("../c8games/BRIX")).unwrap();
+            chip8.seed_rng(seed);
+            return chip8;
+        };
+        let script = InputScript::parse("10 0 4\n11 0 4\n40 3 6").unwrap();
+        assert_eq!(check_determinism(setup(7), setup(7), &script, 120, 8, None), Vec::<String>::new());
+        assert_eq!(check_determinism(setup(7), setup(7), &script, 120, 8, Some(&CycleJitter::new(7, 3))), Vec::<String>::new());
+
+        // Anything that isn't set up the same way shows up, like a different seed
+        let differences = check_determinism(setup(7), setup(8), &script, 120, 8, None);
+        assert_eq!(differences.last().map(String::as_str), Some("RND: the random number generators are in different states"));
+
+        // or uneven frames
+        let (mut steady, mut jittered) = (setup(7), setup(7));
+        replay(&mut steady, &script, 120, 8);
+        replay_frames(&mut jittered, &script, 0..120, 8, Some(&CycleJitter::new(7, 3)));
+        assert!(!steady.state_differences(&jittered).is_empty());
+    }
+
+    #[test]
+    fn mutated_replays_are_deterministic() {
+        let roms: [(&str, Vec<u8>); 4] = [
+            ("BRIX", include_bytes!("../c8games/BRIX").to_vec()),
+            ("TETRIS", include_bytes!("../c8games/TETRIS").to_vec()),
+            ("15PUZZLE", include_bytes!("../c8games/15PUZZLE").to_vec()),
+            ("wait for keys", assemble(WAIT_FOR_KEYS).unwrap()),
+        ];
+
+        for (i, (name, rom)) in roms.iter().enumerate() {
+            let mut rng = StdRng::seed_from_u64(i as u64);
+            let mut script = random_script(&mut rng, 40);
+
+            for _ in 0..MUTATIONS {
+                mutate(&mut script, &mut rng);
+                let seed = rng.gen();
+                assert!(play(rom, &script, seed) == play(rom, &script, seed), "{} diverged on replay with seed {} and script:\n{}", name, seed, script);
+
+                let save_after = rng.gen_range(0..FRAMES);
+                let mut straight = Chip8::new();
+                straight.load_rom(rom).unwrap();
+                straight.seed_rng(seed);
+                replay(&mut straight, &script, FRAMES, TICKS_PER_FRAME);
+                let differences = straight.state_differences(&play_through_save(rom, &script, seed, save_after));
+                assert!(differences.is_empty(), "{} diverged after saving at frame {} with seed {}: {:?}", name, save_after, seed, differences);
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+// The keys the ROM was given, newest first, each with how many frames ago it went down, for finding out
+// why a ROM didn't react to a key, or what FX0A was handed:
+//   Keys  5 0f · A 12f · A 40f · 2 41f
+// A key counts once when it goes down, however long it is held. On terminals that don't report key releases, a held
+// key that the terminal is slow to start repeating is let go after --key-hold and shows up again, as the ROM sees it.
+
+/// Most presses kept, which is more than fit beside the smallest screen
+const KEPT: usize = 10;
+
+/// The last keys to go down, as the ROM saw them
+pub struct KeyHistory {
+    /// Key and the frame it went down on, newest first
+    presses: VecDeque<(usize, u32)>,
+    /// Keys down at the end of the last frame
+    held: [bool; 16],
+}
+
+impl KeyHistory {
+    pub fn new() -> Self {
+        return Self { presses: VecDeque::new(), held: [false; 16] };
+    }
+
+    /// Notes the keys that went down during a frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `frame` - The frame that just ran
+    /// * `keypad` - Every key down in it
+    pub fn record(&mut self, frame: u32, keypad: &[bool]) {
+        for (key, &down) in keypad.iter().enumerate().take(16) {
+            if down && !self.held[key] {
+                self.presses.push_front((key, frame));
+            }
+            self.held[key] = down;
+        }
+        self.presses.truncate(KEPT);
+    }
+
+    /// Lists the presses newest first, as many whole ones as fit
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `label` - What to start the line with
+    /// * `frame` - The frame now, which the ages are counted back from
+    /// * `width` - Columns the line has to fit in
+    pub fn line(&self, label: &str, frame: u32, width: usize) -> String {
+        let mut line = String::from(label);
+        for (index, (key, pressed)) in self.presses.iter().enumerate() {
+            let press = format!("{}{:X} {}f", if index == 0 { "  " } else { " · " }, key, frame.saturating_sub(*pressed));
+            if line.chars().count() + press.chars().count() > width {
+                break;
+            }
+            line.push_str(&press);
+        }
+        return line;
+    }
+}
+
+impl Default for KeyHistory {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_are_counted_when_keys_go_down() {
+        let mut history = KeyHistory::new();
+        let mut keypad = [false; 16];
+        keypad[0xA] = true;
+        for frame in 0..3 {
+            history.record(frame, &keypad);
+        }
+        keypad[0xA] = false;
+        keypad[5] = true;
+        history.record(3, &keypad);
+
+        assert_eq!(history.line("Keys", 4, 40), "Keys  5 1f · A 4f");
+        assert_eq!(history.line("Keys", 4, 12), "Keys  5 1f");
+    }
+}
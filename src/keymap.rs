@@ -0,0 +1,249 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventState, KeyModifiers};
+
+// The keypad's 16 keys can be laid out on the keyboard in a few places, one per player, all pressing the same keypad:
+//   main     1 2 3 4     ijkl     7 8 9 0     numpad   digits are themselves, and
+//            q w e r              u i o p              / * - + Enter . are A B C D E F
+//            a s d f              j k l ;
+//            z x c v              m , . /
+// The first two are laid out like the COSMAC VIP's keypad:
+//            1 2 3 C
+//            4 5 6 D
+//            7 8 9 E
+//            A 0 B F
+// Terminals only tell numpad digits apart from the number row with the kitty keyboard protocol.
+// Each player can be limited to some of the keys, so neither can press the other's in ROMs where two players share the keypad.
+
+/// Where on the keyboard a player's keys are
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    Main,
+    Ijkl,
+    Numpad,
+}
+
+impl Layout {
+    /// Looks a layout up by name: `main`, `ijkl` or `numpad`
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        return match name {
+            "main" => Ok(Self::Main),
+            "ijkl" => Ok(Self::Ijkl),
+            "numpad" => Ok(Self::Numpad),
+            _ => Err(format!("Unknown layout {}, expected main, ijkl or numpad", name)),
+        };
+    }
+
+    /// Finds the keypad key a key press is in this layout, if it is one
+    fn button(&self, key: &KeyEvent) -> Option<usize> {
+        let keypad = key.state.contains(KeyEventState::KEYPAD);
+        let rows = match self {
+            Self::Main => ["1234", "qwer", "asdf", "zxcv"],
+            Self::Ijkl => ["7890", "uiop", "jkl;", "m,./"],
+            Self::Numpad if !keypad => return None,
+            Self::Numpad => {
+                return match key.code {
+                    KeyCode::Char(digit @ '0'..='9') => digit.to_digit(10).map(|digit| digit as usize),
+                    KeyCode::Char('.') => Some(0xF),
+                    KeyCode::Char(symbol) => "/*-+".find(symbol).map(|offset| 0xA + offset),
+                    KeyCode::Enter => Some(0xE),
+                    _ => None,
+                };
+            },
+        };
+
+        let KeyCode::Char(c) = key.code else {
+            return None;
+        };
+        let (row, column) = rows.iter().enumerate().find_map(|(row, keys)| keys.find(c).map(|column| (row, column)))?;
+        return Some(VIP_KEYPAD[row][column]);
+    }
+
+    /// Every key press this layout turns into a keypad key
+    fn keys(&self) -> Vec<KeyEvent> {
+        let (chars, state) = match self {
+            Self::Main => ("1234qwerasdfzxcv", KeyEventState::empty()),
+            Self::Ijkl => ("7890uiopjkl;m,./", KeyEventState::empty()),
+            Self::Numpad => ("0123456789/*-+.", KeyEventState::KEYPAD),
+        };
+        let mut keys: Vec<KeyEvent> = chars.chars().map(|c| KeyEvent { state, ..KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE) }).collect();
+        if *self == Self::Numpad {
+            keys.push(KeyEvent { state, ..KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE) });
+        }
+        return keys;
+    }
+}
+
+/// The COSMAC VIP's keypad, a row at a time
+const VIP_KEYPAD: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// One player's layout, and the keys they are allowed to press
+struct Player {
+    layout: Layout,
+    /// A bit for every keypad key the player can press
+    keys: u16,
+}
+
+/// Every player's layout, merged into the one keypad
+pub struct Keymap {
+    /// The players, checked last added first, so a key two layouts share goes to the more specific one
+    players: Vec<Player>,
+}
+
+impl Keymap {
+    /// Creates a keymap with no layouts, so nothing presses a key
+    pub fn new() -> Self {
+        return Self { players: Vec::new() };
+    }
+
+    /// Adds a player's layout
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `layout` - Where the player's keys are
+    /// * `keys` - The keypad keys the player can press, as hex digits like `1,4`, or every key if `None`
+    pub fn add_player(&mut self, layout: Layout, keys: Option<&str>) -> Result<(), String> {
+        let keys = match keys {
+            Some(keys) => parse_keys(keys)?,
+            None => 0xFFFF,
+        };
+        self.players.push(Player { layout, keys });
+        return Ok(());
+    }
+
+    /// Finds the keypad key a key press is, if it is one that its player can press
+    pub fn button(&self, key: &KeyEvent) -> Option<usize> {
+        let (player, button) = self.players.iter().rev().find_map(|player| player.layout.button(key).map(|button| (player, button)))?;
+        return (player.keys & (1 << button) != 0).then_some(button);
+    }
+
+    /// Finds keys that do something other than what a player would expect
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `hotkeys` - Characters the emulator itself takes at times
+    /// * `taken_by` - What takes them, like `While it is stopped, the debugger takes`
+    /// 
+    /// ## Returns
+    /// 
+    /// A line describing each conflict: keypad keys both players can press, keys one player's layout takes from another's,
+    /// and hotkeys that are also keypad keys
+    pub fn conflicts(&self, hotkeys: &str, taken_by: &str) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (number, player) in self.players.iter().enumerate() {
+            for (other_number, other) in self.players.iter().enumerate().skip(number + 1) {
+                let shared = player.keys & other.keys;
+                if self.players.len() > 1 && shared != 0 {
+                    let keys: Vec<String> = (0..16).filter(|key| shared & (1 << key) != 0).map(|key| format!("{:X}", key)).collect();
+                    conflicts.push(format!("players {} and {} can both press keypad keys {}", number + 1, other_number + 1, keys.join(",")));
+                }
+            }
+
+            for key in player.layout.keys() {
+                let owner = self.players.iter().rposition(|player| player.layout.button(&key).is_some());
+                if let Some(owner) = owner.filter(|&owner| owner != number) {
+                    conflicts.push(format!("{} belongs to player {}, so player {} can't use it", key_name(&key), owner + 1, number + 1));
+                }
+            }
+        }
+
+        let (taken, buttons): (Vec<String>, Vec<String>) = hotkeys.chars()
+            .filter_map(|c| self.button(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)).map(|button| (c.to_string(), format!("{:X}", button))))
+            .unzip();
+        if !taken.is_empty() {
+            conflicts.push(format!("{} {}, which are keypad keys {} the rest of the time", taken_by, taken.join(" "), buttons.join(" ")));
+        }
+        return conflicts;
+    }
+}
+
+/// Names a key press the way the keyboard labels it
+fn key_name(key: &KeyEvent) -> String {
+    let name = match key.code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => String::from("Enter"),
+        code => format!("{:?}", code),
+    };
+    if key.state.contains(KeyEventState::KEYPAD) {
+        return format!("numpad {}", name);
+    }
+    return name;
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self::new();
+        keymap.add_player(Layout::Main, None).expect("Every key is allowed");
+        return keymap;
+    }
+}
+
+/// Reads a list of keypad keys, like `1,4` or `CD`, as a bit for each
+fn parse_keys(keys: &str) -> Result<u16, String> {
+    let mut mask = 0;
+    for c in keys.chars().filter(|&c| c != ',' && !c.is_whitespace()) {
+        let digit = c.to_digit(16).ok_or(format!("{} is not a keypad key, expected hex digits like 1,4", c))?;
+        mask |= 1 << digit;
+    }
+    return Ok(mask);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, state: KeyEventState) -> KeyEvent {
+        return KeyEvent { state, ..KeyEvent::new(code, KeyModifiers::NONE) };
+    }
+
+    #[test]
+    fn two_players_share_the_keypad() {
+        // Pong: player 1 moves with 1 and 4, player 2 with C and D
+        let mut keymap = Keymap::new();
+        keymap.add_player(Layout::Main, Some("1,4")).unwrap();
+        keymap.add_player(Layout::Numpad, Some("CD")).unwrap();
+
+        assert_eq!(keymap.button(&press(KeyCode::Char('1'), KeyEventState::empty())), Some(0x1));
+        assert_eq!(keymap.button(&press(KeyCode::Char('q'), KeyEventState::empty())), Some(0x4));
+        assert_eq!(keymap.button(&press(KeyCode::Char('4'), KeyEventState::empty())), None);
+        assert_eq!(keymap.button(&press(KeyCode::Char('-'), KeyEventState::KEYPAD)), Some(0xC));
+        assert_eq!(keymap.button(&press(KeyCode::Char('+'), KeyEventState::KEYPAD)), Some(0xD));
+
+        // Numpad digits belong to player 2, even though the number row's belong to player 1
+        assert_eq!(keymap.button(&press(KeyCode::Char('1'), KeyEventState::KEYPAD)), None);
+
+        assert!(keymap.add_player(Layout::Ijkl, Some("G")).is_err());
+    }
+
+    #[test]
+    fn ijkl_mirrors_the_main_layout() {
+        let mut keymap = Keymap::new();
+        keymap.add_player(Layout::Ijkl, None).unwrap();
+        let main = Keymap::default();
+        for (left, right) in "1234qwerasdfzxcv".chars().zip("7890uiopjkl;m,./".chars()) {
+            assert_eq!(keymap.button(&press(KeyCode::Char(right), KeyEventState::empty())), main.button(&press(KeyCode::Char(left), KeyEventState::empty())));
+        }
+        assert_eq!(keymap.button(&press(KeyCode::Char('k'), KeyEventState::empty())), Some(0x8));
+    }
+
+    #[test]
+    fn conflicts_are_found() {
+        assert!(Keymap::default().conflicts("", "").is_empty());
+
+        let mut keymap = Keymap::new();
+        keymap.add_player(Layout::Main, Some("1,4,C")).unwrap();
+        keymap.add_player(Layout::Ijkl, Some("C,D")).unwrap();
+        assert_eq!(keymap.conflicts("fn", "The debugger takes"), vec![
+            String::from("players 1 and 2 can both press keypad keys C"),
+        ]);
+
+        let mut keymap = Keymap::default();
+        keymap.add_player(Layout::Main, Some("E")).unwrap();
+        let conflicts = keymap.conflicts("fnc", "The debugger takes");
+        assert!(conflicts.contains(&String::from("q belongs to player 2, so player 1 can't use it")));
+        assert_eq!(conflicts.last(), Some(&String::from("The debugger takes f, which are keypad keys E the rest of the time")));
+    }
+}
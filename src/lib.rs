@@ -0,0 +1,36 @@
+// The emulator core, the assembler and disassembler, and the tools built on them.
+// The chip8, asm and deasm binaries are frontends to this library; anything tied to driving a terminal stays with them.
+// The interpreter, assembler and disassembler live in the chip8-core crate, and are re-exported as the modules they were.
+pub use chip8_core::{chip8, format, instruction, listing, quirks};
+
+pub mod cartridge;
+pub mod clock;
+pub mod pack_data;
+pub mod tracker;
+pub mod img2sprite;
+pub mod text_routine;
+pub mod include;
+pub mod link;
+pub mod octo;
+pub mod cfg;
+pub mod size_report;
+pub mod frame_timing;
+pub mod heatmap;
+pub mod register_usage;
+pub mod cost;
+pub mod input_script;
+pub mod trace;
+pub mod announcer;
+pub mod batch;
+pub mod sandbox;
+pub mod screensaver;
+pub mod session;
+pub mod macros;
+pub mod rewind;
+pub mod dump;
+pub mod profile;
+#[cfg(test)]
+pub mod snapshot;
+
+pub use crate::chip8::{assemble, disassemble, Chip8};
+pub use crate::instruction::Instruction;
@@ -0,0 +1,231 @@
+use crate::chip8::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Object files are JSON, holding the code assembled as if it started at 0x200 along with its symbols:
+//   {"format": "chip8-object", "version": 1, "code": "<hex>", "labels": {"NAME": offset},
+//    "exports": ["NAME"], "relocations": [[offset, "NAME"]]}
+// A relocation is an instruction whose 12-bit address operand is filled in by the linker.
+
+const FORMAT: &str = "chip8-object";
+const VERSION: u64 = 1;
+const START: usize = 0x200;
+
+/// An object and where the layout puts it
+pub struct LinkObject {
+    /// Name used in error messages, usually the object's path
+    pub name: String,
+    pub code: Relocatable,
+    /// Address to place the object at, or `None` to follow the previous object
+    pub addr: Option<usize>,
+}
+
+/// Serializes relocatable code as an object file
+/// 
+/// ## Arguments
+/// 
+/// * `object` - The assembled code
+pub fn write_object(object: &Relocatable) -> String {
+    let code: String = object.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    let labels: serde_json::Map<String, Value> = object.labels.iter().map(|(name, offset)| (name.clone(), json!(offset))).collect();
+    let relocations: Vec<Value> = object.relocations.iter().map(|(offset, name)| json!([offset, name])).collect();
+
+    let value = json!({
+        "format": FORMAT,
+        "version": VERSION,
+        "code": code,
+        "labels": labels,
+        "exports": object.exports,
+        "relocations": relocations,
+    });
+
+    return serde_json::to_string_pretty(&value).unwrap();
+}
+
+/// Reads an object file written by `write_object`
+/// 
+/// ## Arguments
+/// 
+/// * `text` - Contents of the object file
+pub fn read_object(text: &str) -> Result<Relocatable, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| format!("Not an object file: {}", e))?;
+    if value["format"] != FORMAT || value["version"] != VERSION {
+        return Err(String::from("Not a chip8 object file, or from an unsupported version"));
+    }
+
+    let code = value["code"].as_str().ok_or("Object file has no code")?;
+    if code.len() % 2 != 0 {
+        return Err(String::from("Object code has an odd number of hex digits"));
+    }
+    let bytes = (0..code.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&code[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| String::from("Object code is not valid hex"))?;
+
+    let mut labels = Vec::new();
+    for (name, offset) in value["labels"].as_object().ok_or("Object file has no labels")? {
+        let offset = offset.as_u64().ok_or(format!("Label {} has no offset", name))?;
+        labels.push((name.clone(), offset as usize));
+    }
+
+    let mut exports = Vec::new();
+    for name in value["exports"].as_array().ok_or("Object file has no exports")? {
+        exports.push(name.as_str().ok_or("Export names must be strings")?.to_string());
+    }
+
+    let mut relocations = Vec::new();
+    for relocation in value["relocations"].as_array().ok_or("Object file has no relocations")? {
+        let (Some(offset), Some(name)) = (relocation[0].as_u64(), relocation[1].as_str()) else {
+            return Err(String::from("Relocations must be [offset, name] pairs"));
+        };
+        if offset as usize + 1 >= bytes.len() {
+            return Err(format!("Relocation for {} is outside the code", name));
+        }
+        relocations.push((offset as usize, name.to_string()));
+    }
+
+    return Ok(Relocatable { bytes, labels, exports, relocations });
+}
+
+/// Reads a layout file, which lists the objects to link in order, each optionally followed by its address
+/// 
+/// Objects without an address are placed right after the previous one. `#` starts a comment.
+/// Relative object paths are taken from the layout file's directory.
+/// 
+/// ## Arguments
+/// 
+/// * `text` - Contents of the layout file
+/// * `dir` - Directory containing the layout file
+/// 
+/// ## Returns
+/// 
+/// The path and optional address of each object
+pub fn parse_layout(text: &str, dir: &Path) -> Result<Vec<(String, Option<usize>)>, String> {
+    let mut layout = Vec::new();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+        let Some(path) = tokens.next() else {
+            continue;
+        };
+
+        let addr = match tokens.next() {
+            Some(addr) => {
+                let parsed = match addr.strip_prefix("0x").or(addr.strip_prefix("0X")) {
+                    Some(hex) => usize::from_str_radix(hex, 16),
+                    None => addr.parse(),
+                };
+                Some(parsed.map_err(|_| format!("line {}: invalid address {}", line_index + 1, addr))?)
+            },
+            None => None,
+        };
+
+        layout.push((dir.join(path).to_string_lossy().into_owned(), addr));
+    }
+
+    return Ok(layout);
+}
+
+/// Places objects in memory, resolves their symbols, and produces the final ROM
+/// 
+/// ## Arguments
+/// 
+/// * `objects` - The objects to link, in layout order
+/// * `limit` - The most bytes the platform can load
+pub fn link(objects: &[LinkObject], limit: usize) -> Result<Vec<u8>, String> {
+    // Place every object
+    let mut bases = Vec::new();
+    let mut cursor = START;
+    for object in objects.iter() {
+        let base = object.addr.unwrap_or(cursor);
+        let end = base + object.code.bytes.len();
+        if base < START || end > START + limit {
+            return Err(format!("{} does not fit at 0x{:03X}", object.name, base));
+        }
+
+        for (other, other_base) in objects.iter().zip(bases.iter()) {
+            let other_end = other_base + other.code.bytes.len();
+            if base < other_end && *other_base < end {
+                return Err(format!("{} overlaps {}", object.name, other.name));
+            }
+        }
+
+        bases.push(base);
+        cursor = end;
+    }
+
+    // Collect exported symbols
+    let mut globals: HashMap<&str, usize> = HashMap::new();
+    for (object, base) in objects.iter().zip(bases.iter()) {
+        for name in object.code.exports.iter() {
+            let Some((_, offset)) = object.code.labels.iter().find(|(label, _)| label == name) else {
+                return Err(format!("{} exports {}, which it does not define", object.name, name));
+            };
+            if globals.insert(name, base + offset).is_some() {
+                return Err(format!("{} is exported by more than one object", name));
+            }
+        }
+    }
+
+    // Copy the code into place and fill in addresses
+    let end = objects.iter().zip(bases.iter()).map(|(object, base)| base + object.code.bytes.len()).max().unwrap_or(START);
+    let mut rom = vec![0; end - START];
+    for (object, base) in objects.iter().zip(bases.iter()) {
+        let start = base - START;
+        rom[start..start + object.code.bytes.len()].copy_from_slice(&object.code.bytes);
+
+        for (offset, name) in object.code.relocations.iter() {
+            let local = object.code.labels.iter().find(|(label, _)| label == name).map(|(_, offset)| base + offset);
+            let Some(addr) = local.or(globals.get(name.as_str()).copied()) else {
+                return Err(format!("{} refers to undefined symbol {}", object.name, name));
+            };
+            if addr > 0xFFF {
+                return Err(format!("{} refers to {} at 0x{:X}, beyond the reach of a 12-bit address", object.name, name, addr));
+            }
+
+            let at = start + offset;
+            rom[at] = (rom[at] & 0xF0) | (addr >> 8) as u8;
+            rom[at + 1] = (addr & 0xFF) as u8;
+        }
+    }
+
+    return Ok(rom);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(name: &str, source: &str, addr: Option<usize>) -> LinkObject {
+        // Round trip through the file format on the way
+        let code = read_object(&write_object(&assemble_relocatable(source).unwrap())).unwrap();
+        return LinkObject { name: name.to_string(), code, addr };
+    }
+
+    #[test]
+    fn links_imports_to_exports() {
+        let main = object("main", "start: call draw\njp start", None);
+        let draw = object("draw", ".export draw\ndraw: ld i sprite\nret\nsprite: cls", Some(0x300));
+
+        let rom = link(&[main, draw], MAX_ROM_SIZE).unwrap();
+        assert_eq!(&rom[..4], &[0x23, 0x00, 0x12, 0x00]);
+        assert_eq!(&rom[0x100..], &[0xA3, 0x04, 0x00, 0xEE, 0x00, 0xE0]);
+    }
+
+    #[test]
+    fn rejects_undefined_and_unexported_symbols() {
+        let main = object("main", "jp draw", None);
+        let draw = object("draw", "draw: ret", None);
+        assert_eq!(link(&[main, draw], MAX_ROM_SIZE).unwrap_err(), "main refers to undefined symbol DRAW");
+    }
+
+    #[test]
+    fn rejects_overlapping_objects() {
+        let a = object("a", "cls\ncls", Some(0x200));
+        let b = object("b", "cls", Some(0x201));
+        assert_eq!(link(&[a, b], MAX_ROM_SIZE).unwrap_err(), "b overlaps a");
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt::Display;
+
+// What the terminal UI says to the player, from the instructions shown before the ROM starts to the status line under the screen,
+// is looked up here by a `Text` rather than written out where it is printed, so it can be said in another language.
+// The language comes from --lang, or else the first of LC_ALL, LC_MESSAGES and LANG that is set, like es_ES.UTF-8.
+// Texts are templates, and each {} in one is filled in order by `Locale::format`, so a translation keeps the same number of them.
+// A language's table gives None for a text it hasn't translated yet, and English is shown instead.
+
+/// Languages the terminal UI can be shown in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    English,
+    Spanish,
+}
+
+impl Language {
+    /// Names `--lang` accepts
+    pub const NAMES: [&'static str; 2] = ["en", "es"];
+
+    /// Looks a language up from a locale name like `es`, `es_ES` or `es_ES.UTF-8`, in English if it isn't one there are texts for
+    pub fn from_locale(locale: &str) -> Self {
+        let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+        return match language {
+            "es" => Self::Spanish,
+            _ => Self::English,
+        };
+    }
+
+    /// Finds the language the environment asks for, the way the C library does
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter().filter_map(|name| std::env::var(name).ok()).find(|value| !value.is_empty());
+        return Self::from_locale(locale.as_deref().unwrap_or_default());
+    }
+}
+
+/// Everything the terminal UI says
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Text {
+    Running,
+    Keybindings,
+    Player2Ijkl,
+    Player2Numpad,
+    QuitAndWarnings,
+    SaveSlots,
+    Macros,
+    DebugStart,
+    Rewind,
+    BrailleWidth,
+    PressAnyKey,
+    TerminalTooSmall,
+    TerminalTooSmallWithKeypad,
+    Slot,
+    SlotHelp,
+    OneWarning,
+    Warnings,
+    TabToShow,
+    TabToHide,
+    RecordingMacro,
+    BoundMacro,
+    PlayingMacro,
+    NoMacro,
+    Rewound,
+    RewoundAll,
+    Paused,
+    Saved,
+    SaveFailed,
+    Loaded,
+    LoadFailed,
+    KeyHistory,
+    Screensaver,
+    Stopped,
+}
+
+/// The language the terminal UI is shown in
+#[derive(Debug, Clone, Copy)]
+pub struct Locale {
+    language: Language,
+}
+
+impl Locale {
+    pub fn new(language: Language) -> Self {
+        return Self { language };
+    }
+
+    /// Looks a text up in the locale's language, or in English if it hasn't been translated
+    pub fn text(&self, text: Text) -> &'static str {
+        let translated = match self.language {
+            Language::English => None,
+            Language::Spanish => spanish(text),
+        };
+        return translated.unwrap_or(english(text));
+    }
+
+    /// Looks a text up and fills its {}s with the arguments, in order
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `text` - The text to show
+    /// * `args` - What goes in each {}
+    pub fn format(&self, text: Text, args: &[&dyn Display]) -> String {
+        let mut pieces = self.text(text).split("{}");
+        let mut formatted = String::from(pieces.next().unwrap_or_default());
+        for (piece, arg) in pieces.zip(args.iter().map(|arg| arg.to_string()).chain(std::iter::repeat(String::new()))) {
+            formatted.push_str(&arg);
+            formatted.push_str(piece);
+        }
+        return formatted;
+    }
+}
+
+fn english(text: Text) -> &'static str {
+    return match text {
+        Text::Running => "Running ROM {} ({} bytes) at {} FPS",
+        Text::Keybindings => "Keybindings:",
+        Text::Player2Ijkl => "Player 2 uses 7 8 9 0, u i o p, j k l ; and m , . / the same way",
+        Text::Player2Numpad => "Player 2 uses the numpad's digits, and / * - + Enter . for A-F",
+        Text::QuitAndWarnings => "Press Esc to quit, or Tab to show and hide warnings",
+        Text::SaveSlots => "Press Shift+1-4 to save the state to a slot and Alt+1-4 to load one, or F5 and F9 for the last slot used",
+        Text::Macros => "Press F6 to record a macro and F1-F4 to bind it, then F1-F4 to play it",
+        Text::DebugStart => "The ROM starts stopped: press n to run an instruction, f to run a frame, c to carry on, w to watch a value, 1-6 to flip a quirk, and F8 to stop again",
+        Text::Rewind => "Press Backspace to step back through the last {} seconds",
+        Text::BrailleWidth => "This terminal draws Braille characters at the wrong width, so the screen may look garbled",
+        Text::PressAnyKey => "Press any key to start",
+        Text::TerminalTooSmall => "Terminal height is too small, which might lead to rendering issues. Please resize the terminal to have at least {} rows.",
+        Text::TerminalTooSmallWithKeypad => "Terminal height is too small, which might lead to rendering issues. Please resize the terminal to have at least {} rows, or consider running with --no-keypad flag on.",
+        Text::Slot => "Slot {}",
+        Text::SlotHelp => "Shift+1-4 to save, Alt+1-4 to load",
+        Text::OneWarning => "{} warning",
+        Text::Warnings => "{} warnings",
+        Text::TabToShow => "Tab to show",
+        Text::TabToHide => "Tab to hide",
+        Text::RecordingMacro => "Recording a macro, press F1-F4 to bind it",
+        Text::BoundMacro => "Bound {} presses to F{}",
+        Text::PlayingMacro => "Playing the macro on F{}",
+        Text::NoMacro => "No macro on F{}, press F6 to record one",
+        Text::Rewound => "Rewound {} frames, Backspace for more or any other key to carry on",
+        Text::RewoundAll => "Rewound {} frames, which is as far back as there is",
+        Text::Paused => "Paused until the terminal has focus again",
+        Text::Saved => "Saved the state to {}",
+        Text::SaveFailed => "Failed to save the state: {}",
+        Text::Loaded => "Loaded the state from {}",
+        Text::LoadFailed => "Failed to load the state from slot {}: {}",
+        Text::KeyHistory => "Keys",
+        Text::Screensaver => "{} ({} of {}), press any key to stop",
+        Text::Stopped => "The ROM stopped at {}, press any key to quit",
+    };
+}
+
+fn spanish(text: Text) -> Option<&'static str> {
+    return Some(match text {
+        Text::Running => "Ejecutando la ROM {} ({} bytes) a {} FPS",
+        Text::Keybindings => "Teclas:",
+        Text::Player2Ijkl => "El jugador 2 usa 7 8 9 0, u i o p, j k l ; y m , . / de la misma forma",
+        Text::Player2Numpad => "El jugador 2 usa los dígitos del teclado numérico, y / * - + Intro . para A-F",
+        Text::QuitAndWarnings => "Pulsa Esc para salir, o Tab para mostrar y ocultar los avisos",
+        Text::SaveSlots => "Pulsa Mayús+1-4 para guardar el estado en una ranura y Alt+1-4 para cargarlo, o F5 y F9 para la última ranura usada",
+        Text::Macros => "Pulsa F6 para grabar una macro y F1-F4 para asignarla, y luego F1-F4 para reproducirla",
+        Text::DebugStart => "La ROM empieza detenida: pulsa n para ejecutar una instrucción, f para ejecutar un fotograma, c para continuar, w para vigilar un valor, 1-6 para cambiar una peculiaridad, y F8 para detenerla de nuevo",
+        Text::Rewind => "Pulsa Retroceso para volver atrás hasta {} segundos",
+        Text::BrailleWidth => "Este terminal dibuja los caracteres Braille con un ancho incorrecto, así que la pantalla puede verse mal",
+        Text::PressAnyKey => "Pulsa cualquier tecla para empezar",
+        Text::TerminalTooSmall => "El terminal es demasiado bajo y puede haber problemas al dibujar. Cambia su tamaño a al menos {} filas.",
+        Text::TerminalTooSmallWithKeypad => "El terminal es demasiado bajo y puede haber problemas al dibujar. Cambia su tamaño a al menos {} filas, o prueba a ejecutar con --no-keypad.",
+        Text::Slot => "Ranura {}",
+        Text::SlotHelp => "Mayús+1-4 para guardar, Alt+1-4 para cargar",
+        Text::OneWarning => "{} aviso",
+        Text::Warnings => "{} avisos",
+        Text::TabToShow => "Tab para mostrar",
+        Text::TabToHide => "Tab para ocultar",
+        Text::RecordingMacro => "Grabando una macro, pulsa F1-F4 para asignarla",
+        Text::BoundMacro => "{} pulsaciones asignadas a F{}",
+        Text::PlayingMacro => "Reproduciendo la macro de F{}",
+        Text::NoMacro => "No hay macro en F{}, pulsa F6 para grabar una",
+        Text::Rewound => "Retrocedidos {} fotogramas, Retroceso para más o cualquier otra tecla para continuar",
+        Text::RewoundAll => "Retrocedidos {} fotogramas, que es todo lo que hay",
+        Text::Paused => "En pausa hasta que el terminal vuelva a tener el foco",
+        Text::Saved => "Estado guardado en {}",
+        Text::SaveFailed => "No se pudo guardar el estado: {}",
+        Text::Loaded => "Estado cargado de {}",
+        Text::LoadFailed => "No se pudo cargar el estado de la ranura {}: {}",
+        Text::KeyHistory => "Teclas",
+        Text::Screensaver => "{} ({} de {}), pulsa cualquier tecla para parar",
+        Text::Stopped => "La ROM se detuvo en {}, pulsa cualquier tecla para salir",
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_come_from_locale_names() {
+        assert_eq!(Language::from_locale("es_ES.UTF-8"), Language::Spanish);
+        assert_eq!(Language::from_locale("es"), Language::Spanish);
+        assert_eq!(Language::from_locale("en_GB.UTF-8"), Language::English);
+        assert_eq!(Language::from_locale("C"), Language::English);
+        assert_eq!(Language::from_locale(""), Language::English);
+    }
+
+    #[test]
+    fn texts_are_filled_in_order() {
+        let english = Locale::new(Language::English);
+        assert_eq!(english.format(Text::Running, &[&"PONG", &246, &60]), "Running ROM PONG (246 bytes) at 60 FPS");
+        assert_eq!(english.format(Text::Slot, &[&2]), "Slot 2");
+
+        let spanish = Locale::new(Language::Spanish);
+        assert_eq!(spanish.format(Text::BoundMacro, &[&5, &1]), "5 pulsaciones asignadas a F1");
+        assert_eq!(spanish.text(Text::Keybindings), "Teclas:");
+    }
+}
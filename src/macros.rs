@@ -0,0 +1,141 @@
+use crate::input_script::InputScript;
+
+// Macros are short runs of key presses recorded during a session and played back on a hotkey, like a combo that has to land on exact frames.
+// A macro is kept as an input script counted from its first press, and playback starts on the frame after the hotkey,
+// so every press lands the same number of frames and instructions after the last one as when it was recorded.
+
+/// Number of macros that can be bound at once
+pub const MACRO_SLOTS: usize = 4;
+
+/// Records macros, holds the ones bound to hotkeys and plays them back
+#[derive(Debug, Default)]
+pub struct MacroManager {
+    macros: [Option<InputScript>; MACRO_SLOTS],
+    /// Presses since recording started, by the frame they landed on
+    recording: Option<InputScript>,
+    /// Macros being played, and the frame each started on
+    playing: Vec<(usize, u32)>,
+}
+
+impl MacroManager {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        return self.recording.is_some();
+    }
+
+    /// Starts recording a new macro, throwing away any unfinished one
+    pub fn start_recording(&mut self) {
+        self.recording = Some(InputScript::new());
+    }
+
+    /// Adds a key press to the macro being recorded, if there is one
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `frame` - The frame it happened on
+    /// * `tick` - The instruction within the frame it happened before
+    /// * `key` - The key, from 0x0 to 0xF
+    pub fn record(&mut self, frame: u32, tick: u64, key: usize) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.record(frame, tick, key);
+        }
+    }
+
+    /// Stops recording and binds what was recorded to a slot, replacing whatever was there
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `slot` - The slot, from 0
+    /// 
+    /// ## Returns
+    /// 
+    /// The number of presses bound, or why nothing was
+    pub fn finish_recording(&mut self, slot: usize) -> Result<usize, String> {
+        let Some(recording) = self.recording.take() else {
+            return Err(String::from("No macro is being recorded"));
+        };
+        let Some((first, _, _)) = recording.presses().next() else {
+            return Err(String::from("No keys were pressed while recording"));
+        };
+
+        let mut shifted = InputScript::new();
+        for (frame, tick, key) in recording.presses() {
+            shifted.record(frame - first, tick, key);
+        }
+        let count = shifted.presses().count();
+        self.macros[slot] = Some(shifted);
+        return Ok(count);
+    }
+
+    /// Starts playing the macro in a slot on the next frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `slot` - The slot, from 0
+    /// * `frame` - The current frame
+    /// 
+    /// ## Returns
+    /// 
+    /// Whether there was a macro to play
+    pub fn play(&mut self, slot: usize, frame: u32) -> bool {
+        if self.macros[slot].is_none() {
+            return false;
+        }
+        self.playing.push((slot, frame + 1));
+        return true;
+    }
+
+    /// Takes the keys the playing macros press at one point in a frame, forgetting macros that have finished
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `frame` - The current frame
+    /// * `tick` - The instruction about to run within the frame
+    pub fn keys_at(&mut self, frame: u32, tick: u64) -> Vec<usize> {
+        let mut keys = Vec::new();
+        let macros = &self.macros;
+        self.playing.retain(|&(slot, start)| {
+            let Some(script) = macros[slot].as_ref() else {
+                return false;
+            };
+            if frame < start {
+                return true;
+            }
+            keys.extend(script.keys_at(frame - start, tick));
+            return script.presses().last().is_some_and(|(last_frame, last_tick, _)| (frame - start, tick) < (last_frame, last_tick));
+        });
+        return keys;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macros_play_back_frame_aligned() {
+        let mut macros = MacroManager::new();
+        assert_eq!(macros.finish_recording(0), Err(String::from("No macro is being recorded")));
+
+        macros.start_recording();
+        macros.record(100, 3, 0x5);
+        macros.record(102, 2, 0x6);
+        macros.record(102, 2, 0x4);
+        assert_eq!(macros.finish_recording(1), Ok(3));
+        assert!(!macros.is_recording());
+        assert!(!macros.play(0, 10));
+
+        // Triggered on frame 10, the presses land on frames 11 and 13 at the same ticks as recorded
+        assert!(macros.play(1, 10));
+        let pressed: Vec<(u32, u64, Vec<usize>)> = (10..16)
+            .flat_map(|frame| (0..4).map(move |tick| (frame, tick)))
+            .map(|(frame, tick)| (frame, tick, macros.keys_at(frame, tick)))
+            .filter(|(_, _, keys)| !keys.is_empty())
+            .collect();
+        assert_eq!(pressed, vec![(11, 3, vec![0x5]), (13, 2, vec![0x4, 0x6])]);
+        assert!(macros.playing.is_empty());
+    }
+}
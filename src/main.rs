@@ -5,14 +5,66 @@
 *   - https://github.com/cmleon51/cli-chip8-emulator.rs for the keyboard polling fix
 */
 
-use crate::chip8::*;
-pub mod chip8;
+use chip8::chip8::*;
+use chip8::cartridge::*;
+use chip8::clock::*;
+use chip8::pack_data::*;
+use chip8::tracker::*;
+use chip8::img2sprite::*;
+use chip8::text_routine::*;
+use chip8::frame_timing::*;
+use chip8::heatmap::*;
+use chip8::register_usage::*;
+use chip8::cost::*;
+use chip8::input_script::*;
+use chip8::format::*;
+use chip8::trace::*;
+use chip8::announcer::*;
+use chip8::batch::*;
+use chip8::screensaver::*;
+use chip8::quirks::*;
+use chip8::session::*;
+use chip8::macros::*;
+use chip8::rewind::*;
+use chip8::dump::*;
+use chip8::profile::*;
+use chip8::listing::*;
+use crate::frame_pipe::*;
+use crate::cast::*;
+use crate::theme::*;
+use crate::termcaps::*;
+use crate::debugger::*;
+use crate::keymap::*;
+use crate::locale::*;
+use crate::doctor::*;
+use crate::key_history::*;
+use crate::border::*;
+use crate::compositor::*;
+use crate::held_keys::*;
+pub mod frame_pipe;
+pub mod cast;
+pub mod theme;
+pub mod terminal_query;
+pub mod termcaps;
+pub mod debugger;
+pub mod keymap;
+pub mod locale;
+pub mod doctor;
+pub mod key_history;
+pub mod border;
+pub mod compositor;
+pub mod held_keys;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(unix)]
+pub mod daemon;
 
-use crossterm::event::{Event, KeyCode, KeyEvent};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind};
 use crossterm::{event, terminal};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
-use std::thread;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 // Here I use the Braille character set to represent pixels.
 // A Braille character can be mapped to binary, with the bottom right dot being the least significant bit. In this way, I can place each character at the index that it represents, which can easily be indexed into based on the screen data.
@@ -52,141 +104,2050 @@ const BRAILLE_MAP: [char; 256] = [
 ];
 
 /// A struct to clean up the terminal when the program exits/panics
-struct CleanUp;
+struct CleanUp {
+    /// Whether the cursor was hidden, which is skipped when stdout carries piped frames
+    restore_cursor: bool,
+    /// Whether the kitty keyboard protocol was turned on
+    pop_keyboard_flags: bool,
+    /// Whether the terminal was asked to report focus changes
+    disable_focus_events: bool,
+    /// Whether the terminal was asked to report mouse clicks
+    disable_mouse_reports: bool,
+}
+
+/// Implement Drop trait for CleanUp, which will be called when the struct goes out of scope
+impl Drop for CleanUp {
+    fn drop(&mut self) {
+        terminal::disable_raw_mode().expect("Could not disable raw mode");
+
+        if self.pop_keyboard_flags {
+            print!("\x1b[<u");
+        }
+
+        if self.disable_focus_events {
+            print!("\x1b[?1004l");
+        }
+
+        if self.disable_mouse_reports {
+            print!("\x1b[?1006l\x1b[?1000l");
+        }
+
+        // Enable cursor
+        if self.restore_cursor {
+            print!("\x1b[?25h");
+        }
+
+        if std::thread::panicking() {
+            println!("Panic! at the disco");
+        }
+    }
+}
+
+/// Struct to hold the arguments passed to the program
+#[derive(Parser, Debug)]
+#[command(about = "A Chip8 emulator written in Rust", subcommand_negates_reqs = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Number of frames to render per second, from 1 to 1000. The timers count down at 60 Hz whatever it is
+    #[arg(short='f', long="frames", default_value="100", value_parser=clap::value_parser!(u64).range(1..=1000))]
+    framerate: u64,
+
+    /// The ROM to load
+    #[arg(short, long, required_unless_present_any = ["diagnose_terminal", "save_profile"])]
+    rom: Option<String>,
+
+    /// Number of instructions to execute per frame, 8 unless the platform runs at another speed
+    #[arg(short='c', long="cycles")]
+    tick_per_frame: Option<u64>,
+
+    /// Run up to this many instructions more or fewer each frame, as unevenly as a real interpreter. Drawn from --seed, so runs still repeat
+    #[arg(long="cycle-jitter")]
+    cycle_jitter: Option<u64>,
+
+    /// Disable keypad rendering
+    #[arg(long="no-keypad", action)]
+    no_keypad: bool,
+
+    /// Show the last keys the ROM was given under the keypad, with how many frames ago each went down
+    #[arg(long="key-history", action)]
+    key_history: bool,
+
+    /// Give a second player the keypad on another part of the keyboard: ijkl (7890/uiop/jkl;/m,./) or numpad (needs the kitty keyboard protocol)
+    #[arg(long="player2", value_parser=PLAYER2_LAYOUTS)]
+    player2: Option<String>,
+
+    /// Milliseconds a key stays held after the terminal last sent it, on terminals that can't say when keys are let go
+    #[arg(long="key-hold", default_value_t=DEFAULT_HOLD_MS)]
+    key_hold: u64,
+
+    /// Language of the instructions and status line: en or es [default: from LANG]
+    #[arg(long="lang", value_parser=Language::NAMES)]
+    lang: Option<String>,
+
+    /// Keypad keys the first player can press, as hex digits like 1,4 [default: all of them]
+    #[arg(long="player1-keys")]
+    player1_keys: Option<String>,
+
+    /// Keypad keys the second player can press, as hex digits like C,D [default: all of them]
+    #[arg(long="player2-keys")]
+    player2_keys: Option<String>,
+
+    /// Keep save states in a folder for each ROM under this directory, rather than next to the ROM
+    #[arg(long="state-dir")]
+    state_dir: Option<String>,
+
+    /// Write save states and the state a ROM is quit in as compact binary, or as JSON to read and edit by hand
+    #[arg(long="state-format", default_value="binary", value_parser=["binary", "json"])]
+    state_format: String,
+
+    /// Seconds of recent frames Backspace can step back through, or 0 to turn rewinding off
+    #[arg(long="rewind-seconds", default_value="10")]
+    rewind_seconds: u64,
+
+    /// Show the frame after the one emulated, run with the keys held now, to answer key presses a frame sooner
+    #[arg(long="run-ahead", action)]
+    run_ahead: bool,
+
+    /// Keep running when the terminal loses focus, rather than pausing until it gets it back
+    #[arg(long="no-focus-pause", action)]
+    no_focus_pause: bool,
+
+    /// Enable SMPTE color mode
+    #[arg(long="smpte", action)]
+    smpte: bool,
+
+    /// Color the screen with a terminal theme: nord, solarized or gruvbox (optionally with -dark or -light), or auto to match the terminal
+    #[arg(long="theme")]
+    theme: Option<String>,
+
+    /// Stream raw frames to a file or FIFO, or to stdout with "-" (which disables rendering)
+    #[arg(long="frame-pipe")]
+    frame_pipe: Option<String>,
+
+    /// Levels of nested subroutines the stack has room for [default: 16, or 12 with --vip-stack]
+    #[arg(long="stack-depth", global=true)]
+    stack_depth: Option<usize>,
+
+    /// Keep the stack in memory at 0xEA0 like the COSMAC VIP, for ROMs that look at it
+    #[arg(long="vip-stack", action, global=true)]
+    vip_stack: bool,
+
+    /// Fill 0x000-0x1FF with an interpreter image, like a dump of the COSMAC VIP's, moving the font to 0xF00
+    #[arg(long="interpreter-image", global=true)]
+    interpreter_image: Option<String>,
+
+    /// Run this emulator's own nonstandard instructions, like FRAME and PASS
+    #[arg(long="enable-custom-ops", action, global=true)]
+    enable_custom_ops: bool,
+
+    /// Run SUPER-CHIP 1.1 ROMs, with its 128x64 screen, scrolling, big font and RPL flags
+    #[arg(long="schip", action, global=true)]
+    schip: bool,
+
+    /// Start from the quirks, screen, stack and speed of a family of interpreters; flags given on their own override it
+    #[arg(long="platform", value_parser=["vip", "schip", "xochip", "modern"], global=true)]
+    platform: Option<String>,
+
+    /// Pick how an instruction interpreters disagree on behaves, as name=value: shift=vx|vy, memory=increment|fixed, jump=v0|vx, sprites=wrap|clip, vf-reset=off|on or key-wait=release|press. Can be repeated
+    #[arg(long="quirk", global=true)]
+    quirk: Vec<String>,
+
+    /// Warn about instructions that behave differently between interpreters, like shifts and LD [I]
+    #[arg(long="pedantic", action)]
+    pedantic: bool,
+
+    /// Stop at the first unknown opcode, I past the end of memory, read or write outside the ROM's memory, odd PC or stack misuse, and print the machine's state
+    #[arg(long="strict", action, global=true)]
+    strict: bool,
+
+    /// What to do with opcodes that aren't instructions: ignore them, halt at them, or trap to the debugger (trap, or trap-to-debugger)
+    #[arg(long="unknown-opcodes", default_value="ignore", value_parser=OpcodePolicy::from_name, global=true)]
+    unknown_opcodes: OpcodePolicy,
+
+    /// Remember this many of the last instructions run, for --strict's report, run's --dump-history and the debugger
+    #[arg(long="pc-history", default_value_t=PC_HISTORY_SIZE, global=true)]
+    pc_history: usize,
+
+    /// Start stopped, with the registers, stack and next instruction shown beside the screen, to step through the ROM
+    #[arg(long="debug", action)]
+    debug: bool,
+
+    /// Stop in the debugger when an instruction changes a register or byte of memory, like V3 or "mem 0x300". Can be repeated
+    #[arg(long="watch")]
+    watch: Vec<String>,
+
+    /// Stop in the debugger before running the instruction at a label from asm's listing beside the ROM, or an address like 0x20A. Can be repeated
+    #[arg(long="break")]
+    break_at: Vec<String>,
+
+    /// Warn when the ROM reads or runs memory that it never wrote and that wasn't loaded
+    #[arg(long="poison-memory", action)]
+    poison_memory: bool,
+
+    /// Seed for the random numbers RND draws from, so a session can be repeated
+    #[arg(long="seed", global=true)]
+    seed: Option<u64>,
+
+    /// Write every key press to an input script on exit
+    #[arg(long="record-input")]
+    record_input: Option<String>,
+
+    /// Play the key presses from an input script back, on top of any typed ones
+    #[arg(long="replay-input")]
+    replay_input: Option<String>,
+
+    /// Run the ROM twice without a screen for this many frames, with the same seed (0 unless --seed says otherwise) and input script, and fail if the runs end differently
+    #[arg(long="determinism-check")]
+    determinism_check: Option<u32>,
+
+    /// Run the ROM without a screen against a trace of the registers before every instruction, and report where they first differ
+    #[arg(long="compare-trace")]
+    compare_trace: Option<String>,
+
+    /// Announce the values an announcement script names whenever they change, so a game can be played without seeing it
+    #[arg(long="announce")]
+    announce: Option<String>,
+
+    /// Where announcements go: stdout (which disables rendering), speech (through spd-say), or a file to append to
+    #[arg(long="announce-to", default_value="stdout")]
+    announce_to: String,
+
+    /// Print how long frames took to emulate, render and sleep on exit
+    #[arg(long="frame-stats", action)]
+    frame_stats: bool,
+
+    /// Skip drawing frames that are already late, to keep the ROM at full speed on a terminal that can't keep up
+    #[arg(long="frame-skip", action)]
+    frame_skip: bool,
+
+    /// Show roughly how long each frame would have taken the COSMAC VIP and which instructions took the time, with a table on exit
+    #[arg(long="vip-cost", action)]
+    vip_cost: bool,
+
+    /// Count memory reads, writes and instruction fetches, and export them on exit as a PNG heatmap or CSV (by extension)
+    #[arg(long="heatmap")]
+    heatmap: Option<String>,
+
+    /// Count which V registers the instructions that ran used, and print them on exit
+    #[arg(long="register-report", action)]
+    register_report: bool,
+
+    /// Print what the terminal supports and exit
+    #[arg(long="diagnose-terminal", action)]
+    diagnose_terminal: bool,
+
+    /// Take the keymap, theme and quirks from a profile file, under the flags given on their own and over the ROM's own profile
+    #[arg(long="profile-file", global=true)]
+    profile_file: Option<String>,
+
+    /// Save the keymap, theme and quirks the other flags and profiles add up to as a profile file, and exit
+    #[arg(long="save-profile")]
+    save_profile: Option<String>,
+
+    /// Record the terminal output as an asciinema cast
+    #[arg(long="record-cast")]
+    record_cast: Option<String>,
+
+    /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9184
+    #[cfg(feature = "metrics")]
+    #[arg(long="metrics")]
+    metrics: Option<String>,
+
+    /// Run headless, listening on this socket for a terminal to attach
+    #[cfg(unix)]
+    #[arg(long="daemon")]
+    daemon: Option<String>,
+}
+
+/// Subcommands that replace running a ROM
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Start the last ROM played again the way it was started, carrying on from where it was quit
+    Resume {
+        /// Start from power on rather than from the state the ROM was quit in
+        #[arg(long="fresh", action)]
+        fresh: bool,
+    },
+
+    /// Run a ROM for a number of instructions without the terminal UI and write out where it ended up, for CI. Flags for the machine, like --quirk and --seed, apply as usual
+    Run {
+        /// The ROM to load
+        #[arg(short, long)]
+        rom: String,
+
+        /// Run without the terminal UI, which is the only way `run` runs
+        #[arg(long="headless", action, required=true)]
+        headless: bool,
+
+        /// Number of instructions to run, stopping sooner if the ROM exits or a test ROM passes or fails
+        #[arg(long="cycles")]
+        cycles: u64,
+
+        /// Instructions run for every time the timers count down [default: 8, or the platform's speed]
+        #[arg(long="cycles-per-frame", value_parser=clap::value_parser!(u64).range(1..))]
+        cycles_per_frame: Option<u64>,
+
+        /// Write the screen it ends on to this file, a line of # and . for every row
+        #[arg(long="dump-screen")]
+        dump_screen: Option<String>,
+
+        /// Write the registers, timers and stack it ends with to this file, a line each
+        #[arg(long="dump-registers")]
+        dump_registers: Option<String>,
+
+        /// Write the last instructions it ran to this file, a line each, oldest first
+        #[arg(long="dump-history")]
+        dump_history: Option<String>,
+    },
+
+    /// Check the terminal for the problems that most often break the emulator, and print a report to paste into a bug. Flags like --player2 and --debug are checked for clashing keys
+    Doctor {
+        /// Also write the report to this file
+        #[arg(long="output")]
+        output: Option<String>,
+    },
+
+    /// List the quirks, what they change, what each platform picks and what Octo cartridges ask for
+    Quirks {
+        /// ROMs to show the quirks they ask for; only Octo cartridges say
+        roms: Vec<String>,
+
+        /// Print as text, or as JSON for other tools
+        #[arg(long="format", default_value="text", value_parser=["text", "json"])]
+        format: String,
+    },
+
+    /// Attach this terminal to an emulator started with --daemon; Esc detaches without stopping it
+    #[cfg(unix)]
+    Attach {
+        /// The socket the daemon is listening on
+        socket: String,
+    },
+
+    /// Convert a PNG or text tile map into a byte table and an assembler include file
+    PackData {
+        /// The tile map; PNG images are read one pixel per tile, anything else as rows of numbers
+        input: String,
+
+        /// Where to write the assembler source; the table itself is written alongside with a .bin extension
+        output: String,
+
+        /// Label for the table, the input's file name by default
+        #[arg(long="name")]
+        name: Option<String>,
+
+        /// Compress the table with RLE and include a routine that unpacks it
+        #[arg(long="rle", action)]
+        rle: bool,
+
+        /// Where the unpack routine writes the table
+        #[arg(long="unpack-to", default_value="0xE00", value_parser=parse_address)]
+        unpack_to: u16,
+    },
+
+    /// Convert a tracker-style song into XO-CHIP audio data and a routine that plays it
+    Tracker {
+        /// The song, one note per line
+        input: String,
+
+        /// Where to write the assembler source
+        output: String,
+
+        /// Label prefix for the song, the input's file name by default
+        #[arg(long="name")]
+        name: Option<String>,
+    },
+
+    /// Convert a PNG image into sprites and a routine that draws them
+    Img2sprite {
+        /// The image to convert
+        input: String,
+
+        /// Where to write the assembler source, stdout if left out
+        output: Option<String>,
+
+        /// Label prefix for the sprites, the input's file name by default
+        #[arg(long="name")]
+        name: Option<String>,
+
+        /// Brightness (0-255) at which a pixel is lit
+        #[arg(long="threshold", default_value="128")]
+        threshold: u8,
+
+        /// Dither the image instead of thresholding each pixel on its own
+        #[arg(long="dither", action)]
+        dither: bool,
+    },
+
+    /// Convert another emulator's instruction log into a trace for --compare-trace
+    ImportTrace {
+        /// The log, one instruction per line naming the PC, I and V0-VF
+        input: String,
+
+        /// Where to write the trace
+        output: String,
+
+        /// How to write the trace
+        #[arg(long="format", default_value="text", value_parser=["text", "json", "binary"])]
+        format: String,
+    },
+
+    /// Check that a save state decompresses and would load, and print its format, sizes and where it stopped
+    StateInfo {
+        /// The save state
+        path: String,
+    },
+
+    /// Play every ROM in a directory in turn until a key is pressed, with the keys from an input script saved beside each ROM as <ROM>.input, or random ones drawn from --seed
+    Screensaver {
+        /// The directory of ROMs
+        #[arg(long="dir")]
+        dir: String,
+
+        /// Seconds to play each ROM for
+        #[arg(long="seconds", default_value_t=30)]
+        seconds: u64,
+    },
+
+    /// Run a collection of ROMs headless to smoke test the emulator
+    Batch {
+        #[command(subcommand)]
+        command: BatchCommand,
+    },
+
+    /// Generate a routine, font data and strings for drawing text, from lines of NAME = text
+    Text {
+        /// The strings to draw
+        input: String,
+
+        /// Where to write the assembler source, stdout if left out
+        output: Option<String>,
+
+        /// Size of the font
+        #[arg(long="font", default_value="4x5", value_parser=["3x5", "4x5"])]
+        font: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BatchCommand {
+    /// Run every ROM in a directory in parallel, and print their screen hashes, unknown opcodes and crashes as a table
+    Run {
+        /// The directory of ROMs
+        dir: String,
+
+        /// Number of frames to run each ROM for
+        #[arg(long="frames", default_value="600")]
+        frames: u32,
+
+        /// Number of instructions to execute per frame
+        #[arg(short='c', long="cycles", default_value="8")]
+        tick_per_frame: u64,
+
+        /// Number of ROMs to run at once [default: one per CPU]
+        #[arg(long="jobs")]
+        jobs: Option<usize>,
+    },
+}
+
+/// Parses an address given as decimal or 0x-prefixed hex
+fn parse_address(arg: &str) -> Result<u16, String> {
+    let parsed = match arg.strip_prefix("0x").or(arg.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    return parsed.map_err(|_| format!("invalid address {}", arg));
+}
+
+fn main() {
+    // Read arguments
+    let mut args = Args::parse();
+
+    // Resuming starts over with the arguments the last session was started with
+    let mut launch_args: Vec<String> = std::env::args().skip(1).collect();
+    let mut exit_state: Option<PathBuf> = None;
+    if let Some(Command::Resume { fresh }) = &args.command {
+        match resume_session(*fresh) {
+            Ok((session, state)) => {
+                args = Args::parse_from(std::iter::once(String::from("chip8")).chain(session.args.iter().cloned()));
+                launch_args = session.args;
+                exit_state = state;
+            },
+            Err(message) => {
+                println!("{}", message);
+                return;
+            }
+        }
+    }
+
+    // Profiles fill in the keymap, theme and quirks the flags leave out
+    if let Err(message) = apply_profiles(&mut args) {
+        println!("{}", message);
+        return;
+    }
+    if let Some(path) = args.save_profile.as_deref() {
+        match flag_profile(&args).save(Path::new(path)) {
+            Ok(()) => println!("Saved the profile to {}", path),
+            Err(message) => println!("{}", message),
+        }
+        return;
+    }
+
+    // The platform fills in what the flags leave out; its quirks are applied before --quirk in create_chip8
+    let platform = args.platform.as_deref().map(|name| Platform::from_name(name).expect("Clap only allows known platforms"));
+    if let Some(platform) = platform {
+        args.schip |= platform.schip;
+        args.vip_stack |= platform.vip_stack;
+    }
+    let tick_per_frame = args.tick_per_frame.or(platform.map(|platform| platform.cycles_per_frame)).unwrap_or(8);
+
+    if let Some(Command::Run { rom, cycles, cycles_per_frame, dump_screen, dump_registers, dump_history, .. }) = &args.command {
+        let dumps = [dump_screen.as_deref(), dump_registers.as_deref(), dump_history.as_deref()];
+        run_headless(&args, rom, *cycles, cycles_per_frame.unwrap_or(tick_per_frame), dumps);
+        return;
+    }
+
+    #[cfg(unix)]
+    if let Some(Command::Attach { socket }) = &args.command {
+        attach(socket, &args);
+        return;
+    }
+
+    if let Some(Command::Doctor { output }) = &args.command {
+        run_doctor(&args, output.as_deref());
+        return;
+    }
+
+    if let Some(Command::Quirks { roms, format }) = &args.command {
+        list_quirks(roms, format == "json");
+        return;
+    }
+
+    if let Some(Command::PackData { input, output, name, rle, unpack_to }) = &args.command {
+        pack_data(input, output, name.as_deref(), rle.then_some(*unpack_to));
+        return;
+    }
+
+    if let Some(Command::Tracker { input, output, name }) = &args.command {
+        convert_tracker(input, output, name.as_deref());
+        return;
+    }
+
+    if let Some(Command::Img2sprite { input, output, name, threshold, dither }) = &args.command {
+        convert_image(input, output.as_deref(), name.as_deref(), *threshold, *dither);
+        return;
+    }
+
+    if let Some(Command::Text { input, output, font }) = &args.command {
+        let font = if font == "3x5" { Font::Small } else { Font::Large };
+        generate_text(input, output.as_deref(), font);
+        return;
+    }
+
+    if let Some(Command::Batch { command: BatchCommand::Run { dir, frames, tick_per_frame, jobs } }) = &args.command {
+        run_batch_command(dir, *frames, *tick_per_frame, *jobs);
+        return;
+    }
+
+    if let Some(Command::ImportTrace { input, output, format }) = &args.command {
+        import_trace(input, output, Format::from_name(format).expect("Clap only allows known formats"));
+        return;
+    }
+
+    if let Some(Command::StateInfo { path }) = &args.command {
+        print_state_info(path);
+        return;
+    }
+
+    if args.diagnose_terminal {
+        println!("{}", TermCaps::probe());
+        return;
+    }
+
+    // A daemon runs without a terminal of its own
+    #[cfg(unix)]
+    let headless = args.daemon.is_some();
+    #[cfg(not(unix))]
+    let headless = false;
+
+    // Frames piped to stdout would collide with the TUI, so nothing is rendered in that case, nor when checking the ROM or announcing to stdout
+    let announce_to_stdout = args.announce.is_some() && AnnounceTarget::is_stdout(&args.announce_to);
+    let render = !headless && !args.frame_pipe.as_deref().is_some_and(FramePipe::is_stdout) && args.compare_trace.is_none() && args.determinism_check.is_none() && !announce_to_stdout;
+
+    // Check terminal size
+    let locale = create_locale(&args);
+    let (screen_width, screen_height) = screen_size(&args);
+    if render && !check_terminal_size(&args, &locale, screen_height) {
+        return;
+    }
+
+    // Find out what the terminal can do, then pick the theme to suit it
+    let caps = if render { TermCaps::probe() } else { TermCaps::assumed() };
+    let colors = match theme_colors(&args, &caps) {
+        Ok(colors) => colors,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
+    let keymap = match create_keymap(&args, &caps) {
+        Ok(keymap) => keymap,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
+
+    // Pacing, the frame timings and cast recordings all count frames of the same length
+    let frame_period = frame_period(args.framerate);
+
+    if let Some(Command::Screensaver { dir, seconds }) = &args.command {
+        run_screensaver(&args, &locale, dir, *seconds, tick_per_frame, colors.as_deref());
+        return;
+    }
+
+    // Load the ROM, which clap only lets be omitted for subcommands and --diagnose-terminal
+    let rom_path = args.rom.as_deref().expect("No ROM given");
+    let mut rom = std::fs::read(rom_path).expect("Failed to read ROM");
+
+    // Octo cartridges carry their program as Octo source, so it is assembled and run with the quirks and speed the cartridge asks for.
+    // Flags still win: the cartridge's quirks go before --quirk's, and its tickrate only counts without --cycles
+    let mut tickrate = None;
+    if is_cartridge(&rom) {
+        let cartridge = match load_cartridge(&rom) {
+            Ok(cartridge) => cartridge,
+            Err(message) => {
+                println!("Failed to load cartridge {}: {}", rom_path, message);
+                return;
+            }
+        };
+        rom = match cartridge.assemble() {
+            Ok(rom) => rom,
+            Err(message) => {
+                println!("Failed to assemble cartridge {}:\n{}", rom_path, message);
+                return;
+            }
+        };
+        args.quirk.splice(0..0, cartridge.options.quirks());
+        tickrate = cartridge.options.tickrate.filter(|rate| *rate > 0);
+    }
+    let tick_per_frame = args.tick_per_frame.or(tickrate).unwrap_or(tick_per_frame);
+
+    // Any file runs, as garbage if it isn't a ROM, so one that is plainly something else gets a warning
+    let rom_warning = sniff_rom(&rom).map(|warning| format!("warning: {} {}", rom_path, warning));
+
+    // The listing asm wrote beside the ROM, for the debugger to show the source and find labels to break at
+    let source_map = match Listing::beside(Path::new(rom_path)) {
+        Ok(source_map) => source_map,
+        Err(e) => {
+            println!("Failed to read the listing beside {}: {}", rom_path, e);
+            return;
+        },
+    };
+    let mut breakpoints = Vec::new();
+    for target in args.break_at.iter() {
+        match resolve_breakpoint(target, source_map.as_ref()) {
+            Ok(breakpoint) => breakpoints.push(breakpoint),
+            Err(e) => {
+                println!("{}", e);
+                return;
+            },
+        }
+    }
+    let debugging = args.debug || !args.watch.is_empty() || !breakpoints.is_empty() || args.unknown_opcodes == OpcodePolicy::Trap;
+
+    // Open the frame pipe
+    let mut frame_pipe = match args.frame_pipe.as_deref().map(FramePipe::open) {
+        Some(Ok(pipe)) => Some(pipe),
+        Some(Err(e)) => {
+            println!("Failed to open frame pipe: {}", e);
+            return;
+        },
+        None => None,
+    };
+
+    // Start the recording, sized to fit the drawn frame
+    let mut cast = match args.record_cast.as_deref().map(|path| CastRecorder::create(path, screen_width as u16 / 2 + 4, frame_height(&args, screen_height), rom_path)) {
+        Some(Ok(cast)) => Some(cast),
+        Some(Err(e)) => {
+            println!("Failed to create cast: {}", e);
+            return;
+        },
+        None => None,
+    };
+    let mut frames_recorded: u32 = 0;
+
+    // Start the metrics listener
+    #[cfg(feature = "metrics")]
+    let metrics = std::sync::Arc::new(metrics::Metrics::default());
+    #[cfg(feature = "metrics")]
+    if let Some(addr) = &args.metrics {
+        if let Err(e) = metrics::serve(addr, metrics.clone()) {
+            println!("Failed to start metrics listener on {}: {}", addr, e);
+            return;
+        }
+    }
+
+    // Start listening for terminals to attach
+    #[cfg(unix)]
+    let mut daemon = match args.daemon.as_deref().map(|path| daemon::Daemon::listen(path, screen_width, screen_height)) {
+        Some(Ok(daemon)) => {
+            println!("Running ROM {} headless, attach with: chip8 attach {}", rom_path, args.daemon.as_deref().unwrap_or_default());
+            Some(daemon)
+        },
+        Some(Err(e)) => {
+            println!("Failed to listen on socket: {}", e);
+            return;
+        },
+        None => None,
+    };
+
+    // Create the Chip8
+    let mut chip8 = match create_chip8(&args, &rom) {
+        Ok(chip8) => chip8,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
+
+    // Pick up where the resumed session was quit
+    if let Some(path) = exit_state.as_deref() {
+        if let Err(message) = std::fs::read(path).map_err(|e| e.to_string()).and_then(|state| chip8.load_state(&state)) {
+            println!("Failed to load the state {} was quit in, starting from power on: {}", rom_path, message);
+        }
+    }
+
+    let replay_script = match args.replay_input.as_deref().map(InputScript::load) {
+        Some(Ok(script)) => Some(script),
+        Some(Err(message)) => {
+            println!("{}", message);
+            return;
+        },
+        None => None,
+    };
+    let mut recorded_input = args.record_input.is_some().then(InputScript::new);
+
+    // Load the announcement script
+    let mut announcer = match args.announce.as_deref() {
+        Some(path) => {
+            let loaded = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|script| Announcer::parse(&script))
+                .and_then(|announcer| AnnounceTarget::open(&args.announce_to).map(|target| (announcer, target)).map_err(|e| format!("{}: {}", args.announce_to, e)));
+            match loaded {
+                Ok(announcer) => Some(announcer),
+                Err(e) => {
+                    println!("Failed to load announcements from {}: {}", path, e);
+                    return;
+                }
+            }
+        },
+        None => None,
+    };
+
+    // Check the interpreter against a known-good trace instead of playing
+    if let Some(path) = args.compare_trace.as_deref() {
+        let expected = match std::fs::read(path).map_err(|e| e.to_string()).and_then(|bytes| read_trace(&bytes)) {
+            Ok(expected) => expected,
+            Err(e) => {
+                println!("Failed to read trace {}: {}", path, e);
+                return;
+            }
+        };
+        match compare_trace(&mut chip8, &expected, tick_per_frame) {
+            Ok(matched) => println!("All {} instructions match {}", matched, path),
+            Err(divergence) => {
+                println!("{}", divergence);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Run the ROM twice and make sure both runs end the same way
+    if let Some(frames) = args.determinism_check {
+        let second = create_chip8(&args, &rom).expect("The same arguments set up the first emulator");
+        let (mut first, mut second) = (chip8, second);
+        first.seed_rng(args.seed.unwrap_or(0));
+        second.seed_rng(args.seed.unwrap_or(0));
+
+        let jitter = args.cycle_jitter.map(|spread| CycleJitter::new(args.seed.unwrap_or(0), spread));
+        let differences = check_determinism(first, second, &replay_script.unwrap_or_default(), frames, tick_per_frame, jitter.as_ref());
+        if differences.is_empty() {
+            println!("Both runs of {} frames ended in the same state", frames);
+            return;
+        }
+        println!("The runs diverged:\n  {}", differences.join("\n  "));
+        std::process::exit(1);
+    }
+
+    // Prepare the terminal
+    let focus_pause = render && !args.no_focus_pause;
+    let _clean_up = (!headless).then_some(CleanUp { restore_cursor: render, pop_keyboard_flags: render && caps.kitty_keyboard, disable_focus_events: focus_pause, disable_mouse_reports: render && debugging });
+    if !headless {
+        terminal::enable_raw_mode().expect("Failed to enable raw mode");
+    }
+
+    // Have the kitty keyboard protocol tell Esc apart from the start of an escape sequence, and say when keys are let go
+    if render && caps.kitty_keyboard {
+        print!("\x1b[>3u");
+    }
+
+    // Have the terminal say when it loses and regains focus, so the ROM can pause in the background
+    if focus_pause {
+        print!("\x1b[?1004h");
+    }
+
+    // Have the terminal report clicks, so the debugger can find what drew the pixel clicked
+    if render && debugging {
+        print!("\x1b[?1000h\x1b[?1006h");
+    }
+
+    // Disable cursor
+    if render {
+        print!("\x1b[?25l");
+    }
+
+    // Counting starts after loading, so only the ROM's own accesses show up
+    if args.heatmap.is_some() {
+        chip8.enable_access_counts();
+    }
+    let mut register_usage = args.register_report.then(RegisterUsage::new);
+
+    // Display instructions
+    if render {
+        println!("\r{}", locale.format(Text::Running, &[&rom_path, &rom.len(), &args.framerate]));
+        println!("\r{}", locale.text(Text::Keybindings));
+        println!("\r\t1 2 3 4");
+        println!("\r\tq w e r");
+        println!("\r\ta s d f");
+        println!("\r\tz x c v");
+        match args.player2.as_deref() {
+            Some("ijkl") => println!("\r{}", locale.text(Text::Player2Ijkl)),
+            Some(_) => println!("\r{}", locale.text(Text::Player2Numpad)),
+            None => {},
+        }
+        println!("\r{}", locale.text(Text::QuitAndWarnings));
+        println!("\r{}", locale.text(Text::SaveSlots));
+        println!("\r{}", locale.text(Text::Macros));
+        if args.debug {
+            println!("\r{}", locale.text(Text::DebugStart));
+        }
+        if args.rewind_seconds > 0 {
+            println!("\r{}", locale.format(Text::Rewind, &[&args.rewind_seconds]));
+        }
+        if !caps.unicode_width {
+            println!("\r{}", locale.text(Text::BrailleWidth));
+        }
+        println!("\r{}", locale.text(Text::PressAnyKey));
+        read_event();
+        print!("\x1b[2J\x1b[1;1H");
+    }
+
+    // Timers count down at 60 Hz, independent of the framerate
+    let clock = SystemClock::new();
+    let mut timer_ticker = Ticker::new(TIMER_HZ, clock.now());
+    let mut timings = FrameTimings::new(frame_period);
+
+    // Frames end on deadlines from the clock, waiting only for what is left after emulating and drawing them,
+    // and with --frame-skip a frame already late by the time it is emulated isn't drawn, a few in a row at most
+    let mut pacer = Pacer::new(frame_period, clock.now());
+    let mut skipped_frames = 0;
+
+    // What test ROMs report goes to stderr, so it never mixes with frames piped to stdout.
+    // While the screen is being drawn, it waits until the terminal is back to normal.
+    let mut host_log: Vec<String> = Vec::new();
+    let mut test_result: Option<HostCall> = None;
+
+    // Warnings from the emulator go the same way, and are listed under the screen while it is drawn
+    let mut diagnostics: Vec<String> = Vec::new();
+    let mut show_diagnostics = false;
+    if let Some(warning) = rom_warning {
+        if render {
+            diagnostics.push(warning);
+        } else {
+            eprintln!("{}", warning);
+        }
+    }
+
+    // Set when the terminal loses focus, to pause once the current frame is out
+    let mut focus_lost = false;
+
+    // The slot in use and what became of the last save or load are shown under the screen
+    let mut slot = 1;
+    let mut status: Option<String> = None;
+
+    // Saving, loading and quitting wait for the frame to finish, so a state is never taken partway through one
+    let mut state_action: Option<StateAction> = None;
+    let mut quitting = false;
+
+    // Key presses recorded during the session and bound to F1-F4
+    let mut macros = MacroManager::new();
+
+    // The keypad keys held down on the keyboard, let go when the terminal says so or once it stops repeating them
+    let mut held_keys = HeldKeys::new((!(render && caps.kitty_keyboard)).then(|| Duration::from_millis(args.key_hold)));
+
+    // The keys the ROM was given lately, shown under the keypad
+    let mut key_history = args.key_history.then(KeyHistory::new);
+
+    // What the instructions run would have cost the COSMAC VIP
+    let mut cost_meter = args.vip_cost.then(CostMeter::new);
+
+    // A state for every recent frame, and whether Backspace asked to step back through them once the current frame is out
+    let mut rewind = Rewind::new(if render { (args.rewind_seconds * args.framerate) as usize } else { 0 });
+    let mut rewinding = false;
+
+    // With --debug, stops before a frame until told to run on, and remembers the last instruction run.
+    // Watchpoints and breakpoints alone bring it up too, but only --debug stops before the first frame
+    let mut debugger = (render && debugging).then(Debugger::new);
+    if debugger.is_none() && args.unknown_opcodes == OpcodePolicy::Trap {
+        // With no debugger to stop in, a trap stops the ROM
+        chip8.set_opcode_policy(OpcodePolicy::Halt);
+    }
+    if let Some(debugger) = debugger.as_mut() {
+        debugger.stopped = args.debug;
+        debugger.source_map = source_map;
+        debugger.breakpoints = breakpoints;
+        chip8.enable_pixel_sources();
+    }
+
+    // Vary the instructions each frame runs, from the same seed as RND so --seed still repeats a run
+    let jitter = args.cycle_jitter.map(|spread| CycleJitter::new(args.seed.unwrap_or_else(rand::random), spread));
+
+    // Main loop
+    let mut frame: u32 = 0;
+    let mut health = MachineHealth::default();
+    'main_loop: loop {
+        // Wait for the debugger's commands while it is stopped, running an instruction at a time
+        if let Some(debugger) = debugger.as_mut() {
+            let was_stopped = debugger.stopped;
+            while debugger.stopped {
+                draw_debugger(&chip8, screen_width, colors.as_deref(), &args, debugger).expect("Failed to write to the terminal");
+
+                let event = read_event();
+                if let Event::Mouse(MouseEvent { kind: MouseEventKind::Down(_), column, row, .. }) = event {
+                    debugger.pick(&chip8, column, row);
+                }
+                if let Event::Key(KeyEvent { code, .. }) = event {
+                    if debugger.is_typing() {
+                        debugger.type_key(code, &mut chip8);
+                        continue;
+                    }
+                    match DebugCommand::from_key(code) {
+                        Some(DebugCommand::Step) => {
+                            let executed = chip8.step();
+                            debugger.ran(executed, &mut chip8);
+                        },
+                        // Timers count down once for a frame run this way, whatever the clock says
+                        Some(DebugCommand::Frame) => {
+                            chip8.update_timers();
+                            break;
+                        },
+                        Some(DebugCommand::Continue) => debugger.stopped = false,
+                        Some(DebugCommand::Watch) => debugger.start_typing(Prompt::Watch),
+                        Some(DebugCommand::Break) => debugger.start_typing(Prompt::Break),
+                        Some(DebugCommand::History) => debugger.showing_history = !debugger.showing_history,
+                        Some(DebugCommand::FlipQuirk(quirk)) => debugger.flip_quirk(quirk, &mut chip8),
+                        Some(DebugCommand::Quit) => break 'main_loop,
+                        None => {},
+                    }
+                }
+            }
+
+            // Time spent stopped isn't made up for, like a pause
+            if was_stopped {
+                timer_ticker = Ticker::new(TIMER_HZ, clock.now());
+                pacer.restart(clock.now());
+            }
+        }
+
+        let frame_start = clock.now();
+
+        // Keys held on the keyboard stay down until let go, and everything else only presses keys for the frame
+        held_keys.expire(clock.now());
+        for key in 0..16 {
+            chip8.set_key(key, held_keys.is_held(key));
+        }
+
+        // Take input from the attached terminal
+        #[cfg(unix)]
+        if let Some(daemon) = daemon.as_mut() {
+            for button in daemon.poll_keys() {
+                chip8.set_keypress(button);
+                if let Some(script) = recorded_input.as_mut() {
+                    script.record(frame, 0, button);
+                }
+
+                #[cfg(feature = "metrics")]
+                metrics.input_events.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+
+        let budget = jitter.map_or(tick_per_frame, |jitter| jitter.budget(frame, tick_per_frame));
+        for tick in 0..budget {
+            if let Some(script) = replay_script.as_ref() {
+                script.apply(&mut chip8, frame, tick);
+            }
+
+            // Press the keys of any macros playing, keeping them in the recorded input so a replay needs no macros
+            for button in macros.keys_at(frame, tick) {
+                chip8.set_keypress(button);
+                if let Some(script) = recorded_input.as_mut() {
+                    script.record(frame, tick, button);
+                }
+            }
+
+            // Poll for events
+            if !headless && event::poll(Duration::from_micros(1)).expect("Error") {
+                let event = event::read().expect("Failed to read line");
+                match event {
+                    // Keys let go in the background are never reported
+                    Event::FocusLost => {
+                        focus_lost = focus_pause;
+                        held_keys.release_all();
+                    },
+                    Event::FocusGained => focus_lost = false,
+                    _ => {}
+                }
+                if let Event::Key(key @ KeyEvent { code, modifiers, kind, .. }) = event {
+                    if kind == KeyEventKind::Release {
+                        if let Some(button) = keymap.button(&key) {
+                            held_keys.release(button);
+                            chip8.set_key(button, false);
+                        }
+                    } else if let Some(action) = state_hotkey(code, modifiers, slot) {
+                        state_action = Some(action);
+                    } else {
+                        match code {
+                            // Quit
+                            KeyCode::Esc => {
+                                quitting = true;
+                            },
+                            KeyCode::Tab => {
+                                show_diagnostics = !show_diagnostics;
+                            },
+                            KeyCode::Backspace => {
+                                rewinding = !rewind.is_empty();
+                            },
+                            KeyCode::F(8) => {
+                                if let Some(debugger) = debugger.as_mut() {
+                                    debugger.stopped = true;
+                                }
+                            },
+                            KeyCode::F(6) => {
+                                macros.start_recording();
+                                status = Some(String::from(locale.text(Text::RecordingMacro)));
+                            },
+                            KeyCode::F(key @ 1..=4) => {
+                                let macro_slot = key as usize - 1;
+                                status = Some(match macros.is_recording() {
+                                    true => match macros.finish_recording(macro_slot) {
+                                        Ok(presses) => locale.format(Text::BoundMacro, &[&presses, &key]),
+                                        Err(e) => e,
+                                    },
+                                    false if macros.play(macro_slot, frame) => locale.format(Text::PlayingMacro, &[&key]),
+                                    false => locale.format(Text::NoMacro, &[&key]),
+                                });
+                            },
+                            _ => {
+                                if let Some(button) = keymap.button(&key) {
+                                    held_keys.press(button, clock.now());
+                                    chip8.set_keypress(button);
+                                    macros.record(frame, tick, button);
+                                    if let Some(script) = recorded_input.as_mut() {
+                                        script.record(frame, tick, button);
+                                    }
+
+                                    #[cfg(feature = "metrics")]
+                                    metrics.input_events.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                                }
+                            }
+                        }
+                    }
+                };
+            }
+        
+            // Count the registers the next instruction uses
+            if let Some(usage) = register_usage.as_mut() {
+                let (pc, memory) = (chip8.get_pc() as usize, chip8.get_memory());
+                // The PC can be on the last byte of memory, where the machine stops instead of running anything
+                usage.record(pc as u16, (memory[pc] as u16) << 8 | memory.get(pc + 1).copied().unwrap_or(0) as u16);
+            }
+
+            // Tick the Chip8, keeping what ran for the debugger, which stops straight away when a watched value changes or a breakpoint is reached
+            let executed = chip8.step();
+            if let (Some(meter), Some(instruction)) = (cost_meter.as_mut(), executed) {
+                meter.record(instruction);
+            }
+            if let Some(debugger) = debugger.as_mut() {
+                if debugger.ran(executed, &mut chip8) {
+                    break;
+                }
+            }
+        }
+        if let Some(meter) = cost_meter.as_mut() {
+            meter.end_frame();
+        }
+
+        // Handle what test ROMs report, stopping once they pass or fail
+        for call in chip8.take_host_calls() {
+            match call {
+                HostCall::Debug(line) if !render => eprintln!("{}", line),
+                HostCall::Debug(line) => host_log.push(line),
+                result => test_result = Some(result),
+            }
+        }
+
+        // Without a screen to stop on, changes to watched values are printed as they happen
+        if debugger.is_none() {
+            for hit in chip8.take_watch_hits() {
+                eprintln!("watch: {}", hit);
+            }
+        }
+
+        for diagnostic in chip8.take_diagnostics() {
+            if render {
+                diagnostics.push(format!("warning: {}", diagnostic));
+            } else {
+                eprintln!("warning: {}", diagnostic);
+            }
+        }
+
+        // Update the timers
+        let timer_ticks = timer_ticker.ticks_due(clock.now());
+        for _ in 0..timer_ticks {
+            chip8.update_timers();
+        }
+        health.frame(&chip8.take_events(), timer_ticks);
+
+        // A frame ends when the timers count down. If the clock says they aren't due yet, they count down now instead,
+        // so the state saved, loaded over or left behind on quitting is always at the end of a frame
+        if (quitting || test_result.is_some() || state_action.is_some()) && chip8.is_mid_frame() {
+            chip8.update_timers();
+            timer_ticker = Ticker::new(TIMER_HZ, clock.now());
+        }
+        if quitting || test_result.is_some() {
+            break 'main_loop;
+        }
+        if let Some(action) = state_action.take() {
+            let (message, used) = match action {
+                StateAction::Save(used) => (save_state_slot(&chip8, &args, &locale, rom_path, used), used),
+                StateAction::Load(used) => (load_state_slot(&mut chip8, &args, &locale, rom_path, used), used),
+            };
+            (slot, status) = (used, Some(message));
+        }
+        // Say what changed, giving up on announcing if the target stops working
+        if let Some((announcer_state, target)) = announcer.as_mut() {
+            if let Err(e) = announcer_state.announce(&chip8, target) {
+                host_log.push(format!("Stopped announcing: {}", e));
+                announcer = None;
+            }
+        }
+        let emulated = clock.now();
+
+        // Report progress to the metrics listener
+        #[cfg(feature = "metrics")]
+        {
+            use std::sync::atomic::Ordering;
+            metrics.frames.fetch_add(1, Ordering::Relaxed);
+            metrics.instructions.fetch_add(budget, Ordering::Relaxed);
+            metrics.faults.store(chip8.get_unknown_opcode_count(), Ordering::Relaxed);
+        }
+
+        // Stream the frame, stopping once the consumer goes away
+        if let Some(pipe) = frame_pipe.as_mut() {
+            if pipe.write_frame(chip8.get_screen_buffer(), screen_width, screen_height).is_err() {
+                break 'main_loop;
+            }
+        }
+
+        // Send the frame to the attached terminal
+        #[cfg(unix)]
+        if let Some(daemon) = daemon.as_mut() {
+            daemon.send_frame(chip8.get_screen_buffer(), chip8.get_sound_timer(), chip8.get_keypad());
+        }
+
+        if let Some(key_history) = key_history.as_mut() {
+            key_history.record(frame, chip8.get_keypad());
+        }
+        let skip = args.frame_skip && cast.is_none() && skipped_frames < MAX_SKIPPED_FRAMES && pacer.is_late(clock.now());
+        skipped_frames = if skip { skipped_frames + 1 } else { 0 };
+        if (render || cast.is_some()) && !skip {
+            // Guess the next frame from the keys held now and show that instead, rolling the emulator back to the real one
+            let ahead_budget = jitter.map_or(tick_per_frame, |jitter| jitter.budget(frame + 1, tick_per_frame));
+            let ahead = args.run_ahead.then(|| chip8.run_ahead(ahead_budget, timer_ticks).expect("Save states always load into the emulator that saved them"));
+            let screen = ahead.as_deref().unwrap_or(chip8.get_screen_buffer());
+            let history = key_history.as_ref().map(|key_history| key_history.line(locale.text(Text::KeyHistory), frame, screen_width / 2 + 4));
+
+            // Lay the frame out, with the panes stacked under the screen and the debugger's beside it
+            let border = health.border(chip8.get_sound_timer(), frame);
+            let mut compositor = Compositor::new();
+            let mut row = 1;
+            row += compositor.draw(Plane::Screen, row, 1, |out| draw(out, screen, screen_width, chip8.get_keypad(), border, colors.as_deref(), &args)).expect("Failed to draw");
+            row += compositor.draw(Plane::Pane, row, 1, |out| draw_status(out, &locale, slot, status.as_deref())).expect("Failed to draw");
+            if let Some(history) = history {
+                row += compositor.add(Plane::Pane, row, 1, &history);
+            }
+            if let Some(meter) = cost_meter.as_ref() {
+                row += compositor.add(Plane::Pane, row, 1, &meter.line(screen_width / 2 + 4));
+            }
+            compositor.draw(Plane::Pane, row, 1, |out| draw_diagnostics(out, &locale, &diagnostics, show_diagnostics, screen_width)).expect("Failed to draw");
+            if let Some(debugger) = debugger.as_ref() {
+                compositor.draw(Plane::Pane, 2, screen_width / 2 + 6, |out| debugger.draw(out, &chip8)).expect("Failed to draw");
+            }
+
+            // Clear the screen and draw the frame
+            let mut frame = Vec::new();
+            compositor.render(&mut frame).expect("Failed to draw");
+
+            if render {
+                let mut stdout = io::stdout();
+                stdout.write_all(&frame).expect("Failed to write to the terminal");
+                stdout.flush().expect("Failed to write to the terminal");
+            }
+
+            // Frames are timed by count rather than the clock, so recordings are reproducible
+            if let Some(recorder) = cast.as_mut() {
+                if let Err(e) = recorder.write_output(frame_period * frames_recorded, &frame) {
+                    println!("\rFailed to record cast: {}", e);
+                    break 'main_loop;
+                }
+                frames_recorded += 1;
+            }
+        }
+
+        // A SUPER-CHIP ROM that exits stops the emulator once its last frame is out, and one stopped by an error says why first
+        if chip8.is_halted() {
+            if let (Some(error), true) = (chip8.get_error(), render) {
+                let message = locale.format(Text::Stopped, &[error]);
+                draw_still(&chip8, screen_width, colors.as_deref(), &args, Border::paused(), &message).expect("Failed to write to the terminal");
+                while !matches!(read_event(), Event::Key(_)) {}
+            }
+            break 'main_loop;
+        }
+
+        // Sleep until the frame's deadline
+        let rendered = clock.now();
+        clock.sleep(pacer.wait(rendered));
+        timings.record(emulated - frame_start, rendered - emulated, clock.now() - rendered);
+        frame += 1;
+
+        // Keep the frame to rewind to
+        if render && args.rewind_seconds > 0 {
+            rewind.push(chip8.save_state().expect("Save states always encode"));
+        }
+
+        // Step back a frame for every Backspace, with the emulator stopped, until another key carries on from there
+        if rewinding {
+            let mut stepped = 0;
+            loop {
+                let message = match rewind.step_back() {
+                    Some(state) => {
+                        chip8.load_state(state).expect("Rewind states come from this emulator");
+                        stepped += 1;
+                        locale.format(Text::Rewound, &[&stepped])
+                    },
+                    None => locale.format(Text::RewoundAll, &[&stepped]),
+                };
+                draw_still(&chip8, screen_width, colors.as_deref(), &args, Border::paused(), &message).expect("Failed to write to the terminal");
+
+                match read_event() {
+                    Event::Key(KeyEvent { code: KeyCode::Backspace, .. }) => continue,
+                    Event::Key(KeyEvent { code: KeyCode::Esc, .. }) => break 'main_loop,
+                    Event::Key(_) => break,
+                    _ => {}
+                }
+            }
+            rewinding = false;
+            timer_ticker = Ticker::new(TIMER_HZ, clock.now());
+            pacer.restart(clock.now());
+        }
+
+        // Wait in the background without running or drawing anything, with the beep muted, until the terminal gets focus back
+        if focus_lost {
+            draw_still(&chip8, screen_width, colors.as_deref(), &args, Border::paused(), locale.text(Text::Paused)).expect("Failed to write to the terminal");
+
+            loop {
+                match read_event() {
+                    Event::FocusGained => break,
+                    Event::Key(KeyEvent { code: KeyCode::Esc, .. }) => break 'main_loop,
+                    _ => {}
+                }
+            }
+            focus_lost = false;
+
+            // The timers and frames carry on from where they were, rather than catching up on the time spent paused
+            timer_ticker = Ticker::new(TIMER_HZ, clock.now());
+            pacer.restart(clock.now());
+        }
+    }
+
+    // Put the terminal back before printing anything
+    drop(_clean_up);
+
+    // Remember the session, and the state it was quit in, for `chip8 resume`
+    if render {
+        if let Err(message) = save_session(&chip8, state_format(&args), launch_args, rom_path) {
+            println!("Failed to save the session: {}", message);
+        }
+    }
+    if args.frame_stats {
+        print!("{}", timings.report());
+    }
+
+    if let Some(usage) = register_usage {
+        print!("{}", usage.report());
+    }
+
+    if let Some(meter) = cost_meter {
+        print!("{}", meter.report());
+    }
+
+    if let (Some(path), Some(script)) = (args.record_input.as_deref(), recorded_input.as_ref()) {
+        if let Err(message) = script.save(path) {
+            println!("{}", message);
+        }
+    }
+
+    if let (Some(path), Some(counts)) = (args.heatmap.as_deref(), chip8.get_access_counts()) {
+        if let Err(message) = export_heatmap(counts, path) {
+            println!("{}", message);
+        }
+    }
+
+    for line in host_log.iter().chain(diagnostics.iter()) {
+        eprintln!("{}", line);
+    }
+    report_fault(&chip8);
+    match test_result {
+        Some(HostCall::Pass) => eprintln!("Test passed"),
+        Some(HostCall::Fail(code)) => {
+            eprintln!("Test failed with code {}", code);
+            std::process::exit(1);
+        },
+        _ => {}
+    }
+}
+
+/// Run a ROM without the terminal UI and write out where it ended up, exiting with 1 if it was a test ROM that failed
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program, for the machine's settings
+/// * `rom_path` - The ROM
+/// * `cycles` - Most instructions to run
+/// * `cycles_per_frame` - Instructions run for every time the timers count down
+/// * `dumps` - Where to write the screen, the registers and the last instructions run, if anywhere
+fn run_headless(args: &Args, rom_path: &str, cycles: u64, cycles_per_frame: u64, dumps: [Option<&str>; 3]) {
+    let rom = match std::fs::read(rom_path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            println!("Failed to read {}: {}", rom_path, e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(warning) = sniff_rom(&rom) {
+        eprintln!("warning: {} {}", rom_path, warning);
+    }
+    let mut chip8 = match create_chip8(args, &rom) {
+        Ok(chip8) => chip8,
+        Err(message) => {
+            println!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    // With no debugger to stop in, a trap stops the ROM
+    if args.unknown_opcodes == OpcodePolicy::Trap {
+        chip8.set_opcode_policy(OpcodePolicy::Halt);
+    }
+
+    // Timers count down by instructions run rather than the clock, so the same ROM always ends the same way
+    let mut test_result = None;
+    for cycle in 1..=cycles {
+        chip8.step();
+        if cycle % cycles_per_frame == 0 {
+            chip8.update_timers();
+        }
+
+        for call in chip8.take_host_calls() {
+            match call {
+                HostCall::Debug(line) => eprintln!("{}", line),
+                result => test_result = Some(result),
+            }
+        }
+        if test_result.is_some() || chip8.is_halted() {
+            break;
+        }
+    }
+    for diagnostic in chip8.take_diagnostics() {
+        eprintln!("warning: {}", diagnostic);
+    }
+
+    let [dump_screen, dump_registers, dump_history] = dumps;
+    for (path, text) in [(dump_screen, screen_text(&chip8)), (dump_registers, registers_text(&chip8)), (dump_history, history_text(&chip8))] {
+        if let Some(path) = path {
+            if let Err(e) = std::fs::write(path, text) {
+                println!("Failed to write {}: {}", path, e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    report_fault(&chip8);
+    match test_result {
+        Some(HostCall::Pass) => eprintln!("Test passed"),
+        Some(HostCall::Fail(code)) => {
+            eprintln!("Test failed with code {}", code);
+            std::process::exit(1);
+        },
+        _ => {}
+    }
+}
+
+/// Print the error that stopped the ROM, in strict mode or not, with the machine's state at the instruction at fault, and exit with 1
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator, which is left alone if nothing stopped it
+fn report_fault(chip8: &Chip8) {
+    let Some(error) = chip8.get_error() else {
+        return;
+    };
+
+    match error {
+        Chip8Error::Strict(fault) => eprintln!("strict: {}", fault),
+        error => eprintln!("error: {}", error),
+    }
+    eprint!("{}", registers_text(chip8));
+    eprint!("How it got there:\n{}", history_text(chip8));
+    std::process::exit(1);
+}
+
+/// Find the last session, to start it again
+/// 
+/// ## Arguments
+/// 
+/// * `fresh` - Whether to start from power on, rather than from the state the ROM was quit in
+/// 
+/// ## Returns
+/// 
+/// The session, with the working directory changed to the one it was started from, and the state to load if there is one
+fn resume_session(fresh: bool) -> Result<(Session, Option<PathBuf>), String> {
+    let data_dir = data_dir().ok_or("Neither XDG_DATA_HOME nor HOME is set, so there is no session to resume")?;
+    let session = Session::load(&data_dir)?;
+    std::env::set_current_dir(&session.dir).map_err(|e| format!("Failed to go back to {}: {}", session.dir.display(), e))?;
+
+    let state = rom_data_dir(&data_dir, &session.rom).join("exit.state");
+    return Ok((session, (!fresh && state.exists()).then_some(state)));
+}
+
+/// Remember how the ROM was started and the state it was quit in, for `chip8 resume`
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator, as it was quit
+/// * `format` - How to write the state
+/// * `args` - The arguments the ROM was started with, without the program name
+/// * `rom_path` - The ROM
+fn save_session(chip8: &Chip8, format: Format, args: Vec<String>, rom_path: &str) -> Result<(), String> {
+    let data_dir = data_dir().ok_or("Neither XDG_DATA_HOME nor HOME is set")?;
+    let dir = std::env::current_dir().map_err(|e| e.to_string())?;
+    let session = Session { dir, args, rom: PathBuf::from(rom_path) };
+    session.save(&data_dir)?;
+
+    let rom_dir = rom_data_dir(&data_dir, &session.rom);
+    std::fs::create_dir_all(&rom_dir).map_err(|e| format!("Failed to create {}: {}", rom_dir.display(), e))?;
+    let state = encode_state(chip8, format)?;
+    return std::fs::write(rom_dir.join("exit.state"), state).map_err(|e| format!("Failed to save the state it was quit in: {}", e));
+}
+
+/// Play every ROM in a directory in turn, over and over, until a key is pressed
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program, which set up every ROM's emulator the same way
+/// * `locale` - The language to say which ROM is playing in
+/// * `dir` - The directory of ROMs
+/// * `seconds` - How long each ROM plays for, unless it exits sooner
+/// * `tick_per_frame` - Instructions run each frame
+/// * `colors` - The theme's colors, if there is one
+fn run_screensaver(args: &Args, locale: &Locale, dir: &str, seconds: u64, tick_per_frame: u64, colors: Option<&str>) {
+    let roms = match list_roms(Path::new(dir)) {
+        Ok(roms) => roms,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
+    let (width, _) = screen_size(args);
+    let frame_duration = frame_period(args.framerate);
+    let frames_per_rom = (seconds * args.framerate) as u32;
+
+    // Every ROM draws from its own seed, so one --seed repeats the whole show
+    let seed = args.seed.unwrap_or_else(rand::random);
+
+    let _clean_up = CleanUp { restore_cursor: true, pop_keyboard_flags: false, disable_focus_events: false, disable_mouse_reports: false };
+    terminal::enable_raw_mode().expect("Failed to enable raw mode");
+    print!("\x1b[?25l");
+
+    let clock = SystemClock::new();
+    loop {
+        let mut played = false;
+        for (index, path) in roms.iter().enumerate() {
+            // ROMs that can't be read or set up are skipped
+            let Ok(mut chip8) = std::fs::read(path).map_err(|e| e.to_string()).and_then(|rom| create_chip8(args, &rom)) else {
+                continue;
+            };
+            played = true;
+            let rom_seed = seed.wrapping_add(index as u64);
+            chip8.seed_rng(rom_seed);
+            let mut input = DemoInput::for_rom(path, rom_seed);
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let caption = locale.format(Text::Screensaver, &[&name, &(index + 1), &roms.len()]);
+
+            let mut timer_ticker = Ticker::new(TIMER_HZ, clock.now());
+            for frame in 0..frames_per_rom {
+                chip8.clear_keypad();
+                for tick in 0..tick_per_frame {
+                    input.apply(&mut chip8, frame, tick);
+                    chip8.step();
+                }
+                for _ in 0..timer_ticker.ticks_due(clock.now()) {
+                    chip8.update_timers();
+                }
+
+                // Nobody is there to read what test ROMs report or what the emulator warns about
+                chip8.take_host_calls();
+                chip8.take_diagnostics();
+                chip8.take_events();
+                if chip8.is_halted() {
+                    break;
+                }
+
+                draw_still(&chip8, width, colors, args, Border::default(), &caption).expect("Failed to write to the terminal");
+                if event::poll(frame_duration).expect("Failed to read events") && matches!(event::read().expect("Failed to read events"), Event::Key(_)) {
+                    return;
+                }
+            }
+        }
+
+        if !played {
+            drop(_clean_up);
+            println!("None of the ROMs in {} could be played", dir);
+            return;
+        }
+    }
+}
+
+/// Layer the profiles under the flags: the one beside the ROM, then --profile-file, then the flags themselves
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program, which are filled in from the profiles
+fn apply_profiles(args: &mut Args) -> Result<(), String> {
+    let rom = match &args.command {
+        Some(Command::Run { rom, .. }) => Some(rom.as_str()),
+        _ => args.rom.as_deref(),
+    };
+    let mut profile = match rom {
+        Some(rom) => Profile::for_rom(Path::new(rom))?.unwrap_or_default(),
+        None => Profile::default(),
+    };
+    if let Some(path) = args.profile_file.as_deref() {
+        profile = profile.layer(Profile::load(Path::new(path))?);
+    }
+
+    let profile = profile.layer(flag_profile(args));
+    args.player1_keys = profile.player1_keys;
+    args.player2 = profile.player2;
+    args.player2_keys = profile.player2_keys;
+    args.theme = profile.theme;
+    args.platform = profile.platform;
+    args.quirk = profile.quirks;
+    return Ok(());
+}
+
+/// The settings a profile holds, as the flags give them
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+fn flag_profile(args: &Args) -> Profile {
+    return Profile {
+        player1_keys: args.player1_keys.clone(),
+        player2: args.player2.clone(),
+        player2_keys: args.player2_keys.clone(),
+        theme: args.theme.clone(),
+        platform: args.platform.clone(),
+        quirks: args.quirk.clone(),
+        ..Profile::default()
+    };
+}
+
+/// Create the emulator the arguments ask for, with the ROM loaded
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+/// * `rom` - The ROM to load
+fn create_chip8(args: &Args, rom: &[u8]) -> Result<Chip8, String> {
+    let mut chip8 = Chip8::new();
+
+    // Load the ROM into memory, which the platform has to size first
+    let platform = args.platform.as_deref().map(Platform::from_name).transpose()?;
+    if let Some(platform) = platform {
+        chip8.set_memory_size(platform.memory_size)?;
+    }
+    chip8.load_rom(rom)?;
+
+    if args.enable_custom_ops {
+        chip8.enable_custom_ops();
+    }
+
+    if args.schip {
+        chip8.enable_schip();
+    }
+
+    let mut quirks = platform.map_or(Quirks::default(), |platform| platform.quirks);
+    for spec in args.quirk.iter() {
+        quirks.set(spec)?;
+    }
+    chip8.set_quirks(quirks);
+
+    if args.pedantic {
+        chip8.enable_pedantic();
+    }
+
+    if args.poison_memory {
+        chip8.enable_memory_poisoning();
+    }
+
+    if args.strict {
+        chip8.enable_strict();
+    }
+    chip8.set_opcode_policy(args.unknown_opcodes);
+    chip8.set_pc_history_size(args.pc_history);
+
+    for watch in args.watch.iter() {
+        chip8.add_watchpoint(Watchpoint::parse(watch)?);
+    }
+
+    if let Some(path) = args.interpreter_image.as_deref() {
+        let image = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        chip8.load_interpreter_image(&image)?;
+    }
+
+    if let Some(seed) = args.seed {
+        chip8.seed_rng(seed);
+    }
+
+    if args.stack_depth.is_some() || args.vip_stack {
+        let depth = args.stack_depth.unwrap_or(VIP_STACK_SIZE);
+        chip8.configure_stack(depth, args.vip_stack)?;
+    }
+
+    return Ok(chip8);
+}
+
+/// Check that the terminal is tall enough to draw the screen, explaining what to do if it is not
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+/// * `locale` - The language to warn in
+/// * `screen_height` - Height of the screen in pixels
+/// 
+/// ## Returns
+/// 
+/// Whether the terminal is big enough
+fn check_terminal_size(args: &Args, locale: &Locale, screen_height: usize) -> bool {
+    let (_width, height) = terminal::size().expect("Failed to get terminal size");
+    let screen_rows = screen_height as u16 / 4;
+
+    // Weirdly here shifting right drops the entire value to 0, so I have to use division instead. I hope the compiler optimizes this :(
+    if !args.no_keypad && height < screen_rows + 15 {
+        if height >= screen_rows + 6 {
+            println!("{}", locale.format(Text::TerminalTooSmallWithKeypad, &[&(screen_rows + 15)]));
+        } else {
+            println!("{}", locale.format(Text::TerminalTooSmall, &[&(screen_rows + 15)]));
+        }
+        return false;
+    } else if args.no_keypad && height < screen_rows + 6 {
+        println!("{}", locale.format(Text::TerminalTooSmall, &[&(screen_rows + 6)]));
+        return false;
+    }
+
+    return true;
+}
+
+/// Look up the theme asked for on the command line
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+/// * `caps` - What the terminal can do
+/// 
+/// ## Returns
+/// 
+/// The escape sequence that sets the theme's colors, if a theme was asked for
+fn theme_colors(args: &Args, caps: &TermCaps) -> Result<Option<String>, String> {
+    let Some(name) = args.theme.as_deref() else {
+        return Ok(None);
+    };
+
+    return match find_theme(name, caps.background) {
+        Some(theme) => Ok(Some(theme.escape(caps.truecolor))),
+        None => Err(format!("Unknown theme {}. Try nord, solarized, gruvbox or auto.", name)),
+    };
+}
+
+/// Width and height in pixels of the screen the ROM draws on
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+fn screen_size(args: &Args) -> (usize, usize) {
+    if args.schip {
+        return (HIRES_WIDTH, HIRES_HEIGHT);
+    }
+    return (SCREEN_WIDTH, SCREEN_HEIGHT);
+}
 
-/// Implement Drop trait for CleanUp, which will be called when the struct goes out of scope
-impl Drop for CleanUp {
-    fn drop(&mut self) {
-        terminal::disable_raw_mode().expect("Could not disable raw mode");
+/// Number of rows a drawn frame takes up
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+/// * `screen_height` - Height of the screen in pixels
+fn frame_height(args: &Args, screen_height: usize) -> u16 {
+    if args.no_keypad {
+        return screen_height as u16 / 4 + 6;
+    }
+    return screen_height as u16 / 4 + 15;
+}
 
-        // Enable cursor
-        print!("\x1b[?25h");
+/// Convert a tile map into a byte table and an assembler include file
+/// 
+/// ## Arguments
+/// 
+/// * `input` - The tile map, PNG or text
+/// * `output` - Where to write the assembler source
+/// * `name` - Label for the table, if not the input's file name
+/// * `unpack_addr` - If set, compress the table and include a routine unpacking it to this address
+fn pack_data(input: &str, output: &str, name: Option<&str>, unpack_addr: Option<u16>) {
+    let map = if input.to_lowercase().ends_with(".png") {
+        read_png_map(input)
+    } else {
+        std::fs::read_to_string(input).map_err(|e| e.to_string()).and_then(|text| read_text_map(&text))
+    };
 
-        if std::thread::panicking() {
-            println!("Panic! at the disco");
+    let name = name.map(String::from).unwrap_or_else(|| label_from_path(input));
+
+    let packed = map.and_then(|map| pack(&map, &name, unpack_addr).map(|packed| (map, packed)));
+    let (map, (table, source)) = match packed {
+        Ok(packed) => packed,
+        Err(e) => {
+            println!("Failed to pack {}: {}", input, e);
+            return;
         }
+    };
+
+    let table_path = std::path::Path::new(output).with_extension("bin");
+    std::fs::write(output, source).expect("Failed to write output file");
+    std::fs::write(&table_path, &table).expect("Failed to write table");
+
+    println!(
+        "Packed {}x{} tiles into {} bytes. Output: {}, {}",
+        map.width, map.height, table.len(), output, table_path.display()
+    );
+}
+
+/// Convert a tracker-style song into XO-CHIP audio data and a playback routine
+/// 
+/// ## Arguments
+/// 
+/// * `input` - The song
+/// * `output` - Where to write the assembler source
+/// * `name` - Label prefix for the song, if not the input's file name
+fn convert_tracker(input: &str, output: &str, name: Option<&str>) {
+    let name = name.map(String::from).unwrap_or_else(|| label_from_path(input));
+    let song = std::fs::read_to_string(input).map_err(|e| e.to_string()).and_then(|text| convert_song(&text, &name));
+
+    match song {
+        Ok(source) => {
+            std::fs::write(output, source).expect("Failed to write output file");
+            println!("Converted song. Call PLAY_{} to play it. Output: {}", name.to_uppercase(), output);
+        },
+        Err(e) => println!("Failed to convert {}: {}", input, e),
     }
 }
 
-/// Struct to hold the arguments passed to the program
-#[derive(Parser, Debug)]
-#[command(about = "A Chip8 emulator written in Rust")]
-struct Args {
-    /// Number of frames to render per second
-    #[arg(short='f', long="frames", default_value="100")]
-    framerate: u64,
+/// Convert another emulator's instruction log into a trace
+/// 
+/// ## Arguments
+/// 
+/// * `input` - The log
+/// * `output` - Where to write the trace
+/// * `format` - How to write the trace
+fn import_trace(input: &str, output: &str, format: Format) {
+    let entries = match std::fs::read_to_string(input).map_err(|e| e.to_string()).and_then(|text| parse_trace(&text)) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("Failed to import {}: {}", input, e);
+            return;
+        }
+    };
 
-    /// The ROM to load
-    #[arg(short, long)]
-    rom: String,
+    let trace = write_trace(&entries, format).expect("Traces can be written in every format");
+    std::fs::write(output, trace).expect("Failed to write output file");
+    println!("Imported {} instructions. Output: {}", entries.len(), output);
+}
 
-    /// Number of instructions to execute per frame
-    #[arg(short='c', long="cycles", default_value="8")]
-    tick_per_frame: u64,
+/// Print what a save state holds, exiting with an error if it doesn't decompress or wouldn't load
+fn print_state_info(path: &str) {
+    let info = match std::fs::read(path).map_err(|e| e.to_string()).and_then(|state| Chip8::inspect_state(&state)) {
+        Ok(info) => info,
+        Err(e) => {
+            println!("{} is not a state that loads: {}", path, e);
+            std::process::exit(1);
+        }
+    };
 
-    /// Disable keypad rendering
-    #[arg(long="no-keypad", action)]
-    no_keypad: bool,
+    let format = match (info.format, info.compressed) {
+        (Format::Json, _) => String::from("JSON"),
+        (_, true) => format!("binary version {}, LZ4 compressed", FORMAT_VERSION),
+        _ => format!("binary version {}", FORMAT_VERSION),
+    };
+    println!("Format: {}", format);
+    println!("Size: {} bytes, {} decompressed", info.size, info.decompressed_size);
+    println!("Platform: {}", if info.schip { "SUPER-CHIP" } else { "CHIP-8" });
+    println!("Memory: {}K", info.memory_size / 1024);
+    println!("PC: 0x{:03X}", info.pc);
+}
 
-    /// Enable SMPTE color mode
-    #[arg(long="smpte", action)]
-    smpte: bool,
+/// Print every quirk, with what each platform picks and what the ROMs given ask for
+/// 
+/// ## Arguments
+/// 
+/// * `roms` - ROMs to show the quirks of; only Octo cartridges say which they need
+/// * `json` - Whether to print JSON rather than text
+fn list_quirks(roms: &[String], json: bool) {
+    let mut descriptions = Quirks::describe();
+    for rom in roms {
+        let data = match std::fs::read(rom) {
+            Ok(data) => data,
+            Err(e) => {
+                println!("Failed to read {}: {}", rom, e);
+                return;
+            }
+        };
+        if !is_cartridge(&data) {
+            continue;
+        }
+        let cartridge = match load_cartridge(&data) {
+            Ok(cartridge) => cartridge,
+            Err(message) => {
+                println!("Failed to load cartridge {}: {}", rom, message);
+                return;
+            }
+        };
+        for spec in cartridge.options.quirks() {
+            let (name, value) = spec.split_once('=').expect("Cartridges give quirks as name=value");
+            if let Some(description) = descriptions.iter_mut().find(|description| description.name == name) {
+                description.demanded_by.insert(rom.clone(), String::from(value));
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&descriptions).expect("Quirk descriptions always encode"));
+        return;
+    }
+
+    for description in descriptions.iter() {
+        println!("{:<10}{:<20}{}", description.name, description.values.join(" | "), description.description);
+        let platforms: Vec<String> = PLATFORM_NAMES.iter().map(|platform| format!("{} {}", platform, description.platforms[platform])).collect();
+        println!("{:<30}default {}, {}", "", description.default, platforms.join(", "));
+        for (rom, value) in description.demanded_by.iter() {
+            println!("{:<30}{} asks for {}", "", rom, value);
+        }
+    }
 }
 
-fn main() {
-    // Read arguments
-    let args = Args::parse();
+/// Run every ROM in a directory headless and print how each got on
+/// 
+/// ## Arguments
+/// 
+/// * `dir` - The directory of ROMs
+/// * `frames` - Number of frames to run each ROM for
+/// * `tick_per_frame` - Instructions run each frame
+/// * `jobs` - Number of ROMs to run at once, or one per CPU if `None`
+fn run_batch_command(dir: &str, frames: u32, tick_per_frame: u64, jobs: Option<usize>) {
+    let jobs = jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |count| count.get()));
 
-    // Check terminal size
-    let (_width, height) = terminal::size().expect("Failed to get terminal size");
+    // Crashing ROMs end up in the table, so the panic messages would only be noise
+    std::panic::set_hook(Box::new(|_| {}));
+    let reports = run_batch(std::path::Path::new(dir), frames, tick_per_frame, jobs);
+    let _ = std::panic::take_hook();
 
-    // Weirdly here shifting right drops the entire value to 0, so I have to use division instead. I hope the compiler optimizes this :(
-    if !args.no_keypad && height < SCREEN_HEIGHT as u16 / 4 + 14 {
-        if height >= SCREEN_HEIGHT as u16 / 4 + 5 {
-            println!("Terminal height is too small, which might lead to rendering issues. Please resize the terminal to have at least {} rows, or consider running with --no-keypad flag on.", SCREEN_HEIGHT / 4 + 14);
-        } else {
-            println!("Terminal height is too small, which might lead to rendering issues. Please resize the terminal to have at least {} rows.", SCREEN_HEIGHT / 4 + 14);
+    match reports {
+        Ok(reports) => {
+            print!("{}", summary_table(&reports));
+            if reports.iter().any(|report| report.outcome.is_err()) {
+                std::process::exit(1);
+            }
+        },
+        Err(message) => println!("{}", message),
+    }
+}
+
+/// Convert an image into sprites and a routine that draws them
+/// 
+/// ## Arguments
+/// 
+/// * `input` - The PNG image
+/// * `output` - Where to write the assembler source, or `None` for stdout
+/// * `name` - Label prefix for the sprites, if not the input's file name
+/// * `threshold` - Brightness at which a pixel is lit
+/// * `dither` - Whether to dither the image
+fn convert_image(input: &str, output: Option<&str>, name: Option<&str>, threshold: u8, dither: bool) {
+    let (brightness, width, height) = match read_brightness(input) {
+        Ok(image) => image,
+        Err(e) => {
+            println!("{}", e);
+            return;
         }
+    };
+
+    let name = name.map(String::from).unwrap_or_else(|| label_from_path(input));
+    let bitmap = to_bitmap(&brightness, width, threshold, dither);
+    let parts = split_sprites(&bitmap, width, height);
+    let source = sprite_source(&name, &parts);
+
+    let Some(output) = output else {
+        print!("{}", source);
         return;
-    } else if args.no_keypad && height < SCREEN_HEIGHT as u16 / 4 + 5 {
-        println!("Terminal height is too small, which might lead to rendering issues. Please resize the terminal to have at least {} rows.", SCREEN_HEIGHT / 4 + 5);
-        return;
-    }
+    };
 
-    let frame_duration: u64 = 1000 / args.framerate;
+    std::fs::write(output, source).expect("Failed to write output file");
+    println!("Converted {}x{} image into {} sprites. Call DRAW_{} to draw it. Output: {}", width, height, parts.len(), name.to_uppercase(), output);
+}
 
-    // Prepare the terminal
-    let _clean_up = CleanUp;
-    terminal::enable_raw_mode().expect("Failed to enable raw mode");
+/// Generate the routines and data for drawing a set of strings
+/// 
+/// ## Arguments
+/// 
+/// * `input` - The strings file
+/// * `output` - Where to write the assembler source, or `None` for stdout
+/// * `font` - Which font to draw with
+fn generate_text(input: &str, output: Option<&str>, font: Font) {
+    let source = std::fs::read_to_string(input)
+        .map_err(|e| e.to_string())
+        .and_then(|text| parse_strings(&text))
+        .and_then(|strings| text_source(&strings, font));
 
-    // Disable cursor
-    print!("\x1b[?25l");
+    let source = match source {
+        Ok(source) => source,
+        Err(e) => {
+            println!("Failed to generate text from {}: {}", input, e);
+            return;
+        }
+    };
 
-    // Load the ROM
-    let rom = std::fs::read(&args.rom).expect("Failed to read ROM");
+    let Some(output) = output else {
+        print!("{}", source);
+        return;
+    };
 
-    // Create the Chip8
-    let mut chip8 = Chip8::new();
+    std::fs::write(output, source).expect("Failed to write output file");
+    println!("Generated text routines. Output: {}", output);
+}
 
-    // Load the ROM into memory
-    chip8.load_rom(&rom);
+/// Turns a file name into something usable as an assembler label
+fn label_from_path(path: &str) -> String {
+    let stem = std::path::Path::new(path).file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    return stem.replace(['-', ' ', '.'], "_");
+}
 
-    // Display instructions
-    println!("\rRunning ROM {} ({} bytes) at {} FPS", args.rom, rom.len(), args.framerate);
-    println!("\rKeybindings:");
-    println!("\r\t1 2 3 4");
-    println!("\r\tq w e r");
-    println!("\r\ta s d f");
-    println!("\r\tz x c v");
-    println!("\rPress Esc to quit");
-    println!("\rPress any key to start");
-    event::read().expect("Failed to read line");
-    print!("\x1b[2J\x1b[1;1H");
+/// Attach the terminal to a daemon, drawing its frames and forwarding key presses until Esc is pressed
+/// 
+/// ## Arguments
+/// 
+/// * `socket` - The socket the daemon is listening on
+/// * `args` - The arguments passed to the program
+#[cfg(unix)]
+fn attach(socket: &str, args: &Args) {
+    let mut client = match daemon::Client::attach(socket) {
+        Ok(client) => client,
+        Err(e) => {
+            println!("Failed to attach to {}: {}", socket, e);
+            return;
+        }
+    };
 
-    // Main loop
-    'main_loop: loop {
-        // Clear keypresses
-        chip8.clear_keypad();
+    let (screen_width, screen_height) = client.screen_size();
+    if !check_terminal_size(args, &create_locale(args), screen_height) {
+        return;
+    }
 
-        for _ in 0..args.tick_per_frame {
-            // Poll for events
-            if event::poll(Duration::from_micros(1)).expect("Error") {
-                if let Event::Key(event) = event::read().expect("Failed to read line") {
-                    match event {
-                        KeyEvent {
-                            ..
-                        } => {
-                            match event.code {
-                                // Quit
-                                KeyCode::Esc => {
-                                    break 'main_loop;
-                                },
-                                _ => {
-                                    if let Some(button) = map_key_to_button(event.code) {
-                                        chip8.set_keypress(button);
-                                    }
-                                }
-                            }
-                        },
-                    }
-                };
-            }
-        
-            // Tick the Chip8
-            chip8.cycle();
+    let caps = TermCaps::probe();
+    let colors = match theme_colors(args, &caps) {
+        Ok(colors) => colors,
+        Err(message) => {
+            println!("{}", message);
+            return;
         }
+    };
+    let keymap = match create_keymap(args, &caps) {
+        Ok(keymap) => keymap,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
 
-        // Update the timers
-        chip8.update_timers();
-        
-        // Clear the screen
-        print!("\x1b[2J\x1b[1;1H");
+    // Prepare the terminal
+    let _clean_up = CleanUp { restore_cursor: true, pop_keyboard_flags: caps.kitty_keyboard, disable_focus_events: false, disable_mouse_reports: false };
+    terminal::enable_raw_mode().expect("Failed to enable raw mode");
+    if caps.kitty_keyboard {
+        print!("\x1b[>1u");
+    }
+    print!("\x1b[?25l");
 
-        // Draw the screen
-        draw(&chip8, &args);
+    loop {
+        // Forward key presses, detaching on Esc
+        while event::poll(Duration::ZERO).expect("Error") {
+            if let Event::Key(key) = event::read().expect("Failed to read line") {
+                if key.code == KeyCode::Esc {
+                    return;
+                }
+
+                if let Some(button) = keymap.button(&key) {
+                    if client.send_key(button).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
 
-        // Sleep for a bit
-        thread::sleep(Duration::from_millis(frame_duration));
+        // The daemon paces the frames, so this blocks until the next one
+        match client.read_frame() {
+            Ok(frame) => {
+                let mut compositor = Compositor::new();
+                compositor.draw(Plane::Screen, 1, 1, |out| draw(out, &frame.screen, screen_width, &frame.keypad, Border::plain(frame.sound_timer), colors.as_deref(), args)).expect("Failed to write to the terminal");
+                let mut stdout = io::stdout().lock();
+                compositor.render(&mut stdout).expect("Failed to write to the terminal");
+                stdout.flush().expect("Failed to write to the terminal");
+            },
+            Err(_) => {
+                println!("\rThe daemon has stopped");
+                return;
+            }
+        }
     }
 }
 
@@ -206,6 +2167,12 @@ const KEY_ORDER_HEX: [usize; 16] = [
     0xA, 0x0, 0xB, 0xF,
 ];
 
+/// Warnings listed in the opened pane, the latest ones
+const DIAGNOSTICS_SHOWN: usize = 5;
+
+/// Frames in a row --frame-skip leaves undrawn, so the screen still moves on a terminal that never catches up
+const MAX_SKIPPED_FRAMES: u32 = 4;
+
 /// SMPTE color codes
 const SMPTE_COLORS: [&str; 8] = [
     "\x1b[37m", "\x1b[33m", "\x1b[36m", "\x1b[32m",
@@ -218,172 +2185,435 @@ const SMPTE_COLORS: [&str; 8] = [
 /// 
 /// ## Arguments
 /// 
-/// * `chip` - The Chip8 to draw
+/// * `out` - Where to write the frame
+/// * `buffer` - The screen buffer to draw
+/// * `width` - Width of the screen in pixels, which sets the size of the frame
+/// * `keypad` - The keypad state
+/// * `border` - What the border shows, like the beep light and the machine's health
+/// * `colors` - Escape sequence setting the screen's colors, or the terminal's own if `None`
 /// * `args` - The arguments passed to the program
-fn draw(chip: &Chip8, args: &Args) {
+fn draw(out: &mut impl Write, buffer: &[u8], width: usize, keypad: &[bool], border: Border, colors: Option<&str>, args: &Args) -> io::Result<()> {
+    let height = buffer.len() / width;
+
     // Draw the outside border
-    print!("╭");
-    print!("─CHIP-8");
-    for _ in 0..((SCREEN_WIDTH / 2) - 12) {
-        print!("─");
-    }
-    print!("BEEP─");
-    if chip.get_sound_timer() > 0 {
-        print!("●─");
-    } else {
-        print!("○─");
-    }
-    println!("╮\r");
+    let light = if border.beeping { "●" } else { "○" };
+    draw_edge(out, &border, &format!("╭─CHIP-8{}BEEP─{}─╮", "─".repeat((width / 2) - 12), light))?;
+    writeln!(out, "\r")?;
 
     // Draw the top border
-    print!("│╭");
-    for _ in 0..SCREEN_WIDTH / 2 {
-        print!("─");
-    }
-    println!("╮│\r");
+    draw_edge(out, &border, &format!("│╭{}╮│", "─".repeat(width / 2)))?;
+    writeln!(out, "\r")?;
 
     // Draw the screen in blocks of 2x4
-    let buffer = chip.get_screen_buffer();
     let mut color_ptr: usize = 0;
-    for y in 0..SCREEN_HEIGHT / 4 {
+    for y in 0..height / 4 {
         // Draw the left border
-        print!("││");
+        draw_edge(out, &border, "││")?;
+
+        // Set the theme's colors, which the SMPTE colors draw over
+        if let Some(colors) = colors {
+            write!(out, "{}", colors)?;
+        }
 
         // Draw the screen
-        for x in 0..SCREEN_WIDTH / 2 {
+        for x in 0..width / 2 {
             let encoding = 
-                buffer[y * 4 * SCREEN_WIDTH + x * 2] << 7 |
-                buffer[y * 4 * SCREEN_WIDTH + x * 2 + 1] << 3 |
-                buffer[(y * 4 + 1) * SCREEN_WIDTH + x * 2] << 6 |
-                buffer[(y * 4 + 1) * SCREEN_WIDTH + x * 2 + 1] << 2 |
-                buffer[(y * 4 + 2) * SCREEN_WIDTH + x * 2] << 5 |
-                buffer[(y * 4 + 2) * SCREEN_WIDTH + x * 2 + 1] << 1 |
-                buffer[(y * 4 + 3) * SCREEN_WIDTH + x * 2] << 4 |
-                buffer[(y * 4 + 3) * SCREEN_WIDTH + x * 2 + 1];
+                buffer[y * 4 * width + x * 2] << 7 |
+                buffer[y * 4 * width + x * 2 + 1] << 3 |
+                buffer[(y * 4 + 1) * width + x * 2] << 6 |
+                buffer[(y * 4 + 1) * width + x * 2 + 1] << 2 |
+                buffer[(y * 4 + 2) * width + x * 2] << 5 |
+                buffer[(y * 4 + 2) * width + x * 2 + 1] << 1 |
+                buffer[(y * 4 + 3) * width + x * 2] << 4 |
+                buffer[(y * 4 + 3) * width + x * 2 + 1];
 
             // Set the color
             if args.smpte && x % 4 == 0 {
-                print!("{}", SMPTE_COLORS[color_ptr]);
+                write!(out, "{}", SMPTE_COLORS[color_ptr])?;
                 color_ptr = (color_ptr + 1) % 8;
             }
-            print!("{}", BRAILLE_MAP[encoding as usize]);
+            write!(out, "{}", BRAILLE_MAP[encoding as usize])?;
         }
 
         // Reset the color
-        print!("\x1b[0m");
+        write!(out, "\x1b[0m")?;
 
         // Draw the right border
-        println!("││\r");
+        draw_edge(out, &border, "││")?;
+        writeln!(out, "\r")?;
     }
 
-    // Draw the bottom border
-    print!("│╰");
-    for _ in 0..SCREEN_WIDTH / 2 {
-        print!("─");
-    }
-    println!("╯│\r");
+    // Draw the bottom border, with the delay timer's tick on it
+    let tick = border.delay_tick(width / 2);
+    let bottom: String = (0..width / 2).map(|column| if Some(column) == tick { '•' } else { '─' }).collect();
+    draw_edge(out, &border, &format!("│╰{}╯│", bottom))?;
+    writeln!(out, "\r")?;
 
     // Draw the keypad
     if !args.no_keypad {
-        let keypad = chip.get_keypad();
         // Draw the top border
-        print!("│");
-        for _ in 0..((SCREEN_WIDTH / 4) - 9) {
-            print!(" ");
+        draw_edge(out, &border, "│")?;
+        for _ in 0..((width / 4) - 9) {
+            write!(out, " ")?;
         }
-        print!("╭───╮╭───╮╭───╮╭───╮");
-        for _ in 0..((SCREEN_WIDTH / 4) - 9) {
-            print!(" ");
+        write!(out, "╭───╮╭───╮╭───╮╭───╮")?;
+        for _ in 0..((width / 4) - 9) {
+            write!(out, " ")?;
         }
-        println!("│\r");
+        draw_edge(out, &border, "│")?;
+        writeln!(out, "\r")?;
 
 
         for y in 0..4 {
-            print!("│");
-            for _ in 0..((SCREEN_WIDTH / 4) - 9) {
-                print!(" ");
+            draw_edge(out, &border, "│")?;
+            for _ in 0..((width / 4) - 9) {
+                write!(out, " ")?;
             }
             for x in 0..4 {
                 let key = KEY_ORDER[y * 4 + x];
                 let pressed = keypad[KEY_ORDER_HEX[y * 4 + x]];
 
-                print!("│");
+                write!(out, "│")?;
                 if pressed {
-                    print!("\x1b[7m");
+                    write!(out, "\x1b[7m")?;
                 }
 
-                print!(" {} ", key);
+                write!(out, " {} ", key)?;
 
                 if pressed {
-                    print!("\x1b[0m");
+                    write!(out, "\x1b[0m")?;
                 }
 
-                print!("│");
+                write!(out, "│")?;
             }
-            for _ in 0..((SCREEN_WIDTH / 4) - 9) {
-                print!(" ");
+            for _ in 0..((width / 4) - 9) {
+                write!(out, " ")?;
             }
-            println!("│\r");
+            draw_edge(out, &border, "│")?;
+            writeln!(out, "\r")?;
 
             // Draw the middle border
-            print!("│");
-            for _ in 0..((SCREEN_WIDTH / 4) - 9) {
-                print!(" ");
+            draw_edge(out, &border, "│")?;
+            for _ in 0..((width / 4) - 9) {
+                write!(out, " ")?;
             }
             if y < 3 {
-                print!("├───┤├───┤├───┤├───┤");
+                write!(out, "├───┤├───┤├───┤├───┤")?;
             } else {
-                print!("╰───╯╰───╯╰───╯╰───╯");
+                write!(out, "╰───╯╰───╯╰───╯╰───╯")?;
             }
-            for _ in 0..((SCREEN_WIDTH / 4) - 9) {
-                print!(" ");
+            for _ in 0..((width / 4) - 9) {
+                write!(out, " ")?;
             }
-            println!("│\r");
+            draw_edge(out, &border, "│")?;
+            writeln!(out, "\r")?;
         }
     }
 
     // Spacing
-    print!("│");
-    for _ in 0..((SCREEN_WIDTH / 2) + 2) {
-        print!(" ");
+    draw_edge(out, &border, "│")?;
+    for _ in 0..((width / 2) + 2) {
+        write!(out, " ")?;
     }
-    println!("│\r");
+    draw_edge(out, &border, "│")?;
+    writeln!(out, "\r")?;
 
     // Draw the outside border
-    print!("╰");
-    for _ in 0..((SCREEN_WIDTH / 2) + 2) {
-        print!("─");
+    draw_edge(out, &border, &format!("╰{}╯", "─".repeat((width / 2) + 2)))?;
+    writeln!(out, "\r")?;
+
+    return Ok(());
+}
+
+/// Draw part of the frame's border, in the border's color if it has one
+/// 
+/// ## Arguments
+/// 
+/// * `out` - Where to write it
+/// * `border` - The border being drawn
+/// * `text` - The part to draw
+fn draw_edge(out: &mut impl Write, border: &Border, text: &str) -> io::Result<()> {
+    return match border.color {
+        Some(color) => write!(out, "{}{}\x1b[0m", color, text),
+        None => write!(out, "{}", text),
+    };
+}
+
+
+/// Waits for the next event that isn't a key being let go, which only the keypad of the running ROM has a use for
+fn read_event() -> Event {
+    loop {
+        let event = event::read().expect("Failed to read line");
+        if !matches!(event, Event::Key(KeyEvent { kind: KeyEventKind::Release, .. })) {
+            return event;
+        }
     }
-    println!("╯\r");
 }
 
-/// Map a key to a button
+/// Draw the screen while the emulator is stopped, with the beep muted and a line saying why
 /// 
 /// ## Arguments
 /// 
-/// * `key` - The key to map
+/// * `chip8` - The emulator
+/// * `width` - Width of the screen in pixels
+/// * `colors` - The theme's colors, if there is one
+/// * `args` - The arguments passed to the program
+/// * `border` - What the border shows, which has the beep light off
+/// * `message` - Why the emulator is stopped
+fn draw_still(chip8: &Chip8, width: usize, colors: Option<&str>, args: &Args, border: Border, message: &str) -> io::Result<()> {
+    let mut compositor = Compositor::new();
+    let rows = compositor.draw(Plane::Screen, 1, 1, |out| draw(out, chip8.get_screen_buffer(), width, chip8.get_keypad(), Border { beeping: false, ..border }, colors, args))?;
+    compositor.add(Plane::Toast, rows + 1, 1, message);
+    let mut frame = Vec::new();
+    compositor.render(&mut frame)?;
+
+    let mut stdout = io::stdout();
+    stdout.write_all(&frame)?;
+    return stdout.flush();
+}
+
+/// Draw the frame with the debugger's panel beside it, while the debugger has the emulator stopped
+/// 
+/// ## Arguments
 /// 
-/// ## Returns
+/// * `chip8` - The emulator
+/// * `width` - Width of the screen in pixels
+/// * `colors` - The theme's colors, if there is one
+/// * `args` - The arguments passed to the program
+/// * `debugger` - The debugger, for the last instruction run
+fn draw_debugger(chip8: &Chip8, width: usize, colors: Option<&str>, args: &Args, debugger: &Debugger) -> io::Result<()> {
+    let mut compositor = Compositor::new();
+    compositor.draw(Plane::Screen, 1, 1, |out| draw(out, chip8.get_screen_buffer(), width, chip8.get_keypad(), Border::paused(), colors, args))?;
+    compositor.draw(Plane::Pane, 2, width / 2 + 6, |out| debugger.draw(out, chip8))?;
+    let mut frame = Vec::new();
+    compositor.render(&mut frame)?;
+
+    let mut stdout = io::stdout();
+    stdout.write_all(&frame)?;
+    return stdout.flush();
+}
+
+/// Draw the status line under the frame, naming the save state slot in use
+/// 
+/// ## Arguments
+/// 
+/// * `out` - Where to write the line
+/// * `locale` - The language to label it in
+/// * `slot` - The save state slot F5 and F9 use
+/// * `message` - What became of the last save or load, if there was one
+fn draw_status(out: &mut impl Write, locale: &Locale, slot: usize, message: Option<&str>) -> io::Result<()> {
+    let message = message.unwrap_or(locale.text(Text::SlotHelp));
+    return writeln!(out, "{} · {}\r", locale.format(Text::Slot, &[&slot]), message);
+}
+
+/// Draw the warnings pane under the frame, which is a single line until it is opened
+/// 
+/// ## Arguments
+/// 
+/// * `out` - Where to write the pane
+/// * `locale` - The language to label it in
+/// * `diagnostics` - Every warning so far, oldest first
+/// * `open` - Whether to list the latest warnings rather than just count them
+/// * `width` - Width of the screen in pixels, which the lines are cut to fit
+fn draw_diagnostics(out: &mut impl Write, locale: &Locale, diagnostics: &[String], open: bool, width: usize) -> io::Result<()> {
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+
+    let count = locale.format(if diagnostics.len() == 1 { Text::OneWarning } else { Text::Warnings }, &[&diagnostics.len()]);
+    if !open {
+        return writeln!(out, "▸ {} ({})\r", count, locale.text(Text::TabToShow));
+    }
+
+    writeln!(out, "▾ {} ({})\r", count, locale.text(Text::TabToHide))?;
+    for line in diagnostics.iter().skip(diagnostics.len().saturating_sub(DIAGNOSTICS_SHOWN)) {
+        let line: String = line.chars().take(width / 2 + 2).collect();
+        writeln!(out, "  {}\r", line)?;
+    }
+    return Ok(());
+}
+
+/// Save or load a state, and the slot to use
+enum StateAction {
+    Save(usize),
+    Load(usize),
+}
+
+/// Map a key to a save state action, if it is one
+/// 
+/// Shift+1 to Shift+4 save to a slot and Alt+1 to Alt+4 load from one, while F5 and F9 save and load the slot last used.
+/// Terminals without the kitty keyboard protocol send Shift+1 to Shift+4 as the symbols above them on a US keyboard.
 /// 
-/// The button that the key maps to, or None if the key does not map to a button
-fn map_key_to_button(key: KeyCode) -> Option<usize> {
+/// ## Arguments
+/// 
+/// * `key` - The key pressed
+/// * `modifiers` - The modifiers held with it
+/// * `slot` - The slot last used
+fn state_hotkey(key: KeyCode, modifiers: KeyModifiers, slot: usize) -> Option<StateAction> {
     return match key {
-        KeyCode::Char('1') => Some(0x1),
-        KeyCode::Char('2') => Some(0x2),
-        KeyCode::Char('3') => Some(0x3),
-        KeyCode::Char('4') => Some(0xC),
-        KeyCode::Char('q') => Some(0x4),
-        KeyCode::Char('w') => Some(0x5),
-        KeyCode::Char('e') => Some(0x6),
-        KeyCode::Char('r') => Some(0xD),
-        KeyCode::Char('a') => Some(0x7),
-        KeyCode::Char('s') => Some(0x8),
-        KeyCode::Char('d') => Some(0x9),
-        KeyCode::Char('f') => Some(0xE),
-        KeyCode::Char('z') => Some(0xA),
-        KeyCode::Char('x') => Some(0x0),
-        KeyCode::Char('c') => Some(0xB),
-        KeyCode::Char('v') => Some(0xF),
+        KeyCode::F(5) => Some(StateAction::Save(slot)),
+        KeyCode::F(9) => Some(StateAction::Load(slot)),
+        KeyCode::Char(c @ '1'..='4') if modifiers.contains(KeyModifiers::ALT) => Some(StateAction::Load(c as usize - '0' as usize)),
+        KeyCode::Char(c @ '1'..='4') if modifiers.contains(KeyModifiers::SHIFT) => Some(StateAction::Save(c as usize - '0' as usize)),
+        KeyCode::Char('!') => Some(StateAction::Save(1)),
+        KeyCode::Char('@') => Some(StateAction::Save(2)),
+        KeyCode::Char('#') => Some(StateAction::Save(3)),
+        KeyCode::Char('$') => Some(StateAction::Save(4)),
         _ => None,
     };
 }
+
+/// Where a save state slot is kept
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+/// * `rom_path` - The ROM the state belongs to
+/// * `slot` - The slot, from 1 to 4
+fn state_path(args: &Args, rom_path: &str, slot: usize) -> PathBuf {
+    let rom_path = Path::new(rom_path);
+    let Some(dir) = args.state_dir.as_deref() else {
+        return PathBuf::from(format!("{}.{}.state", rom_path.display(), slot));
+    };
+    let name = rom_path.file_name().unwrap_or_default();
+    return Path::new(dir).join(name).join(format!("{}.state", slot));
+}
+
+/// The format --state-format asks save states to be written in
+fn state_format(args: &Args) -> Format {
+    return Format::from_name(&args.state_format).expect("Clap only allows known formats");
+}
+
+/// Encode the emulator's state for a file, compressing it when it is binary
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator
+/// * `format` - Binary or JSON
+fn encode_state(chip8: &Chip8, format: Format) -> Result<Vec<u8>, String> {
+    let state = chip8.save_state_as(format)?;
+    return Ok(if format == Format::Binary { compress(&state) } else { state });
+}
+
+/// Save the emulator's state to a slot
+/// 
+/// ## Returns
+/// 
+/// A message saying where the state went or why it couldn't be saved
+fn save_state_slot(chip8: &Chip8, args: &Args, locale: &Locale, rom_path: &str, slot: usize) -> String {
+    let path = state_path(args, rom_path, slot);
+    let saved = encode_state(chip8, state_format(args)).and_then(|state| {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        return std::fs::write(&path, state).map_err(|e| e.to_string());
+    });
+    return match saved {
+        Ok(()) => locale.format(Text::Saved, &[&path.display()]),
+        Err(e) => locale.format(Text::SaveFailed, &[&e]),
+    };
+}
+
+/// Load the emulator's state from a slot
+/// 
+/// ## Returns
+/// 
+/// A message saying where the state came from or why it couldn't be loaded
+fn load_state_slot(chip8: &mut Chip8, args: &Args, locale: &Locale, rom_path: &str, slot: usize) -> String {
+    let path = state_path(args, rom_path, slot);
+    return match std::fs::read(&path).map_err(|e| e.to_string()).and_then(|state| chip8.load_state(&state)) {
+        Ok(()) => locale.format(Text::Loaded, &[&path.display()]),
+        Err(e) => locale.format(Text::LoadFailed, &[&slot, &e]),
+    };
+}
+
+/// Pick the language of the terminal UI, from --lang or else the environment
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+fn create_locale(args: &Args) -> Locale {
+    let language = args.lang.as_deref().map_or_else(Language::from_env, Language::from_locale);
+    return Locale::new(language);
+}
+
+/// Check the terminal, asking the player whether the test pattern looks right, and print the report
+/// 
+/// Exits with 1 if anything found stops the emulator working.
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program, whose keys are checked for clashes
+/// * `output` - A file to write the report to as well, if there is one
+fn run_doctor(args: &Args, output: Option<&str>) {
+    let interactive = io::stdin().is_terminal() && io::stdout().is_terminal();
+    let caps = TermCaps::probe();
+
+    let size = match terminal::size() {
+        Ok((columns, rows)) => check_size(columns, rows),
+        Err(e) => Check::new(Status::Skipped, "Terminal size", format!("couldn't be found: {}", e)),
+    };
+
+    // Braille is what the screen is drawn with, and half blocks what some fonts fall back to for it
+    let looks_right = interactive.then(|| {
+        println!("  ⣿⣿⣿⣿⣿⣿⣿⣿⣿⣿⣿⣿");
+        println!("  ▀▀▀▀▀▀▀▀▀▀▀▀");
+        println!("  ⡇⢸⡇⢸⡇⢸⡇⢸⡇⢸⡇⢸");
+        print!("Are these three rows the same width, the first solid, the second the top half of a bar and the third evenly striped? [y/n] ");
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        let _ = io::stdin().read_line(&mut answer);
+        println!();
+        return answer.trim().eq_ignore_ascii_case("y");
+    });
+
+    let raw_mode = match io::stdin().is_terminal() {
+        true => check_raw_mode(terminal::enable_raw_mode().and_then(|_| terminal::disable_raw_mode()).map_err(|e| e.to_string())),
+        false => Check::new(Status::Skipped, "Raw mode", String::from("stdin isn't a terminal")),
+    };
+
+    // While the debugger has the emulator stopped, its keys drive it rather than the keypad
+    let hotkeys = if args.debug { "nfcwbh12345" } else { "" };
+    let keymap = check_keymap(create_keymap(args, &caps).map(|keymap| keymap.conflicts(hotkeys, "while it is stopped, the debugger takes")));
+
+    let colorterm = std::env::var("COLORTERM").ok();
+    let term = std::env::var("TERM").ok();
+    let checks = vec![
+        size,
+        check_unicode(&caps, looks_right),
+        check_colors(term.as_deref(), colorterm.as_deref()),
+        raw_mode,
+        check_audio(),
+        keymap,
+    ];
+    let report = Report { caps, checks };
+
+    print!("{}", report);
+    if let Some(path) = output {
+        if let Err(e) = std::fs::write(path, report.to_string()) {
+            println!("Failed to write {}: {}", path, e);
+        }
+    }
+    if report.failed() {
+        std::process::exit(1);
+    }
+}
+
+/// Lay the keypad out on the keyboard for one player, or two with --player2
+/// 
+/// ## Arguments
+/// 
+/// * `args` - The arguments passed to the program
+/// * `caps` - What the terminal can do, since only the kitty keyboard protocol tells the numpad apart
+fn create_keymap(args: &Args, caps: &TermCaps) -> Result<Keymap, String> {
+    let mut keymap = Keymap::new();
+    keymap.add_player(Layout::Main, args.player1_keys.as_deref())?;
+    if let Some(name) = args.player2.as_deref() {
+        let layout = Layout::from_name(name)?;
+        if layout == Layout::Numpad && !caps.kitty_keyboard {
+            return Err(String::from("This terminal can't tell the numpad apart from the number row, which needs the kitty keyboard protocol. Try --player2 ijkl instead."));
+        }
+        keymap.add_player(layout, args.player2_keys.as_deref())?;
+    }
+    return Ok(keymap);
+}
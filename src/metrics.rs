@@ -0,0 +1,70 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Counters shared between the emulation loop and the metrics listener
+#[derive(Default)]
+pub struct Metrics {
+    pub frames: AtomicU64,
+    pub instructions: AtomicU64,
+    pub faults: AtomicU64,
+    pub input_events: AtomicU64,
+}
+
+impl Metrics {
+    /// Renders the counters in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut str_buffer = String::new();
+        let counters = [
+            ("chip8_frames_total", "Frames rendered", &self.frames),
+            ("chip8_instructions_total", "Instructions executed", &self.instructions),
+            ("chip8_faults_total", "Unknown opcodes encountered", &self.faults),
+            ("chip8_input_events_total", "Keypad input events received", &self.input_events),
+        ];
+
+        for (name, help, value) in counters {
+            str_buffer.push_str(&format!("# HELP {} {}\n", name, help));
+            str_buffer.push_str(&format!("# TYPE {} counter\n", name));
+            str_buffer.push_str(&format!("{} {}\n", name, value.load(Ordering::Relaxed)));
+        }
+
+        return str_buffer;
+    }
+}
+
+/// Starts serving the metrics over HTTP on a background thread, so scrapes never block emulation
+/// 
+/// ## Arguments
+/// 
+/// * `addr` - The address to listen on, e.g. `127.0.0.1:9184`
+/// * `metrics` - The counters to report
+pub fn serve(addr: &str, metrics: Arc<Metrics>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A misbehaving client only loses its own response
+            let _ = respond(stream, &metrics);
+        }
+    });
+
+    return Ok(());
+}
+
+/// Answers a single request with the current metrics, whatever path was asked for
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request)?;
+
+    let body = metrics.render();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+
+    return stream.flush();
+}
@@ -0,0 +1,720 @@
+use crate::chip8::{assemble_with_warnings, trace_code, AssembleError, AssembleWarning};
+use crate::listing::{Listing, ListingLine};
+use std::collections::{BTreeMap, HashSet};
+
+const PROGRAM_START: usize = 0x200;
+
+/// Exports a Chip-8 program as Octo source
+/// 
+/// Code reachable from the entry point is translated into Octo statements, and everything else is emitted as `:byte` data.
+/// Jump, call and `i :=` targets inside the program are given labels, so the output can be edited and reassembled with Octo.
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program to export, as a byte array
+pub fn export_octo(program: &[u8]) -> String {
+    let starts = trace_code(program);
+    let labels = collect_labels(program, &starts);
+
+    let mut str_buffer = String::new();
+    str_buffer.push_str("# Exported by the chip8 disassembler\n\n");
+
+    let mut i = 0;
+    while i < program.len() {
+        if let Some(label) = labels.get(&(PROGRAM_START + i)) {
+            str_buffer.push_str(&format!(": {}\n", label));
+        }
+
+        // An instruction can only be emitted whole, so a label pointing into its second byte forces it out as data
+        let whole_instruction = starts[i] && i + 1 < program.len() && !labels.contains_key(&(PROGRAM_START + i + 1));
+        if whole_instruction {
+            let opcode = (program[i] as u16) << 8 | program[i + 1] as u16;
+            str_buffer.push_str(&format!("\t{}\n", translate(opcode, &labels)));
+            i += 2;
+            continue;
+        }
+
+        // Group data bytes until the next label, instruction, or 8 bytes
+        str_buffer.push_str(&format!("\t:byte 0x{:02X}", program[i]));
+        i += 1;
+        let mut count = 1;
+        while i < program.len() && count < 8 && !starts[i] && !labels.contains_key(&(PROGRAM_START + i)) {
+            str_buffer.push_str(&format!(" 0x{:02X}", program[i]));
+            i += 1;
+            count += 1;
+        }
+        str_buffer.push('\n');
+    }
+
+    return str_buffer;
+}
+
+/// Names every address inside the program that is referenced by a jump, call or `i :=`
+fn collect_labels(program: &[u8], starts: &[bool]) -> BTreeMap<usize, String> {
+    let mut labels = BTreeMap::new();
+    labels.insert(PROGRAM_START, String::from("main"));
+
+    for i in 0..program.len().saturating_sub(1) {
+        if !starts[i] {
+            continue;
+        }
+
+        let opcode = (program[i] as u16) << 8 | program[i + 1] as u16;
+        let target = (opcode & 0x0FFF) as usize;
+        let in_program = target >= PROGRAM_START && target < PROGRAM_START + program.len();
+
+        if matches!((opcode & 0xF000) >> 12, 1 | 2 | 0xA | 0xB) && in_program {
+            labels.entry(target).or_insert_with(|| format!("L_{:04X}", target));
+        }
+    }
+
+    return labels;
+}
+
+/// Translates a single opcode into an Octo statement
+fn translate(opcode: u16, labels: &BTreeMap<usize, String>) -> String {
+    let hex1 = (opcode & 0xF000) >> 12;
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let byte = opcode & 0x00FF;
+    let addr = (opcode & 0x0FFF) as usize;
+
+    let target = match labels.get(&addr) {
+        Some(label) => label.clone(),
+        None => format!("0x{:03X}", addr),
+    };
+
+    return match (hex1, x, y, n) {
+        (0, 0, 0xE, 0) => String::from("clear"),
+        (0, 0, 0xE, 0xE) => String::from("return"),
+        (0, 0, 0xC, _) => format!("scroll-down {}", n),
+        (0, 0, 0xF, 0xB) => String::from("scroll-right"),
+        (0, 0, 0xF, 0xC) => String::from("scroll-left"),
+        (0, 0, 0xF, 0xD) => String::from("exit"),
+        (0, 0, 0xF, 0xE) => String::from("lores"),
+        (0, 0, 0xF, 0xF) => String::from("hires"),
+        (1, _, _, _) => format!("jump {}", target),
+        (2, _, _, _) => format!(":call {}", target),
+
+        // Octo conditionals name the condition under which the next statement runs, the opposite of the skip
+        (3, _, _, _) => format!("if v{:x} != 0x{:02X} then", x, byte),
+        (4, _, _, _) => format!("if v{:x} == 0x{:02X} then", x, byte),
+        (5, _, _, 0) => format!("if v{:x} != v{:x} then", x, y),
+        (6, _, _, _) => format!("v{:x} := 0x{:02X}", x, byte),
+        (7, _, _, _) => format!("v{:x} += 0x{:02X}", x, byte),
+        (8, _, _, 0) => format!("v{:x} := v{:x}", x, y),
+        (8, _, _, 1) => format!("v{:x} |= v{:x}", x, y),
+        (8, _, _, 2) => format!("v{:x} &= v{:x}", x, y),
+        (8, _, _, 3) => format!("v{:x} ^= v{:x}", x, y),
+        (8, _, _, 4) => format!("v{:x} += v{:x}", x, y),
+        (8, _, _, 5) => format!("v{:x} -= v{:x}", x, y),
+        (8, _, _, 6) => format!("v{:x} >>= v{:x}", x, y),
+        (8, _, _, 7) => format!("v{:x} =- v{:x}", x, y),
+        (8, _, _, 0xE) => format!("v{:x} <<= v{:x}", x, y),
+        (9, _, _, 0) => format!("if v{:x} == v{:x} then", x, y),
+        (0xA, _, _, _) => format!("i := {}", target),
+        (0xB, _, _, _) => format!("jump0 {}", target),
+        (0xC, _, _, _) => format!("v{:x} := random 0x{:02X}", x, byte),
+        (0xD, _, _, _) => format!("sprite v{:x} v{:x} {}", x, y, n),
+        (0xE, _, 9, 0xE) => format!("if v{:x} -key then", x),
+        (0xE, _, 0xA, 1) => format!("if v{:x} key then", x),
+        (0xF, 0, 0, 2) => String::from("audio"),
+        (0xF, _, 0, 7) => format!("v{:x} := delay", x),
+        (0xF, _, 0, 0xA) => format!("v{:x} := key", x),
+        (0xF, _, 1, 5) => format!("delay := v{:x}", x),
+        (0xF, _, 1, 8) => format!("buzzer := v{:x}", x),
+        (0xF, _, 1, 0xE) => format!("i += v{:x}", x),
+        (0xF, _, 2, 9) => format!("i := hex v{:x}", x),
+        (0xF, _, 3, 0) => format!("i := bighex v{:x}", x),
+        (0xF, _, 3, 3) => format!("bcd v{:x}", x),
+        (0xF, _, 3, 0xA) => format!("pitch := v{:x}", x),
+        (0xF, _, 5, 5) => format!("save v{:x}", x),
+        (0xF, _, 6, 5) => format!("load v{:x}", x),
+        (0xF, _, 7, 5) => format!("saveflags v{:x}", x),
+        (0xF, _, 8, 5) => format!("loadflags v{:x}", x),
+
+        // Anything Octo has no statement for is kept verbatim
+        (_, _, _, _) => format!(":byte 0x{:02X} 0x{:02X}", opcode >> 8, opcode & 0xFF),
+    };
+}
+
+// Octo source is assembled by translating it into the assembler's own syntax, so both dialects share one assembler with
+// its labels, warnings and errors. Octo is free-form rather than one statement to a line, so each translated line
+// remembers the Octo line it came from. Structured control flow becomes skips and jumps to generated labels:
+//   loop                       __LOOP_1:
+//     v0 += 1                  ADD V0 1
+//     while v0 != 10           SNE V0 10
+//                              JP __DONE_1
+//   again                      JP __LOOP_1
+//                              __DONE_1:
+// Octo programs start at main, so a jump to it is put at the start unless main is there already.
+
+/// Directives of Octo's that this assembler can't translate, to tell them apart from labels written `:name`
+const UNSUPPORTED_DIRECTIVES: [&str; 8] = [":next", ":unpack", ":macro", ":calc", ":stringmode", ":assert", ":pointer", ":proto"];
+
+/// An Octo program translated into the assembler's own syntax
+pub struct OctoTranslation {
+    /// The program, ready for `assemble_with_warnings` or `assemble_relocatable`
+    pub source: String,
+    /// The Octo line each line of `source` came from
+    lines: Vec<usize>,
+    /// The line of `source` defining main when it starts the program, which is never referenced as nothing jumps there
+    entry: Option<usize>,
+}
+
+impl OctoTranslation {
+    /// Points errors from assembling the translation at the Octo lines they came from
+    pub fn errors(&self, errors: Vec<AssembleError>) -> Vec<AssembleError> {
+        return errors.into_iter().map(|error| AssembleError { line: self.octo_line(error.line), ..error }).collect();
+    }
+
+    /// Points warnings from assembling the translation at the Octo lines they came from
+    pub fn warnings(&self, warnings: Vec<AssembleWarning>) -> Vec<AssembleWarning> {
+        return warnings
+            .into_iter()
+            .filter(|warning| Some(warning.line) != self.entry)
+            .map(|warning| AssembleWarning { line: self.octo_line(warning.line), ..warning })
+            .collect();
+    }
+
+    /// Turns the listing of the translation into one of the Octo source, leaving out the labels made up for it
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `listing` - The listing from assembling the translation
+    /// * `octo_source` - The Octo source it was translated from
+    pub fn listing(&self, listing: Listing, octo_source: &str) -> Listing {
+        let octo_lines: Vec<&str> = octo_source.lines().collect();
+        let mut lines: Vec<ListingLine> = Vec::new();
+        for listed in listing.lines {
+            let line = self.octo_line(listed.line);
+            // The lines an Octo line became are listed as one, as long as their bytes follow on
+            match lines.last_mut() {
+                Some(last) if last.line == line && last.addr as usize + last.bytes.len() == listed.addr as usize => last.bytes.extend(listed.bytes),
+                _ => lines.push(ListingLine { line, text: octo_lines.get(line - 1).unwrap_or(&"").to_string(), ..listed }),
+            }
+        }
+        let symbols = listing.symbols.into_iter().filter(|(name, _)| !name.starts_with("__")).collect();
+        return Listing { lines, symbols };
+    }
+
+    fn octo_line(&self, line: usize) -> usize {
+        return self.lines.get(line.wrapping_sub(1)).copied().unwrap_or(line);
+    }
+}
+
+/// Assembles a program written in Octo's syntax into machine code
+/// 
+/// ## Arguments
+/// 
+/// * `source` - The Octo source
+/// 
+/// ## Returns
+/// 
+/// The machine code and any warnings about the source, or every mistake that stopped it from being assembled
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8::octo::assemble_octo;
+/// 
+/// let (rom, _) = assemble_octo(": main\n  v0 := 5\n  loop\n    v0 += -1\n    if v0 != 0 then\n  again").unwrap();
+/// assert_eq!(rom, [0x60, 0x05, 0x70, 0xFF, 0x30, 0x00, 0x12, 0x02]);
+/// ```
+pub fn assemble_octo(source: &str) -> Result<(Vec<u8>, Vec<AssembleWarning>), Vec<AssembleError>> {
+    let translation = translate_octo(source)?;
+    return match assemble_with_warnings(&translation.source) {
+        Ok((bytes, warnings)) => Ok((bytes, translation.warnings(warnings))),
+        Err(errors) => Err(translation.errors(errors)),
+    };
+}
+
+/// Translates a program written in Octo's syntax into the assembler's own
+/// 
+/// Covers Octo's statements for the Chip-8 and SUPER-CHIP, `: label` and `:label`, `:const`, `:alias`, `:call`, `:byte`,
+/// `:org`, `if ... then`, `if ... begin ... else ... end` and `loop ... while ... again`. Macros, `:calc` and the
+/// XO-CHIP's extras are reported as errors.
+/// 
+/// ## Arguments
+/// 
+/// * `source` - The Octo source
+pub fn translate_octo(source: &str) -> Result<OctoTranslation, Vec<AssembleError>> {
+    let tokens = source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| line.split('#').next().unwrap_or_default().split_whitespace().map(move |token| (token, index + 1)))
+        .collect();
+    let mut translator = Translator { tokens, next: 0, output: Vec::new(), errors: Vec::new(), blocks: Vec::new(), aliases: HashSet::new(), constants: HashSet::new(), generated: 0, started: false, main: None, entry: None };
+
+    while translator.next < translator.tokens.len() {
+        translator.statement();
+    }
+    return translator.finish();
+}
+
+/// A block of Octo's structured control flow that hasn't been closed yet
+enum Block {
+    /// `if ... begin`, with whether `else` has been seen
+    If { id: usize, line: usize, has_else: bool },
+    /// `loop`, with whether a `while` jumps out of it
+    Loop { id: usize, line: usize, has_exit: bool },
+}
+
+/// Works through Octo's tokens a statement at a time
+struct Translator<'a> {
+    /// Every token with the line it is on
+    tokens: Vec<(&'a str, usize)>,
+    next: usize,
+    /// Translated lines with the Octo line they came from
+    output: Vec<(String, usize)>,
+    errors: Vec<AssembleError>,
+    blocks: Vec<Block>,
+    /// Names given to registers by `:alias`, uppercased
+    aliases: HashSet<String>,
+    /// Names given to numbers by `:const`, uppercased
+    constants: HashSet<String>,
+    /// Number of blocks so far, to name their labels
+    generated: usize,
+    /// Whether anything taking up memory has been translated yet
+    started: bool,
+    /// The line main is defined on
+    main: Option<usize>,
+    /// Index in `output` of main's label when it starts the program
+    entry: Option<usize>,
+}
+
+impl<'a> Translator<'a> {
+    fn statement(&mut self) {
+        let (token, line) = self.tokens[self.next];
+        self.next += 1;
+
+        match token {
+            ":" => {
+                if let Some(name) = self.operand(token, line) {
+                    self.label(name, line);
+                }
+            },
+            ":const" => {
+                if let (Some(name), Some(value)) = (self.operand(token, line), self.operand(token, line)) {
+                    self.constants.insert(name.to_uppercase());
+                    self.declare(format!("{} EQU {}", name.to_uppercase(), number(value)), line);
+                }
+            },
+            ":alias" => {
+                if let (Some(name), Some(x)) = (self.operand(token, line), self.register(token, line)) {
+                    self.aliases.insert(name.to_uppercase());
+                    self.declare(format!("ALIAS {} {}", name.to_uppercase(), x), line);
+                }
+            },
+            ":call" => self.with_operand(token, line, |name| format!("CALL {}", number(name))),
+            ":byte" => self.with_operand(token, line, |value| format!(".DB {}", byte(value))),
+            ":org" => self.with_operand(token, line, |addr| format!(".ORG {}", number(addr))),
+            // Debugger hints have nothing to assemble
+            ":breakpoint" => self.next += 1,
+            ":monitor" => self.next += 2,
+
+            "clear" => self.emit(String::from("CLS"), line),
+            "return" | ";" => self.emit(String::from("RET"), line),
+            "exit" => self.emit(String::from("EXIT"), line),
+            "lores" => self.emit(String::from("LOW"), line),
+            "hires" => self.emit(String::from("HIGH"), line),
+            "scroll-left" => self.emit(String::from("SCL"), line),
+            "scroll-right" => self.emit(String::from("SCR"), line),
+            "scroll-down" => self.with_operand(token, line, |n| format!("SCD {}", number(n))),
+            "audio" => self.emit(String::from("AUDIO"), line),
+            "jump" => self.with_operand(token, line, |addr| format!("JP {}", number(addr))),
+            "jump0" => self.with_operand(token, line, |addr| format!("JP V0 {}", number(addr))),
+            "sprite" => {
+                if let (Some(x), Some(y), Some(n)) = (self.register(token, line), self.register(token, line), self.operand(token, line)) {
+                    self.emit(format!("DRW {} {} {}", x, y, number(n)), line);
+                }
+            },
+            "bcd" | "save" | "load" | "saveflags" | "loadflags" => {
+                let Some(x) = self.register(token, line) else {
+                    return;
+                };
+                if self.peek() == Some("-") {
+                    self.error(line, "-", "ranges of registers are XO-CHIP only, which this assembler can't build");
+                    self.next += 2;
+                    return;
+                }
+                let text = match token {
+                    "bcd" => format!("LD B {}", x),
+                    "save" => format!("LD [I] {}", x),
+                    "load" => format!("LD {} [I]", x),
+                    "saveflags" => format!("LD R {}", x),
+                    _ => format!("LD {} R", x),
+                };
+                self.emit(text, line);
+            },
+            "delay" | "buzzer" | "pitch" => {
+                if self.expect(":=", line) {
+                    if let Some(x) = self.register(token, line) {
+                        let target = match token { "delay" => "LD DT", "buzzer" => "LD ST", _ => "PITCH" };
+                        self.emit(format!("{} {}", target, x), line);
+                    }
+                }
+            },
+            "i" => self.index(line),
+            "if" => self.conditional(line),
+
+            "else" => match self.blocks.last_mut() {
+                Some(Block::If { id, has_else: has_else @ false, .. }) => {
+                    *has_else = true;
+                    let id = *id;
+                    self.emit(format!("JP __END_{}", id), line);
+                    self.declare(format!("__ELSE_{}:", id), line);
+                },
+                _ => self.error(line, token, "isn't inside an if ... begin"),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { id, has_else, .. }) => self.declare(format!("__{}_{}:", if has_else { "END" } else { "ELSE" }, id), line),
+                other => {
+                    self.blocks.extend(other);
+                    self.error(line, token, "isn't closing an if ... begin");
+                },
+            },
+            "loop" => {
+                self.generated += 1;
+                self.blocks.push(Block::Loop { id: self.generated, line, has_exit: false });
+                self.declare(format!("__LOOP_{}:", self.generated), line);
+            },
+            "while" => self.exit_condition(line),
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { id, has_exit, .. }) => {
+                    self.emit(format!("JP __LOOP_{}", id), line);
+                    if has_exit {
+                        self.declare(format!("__DONE_{}:", id), line);
+                    }
+                },
+                other => {
+                    self.blocks.extend(other);
+                    self.error(line, token, "isn't closing a loop");
+                },
+            },
+
+            _ if self.is_register(token) => self.assignment(token, line),
+            _ if UNSUPPORTED_DIRECTIVES.contains(&token) => self.error(line, token, "isn't supported by this assembler"),
+            _ if token.len() > 1 && token.starts_with(':') => self.label(&token[1..], line),
+            // Numbers on their own are data, and names on their own are calls
+            _ if parse_octo_number(token).is_some() || self.constants.contains(&token.to_uppercase()) => self.emit(format!(".DB {}", byte(token)), line),
+            _ => self.emit(format!("CALL {}", token.to_uppercase()), line),
+        }
+    }
+
+    /// `vx := ...` and the other operators that change a register
+    fn assignment(&mut self, x: &str, line: usize) {
+        let x = x.to_uppercase();
+        let Some(operator) = self.operand(&x, line) else {
+            return;
+        };
+        let Some(value) = self.operand(operator, line) else {
+            return;
+        };
+        let y = value.to_uppercase();
+        let from_register = self.is_register(value);
+
+        let text = match (operator, from_register) {
+            (":=", true) => format!("LD {} {}", x, y),
+            (":=", false) if value == "random" => match self.operand(value, line) {
+                Some(mask) => format!("RND {} {}", x, byte(mask)),
+                None => return,
+            },
+            (":=", false) if value == "delay" => format!("LD {} DT", x),
+            (":=", false) if value == "key" => format!("LD {} K", x),
+            (":=", false) => format!("LD {} {}", x, byte(value)),
+            ("+=", true) => format!("ADD {} {}", x, y),
+            ("+=", false) => format!("ADD {} {}", x, byte(value)),
+            ("-=", true) => format!("SUB {} {}", x, y),
+            // Taking a number away is adding its negative, which only a number written out can be turned into
+            ("-=", false) => match parse_octo_number(value) {
+                Some(n) => format!("ADD {} {}", x, n.wrapping_neg() & 0xFF),
+                None => return self.error(line, value, "can only be taken away when written as a number"),
+            },
+            ("|=", true) => format!("OR {} {}", x, y),
+            ("&=", true) => format!("AND {} {}", x, y),
+            ("^=", true) => format!("XOR {} {}", x, y),
+            ("=-", true) => format!("SUBN {} {}", x, y),
+            (">>=", true) => format!("SHR {} {}", x, y),
+            ("<<=", true) => format!("SHL {} {}", x, y),
+            ("|=" | "&=" | "^=" | "=-" | ">>=" | "<<=", false) => return self.error(line, value, "needs to be a register"),
+            _ => return self.error(line, operator, "isn't something that can be done to a register"),
+        };
+        self.emit(text, line);
+    }
+
+    /// `i := ...` and `i += vx`
+    fn index(&mut self, line: usize) {
+        let Some(operator) = self.operand("i", line) else {
+            return;
+        };
+        match operator {
+            ":=" => match self.operand(operator, line) {
+                Some("hex") => self.with_register("hex", line, |x| format!("LD F {}", x)),
+                Some("bighex") => self.with_register("bighex", line, |x| format!("LD HF {}", x)),
+                Some("long") => self.error(line, "long", "is XO-CHIP only, which this assembler can't build"),
+                Some(addr) => self.emit(format!("LD I {}", number(addr)), line),
+                None => {},
+            },
+            "+=" => self.with_register(operator, line, |x| format!("ADD I {}", x)),
+            _ => self.error(line, operator, "isn't something that can be done to i"),
+        }
+    }
+
+    /// `if ... then`, which skips the next statement unless the condition holds, and `if ... begin`
+    fn conditional(&mut self, line: usize) {
+        let Some((when_true, when_false)) = self.condition("if", line) else {
+            return;
+        };
+        match self.operand("if", line) {
+            Some("then") => self.emit(when_false, line),
+            Some("begin") => {
+                self.generated += 1;
+                self.blocks.push(Block::If { id: self.generated, line, has_else: false });
+                self.emit(when_true, line);
+                self.emit(format!("JP __ELSE_{}", self.generated), line);
+            },
+            Some(token) => self.error(line, token, "should be then or begin"),
+            None => {},
+        }
+    }
+
+    /// `while`, which leaves the loop it is in unless the condition holds
+    fn exit_condition(&mut self, line: usize) {
+        let Some((when_true, _)) = self.condition("while", line) else {
+            return;
+        };
+        let Some(Block::Loop { id, has_exit, .. }) = self.blocks.iter_mut().rev().find(|block| matches!(block, Block::Loop { .. })) else {
+            return self.error(line, "while", "isn't inside a loop");
+        };
+        *has_exit = true;
+        let id = *id;
+        self.emit(when_true, line);
+        self.emit(format!("JP __DONE_{}", id), line);
+    }
+
+    /// Reads a condition, returning the instructions that skip when it holds and when it doesn't
+    fn condition(&mut self, keyword: &str, line: usize) -> Option<(String, String)> {
+        let x = self.register(keyword, line)?;
+        let test = self.operand(keyword, line)?;
+        let (skip_equal, skip_not_equal) = match test {
+            "key" => return Some((format!("SKP {}", x), format!("SKNP {}", x))),
+            "-key" => return Some((format!("SKNP {}", x), format!("SKP {}", x))),
+            "==" | "!=" => ("SE", "SNE"),
+            "<" | ">" | "<=" | ">=" => {
+                self.error(line, test, "isn't supported by this assembler, only == != key and -key are");
+                return None;
+            },
+            _ => {
+                self.error(line, test, "isn't a comparison");
+                return None;
+            },
+        };
+
+        let value = self.operand(test, line)?;
+        let value = if self.is_register(value) { value.to_uppercase() } else { byte(value) };
+        let skip_equal = format!("{} {} {}", skip_equal, x, value);
+        let skip_not_equal = format!("{} {} {}", skip_not_equal, x, value);
+        return Some(if test == "==" { (skip_equal, skip_not_equal) } else { (skip_not_equal, skip_equal) });
+    }
+
+    fn label(&mut self, name: &str, line: usize) {
+        if name == "main" {
+            self.main = Some(line);
+            if !self.started {
+                self.entry = Some(self.output.len());
+            }
+        }
+        self.declare(format!("{}:", name.to_uppercase()), line);
+    }
+
+    /// Closes up, putting the jump to main at the start if it needs one
+    fn finish(mut self) -> Result<OctoTranslation, Vec<AssembleError>> {
+        for block in self.blocks.iter() {
+            match block {
+                Block::If { line, .. } => self.errors.push(AssembleError::new(*line, "IF", String::from("is never closed with end"))),
+                Block::Loop { line, .. } => self.errors.push(AssembleError::new(*line, "LOOP", String::from("is never closed with again"))),
+            }
+        }
+        match (self.main, self.entry) {
+            (None, _) => self.errors.push(AssembleError::new(1, "MAIN", String::from("isn't defined, but Octo programs start there"))),
+            (Some(line), None) => self.output.insert(0, (String::from("JP MAIN"), line)),
+            (Some(_), Some(_)) => {},
+        }
+        if !self.errors.is_empty() {
+            self.errors.sort_by_key(|error| error.line);
+            return Err(self.errors);
+        }
+
+        let source = self.output.iter().map(|(text, _)| text.as_str()).collect::<Vec<_>>().join("\n");
+        let lines = self.output.iter().map(|(_, line)| *line).collect();
+        return Ok(OctoTranslation { source, lines, entry: self.entry.map(|index| index + 1) });
+    }
+
+    /// Adds a line that takes up memory
+    fn emit(&mut self, text: String, line: usize) {
+        self.started = true;
+        self.output.push((text, line));
+    }
+
+    /// Adds a line that names something without taking up memory
+    fn declare(&mut self, text: String, line: usize) {
+        self.output.push((text, line));
+    }
+
+    fn error(&mut self, line: usize, token: &str, message: &str) {
+        self.errors.push(AssembleError::new(line, &token.to_uppercase(), String::from(message)));
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        return self.tokens.get(self.next).map(|(token, _)| *token);
+    }
+
+    /// Takes the next token as an operand of `owner`, reporting it missing if the source has run out
+    fn operand(&mut self, owner: &str, line: usize) -> Option<&'a str> {
+        let Some(token) = self.peek() else {
+            self.error(line, owner, "is missing an operand");
+            return None;
+        };
+        self.next += 1;
+        return Some(token);
+    }
+
+    /// Takes the next token as a register operand of `owner`, uppercased
+    fn register(&mut self, owner: &str, line: usize) -> Option<String> {
+        let token = self.operand(owner, line)?;
+        if !self.is_register(token) {
+            self.error(line, token, "needs to be a register");
+            return None;
+        }
+        return Some(token.to_uppercase());
+    }
+
+    fn expect(&mut self, expected: &str, line: usize) -> bool {
+        return match self.operand(expected, line) {
+            Some(token) if token == expected => true,
+            Some(token) => {
+                self.error(line, token, &format!("should be {}", expected));
+                false
+            },
+            None => false,
+        };
+    }
+
+    fn with_operand(&mut self, owner: &str, line: usize, translate: impl Fn(&str) -> String) {
+        if let Some(token) = self.operand(owner, line) {
+            self.emit(translate(token), line);
+        }
+    }
+
+    fn with_register(&mut self, owner: &str, line: usize, translate: impl Fn(&str) -> String) {
+        if let Some(x) = self.register(owner, line) {
+            self.emit(translate(&x), line);
+        }
+    }
+
+    /// Whether a token is a register, `v0` to `vf` or a name given to one
+    fn is_register(&self, token: &str) -> bool {
+        let upper = token.to_uppercase();
+        let numbered = upper.len() == 2 && upper.starts_with('V') && upper[1..].chars().all(|digit| digit.is_ascii_hexdigit());
+        return numbered || self.aliases.contains(&upper);
+    }
+}
+
+/// Parses one of Octo's numbers, which can be decimal, hex with `0x` or binary with `0b`, and negative
+fn parse_octo_number(token: &str) -> Option<i64> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        i64::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse::<i64>().ok()?
+    };
+    return Some(if negative { -value } else { value });
+}
+
+/// An operand written for the assembler: numbers in decimal and names uppercased
+fn number(token: &str) -> String {
+    return match parse_octo_number(token) {
+        Some(value) => value.to_string(),
+        None => token.to_uppercase(),
+    };
+}
+
+/// A byte operand written for the assembler, with negative numbers wrapped around like Octo does
+fn byte(token: &str) -> String {
+    return match parse_octo_number(token) {
+        Some(value) if value < 0 => (value & 0xFF).to_string(),
+        _ => number(token),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble_listing;
+
+    #[test]
+    fn exported_games_assemble_back_to_themselves() {
+        for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/c8games")).unwrap() {
+            let path = entry.unwrap().path();
+            let rom = std::fs::read(&path).unwrap();
+            let (bytes, _) = assemble_octo(&export_octo(&rom)).unwrap_or_else(|errors| panic!("{}: {:?}", path.display(), errors));
+            assert_eq!(bytes, rom, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn octo_control_flow_becomes_skips_and_jumps() {
+        let source = "
+            :const LIMIT 3
+            :alias counter v1
+            : draw-score
+                i := hex counter
+                sprite v0 v0 5
+                ;
+            :main
+                counter := 0
+                loop
+                    counter += 1
+                    if counter == 2 begin
+                        draw-score
+                    else
+                        v2 -= 1
+                    end
+                    while counter != LIMIT
+                again
+                loop again";
+        let (rom, warnings) = assemble_octo(source).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(
+            rom,
+            [
+                0x12, 0x08, 0xF1, 0x29, 0xD0, 0x05, 0x00, 0xEE, // jump main, draw-score
+                0x61, 0x00, 0x71, 0x01, 0x31, 0x02, 0x12, 0x14, // the if skips its jump to the else when counter is 2
+                0x22, 0x02, 0x12, 0x16, 0x72, 0xFF, 0x41, 0x03, // -= 1 adds 255, while skips its jump out of the loop
+                0x12, 0x1C, 0x12, 0x0A, 0x12, 0x1C,
+            ]
+        );
+
+        // Mistakes are reported on the Octo line they are on, including the ones the assembler finds
+        let errors = assemble_octo(": main\n  v0 += 300\n  if v0 < 3 then\n  loop").unwrap_err();
+        let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, ["line 3: < isn't supported by this assembler, only == != key and -key are", "line 4: LOOP is never closed with again"]);
+        let errors = assemble_octo(": main\n\n  v0 += 300").unwrap_err();
+        assert_eq!(errors[0].to_string(), "line 3: 300 doesn't fit in 8 bits");
+        assert_eq!(assemble_octo("clear").unwrap_err()[0].to_string(), "line 1: MAIN isn't defined, but Octo programs start there");
+
+        // Listings are of the Octo lines, without the labels made up for the loops
+        let source = ": main\n  loop v0 += 1 again";
+        let translation = translate_octo(source).unwrap();
+        let listing = translation.listing(assemble_listing(&translation.source).unwrap().1, source);
+        assert_eq!(listing.text(), "ADDR  BYTES         LINE  SOURCE\n0200                   1  : main\n0200  70 01 12 00      2    loop v0 += 1 again\n");
+        assert_eq!(listing.symbol_map(), "0200 MAIN\n");
+    }
+}
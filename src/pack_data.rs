@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+// Tile maps become one byte per tile, row by row.
+// RLE tables are (value, count) pairs ending with a count of 0, unpacked at runtime by a generated routine.
+
+/// Most bytes the generated unpack routine can produce, since it counts with 8-bit registers
+pub const MAX_UNPACKED_SIZE: usize = 256;
+
+/// A grid of tile indices
+pub struct TileMap {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<u8>,
+}
+
+/// Reads a text tile map, one row per line with tiles separated by spaces or commas
+/// 
+/// ## Arguments
+/// 
+/// * `text` - The tile map, with numbers in decimal or `0x` hex
+pub fn read_text_map(text: &str) -> Result<TileMap, String> {
+    let mut tiles = Vec::new();
+    let mut width = 0;
+    let mut height = 0;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let row: Vec<&str> = line.split(|c: char| c.is_whitespace() || c == ',').filter(|token| !token.is_empty()).collect();
+        if row.is_empty() {
+            continue;
+        }
+
+        if height > 0 && row.len() != width {
+            return Err(format!("line {}: expected {} tiles, found {}", line_index + 1, width, row.len()));
+        }
+        width = row.len();
+        height += 1;
+
+        for token in row {
+            let tile = match token.strip_prefix("0x").or(token.strip_prefix("0X")) {
+                Some(hex) => u8::from_str_radix(hex, 16),
+                None => token.parse(),
+            };
+            tiles.push(tile.map_err(|_| format!("line {}: invalid tile {}", line_index + 1, token))?);
+        }
+    }
+
+    return Ok(TileMap { width, height, tiles });
+}
+
+/// Reads a PNG tile map, one pixel per tile
+/// 
+/// Indexed images use the palette index as the tile. Other images number their colors in order of first appearance.
+/// 
+/// ## Arguments
+/// 
+/// * `path` - The image to read
+pub fn read_png_map(path: &str) -> Result<TileMap, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    let palette: Vec<[u8; 3]> = match &reader.info().palette {
+        Some(palette) => palette.chunks(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect(),
+        None => Vec::new(),
+    };
+
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let channels = info.color_type.samples();
+
+    let mut colors: HashMap<Vec<u8>, u8> = HashMap::new();
+    let mut tiles = Vec::new();
+    for pixel in data[..info.buffer_size()].chunks(channels) {
+        let tile = if palette.is_empty() {
+            let next = colors.len();
+            if next > u8::MAX as usize && !colors.contains_key(pixel) {
+                return Err(format!("{} has more than 256 colors", path));
+            }
+            *colors.entry(pixel.to_vec()).or_insert(next as u8)
+        } else {
+            // Expanded palette images come back as RGB(A), so look the color back up
+            let rgb = [pixel[0], pixel[1], pixel[2]];
+            palette.iter().position(|color| *color == rgb).unwrap_or(0) as u8
+        };
+        tiles.push(tile);
+    }
+
+    return Ok(TileMap { width: info.width as usize, height: info.height as usize, tiles });
+}
+
+/// Run-length encodes data as (value, count) pairs, ending with a (0, 0) pair
+/// 
+/// ## Arguments
+/// 
+/// * `data` - The bytes to compress
+pub fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    let mut i = 0;
+    while i < data.len() {
+        let value = data[i];
+        let mut count = 1;
+        while i + count < data.len() && data[i + count] == value && count < u8::MAX as usize {
+            count += 1;
+        }
+
+        encoded.push(value);
+        encoded.push(count as u8);
+        i += count;
+    }
+
+    encoded.push(0);
+    encoded.push(0);
+    return encoded;
+}
+
+/// Writes a byte table as assembler source under a label
+/// 
+/// ## Arguments
+/// 
+/// * `label` - The label to define at the start of the table
+/// * `data` - The bytes in the table
+/// * `per_line` - How many bytes to put on each `.db` line
+pub fn data_source(label: &str, data: &[u8], per_line: usize) -> String {
+    let mut str_buffer = format!("{}:\n", label);
+
+    for chunk in data.chunks(per_line.max(1)) {
+        let bytes: Vec<String> = chunk.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+        str_buffer.push_str(&format!(".db {}\n", bytes.join(" ")));
+    }
+
+    return str_buffer;
+}
+
+/// Writes the routine that unpacks an RLE table to a fixed address
+/// 
+/// Calling `UNPACK_<NAME>` expands `<NAME>_RLE` to `addr`, using V0, V1, VA, VB and I.
+/// 
+/// ## Arguments
+/// 
+/// * `name` - Name of the table
+/// * `addr` - Where to write the unpacked data
+pub fn unpack_routine(name: &str, addr: u16) -> String {
+    return [
+        format!("UNPACK_{}:", name),
+        String::from("LD VA 0"),
+        String::from("LD VB 0"),
+        format!("UNPACK_{}_NEXT:", name),
+        format!("LD I {}_RLE", name),
+        String::from("ADD I VA"),
+        String::from("LD V1 [I]"),
+        String::from("ADD VA 2"),
+        String::from("SE V1 0"),
+        format!("JP UNPACK_{}_FILL", name),
+        String::from("RET"),
+        format!("UNPACK_{}_FILL:", name),
+        format!("LD I 0x{:03X}", addr),
+        String::from("ADD I VB"),
+        String::from("LD [I] V0"),
+        String::from("ADD VB 1"),
+        String::from("ADD V1 0xFF"),
+        String::from("SE V1 0"),
+        format!("JP UNPACK_{}_FILL", name),
+        format!("JP UNPACK_{}_NEXT", name),
+    ].join("\n") + "\n";
+}
+
+/// Packs a tile map into a byte table and the assembler source that embeds it
+/// 
+/// ## Arguments
+/// 
+/// * `map` - The tile map
+/// * `name` - Label for the table
+/// * `unpack_addr` - If set, compress the table and include a routine unpacking it to this address
+/// 
+/// ## Returns
+/// 
+/// The table as it is stored in the ROM, and the assembler source
+pub fn pack(map: &TileMap, name: &str, unpack_addr: Option<u16>) -> Result<(Vec<u8>, String), String> {
+    let name = name.to_uppercase();
+
+    let Some(addr) = unpack_addr else {
+        return Ok((map.tiles.clone(), data_source(&name, &map.tiles, map.width)));
+    };
+
+    if map.tiles.len() > MAX_UNPACKED_SIZE {
+        return Err(format!("{} tiles is more than the unpack routine can handle ({})", map.tiles.len(), MAX_UNPACKED_SIZE));
+    }
+
+    let encoded = rle_encode(&map.tiles);
+    if encoded.len() > MAX_UNPACKED_SIZE {
+        return Err(String::from("The compressed table is too large for the unpack routine to read"));
+    }
+
+    let source = data_source(&format!("{}_RLE", name), &encoded, 16) + &unpack_routine(&name, addr);
+    return Ok((encoded, source));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::*;
+
+    #[test]
+    fn text_maps_need_equal_rows() {
+        let map = read_text_map("0 1 2\n0x3,4,5\n\n").unwrap();
+        assert_eq!((map.width, map.height), (3, 2));
+        assert_eq!(map.tiles, vec![0, 1, 2, 3, 4, 5]);
+
+        assert!(read_text_map("0 1\n2").is_err());
+    }
+
+    #[test]
+    fn rle_splits_long_runs() {
+        let mut data = vec![7; 300];
+        data.push(1);
+        assert_eq!(rle_encode(&data), vec![7, 255, 7, 45, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn unpack_routine_restores_the_map() {
+        let map = read_text_map("0 0 0 0 1 1\n2 2 2 0 0 0").unwrap();
+        let (_, source) = pack(&map, "level", Some(0x400)).unwrap();
+
+        let program = format!("CALL UNPACK_LEVEL\nHALT: JP HALT\n{}", source);
+        let (rom, warnings) = assemble_with_warnings(&program).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..200 {
+            chip8.cycle().unwrap();
+        }
+        assert_eq!(&chip8.get_memory()[0x400..0x400 + map.tiles.len()], &map.tiles[..]);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::quirks::{Platform, Quirks};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// A profile is a setup worth keeping for a kind of game: which keys each player has, the theme and the quirks.
+// It is a TOML file, so it can be edited by hand and passed around:
+//   name = "puzzle"
+//   player1_keys = "2,4,6,8,5"
+//   theme = "gruvbox"
+//   platform = "vip"
+//   quirks = ["sprites=wrap"]
+// A ROM can have one of its own beside it, named after it with .toml on the end (like BRIX.toml), which can start from
+// a shared profile with `profile = "puzzle.toml"`, relative to itself. Settings are layered, the later ones winning:
+// the shared profile, the ROM's own file, --profile-file, then the flags on the command line.
+
+/// Extension of the profile found beside a ROM
+pub const SIDECAR_EXTENSION: &str = "toml";
+
+/// Layouts a second player can be given
+pub const PLAYER2_LAYOUTS: [&str; 2] = ["ijkl", "numpad"];
+
+/// Most profiles one can start from in turn, which is more than anyone needs unless two start from each other
+const MAX_DEPTH: usize = 8;
+
+/// Settings for the keymap, theme and quirks, any of which can be left out to keep what it is layered over
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub name: Option<String>,
+    /// Another profile this one starts from, relative to this one's file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player1_keys: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player2: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player2_keys: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// Quirks as name=value, applied after the platform's like `--quirk`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub quirks: Vec<String>,
+}
+
+impl Profile {
+    /// Reads a profile file, with the profiles it starts from layered underneath
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `path` - The file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        return Self::load_nested(path, 0);
+    }
+
+    fn load_nested(path: &Path, depth: usize) -> Result<Self, String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("{} starts from too many profiles, do two start from each other?", path.display()));
+        }
+
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let profile: Profile = toml::from_str(&text).map_err(|e| format!("{} isn't a profile: {}", path.display(), e.message()))?;
+        profile.check().map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let Some(base) = profile.profile.as_deref() else {
+            return Ok(profile);
+        };
+        let base_path = path.parent().unwrap_or(Path::new(".")).join(base);
+        return Ok(Self::load_nested(&base_path, depth + 1)?.layer(profile));
+    }
+
+    /// Finds the profile beside a ROM, if it has one
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `rom_path` - The ROM
+    pub fn for_rom(rom_path: &Path) -> Result<Option<Self>, String> {
+        let path = sidecar_path(rom_path);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        return Self::load(&path).map(Some);
+    }
+
+    /// Writes the profile to a file, named after the file unless it has a name already
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `path` - Where to write it
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut profile = self.clone();
+        profile.profile = None;
+        if profile.name.is_none() {
+            profile.name = path.file_stem().map(|stem| stem.to_string_lossy().to_string());
+        }
+
+        let text = toml::to_string(&profile).map_err(|e| format!("Failed to write the profile: {}", e))?;
+        return std::fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e));
+    }
+
+    /// Puts another profile on top of this one, its settings replacing these where it has them
+    /// 
+    /// Quirks are added after these, so the other profile's win where both pick the same one.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `over` - The profile on top
+    pub fn layer(self, over: Profile) -> Self {
+        return Self {
+            name: over.name.or(self.name),
+            profile: over.profile.or(self.profile),
+            player1_keys: over.player1_keys.or(self.player1_keys),
+            player2: over.player2.or(self.player2),
+            player2_keys: over.player2_keys.or(self.player2_keys),
+            theme: over.theme.or(self.theme),
+            platform: over.platform.or(self.platform),
+            quirks: self.quirks.into_iter().chain(over.quirks).collect(),
+        };
+    }
+
+    /// Checks the settings that have a fixed set of values, so a bad file is caught when it is read
+    fn check(&self) -> Result<(), String> {
+        if let Some(layout) = self.player2.as_deref().filter(|layout| !PLAYER2_LAYOUTS.contains(layout)) {
+            return Err(format!("player2 is {}, but can only be {}", layout, PLAYER2_LAYOUTS.join(" or ")));
+        }
+        if let Some(name) = self.platform.as_deref() {
+            Platform::from_name(name)?;
+        }
+        let mut quirks = Quirks::default();
+        for spec in self.quirks.iter() {
+            quirks.set(spec)?;
+        }
+        return Ok(());
+    }
+}
+
+/// The profile file beside a ROM, which has the ROM's name with .toml on the end
+pub fn sidecar_path(rom_path: &Path) -> PathBuf {
+    let mut path = rom_path.as_os_str().to_owned();
+    path.push(".");
+    path.push(SIDECAR_EXTENSION);
+    return PathBuf::from(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_layer_and_round_trip() {
+        let dir = std::env::temp_dir().join(format!("chip8-profile-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("profiles")).unwrap();
+        std::fs::write(dir.join("profiles").join("puzzle.toml"), "name = \"puzzle\"\ntheme = \"nord\"\nplatform = \"vip\"\nquirks = [\"shift=vx\"]\n").unwrap();
+        std::fs::write(dir.join("GAME.toml"), "profile = \"profiles/puzzle.toml\"\nplayer1_keys = \"2,4,6,8\"\nquirks = [\"shift=vy\"]\n").unwrap();
+
+        // The ROM's own settings go on top of the profile it starts from
+        let profile = Profile::for_rom(&dir.join("GAME")).unwrap().unwrap();
+        assert_eq!(profile.name.as_deref(), Some("puzzle"));
+        assert_eq!((profile.theme.as_deref(), profile.player1_keys.as_deref()), (Some("nord"), Some("2,4,6,8")));
+        assert_eq!(profile.quirks, vec![String::from("shift=vx"), String::from("shift=vy")]);
+        assert_eq!(Profile::for_rom(&dir.join("OTHER")), Ok(None));
+
+        let path = dir.join("saved.toml");
+        Profile { name: None, ..profile.clone() }.save(&path).unwrap();
+        assert_eq!(Profile::load(&path), Ok(Profile { name: Some(String::from("saved")), profile: None, ..profile }));
+
+        // Mistakes are caught when the file is read
+        std::fs::write(&path, "player2 = \"wasd\"").unwrap();
+        assert!(Profile::load(&path).unwrap_err().ends_with("player2 is wasd, but can only be ijkl or numpad"));
+        std::fs::write(&path, "colour = \"red\"").unwrap();
+        assert!(Profile::load(&path).unwrap_err().contains("isn't a profile"));
+        std::fs::write(&path, "profile = \"saved.toml\"").unwrap();
+        assert!(Profile::load(&path).unwrap_err().contains("starts from too many profiles"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
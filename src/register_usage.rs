@@ -0,0 +1,193 @@
+use std::fmt::Write;
+
+const REGISTER_COUNT: usize = 16;
+
+/// Start of the program in memory
+const PROGRAM_START: u16 = 0x200;
+
+/// How an instruction uses a register
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Lists the V registers an instruction reads and writes
+/// 
+/// ## Arguments
+/// 
+/// * `opcode` - The instruction
+pub fn register_accesses(opcode: u16) -> Vec<(usize, Access)> {
+    let x = ((opcode & 0x0F00) >> 8) as usize;
+    let y = ((opcode & 0x00F0) >> 4) as usize;
+    let (read, write) = (Access::Read, Access::Write);
+
+    return match (opcode >> 12, opcode & 0x000F, opcode & 0x00FF) {
+        (0x3, _, _) | (0x4, _, _) => vec![(x, read)],
+        (0x5, 0, _) | (0x9, 0, _) => vec![(x, read), (y, read)],
+        (0x6, _, _) | (0xC, _, _) => vec![(x, write)],
+        (0x7, _, _) => vec![(x, read), (x, write)],
+        (0x8, 0, _) => vec![(y, read), (x, write)],
+        (0x8, 1..=3, _) => vec![(x, read), (y, read), (x, write)],
+        (0x8, 4 | 5 | 7, _) => vec![(x, read), (y, read), (x, write), (0xF, write)],
+        (0x8, 6 | 0xE, _) => vec![(x, read), (x, write), (0xF, write)],
+        (0xB, _, _) => vec![(0, read)],
+        (0xD, _, _) => vec![(x, read), (y, read), (0xF, write)],
+        (0xE, _, 0x9E) | (0xE, _, 0xA1) => vec![(x, read)],
+        (0xF, _, 0x07) | (0xF, _, 0x0A) => vec![(x, write)],
+        (0xF, _, 0x15) | (0xF, _, 0x18) | (0xF, _, 0x1E) | (0xF, _, 0x29) | (0xF, _, 0x33) | (0xF, _, 0x3A) => vec![(x, read)],
+        (0xF, _, 0x55) => (0..=x).map(|register| (register, read)).collect(),
+        (0xF, _, 0x65) => (0..=x).map(|register| (register, write)).collect(),
+        _ => Vec::new(),
+    };
+}
+
+/// Which registers a ROM uses, how often, and over which addresses
+pub struct RegisterUsage {
+    reads: [u64; REGISTER_COUNT],
+    writes: [u64; REGISTER_COUNT],
+    /// Lowest and highest address of an instruction touching each register
+    span: [Option<(u16, u16)>; REGISTER_COUNT],
+    /// Load/store ranges that might not do what was meant
+    conflicts: Vec<String>,
+}
+
+impl RegisterUsage {
+    pub fn new() -> Self {
+        return Self {
+            reads: [0; REGISTER_COUNT],
+            writes: [0; REGISTER_COUNT],
+            span: [None; REGISTER_COUNT],
+            conflicts: Vec::new(),
+        };
+    }
+
+    /// Counts the registers used by one instruction
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `addr` - Where the instruction is
+    /// * `opcode` - The instruction
+    pub fn record(&mut self, addr: u16, opcode: u16) {
+        for (register, access) in register_accesses(opcode) {
+            match access {
+                Access::Read => self.reads[register] += 1,
+                Access::Write => self.writes[register] += 1,
+            }
+
+            let (low, high) = self.span[register].unwrap_or((addr, addr));
+            self.span[register] = Some((low.min(addr), high.max(addr)));
+        }
+    }
+
+    /// Reads through a ROM two bytes at a time, without running it
+    /// 
+    /// Data mixed in with the code is read as instructions too, so treat the result as a guide rather than gospel.
+    /// Besides the counts, this looks for load/store ranges that might clobber or save the wrong registers:
+    /// `LD Vx, [I]` overwriting a register that other code sets, and `LD [I], Vx` saving one that nothing ever sets.
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `rom` - The program, loaded at 0x200
+    pub fn analyze(rom: &[u8]) -> Self {
+        let mut usage = Self::new();
+        let instructions: Vec<(u16, u16)> = rom
+            .chunks_exact(2)
+            .enumerate()
+            .map(|(i, bytes)| (PROGRAM_START + i as u16 * 2, (bytes[0] as u16) << 8 | bytes[1] as u16))
+            .collect();
+
+        // Where each register is first set, other than by a bulk load
+        let mut first_setter: [Option<u16>; REGISTER_COUNT] = [None; REGISTER_COUNT];
+        for (addr, opcode) in instructions.iter() {
+            usage.record(*addr, *opcode);
+            if opcode & 0xF0FF != 0xF065 {
+                for (register, access) in register_accesses(*opcode) {
+                    if access == Access::Write {
+                        first_setter[register].get_or_insert(*addr);
+                    }
+                }
+            }
+        }
+
+        for (addr, opcode) in instructions.iter() {
+            let x = ((opcode & 0x0F00) >> 8) as usize;
+            match opcode & 0xF0FF {
+                0xF065 => {
+                    for (register, setter) in first_setter.iter().enumerate().take(x + 1) {
+                        if let Some(setter) = setter {
+                            usage.conflicts.push(format!("0x{:03X}: LD V{:X}, [I] overwrites V{:X}, which is also set at 0x{:03X}", addr, x, register, setter));
+                        }
+                    }
+                },
+                0xF055 => {
+                    for register in 0..=x {
+                        if usage.writes[register] == 0 {
+                            usage.conflicts.push(format!("0x{:03X}: LD [I], V{:X} saves V{:X}, which is never set", addr, x, register));
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        return usage;
+    }
+
+    /// Registers that nothing reads or writes
+    pub fn free_registers(&self) -> Vec<usize> {
+        return (0..REGISTER_COUNT).filter(|register| self.span[*register].is_none()).collect();
+    }
+
+    /// Lays the usage out as a table, followed by the free registers and any conflicts
+    pub fn report(&self) -> String {
+        let mut str_buffer = format!("{:<5}{:>8}{:>8}  {}\n", "Reg", "Reads", "Writes", "Used between");
+        for register in 0..REGISTER_COUNT {
+            let span = match self.span[register] {
+                Some((low, high)) => format!("0x{:03X}-0x{:03X}", low, high),
+                None => String::from("free"),
+            };
+            let _ = writeln!(str_buffer, "V{:<4X}{:>8}{:>8}  {}", register, self.reads[register], self.writes[register], span);
+        }
+
+        let free: Vec<String> = self.free_registers().iter().map(|register| format!("V{:X}", register)).collect();
+        let _ = writeln!(str_buffer, "Free: {}", if free.is_empty() { String::from("none") } else { free.join(" ") });
+
+        for conflict in self.conflicts.iter() {
+            let _ = writeln!(str_buffer, "warning: {}", conflict);
+        }
+        return str_buffer;
+    }
+}
+
+impl Default for RegisterUsage {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble;
+
+    #[test]
+    fn accesses_follow_the_instruction() {
+        assert_eq!(register_accesses(0x8124), vec![(1, Access::Read), (2, Access::Read), (1, Access::Write), (0xF, Access::Write)]);
+        assert_eq!(register_accesses(0xF265), vec![(0, Access::Write), (1, Access::Write), (2, Access::Write)]);
+        assert_eq!(register_accesses(0x00E0), vec![]);
+    }
+
+    #[test]
+    fn report_shows_free_registers_and_conflicts() {
+        let rom = assemble("LD V3 1\nLD I 0x300\nLD V3 [I]\nLD [I] V4\nADD V3 V0\nloop: JP loop").unwrap();
+        let usage = RegisterUsage::analyze(&rom);
+        let report = usage.report();
+
+        assert_eq!(usage.free_registers(), (5..=0xE).collect::<Vec<usize>>());
+        assert!(report.contains("V3          2       3  0x200-0x208\n"), "{}", report);
+        assert!(report.contains("warning: 0x204: LD V3, [I] overwrites V3, which is also set at 0x200\n"), "{}", report);
+        assert!(report.contains("warning: 0x206: LD [I], V4 saves V4, which is never set\n"), "{}", report);
+        assert!(!report.contains("saves V3"), "{}", report);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::format::{compress, decompress};
+use std::collections::VecDeque;
+
+// Rewinding keeps a save state for every recent frame. Consecutive states differ in a handful of bytes, so only the newest is kept whole,
+// and each older one as the bytes that changed between it and the one after it. A change is stored as runs of unchanged bytes and
+// the XOR of changed ones:
+//   1, then repeated: unchanged count (LEB128), changed count (LEB128), changed bytes XORed with the newer state
+// States of different lengths, like one saved after the ROM set a per-frame subroutine and one before, are stored whole after a 0,
+// compressed with LZ4 when the compression feature is on.
+// A few hundred frames of a typical ROM take tens of kilobytes this way, rather than megabytes.
+
+/// Recent save states, newest first, for stepping back through
+pub struct Rewind {
+    /// Most states kept, counting the newest
+    capacity: usize,
+    /// The newest state, whole
+    newest: Option<Vec<u8>>,
+    /// Older states, each as the change from the one after it, newest first
+    older: VecDeque<Vec<u8>>,
+}
+
+impl Rewind {
+    /// Creates an empty history
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `capacity` - Most states to keep; the oldest are dropped to make room
+    pub fn new(capacity: usize) -> Self {
+        return Self { capacity, newest: None, older: VecDeque::new() };
+    }
+
+    /// Adds a state as the newest
+    pub fn push(&mut self, state: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some(newest) = self.newest.take() {
+            self.older.push_front(encode_change(&state, &newest));
+            self.older.truncate(self.capacity - 1);
+        }
+        self.newest = Some(state);
+    }
+
+    /// Drops the newest state, making the one before it the newest
+    /// 
+    /// ## Returns
+    /// 
+    /// The state before the one dropped, or `None` if there is nothing to go back to
+    pub fn step_back(&mut self) -> Option<&[u8]> {
+        let change = self.older.pop_front()?;
+        let newest = self.newest.take().expect("Older states always come with a newest one");
+        self.newest = Some(apply_change(&newest, &change));
+        return self.newest.as_deref();
+    }
+
+    /// Number of states kept
+    pub fn len(&self) -> usize {
+        return self.older.len() + self.newest.is_some() as usize;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.newest.is_none();
+    }
+
+    /// Bytes the states take up
+    pub fn memory_used(&self) -> usize {
+        return self.newest.as_ref().map_or(0, Vec::len) + self.older.iter().map(Vec::len).sum::<usize>();
+    }
+}
+
+/// Writes a length as LEB128, seven bits a byte with the top bit set on all but the last
+fn write_length(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads a length `write_length` wrote, moving `at` past it
+fn read_length(bytes: &[u8], at: &mut usize) -> usize {
+    let (mut value, mut shift) = (0, 0);
+    loop {
+        let byte = bytes[*at];
+        *at += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// Encodes how to get from a state to the one before it
+/// 
+/// ## Arguments
+/// 
+/// * `newer` - The state
+/// * `older` - The state before it
+fn encode_change(newer: &[u8], older: &[u8]) -> Vec<u8> {
+    if newer.len() != older.len() {
+        let mut change = vec![0];
+        change.extend(compress(older));
+        return change;
+    }
+
+    let mut change = vec![1];
+    let mut i = 0;
+    while i < newer.len() {
+        let unchanged = newer[i..].iter().zip(&older[i..]).take_while(|(a, b)| a == b).count();
+        let start = i + unchanged;
+        let changed = newer[start..].iter().zip(&older[start..]).take_while(|(a, b)| a != b).count();
+        write_length(&mut change, unchanged);
+        write_length(&mut change, changed);
+        change.extend(newer[start..start + changed].iter().zip(&older[start..start + changed]).map(|(a, b)| a ^ b));
+        i = start + changed;
+    }
+    return change;
+}
+
+/// Gets the state before one back from the change `encode_change` made
+fn apply_change(newer: &[u8], change: &[u8]) -> Vec<u8> {
+    if change[0] == 0 {
+        return decompress(&change[1..]).expect("Rewind compressed the state itself");
+    }
+
+    let mut older = newer.to_vec();
+    let (mut at, mut i) = (1, 0);
+    while at < change.len() {
+        i += read_length(change, &mut at);
+        let changed = read_length(change, &mut at);
+        for byte in &mut older[i..i + changed] {
+            *byte ^= change[at];
+            at += 1;
+        }
+        i += changed;
+    }
+    return older;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::Chip8;
+
+    #[test]
+    fn steps_back_through_states() {
+        let mut rewind = Rewind::new(3);
+        assert!(rewind.step_back().is_none());
+        for state in [vec![1, 2, 3, 4], vec![1, 2, 9, 4], vec![1, 2, 9], vec![7, 2, 9]] {
+            rewind.push(state);
+        }
+
+        // The first state was dropped to make room
+        assert_eq!(rewind.len(), 3);
+        assert_eq!(rewind.step_back(), Some(&[1, 2, 9][..]));
+        assert_eq!(rewind.step_back(), Some(&[1, 2, 9, 4][..]));
+        assert_eq!(rewind.step_back(), None);
+        assert_eq!(rewind.len(), 1);
+    }
+
+    #[test]
+    fn real_states_are_small() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(include_bytes!("../c8games/BRIX")).unwrap();
+        chip8.seed_rng(0);
+
+        let mut rewind = Rewind::new(600);
+        let mut states = Vec::new();
+        for _ in 0..600 {
+            for _ in 0..8 {
+                chip8.cycle().unwrap();
+            }
+            chip8.update_timers();
+            states.push(chip8.save_state().unwrap());
+            rewind.push(states.last().unwrap().clone());
+        }
+        assert!(rewind.memory_used() * 20 < states.iter().map(Vec::len).sum::<usize>(), "{} bytes", rewind.memory_used());
+
+        for expected in states.iter().rev().skip(1) {
+            assert_eq!(rewind.step_back(), Some(&expected[..]));
+        }
+    }
+}
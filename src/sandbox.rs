@@ -0,0 +1,190 @@
+use crate::batch::hash_screen;
+use crate::chip8::{Chip8, Diagnostic, LimitHit, RunLimits};
+use crate::input_script::InputScript;
+use std::time::Duration;
+
+// Runs ROMs nobody has vetted, like ones sent to a web service, and reports how they ended with a hash and a GIF of the screen.
+// Nothing here trusts the ROM to stop or to stay in bounds: the machine itself stops it at the instruction or time limit,
+// and runs it strictly, so anything the most conservative interpreter wouldn't allow stops it with a fault.
+// What the machine raises for a frontend is thrown away every frame, so a long run doesn't pile it up.
+
+/// How much bigger than the screen the GIF is drawn, so it can be seen without zooming in
+const GIF_SCALE: usize = 4;
+
+/// Colors of dark and lit pixels in the GIF
+const GIF_PALETTE: [u8; 6] = [0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF];
+
+/// How far a sandboxed ROM may run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SandboxLimits {
+    /// Frames to run, unless the ROM is stopped sooner
+    pub frames: u32,
+    /// Instructions run each frame
+    pub ticks_per_frame: u64,
+    /// Most instructions to run, across every frame
+    pub max_instructions: u64,
+    /// Most time to spend running
+    pub max_duration: Duration,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        return Self { frames: 600, ticks_per_frame: 8, max_instructions: 1_000_000, max_duration: Duration::from_secs(1) };
+    }
+}
+
+/// How a sandboxed ROM ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum SandboxOutcome {
+    /// It ran every frame it was given
+    Finished,
+    /// It did something strict mode doesn't allow, like reading past the end of memory
+    Fault(Diagnostic),
+    /// It ran out of instructions or time
+    Limit(LimitHit),
+}
+
+/// What came of running a ROM in the sandbox
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunReport {
+    pub outcome: SandboxOutcome,
+    /// Frames run, counting the one the ROM was stopped in
+    pub frames: u32,
+    /// FNV-1a hash of the final screen, the same as `batch run` gives
+    pub screen_hash: u64,
+    /// Where the ROM got to
+    pub pc: u16,
+    /// The final screen as a GIF, white on black
+    pub gif: Vec<u8>,
+}
+
+/// Runs a ROM that can't be trusted from power on, with RND seeded to 0, pressing keys from an input script
+/// 
+/// ## Arguments
+/// 
+/// * `rom` - The ROM
+/// * `input_script` - The keys to press, and when
+/// * `limits` - How far the ROM may run
+/// 
+/// ## Returns
+/// 
+/// How the ROM ended and what it left on the screen, or why it couldn't be loaded
+/// 
+/// ## Example
+/// 
+/// ```
+/// use chip8::assemble;
+/// use chip8::chip8::LimitHit;
+/// use chip8::input_script::InputScript;
+/// use chip8::sandbox::{run_sandboxed, SandboxLimits, SandboxOutcome};
+/// 
+/// let rom = assemble("loop: JP loop").unwrap();
+/// let limits = SandboxLimits { max_instructions: 500, ..SandboxLimits::default() };
+/// let report = run_sandboxed(&rom, &InputScript::new(), limits).unwrap();
+/// assert_eq!(report.outcome, SandboxOutcome::Limit(LimitHit::Instructions(500)));
+/// assert!(report.gif.starts_with(b"GIF89a"));
+/// ```
+pub fn run_sandboxed(rom: &[u8], input_script: &InputScript, limits: SandboxLimits) -> Result<RunReport, String> {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(rom)?;
+    chip8.seed_rng(0);
+    chip8.enable_strict();
+    chip8.set_pc_history_size(0);
+    chip8.set_limits(RunLimits { max_instructions: Some(limits.max_instructions), max_duration: Some(limits.max_duration) });
+
+    let mut frames = 0;
+    while frames < limits.frames && !chip8.is_halted() {
+        chip8.clear_keypad();
+        for tick in 0..limits.ticks_per_frame {
+            input_script.apply(&mut chip8, frames, tick);
+            if chip8.step().is_none() {
+                break;
+            }
+        }
+        chip8.update_timers();
+        chip8.take_events();
+        chip8.take_host_calls();
+        chip8.take_diagnostics();
+        frames += 1;
+    }
+
+    let outcome = match (chip8.get_limit_hit(), chip8.get_fault()) {
+        (Some(hit), _) => SandboxOutcome::Limit(hit),
+        (None, Some(fault)) => SandboxOutcome::Fault(fault.clone()),
+        (None, None) => SandboxOutcome::Finished,
+    };
+    return Ok(RunReport {
+        outcome,
+        frames,
+        screen_hash: hash_screen(chip8.get_screen_buffer()),
+        pc: chip8.get_pc(),
+        gif: screen_gif(&chip8)?,
+    });
+}
+
+/// Draws the screen as a GIF, `GIF_SCALE` times its size
+fn screen_gif(chip8: &Chip8) -> Result<Vec<u8>, String> {
+    let (width, height) = chip8.get_screen_size();
+    let screen = chip8.get_screen_buffer();
+    let pixels: Vec<u8> = (0..height * GIF_SCALE)
+        .flat_map(|y| (0..width * GIF_SCALE).map(move |x| (y / GIF_SCALE, x / GIF_SCALE)))
+        .map(|(y, x)| (screen[y * width + x] != 0) as u8)
+        .collect();
+
+    let (gif_width, gif_height) = ((width * GIF_SCALE) as u16, (height * GIF_SCALE) as u16);
+    let mut gif = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut gif, gif_width, gif_height, &GIF_PALETTE).map_err(|e| format!("Failed to write the GIF: {}", e))?;
+        let frame = gif::Frame::from_indexed_pixels(gif_width, gif_height, pixels, None);
+        encoder.write_frame(&frame).map_err(|e| format!("Failed to write the GIF: {}", e))?;
+    }
+    return Ok(gif);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::run_rom;
+    use crate::chip8::assemble;
+
+    #[test]
+    fn untrusted_roms_are_stopped_by_the_machine() {
+        let limits = SandboxLimits::default();
+        let run = |program: &str, limits: SandboxLimits| run_sandboxed(&assemble(program).unwrap(), &InputScript::new(), limits).unwrap();
+
+        // Misusing the stack is a strict fault here, rather than the plain error it is elsewhere
+        let report = run("RET", limits);
+        assert_eq!(report.outcome, SandboxOutcome::Fault(Diagnostic { pc: 0x200, message: String::from("RET with nothing on the stack to return to") }));
+        assert_eq!(report.frames, 1);
+        let report = run("LD I 0xFFE\nLD V3 [I]", limits);
+        assert!(matches!(report.outcome, SandboxOutcome::Fault(Diagnostic { pc: 0x202, .. })), "{:?}", report.outcome);
+
+        // However many instructions a frame is given, the machine stops a ROM that never ends
+        let report = run("loop: JP loop", SandboxLimits { ticks_per_frame: u64::MAX, ..limits });
+        assert_eq!((report.outcome, report.frames), (SandboxOutcome::Limit(LimitHit::Instructions(limits.max_instructions)), 1));
+        let report = run("loop: JP loop", SandboxLimits { ticks_per_frame: u64::MAX, max_instructions: u64::MAX, max_duration: Duration::from_millis(10), ..limits });
+        assert_eq!(report.outcome, SandboxOutcome::Limit(LimitHit::Duration(Duration::from_millis(10))));
+
+        // Keys past the keypad are the low digit of the register, as on the COSMAC VIP, rather than a crash
+        let report = run("LD V0 0x20\nloop: SKP V0\nSKNP V0\nJP loop", SandboxLimits { frames: 3, ..limits });
+        assert_eq!((report.outcome, report.frames), (SandboxOutcome::Finished, 3));
+
+        // Keys from the script reach the ROM, which only goes wrong once it has one, when the key pressed in frame 3 is let go
+        let mut script = InputScript::new();
+        script.record(3, 0, 5);
+        let report = run_sandboxed(&assemble("LD V0 K\nRET").unwrap(), &script, limits).unwrap();
+        assert!(matches!(report.outcome, SandboxOutcome::Fault(Diagnostic { pc: 0x202, .. })), "{:?}", report.outcome);
+        assert_eq!(report.frames, 5);
+    }
+
+    #[test]
+    fn reports_match_the_batch_runner() {
+        let brix = include_bytes!("../c8games/BRIX");
+        let report = run_sandboxed(brix, &InputScript::new(), SandboxLimits { frames: 60, ..SandboxLimits::default() }).unwrap();
+        assert_eq!(report.outcome, SandboxOutcome::Finished);
+        assert_eq!(report.screen_hash, run_rom(brix, 60, 8).unwrap().screen_hash);
+
+        let gif = gif::DecodeOptions::new().read_info(report.gif.as_slice()).unwrap();
+        assert_eq!((gif.width(), gif.height()), (64 * GIF_SCALE as u16, 32 * GIF_SCALE as u16));
+    }
+}
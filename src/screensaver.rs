@@ -0,0 +1,128 @@
+use crate::chip8::Chip8;
+use crate::input_script::InputScript;
+use crate::profile::SIDECAR_EXTENSION;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::{Path, PathBuf};
+
+// The screensaver plays every ROM in a directory in turn, for a while each, then starts over from the first.
+// A ROM plays the input script saved next to it with the same name plus .input (like BRIX.input) if there is one,
+// and otherwise random key presses drawn from the seed, so one seed always puts on the same show.
+
+/// Extension of the input scripts played along with the ROMs, which aren't ROMs themselves
+pub const SCRIPT_EXTENSION: &str = "input";
+
+/// Finds the ROMs in a directory to play
+/// 
+/// ## Arguments
+/// 
+/// * `dir` - The directory; subdirectories, input scripts and profiles are skipped
+/// 
+/// ## Returns
+/// 
+/// The ROMs, in order of file name
+pub fn list_roms(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_none_or(|extension| extension != SCRIPT_EXTENSION && extension != SIDECAR_EXTENSION))
+        .collect();
+    paths.sort();
+    return Ok(paths);
+}
+
+/// Where a ROM's keys come from while it plays: the presses of a recorded session, or else keys pressed now and then
+/// and held for a few frames, like someone idly trying the controls
+pub struct DemoInput {
+    script: Option<InputScript>,
+    rng: StdRng,
+    /// The random key held and the frame it is let go on
+    held: Option<(usize, u32)>,
+}
+
+impl DemoInput {
+    /// Picks the input for a ROM, the script beside it if there is one that reads
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `rom_path` - The ROM
+    /// * `seed` - Seed for the random presses when there's no script
+    pub fn for_rom(rom_path: &Path, seed: u64) -> Self {
+        let mut script_path = rom_path.as_os_str().to_owned();
+        script_path.push(".");
+        script_path.push(SCRIPT_EXTENSION);
+        return Self { script: InputScript::load(&script_path.to_string_lossy()).ok(), ..Self::random(seed) };
+    }
+
+    /// Random presses drawn from a seed
+    pub fn random(seed: u64) -> Self {
+        return Self { script: None, rng: StdRng::seed_from_u64(seed), held: None };
+    }
+
+    /// Presses the keys due at one tick of a frame
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `chip8` - The emulator, with its keypad cleared at the start of the frame
+    /// * `frame` - The frame, counting from 0 when the ROM started
+    /// * `tick` - The instruction of the frame about to run, from 0
+    pub fn apply(&mut self, chip8: &mut Chip8, frame: u32, tick: u64) {
+        if let Some(script) = self.script.as_ref() {
+            script.apply(chip8, frame, tick);
+            return;
+        }
+
+        // A new key goes down every 30 frames or so once the last is let go, and is held for 12 to 60 frames
+        if tick == 0 && self.held.is_none_or(|(_, until)| frame >= until) {
+            self.held = self.rng.gen_bool(1.0 / 30.0).then(|| (self.rng.gen_range(0..16), frame + self.rng.gen_range(12..60)));
+        }
+        if let Some((key, _)) = self.held {
+            chip8.set_keypress(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays the input for some frames, returning the key down at the end of each, or 0xFF for none
+    fn keys_seen(mut input: DemoInput, frames: u32) -> Vec<u8> {
+        let mut chip8 = Chip8::new();
+        let mut seen = Vec::new();
+        for frame in 0..frames {
+            chip8.clear_keypad();
+            for tick in 0..8 {
+                input.apply(&mut chip8, frame, tick);
+            }
+            seen.push(chip8.get_keypad().iter().position(|&down| down).map_or(0xFF, |key| key as u8));
+        }
+        return seen;
+    }
+
+    #[test]
+    fn random_presses_repeat_with_the_seed() {
+        let seen = keys_seen(DemoInput::random(3), 600);
+        assert_eq!(seen, keys_seen(DemoInput::random(3), 600));
+        assert_ne!(seen, keys_seen(DemoInput::random(4), 600));
+        assert!(seen.iter().filter(|&&key| key != 0xFF).count() > 60);
+        assert!(seen.contains(&0xFF));
+    }
+
+    #[test]
+    fn scripts_beside_roms_are_played() {
+        let dir = std::env::temp_dir().join(format!("chip8-screensaver-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("B.ch8"), [0x12, 0x00]).unwrap();
+        std::fs::write(dir.join("A.ch8"), [0x12, 0x00]).unwrap();
+        std::fs::write(dir.join("A.ch8.input"), "2 0 5\n").unwrap();
+        std::fs::write(dir.join("A.ch8.toml"), "theme = \"nord\"\n").unwrap();
+
+        let roms = list_roms(&dir).unwrap();
+        assert_eq!(roms, vec![dir.join("A.ch8"), dir.join("B.ch8")]);
+        assert_eq!(keys_seen(DemoInput::for_rom(&roms[0], 0), 4), vec![0xFF, 0xFF, 5, 0xFF]);
+        assert!(DemoInput::for_rom(&roms[1], 0).script.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
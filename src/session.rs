@@ -0,0 +1,74 @@
+use crate::format::{decode, encode, Format};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Everything the emulator keeps between runs lives in one data directory, $XDG_DATA_HOME/chip8 or ~/.local/share/chip8:
+//   session.json        the last ROM played, and how it was started
+//   roms/<name>/        a folder for each ROM, named after its file, holding the state it was in when it was last quit
+// `chip8 resume` reads the session to start the last ROM again the same way, and picks it up from that state.
+
+/// Magic the session index would start with in binary, which it is never written as
+const SESSION_MAGIC: &[u8; 4] = b"C8SN";
+
+/// How the last ROM was started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// The directory it was started from, which relative paths in the arguments are relative to
+    pub dir: PathBuf,
+    /// The arguments it was started with, without the program name
+    pub args: Vec<String>,
+    /// The ROM, so its folder can be found without parsing the arguments
+    pub rom: PathBuf,
+}
+
+impl Session {
+    /// Reads the session index from a data directory
+    pub fn load(data_dir: &Path) -> Result<Self, String> {
+        let path = data_dir.join("session.json");
+        let bytes = std::fs::read(&path).map_err(|e| format!("No ROM has been played yet ({}: {})", path.display(), e))?;
+        return decode(&bytes, SESSION_MAGIC).map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+    }
+
+    /// Writes the session index to a data directory, creating it if need be
+    pub fn save(&self, data_dir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(data_dir).map_err(|e| format!("Failed to create {}: {}", data_dir.display(), e))?;
+        let path = data_dir.join("session.json");
+        let bytes = encode(self, Format::Json, SESSION_MAGIC)?;
+        return std::fs::write(&path, bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e));
+    }
+}
+
+/// Finds the directory the emulator keeps its data in, from `$XDG_DATA_HOME` or `$HOME`
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("chip8"));
+    }
+    return std::env::var_os("HOME").filter(|dir| !dir.is_empty()).map(|home| PathBuf::from(home).join(".local/share/chip8"));
+}
+
+/// The folder a ROM's data goes in
+/// 
+/// ## Arguments
+/// 
+/// * `data_dir` - The emulator's data directory
+/// * `rom` - The ROM's path
+pub fn rom_data_dir(data_dir: &Path, rom: &Path) -> PathBuf {
+    return data_dir.join("roms").join(rom.file_name().unwrap_or_default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_round_trip() {
+        let dir = std::env::temp_dir().join(format!("chip8-session-{}", std::process::id()));
+        assert!(Session::load(&dir).unwrap_err().starts_with("No ROM has been played yet"));
+
+        let session = Session { dir: PathBuf::from("/home/player"), args: vec![String::from("--rom"), String::from("c8games/BRIX")], rom: PathBuf::from("c8games/BRIX") };
+        session.save(&dir).unwrap();
+        assert_eq!(Session::load(&dir), Ok(session));
+        assert_eq!(rom_data_dir(&dir, Path::new("c8games/BRIX")), dir.join("roms").join("BRIX"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use crate::chip8::*;
+
+/// How many of the largest sections to call out at the end of the report
+const LARGEST_COUNT: usize = 5;
+
+/// A run of bytes starting at a label and ending where the next one begins
+pub struct Section {
+    pub name: String,
+    pub addr: usize,
+    pub size: usize,
+}
+
+/// Splits an assembled program into sections at each label
+/// 
+/// Bytes before the first label are reported as `(start)`.
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program source
+/// * `total` - Size of the assembled program in bytes
+pub fn sections(program: &str, total: usize) -> Vec<Section> {
+    let labels = assemble_labels(program);
+    let end = 0x200 + total;
+
+    let mut sections = Vec::new();
+    let first = labels.first().map(|(_, addr)| *addr as usize).unwrap_or(end);
+    if first > 0x200 {
+        sections.push(Section { name: String::from("(start)"), addr: 0x200, size: first - 0x200 });
+    }
+
+    for (i, (name, addr)) in labels.iter().enumerate() {
+        let next = labels.get(i + 1).map(|(_, next)| *next as usize).unwrap_or(end);
+        sections.push(Section { name: name.clone(), addr: *addr as usize, size: next.saturating_sub(*addr as usize) });
+    }
+
+    return sections;
+}
+
+/// Builds a report of where the bytes of a program go, measured against the platform's memory limit
+/// 
+/// ## Arguments
+/// 
+/// * `program` - The Chip-8 program source
+/// * `total` - Size of the assembled program in bytes
+/// * `limit` - The most bytes the platform can load
+pub fn size_report(program: &str, total: usize, limit: usize) -> String {
+    let sections = sections(program, total);
+    let mut str_buffer = String::new();
+
+    let name_width = sections.iter().map(|section| section.name.len()).max().unwrap_or(0).max(7);
+    str_buffer.push_str(&format!("{:<width$}  Address  Bytes\n", "Section", width = name_width));
+    for section in sections.iter() {
+        str_buffer.push_str(&format!("{:<width$}  0x{:03X}    {:>5}\n", section.name, section.addr, section.size, width = name_width));
+    }
+
+    // Total against the limit
+    let percent = total as f64 * 100.0 / limit as f64;
+    str_buffer.push_str(&format!("\nTotal: {} / {} bytes ({:.1}%)", total, limit, percent));
+    if total > limit {
+        str_buffer.push_str(&format!(", {} bytes over the limit\n", total - limit));
+    } else {
+        str_buffer.push_str(&format!(", {} bytes free\n", limit - total));
+    }
+
+    // Largest contributors
+    let mut largest: Vec<&Section> = sections.iter().filter(|section| section.size > 0).collect();
+    largest.sort_by_key(|section| std::cmp::Reverse(section.size));
+    if !largest.is_empty() {
+        str_buffer.push_str("Largest:");
+        for section in largest.iter().take(LARGEST_COUNT) {
+            str_buffer.push_str(&format!(" {} ({}),", section.name, section.size));
+        }
+        str_buffer.pop();
+        str_buffer.push('\n');
+    }
+
+    return str_buffer;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_split_at_labels() {
+        let source = "cls\nmain: ld i sprite\ndrw v0 v1 2\nloop: jp loop\nsprite:\nld v0 0\n";
+        let total = assemble(source).unwrap().len();
+        let sections: Vec<(String, usize, usize)> = sections(source, total)
+            .into_iter()
+            .map(|section| (section.name, section.addr, section.size))
+            .collect();
+
+        assert_eq!(sections, vec![
+            (String::from("(start)"), 0x200, 2),
+            (String::from("MAIN"), 0x202, 4),
+            (String::from("LOOP"), 0x206, 2),
+            (String::from("SPRITE"), 0x208, 2),
+        ]);
+    }
+
+    #[test]
+    fn report_flags_programs_over_the_limit() {
+        let report = size_report("main: jp main", 2, 1);
+        assert!(report.contains("Total: 2 / 1 bytes (200.0%), 1 bytes over the limit"));
+        assert!(report.contains("Largest: MAIN (2)"));
+    }
+}
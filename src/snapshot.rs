@@ -0,0 +1,118 @@
+// Test helper comparing framebuffers against reference PNGs in tests/snapshots.
+// Run the tests with BLESS=1 to write the current output as the new reference instead of comparing.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+/// Asserts that a framebuffer matches its reference image, allowing up to `threshold` differing pixels
+/// 
+/// On a mismatch, the actual output is written next to the reference as `<name>.actual.png` for inspection.
+/// 
+/// ## Arguments
+/// 
+/// * `name` - Name of the reference image, without extension
+/// * `buffer` - The screen buffer, one byte per pixel
+/// * `width` - Width of the screen in pixels
+/// * `height` - Height of the screen in pixels
+/// * `threshold` - Number of pixels allowed to differ
+pub fn assert_screen_matches(name: &str, buffer: &[u8], width: usize, height: usize, threshold: usize) {
+    let reference = snapshot_path(name, "png");
+
+    if std::env::var_os("BLESS").is_some() {
+        write_png(&reference, buffer, width, height);
+        return;
+    }
+
+    let Some((expected, expected_width, expected_height)) = read_png(&reference) else {
+        panic!("No reference image at {}; run the tests with BLESS=1 to create it", reference.display());
+    };
+
+    let actual = snapshot_path(name, "actual.png");
+    if (expected_width, expected_height) != (width, height) {
+        write_png(&actual, buffer, width, height);
+        panic!(
+            "Screen size {}x{} does not match reference {}x{} for {}",
+            width, height, expected_width, expected_height, name
+        );
+    }
+
+    let differing = buffer.iter().zip(&expected).filter(|(a, b)| (**a != 0) != (**b != 0)).count();
+    if differing > threshold {
+        write_png(&actual, buffer, width, height);
+        panic!(
+            "{} pixels differ from the reference for {} (threshold {}); see {}",
+            differing, name, threshold, actual.display()
+        );
+    }
+}
+
+fn snapshot_path(name: &str, extension: &str) -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{}.{}", name, extension));
+}
+
+/// Writes the framebuffer as an 8-bit grayscale PNG, with lit pixels in white
+fn write_png(path: &PathBuf, buffer: &[u8], width: usize, height: usize) {
+    std::fs::create_dir_all(path.parent().unwrap()).expect("Failed to create snapshot directory");
+    let file = File::create(path).expect("Failed to create snapshot");
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let pixels: Vec<u8> = buffer.iter().map(|pixel| if *pixel != 0 { 0xFF } else { 0x00 }).collect();
+    let mut writer = encoder.write_header().expect("Failed to write snapshot");
+    writer.write_image_data(&pixels).expect("Failed to write snapshot");
+}
+
+/// Reads a reference image back into one byte per pixel, treating anything brighter than mid-gray as lit
+fn read_png(path: &PathBuf) -> Option<(Vec<u8>, usize, usize)> {
+    let file = File::open(path).ok()?;
+
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().ok()?;
+
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).ok()?;
+    let channels = info.color_type.samples();
+
+    let pixels = data[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| (pixel[0] >= 0x80) as u8)
+        .collect();
+
+    return Some((pixels, info.width as usize, info.height as usize));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::*;
+
+    #[test]
+    fn font_sprites() {
+        // Draw all 16 hex digits in a 8x2 grid:
+        //   V0 = digit, V1 = x, V2 = y
+        //   loop: LD F, V0; DRW V1, V2, 5; ADD V0, 1; ADD V1, 8
+        //         SE V1, 40 (64); JP skip; LD V1, 0; ADD V2, 8
+        //   skip: SE V0, 10; JP loop; JP self
+        let rom = [
+            0x60, 0x00, 0x61, 0x00, 0x62, 0x00,
+            0xF0, 0x29, 0xD1, 0x25, 0x70, 0x01, 0x71, 0x08,
+            0x31, 0x40, 0x12, 0x16, 0x61, 0x00, 0x72, 0x08,
+            0x30, 0x10, 0x12, 0x06, 0x12, 0x1A,
+        ];
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..200 {
+            chip8.cycle().unwrap();
+        }
+
+        assert_screen_matches("font_sprites", chip8.get_screen_buffer(), SCREEN_WIDTH, SCREEN_HEIGHT, 0);
+    }
+}
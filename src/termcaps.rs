@@ -0,0 +1,140 @@
+use crate::terminal_query::{parse_color_reply, query};
+use std::fmt;
+
+// Everything is asked for in one round trip, each query's reply (if any) arriving in this order:
+//   OSC 11 ?              background color      ESC ] 11 ; rgb:RRRR/GGGG/BBBB BEL
+//   CSI ? u               kitty keyboard flags  ESC [ ? <flags> u
+//   XTGETTCAP Ms          OSC 52 clipboard      ESC P 1 + r ... ESC \ (0 instead of 1 when unsupported)
+//   ⣿ then CSI 6 n        cursor position       ESC [ <row> ; <column> R
+//   CSI c                 device attributes     ESC [ ? <attributes> c
+// A Braille character is printed at the start of the line before asking for the cursor position, to see whether the terminal agrees it is one column wide.
+
+/// The queries sent to the terminal, in order
+const PROBE: &str = "\x1b]11;?\x07\x1b[?u\x1bP+q4d73\x1b\\\r⣿\x1b[6n\r\x1b[2K";
+
+/// Device attribute advertising sixel graphics
+const SIXEL_ATTRIBUTE: &str = "4";
+
+/// What the terminal can do, found once at startup
+#[derive(Debug, Default, PartialEq)]
+pub struct TermCaps {
+    /// 24-bit color escapes are understood
+    pub truecolor: bool,
+    /// Braille characters take up one column, as the renderer assumes
+    pub unicode_width: bool,
+    /// The kitty keyboard protocol can be turned on
+    pub kitty_keyboard: bool,
+    /// Sixel graphics can be drawn
+    pub sixel: bool,
+    /// The clipboard can be set with OSC 52
+    pub osc52: bool,
+    /// The background color, if the terminal would say
+    pub background: Option<[u8; 3]>,
+    /// Whether the terminal answered at all; if not, everything above is a guess
+    pub answered: bool,
+}
+
+impl TermCaps {
+    /// Capabilities of a terminal that cannot be asked, like a pipe or a daemon without one
+    pub fn assumed() -> Self {
+        return Self { unicode_width: true, ..Default::default() };
+    }
+
+    /// Probes the terminal attached to stdin and stdout
+    pub fn probe() -> Self {
+        let colorterm = std::env::var("COLORTERM").ok();
+        return Self::from_reply(colorterm.as_deref(), query(PROBE).as_deref());
+    }
+
+    /// Works out the capabilities from what the terminal sent back
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `colorterm` - The `COLORTERM` environment variable, the usual way of advertising truecolor
+    /// * `reply` - The terminal's reply to the probe, or `None` if it never answered
+    pub fn from_reply(colorterm: Option<&str>, reply: Option<&[u8]>) -> Self {
+        let truecolor = colorterm.is_some_and(|value| value == "truecolor" || value == "24bit");
+        let Some(reply) = reply else {
+            return Self { truecolor, ..Self::assumed() };
+        };
+        let text = String::from_utf8_lossy(reply);
+
+        let kitty_keyboard = csi_replies(&text, 'u').any(|params| params.starts_with('?'));
+        let osc52 = text.contains("\x1bP1+r");
+        let unicode_width = csi_replies(&text, 'R')
+            .filter_map(|params| params.split(';').nth(1)?.parse::<u32>().ok())
+            .last()
+            .is_none_or(|column| column == 2);
+        let sixel = csi_replies(&text, 'c')
+            .filter_map(|params| params.strip_prefix('?'))
+            .any(|params| params.split(';').skip(1).any(|attribute| attribute == SIXEL_ATTRIBUTE));
+
+        return Self {
+            truecolor,
+            unicode_width,
+            kitty_keyboard,
+            sixel,
+            osc52,
+            background: parse_color_reply(reply),
+            answered: true,
+        };
+    }
+}
+
+/// Finds the parameters of every CSI reply ending in a given character
+fn csi_replies(text: &str, end: char) -> impl Iterator<Item = &str> {
+    return text.split("\x1b[").skip(1).filter_map(move |reply| {
+        let length = reply.find(|c: char| !(c.is_ascii_digit() || c == ';' || c == '?'))?;
+        return reply[length..].starts_with(end).then(|| &reply[..length]);
+    });
+}
+
+impl fmt::Display for TermCaps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |supported: bool| if supported { "yes" } else { "no" };
+
+        if !self.answered {
+            writeln!(f, "The terminal did not answer, so these are guesses")?;
+        }
+        writeln!(f, "Truecolor:        {}", yes_no(self.truecolor))?;
+        writeln!(f, "Braille width:    {}", if self.unicode_width { "1 column" } else { "wrong" })?;
+        writeln!(f, "Kitty keyboard:   {}", yes_no(self.kitty_keyboard))?;
+        writeln!(f, "Sixel:            {}", yes_no(self.sixel))?;
+        writeln!(f, "OSC 52 clipboard: {}", yes_no(self.osc52))?;
+        return match self.background {
+            Some([r, g, b]) => write!(f, "Background:       #{:02x}{:02x}{:02x}", r, g, b),
+            None => write!(f, "Background:       unknown"),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_reply_is_understood() {
+        let reply = b"\x1b]11;rgb:2e2e/3434/4040\x07\x1b[?1u\x1bP1+r4d73=\x1b\\\x1b[12;2R\x1b[?62;4;22c";
+        assert_eq!(TermCaps::from_reply(Some("truecolor"), Some(reply)), TermCaps {
+            truecolor: true,
+            unicode_width: true,
+            kitty_keyboard: true,
+            sixel: true,
+            osc52: true,
+            background: Some([0x2E, 0x34, 0x40]),
+            answered: true,
+        });
+    }
+
+    #[test]
+    fn missing_replies_mean_unsupported() {
+        // Only the cursor position and device attributes came back, with Braille drawn two columns wide
+        let caps = TermCaps::from_reply(None, Some(b"\x1bP0+r\x1b\\\x1b[3;3R\x1b[?1;2c"));
+        assert!(!caps.truecolor && !caps.kitty_keyboard && !caps.sixel && !caps.osc52);
+        assert!(!caps.unicode_width);
+        assert_eq!(caps.background, None);
+
+        // A silent terminal is assumed to draw like it always has
+        assert_eq!(TermCaps::from_reply(Some("24bit"), None), TermCaps { truecolor: true, ..TermCaps::assumed() });
+    }
+}
@@ -0,0 +1,109 @@
+use crossterm::terminal;
+use std::io::{self, Read, Write};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// Terminals answer some escape sequences by typing a reply into stdin.
+// Every query is followed by a primary device attributes request (ESC [ c), which practically every terminal answers.
+// Its reply ends in 'c', so reading stops there even when the terminal ignored the query itself.
+
+/// Asks for the primary device attributes
+const DEVICE_ATTRIBUTES: &str = "\x1b[c";
+
+/// How long to wait for the terminal before giving up
+const REPLY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Sends a query to the terminal and collects whatever it answers
+/// 
+/// ## Arguments
+/// 
+/// * `query` - The escape sequence to send
+/// 
+/// ## Returns
+/// 
+/// The reply, including the device attributes that close it, or `None` if the terminal did not answer in time
+pub fn query(query: &str) -> Option<Vec<u8>> {
+    let was_raw = terminal::is_raw_mode_enabled().unwrap_or(false);
+    if !was_raw && terminal::enable_raw_mode().is_err() {
+        return None;
+    }
+
+    let mut stdout = io::stdout();
+    let sent = write!(stdout, "{}{}", query, DEVICE_ATTRIBUTES).and_then(|_| stdout.flush());
+
+    let reply = sent.ok().and_then(|_| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reply = Vec::new();
+            for byte in io::stdin().lock().bytes() {
+                let Ok(byte) = byte else {
+                    break;
+                };
+                reply.push(byte);
+                if ends_with_device_attributes(&reply) {
+                    break;
+                }
+            }
+            let _ = sender.send(reply);
+        });
+        receiver.recv_timeout(REPLY_TIMEOUT).ok()
+    });
+
+    if !was_raw {
+        let _ = terminal::disable_raw_mode();
+    }
+    return reply;
+}
+
+/// Whether a reply has reached the end of the device attributes (ESC [ ? ... c)
+fn ends_with_device_attributes(reply: &[u8]) -> bool {
+    let Some(start) = reply.iter().rposition(|byte| *byte == 0x1B) else {
+        return false;
+    };
+    let Some(params) = reply[start..].strip_prefix(b"\x1b[?").and_then(|tail| tail.strip_suffix(b"c")) else {
+        return false;
+    };
+    return params.iter().all(|byte| byte.is_ascii_digit() || *byte == b';');
+}
+
+/// Reads the color out of an OSC 10/11 reply, like `ESC ] 11 ; rgb:ffff/ffff/ffff BEL`
+/// 
+/// ## Arguments
+/// 
+/// * `reply` - What the terminal sent back
+pub fn parse_color_reply(reply: &[u8]) -> Option<[u8; 3]> {
+    let reply = String::from_utf8_lossy(reply);
+    let start = reply.find("rgb:")? + 4;
+    let end = reply[start..].find(['\x07', '\x1b']).map_or(reply.len(), |end| start + end);
+
+    let channels: Vec<u8> = reply[start..end]
+        .split('/')
+        .map(|channel| {
+            // Each channel has 1 to 4 hex digits, scaled to the full range
+            let value = u32::from_str_radix(channel, 16).ok()?;
+            let max = 16u32.checked_pow(channel.len() as u32)? - 1;
+            Some((value * 255 / max) as u8)
+        })
+        .collect::<Option<Vec<u8>>>()?;
+
+    return channels.try_into().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_replies_parse() {
+        assert_eq!(parse_color_reply(b"\x1b]11;rgb:ffff/ffff/ffff\x07\x1b[?62;22c"), Some([0xFF, 0xFF, 0xFF]));
+        assert_eq!(parse_color_reply(b"\x1b]11;rgb:2e2e/3434/4040\x1b\\"), Some([0x2E, 0x34, 0x40]));
+        assert_eq!(parse_color_reply(b"\x1b]11;rgb:f/0/8\x07"), Some([0xFF, 0x00, 0x88]));
+
+        // A terminal that ignored the query only answers the device attributes
+        assert_eq!(parse_color_reply(b"\x1b[?1;2c"), None);
+        assert!(ends_with_device_attributes(b"\x1b]11;rgb:0/0/0\x07\x1b[?1;2c"));
+        assert!(!ends_with_device_attributes(b"\x1b]11;rgb:c"));
+        assert!(!ends_with_device_attributes(b"\x1b[?1u\x1b]11;rgb:c"));
+    }
+}
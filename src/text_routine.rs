@@ -0,0 +1,337 @@
+use crate::pack_data::data_source;
+
+// Strings are stored as byte offsets of their glyphs in TEXT_FONT, so the routine can add them straight to I.
+// Only the glyphs the strings use are included. Spaces take no glyph, just an advance.
+
+const SPACE: u8 = 0xFE;
+const END: u8 = 0xFF;
+const GLYPH_HEIGHT: usize = 5;
+
+/// Most distinct glyphs the font table can hold while every offset stays below the space and end markers
+pub const MAX_GLYPHS: usize = 51;
+
+/// Most bytes of string data the routine can index with an 8-bit register
+pub const MAX_STRING_BYTES: usize = 256;
+
+/// The micro-fonts available for text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Font {
+    /// 3 pixels wide, 4 with spacing
+    Small,
+    /// 4 pixels wide, 5 with spacing
+    Large,
+}
+
+impl Font {
+    /// Pixels from the start of one character to the start of the next
+    fn advance(&self) -> u8 {
+        return match self {
+            Font::Small => 4,
+            Font::Large => 5,
+        };
+    }
+}
+
+// Glyphs are written as rows of # and ., both fonts fall back to the 3x5 drawing for punctuation
+const GLYPHS_3X5: [(char, [&str; 5]); 65] = [
+    ('0', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["###", "..#", "###", "#..", "###"]),
+    ('3', ["###", "..#", ".##", "..#", "###"]),
+    ('4', ["#.#", "#.#", "###", "..#", "..#"]),
+    ('5', ["###", "#..", "###", "..#", "###"]),
+    ('6', ["###", "#..", "###", "#.#", "###"]),
+    ('7', ["###", "..#", "..#", ".#.", ".#."]),
+    ('8', ["###", "#.#", "###", "#.#", "###"]),
+    ('9', ["###", "#.#", "###", "..#", "###"]),
+    ('A', [".#.", "#.#", "###", "#.#", "#.#"]),
+    ('B', ["##.", "#.#", "##.", "#.#", "##."]),
+    ('C', [".##", "#..", "#..", "#..", ".##"]),
+    ('D', ["##.", "#.#", "#.#", "#.#", "##."]),
+    ('E', ["###", "#..", "##.", "#..", "###"]),
+    ('F', ["###", "#..", "##.", "#..", "#.."]),
+    ('G', [".##", "#..", "#.#", "#.#", ".##"]),
+    ('H', ["#.#", "#.#", "###", "#.#", "#.#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..#", "..#", "..#", "#.#", ".#."]),
+    ('K', ["#.#", "#.#", "##.", "#.#", "#.#"]),
+    ('L', ["#..", "#..", "#..", "#..", "###"]),
+    ('M', ["#.#", "###", "###", "#.#", "#.#"]),
+    ('N', ["##.", "#.#", "#.#", "#.#", "#.#"]),
+    ('O', [".#.", "#.#", "#.#", "#.#", ".#."]),
+    ('P', ["##.", "#.#", "##.", "#..", "#.."]),
+    ('Q', [".#.", "#.#", "#.#", "##.", ".##"]),
+    ('R', ["##.", "#.#", "##.", "#.#", "#.#"]),
+    ('S', [".##", "#..", ".#.", "..#", "##."]),
+    ('T', ["###", ".#.", ".#.", ".#.", ".#."]),
+    ('U', ["#.#", "#.#", "#.#", "#.#", "###"]),
+    ('V', ["#.#", "#.#", "#.#", "#.#", ".#."]),
+    ('W', ["#.#", "#.#", "###", "###", "#.#"]),
+    ('X', ["#.#", "#.#", ".#.", "#.#", "#.#"]),
+    ('Y', ["#.#", "#.#", ".#.", ".#.", ".#."]),
+    ('Z', ["###", "..#", ".#.", "#..", "###"]),
+    ('!', [".#.", ".#.", ".#.", "...", ".#."]),
+    ('"', ["#.#", "#.#", "...", "...", "..."]),
+    ('#', ["#.#", "###", "#.#", "###", "#.#"]),
+    ('%', ["#.#", "..#", ".#.", "#..", "#.#"]),
+    ('&', [".#.", "#.#", ".#.", "#.#", ".##"]),
+    ('\'', [".#.", ".#.", "...", "...", "..."]),
+    ('(', ["..#", ".#.", ".#.", ".#.", "..#"]),
+    (')', ["#..", ".#.", ".#.", ".#.", "#.."]),
+    ('*', ["...", "#.#", ".#.", "#.#", "..."]),
+    ('+', ["...", ".#.", "###", ".#.", "..."]),
+    (',', ["...", "...", "...", ".#.", "#.."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('.', ["...", "...", "...", "...", ".#."]),
+    ('/', ["..#", "..#", ".#.", "#..", "#.."]),
+    (':', ["...", ".#.", "...", ".#.", "..."]),
+    (';', ["...", ".#.", "...", ".#.", "#.."]),
+    ('<', ["..#", ".#.", "#..", ".#.", "..#"]),
+    ('=', ["...", "###", "...", "###", "..."]),
+    ('>', ["#..", ".#.", "..#", ".#.", "#.."]),
+    ('?', ["##.", "..#", ".#.", "...", ".#."]),
+    ('@', [".#.", "#.#", "###", "#..", ".##"]),
+    ('[', ["##.", "#..", "#..", "#..", "##."]),
+    (']', [".##", "..#", "..#", "..#", ".##"]),
+    ('_', ["...", "...", "...", "...", "###"]),
+    ('^', [".#.", "#.#", "...", "...", "..."]),
+    ('$', [".##", "##.", ".#.", ".##", "##."]),
+    ('|', [".#.", ".#.", ".#.", ".#.", ".#."]),
+    ('`', ["#..", ".#.", "...", "...", "..."]),
+    ('~', ["...", ".#.", "#.#", "...", "..."]),
+];
+
+const GLYPHS_4X5: [(char, [&str; 5]); 36] = [
+    ('0', [".##.", "#..#", "#..#", "#..#", ".##."]),
+    ('1', ["..#.", ".##.", "..#.", "..#.", ".###"]),
+    ('2', ["###.", "...#", ".##.", "#...", "####"]),
+    ('3', ["###.", "...#", ".##.", "...#", "###."]),
+    ('4', ["#..#", "#..#", "####", "...#", "...#"]),
+    ('5', ["####", "#...", "###.", "...#", "###."]),
+    ('6', [".##.", "#...", "###.", "#..#", ".##."]),
+    ('7', ["####", "...#", "..#.", ".#..", ".#.."]),
+    ('8', [".##.", "#..#", ".##.", "#..#", ".##."]),
+    ('9', [".##.", "#..#", ".###", "...#", ".##."]),
+    ('A', [".##.", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "###."]),
+    ('C', [".###", "#...", "#...", "#...", ".###"]),
+    ('D', ["###.", "#..#", "#..#", "#..#", "###."]),
+    ('E', ["####", "#...", "###.", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#..."]),
+    ('G', [".###", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#"]),
+    ('I', ["###.", ".#..", ".#..", ".#..", "###."]),
+    ('J', ["...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "####"]),
+    ('M', ["#..#", "####", "####", "#..#", "#..#"]),
+    ('N', ["#..#", "##.#", "#.##", "#..#", "#..#"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "###.", "#...", "#..."]),
+    ('Q', [".##.", "#..#", "#..#", "#.#.", ".#.#"]),
+    ('R', ["###.", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", ".##.", "...#", "###."]),
+    ('T', ["###.", ".#..", ".#..", ".#..", ".#.."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('V', ["#..#", "#..#", "#..#", ".##.", ".##."]),
+    ('W', ["#..#", "#..#", "####", "####", "#..#"]),
+    ('X', ["#..#", "#..#", ".##.", "#..#", "#..#"]),
+    ('Y', ["#.#.", "#.#.", ".#..", ".#..", ".#.."]),
+    ('Z', ["####", "...#", ".##.", "#...", "####"]),
+];
+
+/// Looks up the sprite rows for a character, with lowercase drawn as uppercase
+/// 
+/// ## Arguments
+/// 
+/// * `c` - The character to draw
+/// * `font` - Which font to draw it in
+pub fn glyph(c: char, font: Font) -> Option<[u8; GLYPH_HEIGHT]> {
+    let c = c.to_ascii_uppercase();
+
+    let large = match font {
+        Font::Large => GLYPHS_4X5.iter().find(|(glyph, _)| *glyph == c),
+        Font::Small => None,
+    };
+    let (_, rows) = large.or_else(|| GLYPHS_3X5.iter().find(|(glyph, _)| *glyph == c))?;
+
+    let mut sprite = [0; GLYPH_HEIGHT];
+    for (byte, row) in sprite.iter_mut().zip(rows.iter()) {
+        for (bit, pixel) in row.chars().enumerate() {
+            if pixel == '#' {
+                *byte |= 0x80 >> bit;
+            }
+        }
+    }
+    return Some(sprite);
+}
+
+/// Reads the strings to generate, one `NAME = text` per line
+/// 
+/// Blank lines and lines starting with `#` are skipped.
+/// 
+/// ## Arguments
+/// 
+/// * `text` - The strings file
+pub fn parse_strings(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut strings = Vec::new();
+
+    for (line_index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected NAME = text", line_index + 1));
+        };
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("line {}: {} is not a valid label", line_index + 1, name));
+        }
+
+        strings.push((name.to_uppercase(), value.trim().to_string()));
+    }
+
+    return Ok(strings);
+}
+
+/// Generates the font data, strings, and routines for drawing them
+/// 
+/// ## Arguments
+/// 
+/// * `strings` - Name and text of every string
+/// * `font` - Which font to draw with
+pub fn text_source(strings: &[(String, String)], font: Font) -> Result<String, String> {
+    let mut glyphs: Vec<char> = Vec::new();
+    let mut font_data = Vec::new();
+    let mut string_data = Vec::new();
+    let mut entries = String::new();
+
+    for (name, text) in strings.iter() {
+        entries.push_str(&format!("PRINT_{}:\nLD V3 {}\nJP TEXT_DRAW\n", name, string_data.len()));
+
+        for c in text.chars() {
+            if c == ' ' {
+                string_data.push(SPACE);
+                continue;
+            }
+
+            let c = c.to_ascii_uppercase();
+            let index = match glyphs.iter().position(|glyph| *glyph == c) {
+                Some(index) => index,
+                None => {
+                    let sprite = glyph(c, font).ok_or(format!("{:?} has no glyph in the font", c))?;
+                    glyphs.push(c);
+                    font_data.extend_from_slice(&sprite);
+                    glyphs.len() - 1
+                },
+            };
+            if index >= MAX_GLYPHS {
+                return Err(format!("The strings use more than {} different characters", MAX_GLYPHS));
+            }
+            string_data.push((index * GLYPH_HEIGHT) as u8);
+        }
+        string_data.push(END);
+    }
+
+    if string_data.len() > MAX_STRING_BYTES {
+        return Err(format!("The strings take {} bytes, more than the {} the routine can reach", string_data.len(), MAX_STRING_BYTES));
+    }
+
+    let advance = font.advance();
+    let routine = [
+        String::from("TEXT_DRAW:"),
+        String::from("LD I TEXT_STRINGS"),
+        String::from("ADD I V3"),
+        String::from("LD V0 [I]"),
+        String::from("ADD V3 1"),
+        format!("SNE V0 0x{:02X}", END),
+        String::from("RET"),
+        format!("SE V0 0x{:02X}", SPACE),
+        String::from("JP TEXT_DRAW_GLYPH"),
+        format!("ADD V1 {}", advance),
+        String::from("JP TEXT_DRAW"),
+        String::from("TEXT_DRAW_GLYPH:"),
+        String::from("LD I TEXT_FONT"),
+        String::from("ADD I V0"),
+        format!("DRW V1 V2 {}", GLYPH_HEIGHT),
+        format!("ADD V1 {}", advance),
+        String::from("JP TEXT_DRAW"),
+    ].join("\n") + "\n";
+
+    let header = [
+        "; Text drawing routines generated by chip8 text",
+        ";",
+        "; To draw a string, set V1 to x and V2 to y, then CALL PRINT_<NAME>.",
+        "; V1 is left just past the last character, so strings can be drawn one after another.",
+        "; For other strings in TEXT_STRINGS, set V3 to the string's offset and CALL TEXT_DRAW instead.",
+        "; Uses V0, V3, I and VF.",
+    ].join("\n") + "\n";
+
+    return Ok(
+        header
+            + &entries
+            + &routine
+            + &data_source("TEXT_FONT", &font_data, GLYPH_HEIGHT)
+            + &data_source("TEXT_STRINGS", &string_data, 16)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::*;
+
+    #[test]
+    fn glyphs_fall_back_to_small_punctuation() {
+        assert_eq!(glyph('a', Font::Small), Some([0x40, 0xA0, 0xE0, 0xA0, 0xA0]));
+        assert_eq!(glyph('A', Font::Large), Some([0x60, 0x90, 0xF0, 0x90, 0x90]));
+        assert_eq!(glyph('!', Font::Large), glyph('!', Font::Small));
+        assert_eq!(glyph('\u{e9}', Font::Large), None);
+    }
+
+    #[test]
+    fn strings_parse() {
+        let strings = parse_strings("# title screen\ntitle = Hello, world\n\nscore=0").unwrap();
+        assert_eq!(strings, vec![
+            (String::from("TITLE"), String::from("Hello, world")),
+            (String::from("SCORE"), String::from("0")),
+        ]);
+        assert!(parse_strings("no equals sign").is_err());
+    }
+
+    #[test]
+    fn generated_routine_draws_strings() {
+        let strings = vec![(String::from("HI"), String::from("Hi 1")), (String::from("BYE"), String::from("ih"))];
+        let source = text_source(&strings, Font::Small).unwrap();
+        let program = format!("LD V1 2\nLD V2 3\nCALL PRINT_HI\nLD V1 2\nLD V2 10\nCALL PRINT_BYE\nHALT: JP HALT\n{}", source);
+        let (rom, warnings) = assemble_with_warnings(&program).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom).unwrap();
+        for _ in 0..200 {
+            chip8.cycle().unwrap();
+        }
+
+        // Draw what the screen should look like and compare
+        let mut expected = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        let mut draw = |c: char, x: usize, y: usize| {
+            for (row, byte) in glyph(c, Font::Small).unwrap().iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        expected[(y + row) * SCREEN_WIDTH + x + bit] = 1;
+                    }
+                }
+            }
+        };
+        draw('H', 2, 3);
+        draw('I', 6, 3);
+        draw('1', 14, 3);
+        draw('I', 2, 10);
+        draw('H', 6, 10);
+
+        let screen: Vec<u8> = chip8.get_screen_buffer().iter().map(|pixel| (*pixel != 0) as u8).collect();
+        assert_eq!(screen, expected);
+    }
+}
@@ -0,0 +1,108 @@
+/// Colors to draw the screen with, borrowed from a terminal theme
+#[derive(Debug, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    /// Color of lit pixels
+    pub foreground: [u8; 3],
+    /// Color of unlit pixels
+    pub background: [u8; 3],
+    /// Whether the theme is meant for light terminals
+    pub light: bool,
+}
+
+/// Built-in themes, each family with a dark and a light variant
+pub const THEMES: [Theme; 6] = [
+    Theme { name: "nord-dark", foreground: [0x88, 0xC0, 0xD0], background: [0x2E, 0x34, 0x40], light: false },
+    Theme { name: "nord-light", foreground: [0x5E, 0x81, 0xAC], background: [0xEC, 0xEF, 0xF4], light: true },
+    Theme { name: "solarized-dark", foreground: [0x93, 0xA1, 0xA1], background: [0x00, 0x2B, 0x36], light: false },
+    Theme { name: "solarized-light", foreground: [0x58, 0x6E, 0x75], background: [0xFD, 0xF6, 0xE3], light: true },
+    Theme { name: "gruvbox-dark", foreground: [0xEB, 0xDB, 0xB2], background: [0x28, 0x28, 0x28], light: false },
+    Theme { name: "gruvbox-light", foreground: [0x3C, 0x38, 0x36], background: [0xFB, 0xF1, 0xC7], light: true },
+];
+
+/// Family picked by `auto`
+const DEFAULT_FAMILY: &str = "nord";
+
+impl Theme {
+    /// Escape sequence that switches the terminal to the theme's colors
+    /// 
+    /// ## Arguments
+    /// 
+    /// * `truecolor` - Whether the terminal takes 24-bit colors, otherwise the nearest of the 256 colors is used
+    pub fn escape(&self, truecolor: bool) -> String {
+        if !truecolor {
+            return format!("\x1b[38;5;{}m\x1b[48;5;{}m", nearest_256(self.foreground), nearest_256(self.background));
+        }
+
+        let [fr, fg, fb] = self.foreground;
+        let [br, bg, bb] = self.background;
+        return format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m", fr, fg, fb, br, bg, bb);
+    }
+}
+
+/// Finds the closest color in the 6x6x6 cube of the 256 color palette
+fn nearest_256(rgb: [u8; 3]) -> u8 {
+    let [r, g, b] = rgb.map(|channel| (channel as u16 * 5 + 127) / 255);
+    return (16 + r * 36 + g * 6 + b) as u8;
+}
+
+/// Looks up a theme by name
+/// 
+/// A family name like `gruvbox` picks the variant matching the terminal's background, as does `auto`, which uses the default family.
+/// Without a known background, the dark variant is used.
+/// 
+/// ## Arguments
+/// 
+/// * `name` - A theme, a family, or `auto`
+/// * `background` - The terminal's background color, if it could be found
+pub fn find_theme(name: &str, background: Option<[u8; 3]>) -> Option<&'static Theme> {
+    let name = name.to_lowercase();
+    if let Some(theme) = THEMES.iter().find(|theme| theme.name == name) {
+        return Some(theme);
+    }
+
+    let family = if name == "auto" { DEFAULT_FAMILY } else { &name };
+    let variant = if background.is_some_and(is_light) { "light" } else { "dark" };
+    return THEMES.iter().find(|theme| theme.name == format!("{}-{}", family, variant));
+}
+
+/// Whether a color reads as a light background
+pub fn is_light(rgb: [u8; 3]) -> bool {
+    let [r, g, b] = rgb.map(|channel| channel as u32);
+    return r * 299 + g * 587 + b * 114 > 128 * 1000;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn families_follow_the_background() {
+        let white = Some([0xFF, 0xFF, 0xFF]);
+        let black = Some([0x00, 0x00, 0x00]);
+
+        assert_eq!(find_theme("gruvbox", white).unwrap().name, "gruvbox-light");
+        assert_eq!(find_theme("Gruvbox", black).unwrap().name, "gruvbox-dark");
+        assert_eq!(find_theme("auto", white).unwrap().name, "nord-light");
+        assert_eq!(find_theme("auto", None).unwrap().name, "nord-dark");
+
+        // Naming a variant overrides the background
+        assert_eq!(find_theme("solarized-dark", white).unwrap().name, "solarized-dark");
+        assert!(find_theme("dracula", None).is_none());
+    }
+
+    #[test]
+    fn colors_fall_back_to_256() {
+        let theme = find_theme("gruvbox-dark", None).unwrap();
+        assert_eq!(theme.escape(true), "\x1b[38;2;235;219;178m\x1b[48;2;40;40;40m");
+        assert_eq!(theme.escape(false), "\x1b[38;5;223m\x1b[48;5;59m");
+    }
+
+    #[test]
+    fn themes_match_their_variant() {
+        for theme in THEMES.iter() {
+            assert_eq!(is_light(theme.background), theme.light, "{}", theme.name);
+            assert_ne!(is_light(theme.foreground), theme.light, "{}", theme.name);
+        }
+    }
+}
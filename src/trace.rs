@@ -0,0 +1,243 @@
+use crate::chip8::Chip8;
+use crate::format::{decode, encode, Format};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// A trace is the machine state before every instruction a ROM runs, one line each:
+//   PC=200 OP=60FF I=000 V0=00 V1=00 ... VF=00
+// This is the same layout the DEBUG host call prints. Logs from other emulators can be imported as long as every line names
+// the PC, I and V0-VF as key=value or key:value pairs, in any order and case, with or without 0x or $ in front of the values.
+// Lines without a PC are skipped, so a log's headers and chatter don't need cleaning up first.
+// Traces can also be stored as JSON or compact binary, which are read back without guessing at field names.
+
+/// Magic at the start of binary traces
+const TRACE_MAGIC: &[u8; 4] = b"C8TR";
+
+/// The state of the machine before one instruction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub pc: u16,
+    /// The instruction at PC, if the log recorded it
+    pub opcode: Option<u16>,
+    pub index: u16,
+    pub registers: [u8; 16],
+}
+
+impl TraceEntry {
+    /// Reads the state of an emulator about to run its next instruction
+    pub fn capture(chip8: &Chip8) -> Self {
+        let (pc, memory) = (chip8.get_pc(), chip8.get_memory());
+        let registers: [u8; 16] = chip8.get_registers().try_into().expect("Chip8 has 16 registers");
+        return Self {
+            pc,
+            opcode: Some((memory[pc as usize] as u16) << 8 | memory[pc as usize + 1] as u16),
+            index: chip8.get_index(),
+            registers,
+        };
+    }
+
+    /// Reads one line of a trace
+    /// 
+    /// ## Returns
+    /// 
+    /// The entry, `None` if the line has no PC, or an error naming what is missing or malformed
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let mut fields: Vec<(String, &str)> = Vec::new();
+        let mut tokens = line.split(|c: char| c.is_whitespace() || c == ',').filter(|token| !token.is_empty()).peekable();
+        while let Some(token) = tokens.next() {
+            match token.split_once(['=', ':']) {
+                // A key whose value is the next token, like "PC: 0200"
+                Some((key, "")) => {
+                    if let Some(value) = tokens.next() {
+                        fields.push((key.to_uppercase(), value));
+                    }
+                },
+                Some((key, value)) => fields.push((key.to_uppercase(), value)),
+                None => {}
+            }
+        }
+
+        let field = |key: &str| fields.iter().find(|(name, _)| name == key).map(|(_, value)| *value);
+        let Some(pc) = field("PC") else {
+            return Ok(None);
+        };
+
+        let hex = |key: &str, value: &str| {
+            let digits = value.trim_start_matches("0x").trim_start_matches("0X").trim_start_matches('$');
+            return u16::from_str_radix(digits, 16).map_err(|_| format!("{} is not a hex value for {}", value, key));
+        };
+        let required = |key: &str| field(key).ok_or_else(|| format!("no {} in \"{}\"", key, line)).and_then(|value| hex(key, value));
+
+        let mut registers = [0; 16];
+        for (register, value) in registers.iter_mut().enumerate() {
+            let key = format!("V{:X}", register);
+            *value = u8::try_from(required(&key)?).map_err(|_| format!("{} does not fit in a byte", key))?;
+        }
+
+        return Ok(Some(Self {
+            pc: hex("PC", pc)?,
+            opcode: field("OP").or(field("OPCODE")).map(|value| hex("OP", value)).transpose()?,
+            index: required("I")?,
+            registers,
+        }));
+    }
+
+    /// Lists how another entry differs from this one, or nothing if they match
+    /// 
+    /// The opcode is only compared when both entries have one.
+    pub fn differences(&self, actual: &Self) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.pc != actual.pc {
+            differences.push(format!("PC={:03X} (expected {:03X})", actual.pc, self.pc));
+        }
+        if let (Some(expected), Some(opcode)) = (self.opcode, actual.opcode) {
+            if expected != opcode {
+                differences.push(format!("OP={:04X} (expected {:04X})", opcode, expected));
+            }
+        }
+        if self.index != actual.index {
+            differences.push(format!("I={:03X} (expected {:03X})", actual.index, self.index));
+        }
+        for register in 0..16 {
+            if self.registers[register] != actual.registers[register] {
+                differences.push(format!("V{:X}={:02X} (expected {:02X})", register, actual.registers[register], self.registers[register]));
+            }
+        }
+        return differences;
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PC={:03X}", self.pc)?;
+        if let Some(opcode) = self.opcode {
+            write!(f, " OP={:04X}", opcode)?;
+        }
+        write!(f, " I={:03X}", self.index)?;
+        for (register, value) in self.registers.iter().enumerate() {
+            write!(f, " V{:X}={:02X}", register, value)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Reads a trace, or imports another emulator's log
+/// 
+/// ## Arguments
+/// 
+/// * `text` - The trace, one instruction per line
+pub fn parse_trace(text: &str) -> Result<Vec<TraceEntry>, String> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if let Some(entry) = TraceEntry::parse(line).map_err(|message| format!("line {}: {}", number + 1, message))? {
+            entries.push(entry);
+        }
+    }
+    return Ok(entries);
+}
+
+/// Reads a trace in any format
+/// 
+/// ## Arguments
+/// 
+/// * `bytes` - The trace as text, JSON or binary
+pub fn read_trace(bytes: &[u8]) -> Result<Vec<TraceEntry>, String> {
+    return match Format::detect(bytes, TRACE_MAGIC) {
+        Format::Text => parse_trace(&String::from_utf8_lossy(bytes)),
+        // Logs with lines like "[cycle 1] PC=200 ..." look like JSON at first glance
+        Format::Json => decode(bytes, TRACE_MAGIC).or_else(|message| parse_trace(&String::from_utf8_lossy(bytes)).map_err(|_| message)),
+        Format::Binary => decode(bytes, TRACE_MAGIC),
+    };
+}
+
+/// Writes a trace in the given format
+/// 
+/// ## Arguments
+/// 
+/// * `entries` - The trace
+/// * `format` - Text (one line per instruction), JSON or binary
+pub fn write_trace(entries: &[TraceEntry], format: Format) -> Result<Vec<u8>, String> {
+    if format == Format::Text {
+        return Ok(entries.iter().map(|entry| format!("{}\n", entry)).collect::<String>().into_bytes());
+    }
+    return encode(&entries, format, TRACE_MAGIC);
+}
+
+/// Runs an emulator alongside a trace, stopping at the first instruction where they disagree
+/// 
+/// The timers tick once every `ticks_per_frame` instructions, as if the emulator kept up with the framerate perfectly.
+/// 
+/// ## Arguments
+/// 
+/// * `chip8` - The emulator, with its ROM loaded
+/// * `expected` - The trace to follow
+/// * `ticks_per_frame` - Instructions run between timer ticks
+/// 
+/// ## Returns
+/// 
+/// How many instructions matched, or a description of where they stopped matching
+pub fn compare_trace(chip8: &mut Chip8, expected: &[TraceEntry], ticks_per_frame: u64) -> Result<usize, String> {
+    for (step, entry) in expected.iter().enumerate() {
+        let actual = TraceEntry::capture(chip8);
+        let differences = entry.differences(&actual);
+        if !differences.is_empty() {
+            let previous = step.checked_sub(1).map_or(String::from("power on"), |previous| expected[previous].to_string());
+            return Err(format!("Diverged at instruction {}, after {}:\n  {}", step, previous, differences.join("\n  ")));
+        }
+
+        // A machine stopped by an error stays as it was, which the next entry catches unless the trace stopped there too
+        let _ = chip8.cycle();
+        if (step as u64 + 1).is_multiple_of(ticks_per_frame) {
+            chip8.update_timers();
+        }
+    }
+    return Ok(expected.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::assemble;
+
+    /// Arithmetic and shifts, with the flags they set
+    const ARITHMETIC: &str = "LD V0 0xFF\nLD V1 2\nADD V0 V1\nSUB V1 V0\nSUBN V0 V1\nLD V2 0x81\nSHL V2\nSHR V2\nLD I 0x300\nADD I V2\nloop: JP loop";
+
+    #[test]
+    fn foreign_logs_import() {
+        let line = "cycle 12 | pc: $0204 opcode=0x8014 i:0x000 v0=FF, v1=02, V2=0 V3=0 V4=0 V5=0 V6=0 V7=0 V8=0 V9=0 VA=0 VB=0 VC=0 VD=0 VE=0 VF=0";
+        let entries = parse_trace(&format!("Reference log\n{}\n", line)).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].pc, entries[0].opcode, entries[0].index), (0x204, Some(0x8014), 0));
+        assert_eq!(entries[0].registers[..3], [0xFF, 0x02, 0x00]);
+        assert_eq!(entries[0].to_string(), "PC=204 OP=8014 I=000 V0=FF V1=02 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00 V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=00");
+        assert_eq!(parse_trace(&entries[0].to_string()), Ok(entries));
+
+        assert_eq!(parse_trace("PC=200 I=000"), Err(String::from("line 1: no V0 in \"PC=200 I=000\"")));
+    }
+
+    #[test]
+    fn every_format_reads_back() {
+        let expected = parse_trace(include_str!("../tests/traces/arithmetic.trace")).unwrap();
+        for format in [Format::Text, Format::Json, Format::Binary] {
+            assert_eq!(read_trace(&write_trace(&expected, format).unwrap()), Ok(expected.clone()), "{:?}", format);
+        }
+        assert_eq!(write_trace(&expected, Format::Text).unwrap(), include_bytes!("../tests/traces/arithmetic.trace"));
+    }
+
+    #[test]
+    fn golden_trace_matches() {
+        let expected = parse_trace(include_str!("../tests/traces/arithmetic.trace")).unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble(ARITHMETIC).unwrap()).unwrap();
+        assert_eq!(compare_trace(&mut chip8, &expected, 8), Ok(expected.len()));
+
+        // A wrong flag is caught at the instruction after the one that set it
+        let mut tampered = expected.clone();
+        tampered[3].registers[0xF] = 0;
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&assemble(ARITHMETIC).unwrap()).unwrap();
+        let divergence = compare_trace(&mut chip8, &tampered, 8).unwrap_err();
+        assert!(divergence.starts_with("Diverged at instruction 3, after PC=204 OP=8014"), "{}", divergence);
+        assert!(divergence.ends_with("\n  VF=01 (expected 00)"), "{}", divergence);
+    }
+}
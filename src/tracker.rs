@@ -0,0 +1,217 @@
+use crate::pack_data::data_source;
+
+// Songs are written one row per line, tracker style:
+//   tempo 6        frames per row, 6 unless set
+//   C-4 2          a note and how many rows it lasts (1 if left out)
+//   C#4            sharps take the place of the dash
+//   --- 4          a rest
+// `#` starts a comment.
+//
+// XO-CHIP plays a 16-byte, 1-bit audio pattern at 4000 * 2^((pitch - 64) / 48) samples per second.
+// The pattern here is a square wave with a 16 sample period, so a note's frequency is the playback rate / 16.
+
+const DEFAULT_TEMPO: u32 = 6;
+
+/// One cycle of a square wave every 16 samples
+const SQUARE_PATTERN: [u8; 16] = [0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00];
+const PATTERN_PERIOD: f64 = 16.0;
+
+/// Most notes the playback routine can step through, since it indexes the song with an 8-bit register
+pub const MAX_NOTES: usize = 127;
+
+/// A note, or a rest when `pitch` is `None`, held for a number of frames
+#[derive(Debug, PartialEq)]
+pub struct Note {
+    pub pitch: Option<u8>,
+    pub frames: u32,
+}
+
+/// Reads a song written in the tracker format
+/// 
+/// ## Arguments
+/// 
+/// * `text` - The song
+pub fn parse_song(text: &str) -> Result<Vec<Note>, String> {
+    let mut notes = Vec::new();
+    let mut tempo = DEFAULT_TEMPO;
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+        let Some(first) = tokens.next() else {
+            continue;
+        };
+        let error = |message: &str| format!("line {}: {}", line_index + 1, message);
+
+        let number = tokens.next().map(|token| token.parse::<u32>().map_err(|_| error("expected a number")));
+        if first.eq_ignore_ascii_case("tempo") {
+            tempo = number.ok_or_else(|| error("tempo needs a number of frames"))??;
+            continue;
+        }
+
+        let rows = number.unwrap_or(Ok(1))?;
+        let pitch = if first.chars().all(|c| c == '-') {
+            None
+        } else {
+            Some(note_pitch(first).ok_or_else(|| error(&format!("{} is not a note XO-CHIP can play", first)))?)
+        };
+        notes.push(Note { pitch, frames: rows * tempo });
+    }
+
+    return Ok(notes);
+}
+
+/// Works out the pitch register value that plays a note with the square wave pattern
+/// 
+/// ## Arguments
+/// 
+/// * `name` - The note, like `A-4`, `C#5` or `G3`
+pub fn note_pitch(name: &str) -> Option<u8> {
+    let name = name.to_uppercase();
+    let mut chars = name.chars();
+
+    let semitone: i32 = match chars.next()? {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+
+    let rest = chars.as_str();
+    let (semitone, octave) = match rest.strip_prefix('#') {
+        Some(octave) => (semitone + 1, octave),
+        None => (semitone, rest.strip_prefix('-').unwrap_or(rest)),
+    };
+    let octave: i32 = octave.parse().ok()?;
+
+    let midi = 12 * (octave + 1) + semitone;
+    let frequency = 440.0 * 2f64.powf((midi - 69) as f64 / 12.0);
+    let pitch = (64.0 + 48.0 * (frequency * PATTERN_PERIOD / 4000.0).log2()).round();
+
+    // Pitch 0 is kept for rests
+    if !(1.0..=255.0).contains(&pitch) {
+        return None;
+    }
+    return Some(pitch as u8);
+}
+
+/// Encodes notes as (pitch, frames) pairs ending with a (0, 0) pair, splitting anything too long for one byte
+/// 
+/// ## Arguments
+/// 
+/// * `notes` - The song
+pub fn encode_song(notes: &[Note]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    for note in notes.iter() {
+        let mut frames = note.frames;
+        while frames > 0 {
+            let chunk = frames.min(u8::MAX as u32);
+            encoded.push(note.pitch.unwrap_or(0));
+            encoded.push(chunk as u8);
+            frames -= chunk;
+        }
+    }
+
+    encoded.push(0);
+    encoded.push(0);
+    return encoded;
+}
+
+/// Writes the routine that plays a song from start to finish
+/// 
+/// Calling `PLAY_<NAME>` returns once the song is over, using V0, V1, V2, VA, I, and both timers.
+/// 
+/// ## Arguments
+/// 
+/// * `name` - Name of the song
+pub fn playback_routine(name: &str) -> String {
+    return [
+        format!("PLAY_{}:", name),
+        format!("LD I {}_PATTERN", name),
+        String::from("AUDIO"),
+        String::from("LD VA 0"),
+        format!("PLAY_{}_NEXT:", name),
+        format!("LD I {}_SONG", name),
+        String::from("ADD I VA"),
+        String::from("LD V1 [I]"),
+        String::from("ADD VA 2"),
+        String::from("SNE V1 0"),
+        String::from("RET"),
+        String::from("PITCH V0"),
+        String::from("SE V0 0"),
+        String::from("LD ST V1"),
+        String::from("LD DT V1"),
+        format!("PLAY_{}_WAIT:", name),
+        String::from("LD V2 DT"),
+        String::from("SE V2 0"),
+        format!("JP PLAY_{}_WAIT", name),
+        format!("JP PLAY_{}_NEXT", name),
+    ].join("\n") + "\n";
+}
+
+/// Converts a song into XO-CHIP pattern data and the source of a routine that plays it
+/// 
+/// ## Arguments
+/// 
+/// * `text` - The song, in the tracker format
+/// * `name` - Label prefix for the song
+pub fn convert_song(text: &str, name: &str) -> Result<String, String> {
+    let name = name.to_uppercase();
+    let song = encode_song(&parse_song(text)?);
+    if song.len() / 2 > MAX_NOTES + 1 {
+        return Err(format!("The song has more than {} notes once long ones are split", MAX_NOTES));
+    }
+
+    return Ok(
+        data_source(&format!("{}_PATTERN", name), &SQUARE_PATTERN, 16)
+            + &data_source(&format!("{}_SONG", name), &song, 16)
+            + &playback_routine(&name)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::*;
+
+    #[test]
+    fn notes_map_to_pitches() {
+        // A-4 is 440Hz, a playback rate of 7040 samples per second
+        assert_eq!(note_pitch("A-4"), Some(103));
+        assert_eq!(note_pitch("a4"), Some(103));
+        assert_eq!(note_pitch("A-5"), Some(151));
+        assert_eq!(note_pitch("A#4"), Some(107));
+        assert_eq!(note_pitch("C-1"), None);
+        assert_eq!(note_pitch("H-4"), None);
+    }
+
+    #[test]
+    fn songs_parse_with_tempo_and_rests() {
+        let notes = parse_song("tempo 4\nC-4 2 # held\n--- \n\ntempo 100\nC-4 3").unwrap();
+        let c4 = note_pitch("C-4");
+        assert_eq!(notes, vec![
+            Note { pitch: c4, frames: 8 },
+            Note { pitch: None, frames: 4 },
+            Note { pitch: c4, frames: 300 },
+        ]);
+
+        assert_eq!(encode_song(&notes[2..]), vec![c4.unwrap(), 255, c4.unwrap(), 45, 0, 0]);
+        assert!(parse_song("X-4").is_err());
+    }
+
+    #[test]
+    fn converted_song_assembles_cleanly() {
+        let source = convert_song("C-4 1\n--- 1\nG-4 2", "theme").unwrap();
+        let program = format!("CALL PLAY_THEME\nHALT: JP HALT\n{}", source);
+        let (rom, warnings) = assemble_with_warnings(&program).unwrap();
+
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(rom.windows(2).any(|opcode| opcode == [0xF0, 0x02]));
+        assert!(rom.windows(2).any(|opcode| opcode == [0xF0, 0x3A]));
+    }
+}
@@ -0,0 +1,174 @@
+#![cfg(unix)]
+
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Runs the chip8 binary under a pseudo-terminal, the way someone at a terminal would, to cover what the unit tests can't:
+// answering the terminal queries at startup, raw mode, keys arriving as bytes, frames drawn with escape sequences,
+// and the CleanUp guard putting the terminal back on the way out.
+
+/// How long to wait for the emulator to get somewhere before giving up on it
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The emulator running under a pseudo-terminal, with everything it has written so far
+struct Session {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    output: Arc<Mutex<Vec<u8>>>,
+    /// The terminal's settings before the emulator started, for it to leave them as it found them
+    cooked: String,
+    /// Scratch directory for the session files it saves on exit
+    data_dir: PathBuf,
+}
+
+impl Session {
+    /// Starts the emulator with the given arguments on a terminal of the given size
+    fn start(name: &str, args: &[&str], rows: u16, cols: u16) -> Self {
+        let pair = native_pty_system().openpty(PtySize { rows, cols, pixel_width: 0, pixel_height: 0 }).unwrap();
+        let cooked = format!("{:?}", pair.master.get_termios().expect("Pseudo-terminals have termios on unix"));
+        let data_dir = std::env::temp_dir().join(format!("chip8-pty-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+
+        let mut command = CommandBuilder::new(env!("CARGO_BIN_EXE_chip8"));
+        command.args(args);
+        command.env("XDG_DATA_HOME", &data_dir);
+        command.env("LANG", "C");
+        command.env_remove("LC_ALL");
+        command.env_remove("LC_MESSAGES");
+        let child = pair.slave.spawn_command(command).unwrap();
+        // The slave is kept open by the child alone, so reads end once it exits
+        drop(pair.slave);
+
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut reader = pair.master.try_clone_reader().unwrap();
+        let sink = Arc::clone(&output);
+        thread::spawn(move || {
+            let mut buffer = [0; 4096];
+            while let Ok(read @ 1..) = reader.read(&mut buffer) {
+                sink.lock().unwrap().extend_from_slice(&buffer[..read]);
+            }
+        });
+        let writer = pair.master.take_writer().unwrap();
+        return Self { master: pair.master, writer, child, output, cooked, data_dir };
+    }
+
+    /// Everything written to the terminal so far
+    fn output(&self) -> String {
+        return String::from_utf8_lossy(&self.output.lock().unwrap()).to_string();
+    }
+
+    /// Waits until the emulator has written `text`, failing the test if it never does
+    fn wait_for(&self, text: &str) {
+        let start = Instant::now();
+        while !self.output().contains(text) {
+            assert!(start.elapsed() < TIMEOUT, "Never saw {:?} in {:?}", text, self.output());
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// Types bytes into the terminal
+    fn send(&mut self, bytes: &[u8]) {
+        self.writer.write_all(bytes).unwrap();
+        self.writer.flush().unwrap();
+    }
+
+    /// Answers the queries the emulator sends at startup like a plain VT220 would, with the cursor left where it started
+    fn answer_queries(&mut self) {
+        self.wait_for("\x1b[c");
+        self.send(b"\x1b[1;2R\x1b[?62c");
+    }
+
+    /// Waits for the emulator to exit, returning its exit code
+    fn exit_code(&mut self) -> u32 {
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return status.exit_code();
+            }
+            if start.elapsed() > TIMEOUT {
+                self.child.kill().unwrap();
+                panic!("The emulator didn't exit, having written {:?}", self.output());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// The terminal's settings, to tell raw mode from the line discipline it starts with
+    fn termios(&self) -> String {
+        return format!("{:?}", self.master.get_termios().expect("Pseudo-terminals have termios on unix"));
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
+
+/// Whether any frame drawn so far has lit pixels, which are Braille other than the blank pattern
+/// 
+/// The width probe at startup writes Braille too, so only what comes after the first frame's border counts.
+fn has_lit_pixels(output: &str) -> bool {
+    let frames = output.find("CHIP-8").map_or("", |start| &output[start..]);
+    return frames.chars().any(|c| ('\u{2801}'..='\u{28FF}').contains(&c));
+}
+
+#[test]
+fn playing_a_rom_puts_the_terminal_back_on_exit() {
+    let rom = concat!(env!("CARGO_MANIFEST_DIR"), "/c8games/BRIX");
+    let mut session = Session::start("brix", &["--rom", rom], 50, 140);
+    session.answer_queries();
+
+    session.wait_for("Press any key to start");
+    assert_ne!(session.termios(), session.cooked, "the terminal should be in raw mode while the ROM runs");
+    session.send(b" ");
+    session.wait_for("CHIP-8");
+    let start = Instant::now();
+    while !has_lit_pixels(&session.output()) {
+        assert!(start.elapsed() < TIMEOUT, "BRIX never drew anything");
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    session.send(b"\x1b");
+    assert_eq!(session.exit_code(), 0);
+    assert_eq!(session.termios(), session.cooked);
+    let output = session.output();
+    let last_frame = output.rfind("CHIP-8").unwrap();
+    assert!(output[last_frame..].contains("\x1b[?25h"), "the cursor should be shown again");
+}
+
+#[test]
+fn keys_reach_the_rom_and_test_results_set_the_exit_code() {
+    // Waits for a key and fails with its number
+    let path = std::env::temp_dir().join(format!("chip8-pty-key-{}.ch8", std::process::id()));
+    std::fs::write(&path, chip8::assemble("LD V0 K\nFAIL").unwrap()).unwrap();
+    let mut session = Session::start("key", &["--rom", path.to_str().unwrap(), "--enable-custom-ops"], 50, 140);
+    session.answer_queries();
+
+    session.wait_for("Press any key to start");
+    session.send(b" ");
+    session.wait_for("CHIP-8");
+    // w is key 5 on the keypad
+    session.send(b"w");
+
+    assert_eq!(session.exit_code(), 1);
+    assert_eq!(session.termios(), session.cooked);
+    assert!(session.output().contains("Test failed with code 5"), "{:?}", session.output());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn small_terminals_are_turned_away_untouched() {
+    let rom = concat!(env!("CARGO_MANIFEST_DIR"), "/c8games/BRIX");
+    let mut session = Session::start("small", &["--rom", rom], 12, 80);
+
+    assert_eq!(session.exit_code(), 0);
+    assert_eq!(session.termios(), session.cooked);
+    assert!(session.output().contains("Terminal height is too small"), "{:?}", session.output());
+}